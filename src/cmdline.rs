@@ -0,0 +1,409 @@
+//! Command-line editing helpers: input history and tab completion.
+//!
+//! History is shared by the `:` command line and the `/` `?` search prompts
+//! (each keeps its own list) and persists across sessions in
+//! `$XDG_STATE_HOME/seqtui/history` (`~/.local/state/seqtui/history`).
+//! Lines starting with `:` are commands, lines starting with `/` are searches.
+//!
+//! Tab completion covers command names, file paths after `:w`, `:e` and `:colmap`,
+//! genetic code numbers or names after `:setcode`, sort keys after `:sort`
+//! and methods after `:trim`.
+
+use std::path::{Path, PathBuf};
+
+use crate::genetic_code::GeneticCodes;
+use crate::rows::SortKey;
+
+/// Maximum number of entries kept per history list.
+const MAX_HISTORY: usize = 200;
+
+/// Command names offered by tab completion.
+const COMMAND_NAMES: &[&str] = &[
+    "asAA", "asNT", "block", "colmap", "degap", "dist", "e", "edit", "h", "help", "hide", "inserts", "map",
+    "pin", "q", "quit", "seq", "setcode", "sets", "sort", "tree", "trim", "unhide", "unmap", "unpin", "w",
+];
+
+/// A list of previously entered lines with Up/Down navigation.
+///
+/// Navigation is filtered by the text typed before the first Up, like Vim:
+/// typing `:w` then Up only recalls commands starting with `w`.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: Vec<String>,
+    /// Index of the entry currently shown (None = not navigating)
+    position: Option<usize>,
+    /// Text typed before navigation started
+    draft: String,
+}
+
+impl History {
+    /// Creates a history from existing entries (oldest first).
+    pub fn new(entries: Vec<String>) -> Self {
+        let mut history = Self::default();
+        for entry in entries {
+            history.push(&entry);
+        }
+        history
+    }
+
+    /// Returns the entries, oldest first.
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Adds an entry (moving it to the end if already present) and ends navigation.
+    pub fn push(&mut self, entry: &str) {
+        self.reset();
+        if entry.trim().is_empty() {
+            return;
+        }
+        self.entries.retain(|e| e != entry);
+        self.entries.push(entry.to_string());
+        if self.entries.len() > MAX_HISTORY {
+            let excess = self.entries.len() - MAX_HISTORY;
+            self.entries.drain(..excess);
+        }
+    }
+
+    /// Returns the previous (older) entry matching the draft, if any.
+    /// `current` is the text being edited; it is saved as the draft on the first call.
+    pub fn older(&mut self, current: &str) -> Option<String> {
+        if self.position.is_none() {
+            self.draft = current.to_string();
+        }
+        let end = self.position.unwrap_or(self.entries.len());
+        let found = self.entries[..end]
+            .iter()
+            .rposition(|e| e.starts_with(&self.draft))?;
+        self.position = Some(found);
+        Some(self.entries[found].clone())
+    }
+
+    /// Returns the next (newer) entry matching the draft, or the draft itself
+    /// when moving past the newest entry. Returns None when not navigating.
+    pub fn newer(&mut self) -> Option<String> {
+        let start = self.position? + 1;
+        match self.entries[start..].iter().position(|e| e.starts_with(&self.draft)) {
+            Some(offset) => {
+                self.position = Some(start + offset);
+                Some(self.entries[start + offset].clone())
+            }
+            None => {
+                self.position = None;
+                Some(std::mem::take(&mut self.draft))
+            }
+        }
+    }
+
+    /// Ends history navigation.
+    pub fn reset(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+}
+
+/// Command and search histories.
+#[derive(Debug, Clone, Default)]
+pub struct InputHistory {
+    /// `:` command history
+    pub commands: History,
+    /// `/` and `?` search history
+    pub searches: History,
+}
+
+impl InputHistory {
+    /// Loads the history file. A missing or unreadable file gives an empty history.
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        let mut commands = Vec::new();
+        let mut searches = Vec::new();
+        for line in content.lines() {
+            if let Some(cmd) = line.strip_prefix(':') {
+                commands.push(cmd.to_string());
+            } else if let Some(pattern) = line.strip_prefix('/') {
+                searches.push(pattern.to_string());
+            }
+        }
+        Self {
+            commands: History::new(commands),
+            searches: History::new(searches),
+        }
+    }
+
+    /// Writes the history file, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut content = String::new();
+        for cmd in self.commands.entries() {
+            content.push(':');
+            content.push_str(cmd);
+            content.push('\n');
+        }
+        for pattern in self.searches.entries() {
+            content.push('/');
+            content.push_str(pattern);
+            content.push('\n');
+        }
+        std::fs::write(path, content)
+    }
+}
+
+/// Returns the path of the history file, if a home directory can be found.
+pub fn history_path() -> Option<PathBuf> {
+    let non_empty = |var: &str| std::env::var_os(var).filter(|v| !v.is_empty());
+
+    let dir = if let Some(xdg) = non_empty("XDG_STATE_HOME") {
+        PathBuf::from(xdg).join("seqtui")
+    } else if cfg!(windows) {
+        PathBuf::from(non_empty("LOCALAPPDATA")?).join("seqtui")
+    } else {
+        PathBuf::from(non_empty("HOME")?).join(".local").join("state").join("seqtui")
+    };
+    Some(dir.join("history"))
+}
+
+/// A completion candidate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// Text inserted in the command line
+    pub text: String,
+    /// Text shown in the candidate list
+    pub label: String,
+}
+
+impl Candidate {
+    fn plain(text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self {
+            label: text.clone(),
+            text,
+        }
+    }
+}
+
+/// An active tab completion (cycled with Tab / Shift+Tab).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// Byte offset in the input where the completed word starts
+    pub start: usize,
+    /// Text that was typed before completion started
+    pub original: String,
+    /// Candidates, in display order
+    pub candidates: Vec<Candidate>,
+    /// Index of the candidate currently inserted (None = original text)
+    pub selected: Option<usize>,
+}
+
+/// Computes completion candidates for a command line (without the leading `:`).
+///
+/// Returns the byte offset where the completed word starts and the candidates,
+/// or None if nothing can be completed.
+pub fn complete_command(input: &str) -> Option<(usize, Vec<Candidate>)> {
+    let Some(space) = input.find(' ') else {
+        let candidates: Vec<Candidate> = COMMAND_NAMES
+            .iter()
+            .filter(|name| name.starts_with(input))
+            .map(|name| Candidate::plain(*name))
+            .collect();
+        return (!candidates.is_empty()).then_some((0, candidates));
+    };
+
+    let command = &input[..space];
+    let arg_start = space + 1 + (input[space + 1..].len() - input[space + 1..].trim_start().len());
+    let arg = &input[arg_start..];
+
+    let candidates = match command {
+        "w" | "e" | "edit" | "colmap" | "sets" | "tree" => complete_path(arg),
+        "trim" => ["allgaps", "gappyout", "strict"]
+            .iter()
+            .filter(|name| name.starts_with(arg))
+            .map(|name| Candidate::plain(*name))
+            .collect(),
+        "setcode" => complete_genetic_code(arg),
+        "sort" | "sort!" => SortKey::NAMES
+            .iter()
+            .filter(|name| name.starts_with(arg))
+            .map(|name| Candidate::plain(*name))
+            .collect(),
+        _ => Vec::new(),
+    };
+    (!candidates.is_empty()).then_some((arg_start, candidates))
+}
+
+/// Completes a file path. Directories get a trailing separator.
+fn complete_path(partial: &str) -> Vec<Candidate> {
+    let (dir_part, name_prefix) = match partial.rfind('/') {
+        Some(idx) => (&partial[..=idx], &partial[idx + 1..]),
+        None => ("", partial),
+    };
+
+    let dir = if dir_part.is_empty() {
+        PathBuf::from(".")
+    } else if let Some(rest) = dir_part.strip_prefix("~/") {
+        match std::env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(rest),
+            None => PathBuf::from(dir_part),
+        }
+    } else {
+        PathBuf::from(dir_part)
+    };
+
+    let Ok(read_dir) = std::fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+
+    let mut matches: Vec<(String, bool)> = read_dir
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            // Hidden files only when explicitly asked for
+            if name.starts_with('.') && !name_prefix.starts_with('.') {
+                return None;
+            }
+            if !name.starts_with(name_prefix) {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            Some((name, is_dir))
+        })
+        .collect();
+    matches.sort_by_key(|(name, is_dir)| (!*is_dir, name.to_lowercase()));
+
+    matches
+        .into_iter()
+        .map(|(name, is_dir)| {
+            let label = if is_dir { format!("{}/", name) } else { name.clone() };
+            Candidate {
+                text: format!("{}{}", dir_part, label),
+                label,
+            }
+        })
+        .collect()
+}
+
+/// Completes a genetic code from its number or (part of) its name.
+/// Candidates are inserted as numbers; the label shows the name.
+fn complete_genetic_code(partial: &str) -> Vec<Candidate> {
+    let codes = GeneticCodes::new();
+    let lower = partial.to_lowercase();
+    let by_number = !partial.is_empty() && partial.chars().all(|c| c.is_ascii_digit());
+
+    codes
+        .all()
+        .iter()
+        .filter(|code| {
+            if by_number {
+                code.id.to_string().starts_with(partial)
+            } else {
+                code.name.to_lowercase().contains(&lower)
+            }
+        })
+        .map(|code| Candidate {
+            text: code.id.to_string(),
+            label: format!("{} {}", code.id, code.name),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(candidates: &[Candidate]) -> Vec<&str> {
+        candidates.iter().map(|c| c.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_history_navigation() {
+        let mut history = History::new(vec!["w a.fa".into(), "asAA".into(), "w b.fa".into()]);
+
+        assert_eq!(history.older("").as_deref(), Some("w b.fa"));
+        assert_eq!(history.older("w b.fa").as_deref(), Some("asAA"));
+        assert_eq!(history.older("asAA").as_deref(), Some("w a.fa"));
+        assert_eq!(history.older("w a.fa"), None, "No older entry");
+        assert_eq!(history.newer().as_deref(), Some("asAA"));
+        assert_eq!(history.newer().as_deref(), Some("w b.fa"));
+        assert_eq!(history.newer().as_deref(), Some(""), "Back to the draft");
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn test_history_prefix_filter() {
+        let mut history = History::new(vec!["w a.fa".into(), "asAA".into(), "w b.fa".into()]);
+        assert_eq!(history.older("w").as_deref(), Some("w b.fa"));
+        assert_eq!(history.older("w b.fa").as_deref(), Some("w a.fa"));
+        assert_eq!(history.newer().as_deref(), Some("w b.fa"));
+        assert_eq!(history.newer().as_deref(), Some("w"));
+    }
+
+    #[test]
+    fn test_history_deduplicates() {
+        let mut history = History::default();
+        history.push("asAA");
+        history.push("12");
+        history.push("asAA");
+        history.push("  ");
+        assert_eq!(history.entries(), &["12".to_string(), "asAA".to_string()]);
+    }
+
+    #[test]
+    fn test_history_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join("history");
+
+        let mut history = InputHistory::default();
+        history.commands.push("w out.fa");
+        history.searches.push("ACGT");
+        history.save(&path).unwrap();
+
+        let loaded = InputHistory::load(&path);
+        assert_eq!(loaded.commands.entries(), &["w out.fa".to_string()]);
+        assert_eq!(loaded.searches.entries(), &["ACGT".to_string()]);
+    }
+
+    #[test]
+    fn test_complete_command_names() {
+        let (start, candidates) = complete_command("as").unwrap();
+        assert_eq!(start, 0);
+        assert_eq!(texts(&candidates), vec!["asAA", "asNT"]);
+        assert!(complete_command("xyz").is_none());
+    }
+
+    #[test]
+    fn test_complete_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("gene1.fasta"), ">a\nA\n").unwrap();
+        std::fs::write(dir.path().join("gene2.fasta"), ">a\nA\n").unwrap();
+        std::fs::create_dir(dir.path().join("genes")).unwrap();
+
+        let prefix = format!("{}/gene", dir.path().display());
+        let (start, candidates) = complete_command(&format!("e {}", prefix)).unwrap();
+        assert_eq!(start, 2);
+        // Directories first
+        assert_eq!(
+            texts(&candidates),
+            vec![
+                format!("{}s/", prefix),
+                format!("{}1.fasta", prefix),
+                format!("{}2.fasta", prefix)
+            ]
+        );
+        assert_eq!(candidates[0].label, "genes/");
+    }
+
+    #[test]
+    fn test_complete_genetic_codes() {
+        let (start, candidates) = complete_command("setcode 2").unwrap();
+        assert_eq!(start, 8);
+        assert_eq!(texts(&candidates), vec!["2", "21", "22", "23", "24", "25", "26", "27", "28", "29"]);
+
+        // Names match anywhere, case-insensitively
+        let (_, candidates) = complete_command("setcode vertebrate").unwrap();
+        assert_eq!(texts(&candidates), vec!["2", "5"]);
+        assert_eq!(candidates[0].label, "2 Vertebrate Mitochondrial");
+    }
+}
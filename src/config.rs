@@ -0,0 +1,566 @@
+//! Persistent user configuration.
+//!
+//! Default settings are read from TOML files so they survive between sessions.
+//! Lookup order (later entries override earlier ones):
+//! 1. Global file: `$XDG_CONFIG_HOME/seqtui/config.toml`
+//!    (`~/.config/seqtui/config.toml`, or `%APPDATA%\seqtui\config.toml` on Windows)
+//! 2. Project file: `.seqtui.toml` in the working directory
+//! 3. Command-line flags (merged in `main.rs`, they always win)
+//!
+//! ## Example
+//!
+//! ```toml
+//! genetic_code = 2        # NCBI genetic code (1-33)
+//! reading_frame = 1       # 1-3
+//! fancy = true            # Unicode glyphs in the TUI
+//! color_scheme = "mono"   # "seaview" (default) or "mono"
+//! name_width = 30         # Width of the sequence names panel
+//! gap_char = "?"          # Default fill character for -s/--supermatrix
+//! keymap = "emacs"        # Key binding preset: "vim" (default) or "emacs"
+//!
+//! [keys]                  # Extra normal-mode bindings (see keymap.rs)
+//! "<C-e>" = "goto_last_column"
+//! "L" = "half_page_right"
+//! "zH" = "nop"            # Remove a binding
+//! ```
+//!
+//! Only the small TOML subset needed here is understood: `[sections]`,
+//! `key = value` pairs, strings, integers, booleans and `#` comments
+//! (documented in the README "Configuration File" section). Arrays, inline
+//! tables, dotted keys, multi-line strings, floats and dates are rejected.
+
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::event::Action;
+use crate::genetic_code::GeneticCodes;
+use crate::keymap::{self, KeyChord, Keymap, KeymapPreset};
+use crate::model::Palette;
+
+/// File name of the per-project configuration file.
+pub const PROJECT_CONFIG_FILE: &str = ".seqtui.toml";
+
+/// Smallest accepted width for the names panel (border included).
+const MIN_NAME_WIDTH: u16 = 8;
+/// Largest accepted width for the names panel (border included).
+const MAX_NAME_WIDTH: u16 = 120;
+
+/// Errors that can occur while reading a configuration file.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Config file {path}, line {line}: {message}")]
+    Invalid {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+/// Result type for configuration operations.
+pub type ConfigResult<T> = Result<T, ConfigError>;
+
+/// A value of the supported TOML subset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigValue {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+}
+
+impl ConfigValue {
+    /// Returns a short type name for error messages.
+    fn type_name(&self) -> &'static str {
+        match self {
+            ConfigValue::String(_) => "string",
+            ConfigValue::Integer(_) => "integer",
+            ConfigValue::Boolean(_) => "boolean",
+        }
+    }
+}
+
+/// A single `key = value` line, with the section it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigEntry {
+    /// Section name (empty for top-level keys)
+    pub section: String,
+    /// Key (unquoted)
+    pub key: String,
+    /// Parsed value
+    pub value: ConfigValue,
+    /// 1-based line number (for error messages)
+    pub line: usize,
+}
+
+/// User settings. `None` means "not set", so layers can be merged.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    /// Default genetic code (1-33)
+    pub genetic_code: Option<u8>,
+    /// Default reading frame (1-3)
+    pub reading_frame: Option<u8>,
+    /// Use fancy Unicode glyphs in the TUI
+    pub fancy: Option<bool>,
+    /// Colour palette for residues
+    pub color_scheme: Option<Palette>,
+    /// Width of the sequence names panel
+    pub name_width: Option<u16>,
+    /// Default fill character for -s/--supermatrix
+    pub gap_char: Option<char>,
+    /// Key binding preset
+    pub keymap: Option<KeymapPreset>,
+    /// Extra key bindings from `[keys]`, applied in order on top of the preset
+    pub key_bindings: Vec<(Vec<KeyChord>, Action)>,
+}
+
+impl Config {
+    /// Loads the global config file, then the project file from the working directory.
+    /// Missing files are silently ignored; malformed ones are reported.
+    pub fn load() -> ConfigResult<Self> {
+        let mut config = Config::default();
+        if let Some(path) = global_config_path() {
+            if let Some(global) = Self::load_file(&path)? {
+                config.merge(global);
+            }
+        }
+        if let Some(project) = Self::load_file(Path::new(PROJECT_CONFIG_FILE))? {
+            config.merge(project);
+        }
+        Ok(config)
+    }
+
+    /// Loads a single config file. Returns `Ok(None)` if the file does not exist.
+    pub fn load_file(path: &Path) -> ConfigResult<Option<Self>> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content, path).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(source) => Err(ConfigError::Io {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    /// Parses config content. `path` is only used for error messages.
+    pub fn parse(content: &str, path: &Path) -> ConfigResult<Self> {
+        let mut config = Config::default();
+        for entry in parse_entries(content, path)? {
+            config.apply_entry(&entry, path)?;
+        }
+        Ok(config)
+    }
+
+    /// Overrides the settings of `self` with those set in `other`.
+    pub fn merge(&mut self, other: Config) {
+        if other.genetic_code.is_some() {
+            self.genetic_code = other.genetic_code;
+        }
+        if other.reading_frame.is_some() {
+            self.reading_frame = other.reading_frame;
+        }
+        if other.fancy.is_some() {
+            self.fancy = other.fancy;
+        }
+        if other.color_scheme.is_some() {
+            self.color_scheme = other.color_scheme;
+        }
+        if other.name_width.is_some() {
+            self.name_width = other.name_width;
+        }
+        if other.gap_char.is_some() {
+            self.gap_char = other.gap_char;
+        }
+        if other.keymap.is_some() {
+            self.keymap = other.keymap;
+        }
+        self.key_bindings.extend(other.key_bindings);
+    }
+
+    /// Builds the normal-mode keymap: preset first, then `[keys]` bindings.
+    pub fn build_keymap(&self) -> Keymap {
+        let mut keymap = Keymap::preset(self.keymap.unwrap_or_default());
+        for (keys, action) in &self.key_bindings {
+            keymap.bind(keys.clone(), action.clone());
+        }
+        keymap
+    }
+
+    /// Validates and stores one entry.
+    fn apply_entry(&mut self, entry: &ConfigEntry, path: &Path) -> ConfigResult<()> {
+        let invalid = |message: String| ConfigError::Invalid {
+            path: path.to_path_buf(),
+            line: entry.line,
+            message,
+        };
+
+        match entry.section.as_str() {
+            "" => {}
+            "keys" => {
+                let keys = keymap::parse_key_sequence(&entry.key).map_err(&invalid)?;
+                let name = expect_string(entry).map_err(&invalid)?;
+                let action = keymap::action_from_name(name).ok_or_else(|| {
+                    invalid(format!(
+                        "unknown action '{}' (expected one of: {})",
+                        name,
+                        keymap::action_names().collect::<Vec<_>>().join(", ")
+                    ))
+                })?;
+                self.key_bindings.push((keys, action));
+                return Ok(());
+            }
+            other => return Err(invalid(format!("unknown section [{}]", other))),
+        }
+
+        match entry.key.as_str() {
+            "genetic_code" => {
+                let id = expect_integer(entry).map_err(&invalid)?;
+                let codes = GeneticCodes::new();
+                let id = u8::try_from(id)
+                    .ok()
+                    .filter(|id| codes.get(*id).is_some())
+                    .ok_or_else(|| invalid(format!("unknown genetic code {}", id)))?;
+                self.genetic_code = Some(id);
+            }
+            "reading_frame" => {
+                let frame = expect_integer(entry).map_err(&invalid)?;
+                if !(1..=3).contains(&frame) {
+                    return Err(invalid(format!("reading_frame must be 1-3 (got {})", frame)));
+                }
+                self.reading_frame = Some(frame as u8);
+            }
+            "fancy" => match &entry.value {
+                ConfigValue::Boolean(b) => self.fancy = Some(*b),
+                other => {
+                    return Err(invalid(format!("fancy must be a boolean (got {})", other.type_name())))
+                }
+            },
+            "color_scheme" | "colour_scheme" => {
+                let name = expect_string(entry).map_err(&invalid)?;
+                let palette = Palette::from_name(name).ok_or_else(|| {
+                    invalid(format!(
+                        "unknown color_scheme '{}' (expected one of: {})",
+                        name,
+                        Palette::all_names().join(", ")
+                    ))
+                })?;
+                self.color_scheme = Some(palette);
+            }
+            "name_width" => {
+                let width = expect_integer(entry).map_err(&invalid)?;
+                if width < MIN_NAME_WIDTH as i64 || width > MAX_NAME_WIDTH as i64 {
+                    return Err(invalid(format!(
+                        "name_width must be {}-{} (got {})",
+                        MIN_NAME_WIDTH, MAX_NAME_WIDTH, width
+                    )));
+                }
+                self.name_width = Some(width as u16);
+            }
+            "gap_char" => {
+                let s = expect_string(entry).map_err(&invalid)?;
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if c.is_ascii() => self.gap_char = Some(c),
+                    _ => {
+                        return Err(invalid(format!(
+                            "gap_char must be a single ASCII character (got '{}')",
+                            s
+                        )))
+                    }
+                }
+            }
+            "keymap" => {
+                let name = expect_string(entry).map_err(&invalid)?;
+                let preset = KeymapPreset::from_name(name).ok_or_else(|| {
+                    invalid(format!("unknown keymap '{}' (expected vim or emacs)", name))
+                })?;
+                self.keymap = Some(preset);
+            }
+            other => return Err(invalid(format!("unknown setting '{}'", other))),
+        }
+        Ok(())
+    }
+}
+
+/// Returns the integer value of an entry, or an error message.
+fn expect_integer(entry: &ConfigEntry) -> Result<i64, String> {
+    match &entry.value {
+        ConfigValue::Integer(n) => Ok(*n),
+        other => Err(format!("{} must be an integer (got {})", entry.key, other.type_name())),
+    }
+}
+
+/// Returns the string value of an entry, or an error message.
+fn expect_string(entry: &ConfigEntry) -> Result<&str, String> {
+    match &entry.value {
+        ConfigValue::String(s) => Ok(s),
+        other => Err(format!("{} must be a string (got {})", entry.key, other.type_name())),
+    }
+}
+
+/// Returns the path of the global config file, if a home directory can be found.
+pub fn global_config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+/// Returns the seqtui configuration directory (not necessarily existing).
+pub fn config_dir() -> Option<PathBuf> {
+    let non_empty = |var: &str| std::env::var_os(var).filter(|v| !v.is_empty());
+
+    if let Some(xdg) = non_empty("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(xdg).join("seqtui"));
+    }
+    if cfg!(windows) {
+        if let Some(appdata) = non_empty("APPDATA") {
+            return Some(PathBuf::from(appdata).join("seqtui"));
+        }
+    }
+    non_empty("HOME").map(|home| PathBuf::from(home).join(".config").join("seqtui"))
+}
+
+/// Parses the TOML subset into a flat list of entries.
+pub fn parse_entries(content: &str, path: &Path) -> ConfigResult<Vec<ConfigEntry>> {
+    let mut entries = Vec::new();
+    let mut section = String::new();
+
+    for (idx, raw_line) in content.lines().enumerate() {
+        let line_number = idx + 1;
+        let invalid = |message: String| ConfigError::Invalid {
+            path: path.to_path_buf(),
+            line: line_number,
+            message,
+        };
+
+        let line = strip_comment(raw_line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        // Section header: [name]
+        if let Some(rest) = line.strip_prefix('[') {
+            let name = rest
+                .strip_suffix(']')
+                .ok_or_else(|| invalid("unterminated section header".to_string()))?;
+            section = name.trim().to_string();
+            continue;
+        }
+
+        // key = value
+        let (key, rest) = parse_key(line).map_err(&invalid)?;
+        let rest = rest.trim_start();
+        let rest = rest
+            .strip_prefix('=')
+            .ok_or_else(|| invalid(format!("expected '=' after key '{}'", key)))?;
+        let value = parse_value(rest.trim()).map_err(&invalid)?;
+
+        entries.push(ConfigEntry {
+            section: section.clone(),
+            key,
+            value,
+            line: line_number,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Removes a trailing `#` comment, ignoring `#` inside quoted strings.
+fn strip_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' && q == '"' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '#' => return &line[..i],
+                _ => {}
+            },
+        }
+    }
+    line
+}
+
+/// Parses a bare or quoted key. Returns the key and the remaining input.
+fn parse_key(line: &str) -> Result<(String, &str), String> {
+    if line.starts_with('"') || line.starts_with('\'') {
+        let (key, consumed) = parse_quoted(line)?;
+        return Ok((key, &line[consumed..]));
+    }
+    let end = line
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+        .unwrap_or(line.len());
+    if end == 0 {
+        return Err(format!("expected a key, found '{}'", line));
+    }
+    Ok((line[..end].to_string(), &line[end..]))
+}
+
+/// Parses a value: quoted string, integer or boolean.
+fn parse_value(text: &str) -> Result<ConfigValue, String> {
+    if text.is_empty() {
+        return Err("missing value".to_string());
+    }
+    if text.starts_with('"') || text.starts_with('\'') {
+        let (s, consumed) = parse_quoted(text)?;
+        if !text[consumed..].trim().is_empty() {
+            return Err(format!("unexpected text after string: '{}'", text[consumed..].trim()));
+        }
+        return Ok(ConfigValue::String(s));
+    }
+    match text {
+        "true" => return Ok(ConfigValue::Boolean(true)),
+        "false" => return Ok(ConfigValue::Boolean(false)),
+        _ => {}
+    }
+    text.replace('_', "")
+        .parse::<i64>()
+        .map(ConfigValue::Integer)
+        .map_err(|_| format!("invalid value '{}' (strings must be quoted)", text))
+}
+
+/// Parses a `"basic"` or `'literal'` string at the start of `text`.
+/// Returns the unescaped string and the number of bytes consumed.
+fn parse_quoted(text: &str) -> Result<(String, usize), String> {
+    let mut chars = text.char_indices();
+    let quote = match chars.next() {
+        Some((_, q @ ('"' | '\''))) => q,
+        _ => return Err("expected a quoted string".to_string()),
+    };
+
+    let mut result = String::new();
+    let mut escaped = false;
+    for (i, c) in chars {
+        if escaped {
+            result.push(match c {
+                'n' => '\n',
+                't' => '\t',
+                other => other,
+            });
+            escaped = false;
+        } else if c == '\\' && quote == '"' {
+            escaped = true;
+        } else if c == quote {
+            return Ok((result, i + 1));
+        } else {
+            result.push(c);
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> ConfigResult<Config> {
+        Config::parse(content, Path::new("test.toml"))
+    }
+
+    #[test]
+    fn test_parse_all_settings() {
+        let config = parse(
+            r#"
+# SeqTUI defaults
+genetic_code = 2
+reading_frame = 3   # trailing comment
+fancy = true
+color_scheme = "mono"
+name_width = 30
+gap_char = '?'
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.genetic_code, Some(2));
+        assert_eq!(config.reading_frame, Some(3));
+        assert_eq!(config.fancy, Some(true));
+        assert_eq!(config.color_scheme, Some(Palette::Mono));
+        assert_eq!(config.name_width, Some(30));
+        assert_eq!(config.gap_char, Some('?'));
+    }
+
+    #[test]
+    fn test_empty_config() {
+        assert_eq!(parse("\n# nothing here\n").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_merge_overrides_only_set_values() {
+        let mut global = parse("genetic_code = 2\nfancy = true\n").unwrap();
+        let project = parse("genetic_code = 5\n").unwrap();
+        global.merge(project);
+        assert_eq!(global.genetic_code, Some(5));
+        assert_eq!(global.fancy, Some(true));
+    }
+
+    #[test]
+    fn test_invalid_values_report_line() {
+        let err = parse("fancy = true\nreading_frame = 4\n").unwrap_err().to_string();
+        assert!(err.contains("line 2"), "Error should mention the line: {}", err);
+        assert!(err.contains("reading_frame"));
+
+        assert!(parse("genetic_code = 7\n").is_err(), "Code 7 does not exist");
+        assert!(parse("gap_char = \"--\"\n").is_err());
+        assert!(parse("color_scheme = rainbow\n").is_err(), "Strings must be quoted");
+        assert!(parse("fancy = \"yes\"\n").is_err());
+    }
+
+    #[test]
+    fn test_unknown_setting_rejected() {
+        let err = parse("genetic_cod = 2\n").unwrap_err().to_string();
+        assert!(err.contains("unknown setting 'genetic_cod'"));
+    }
+
+    #[test]
+    fn test_strip_comment_keeps_hash_in_strings() {
+        assert_eq!(strip_comment("gap_char = \"#\" # comment"), "gap_char = \"#\" ");
+        let config = parse("gap_char = \"#\"\n").unwrap();
+        assert_eq!(config.gap_char, Some('#'));
+    }
+
+    #[test]
+    fn test_keys_section() {
+        let config = parse(
+            r#"
+keymap = "emacs"
+
+[keys]
+"<C-e>" = "goto_middle_visible_column"
+L = "half_page_right"
+zH = "nop"
+"#,
+        )
+        .unwrap();
+        let keymap = config.build_keymap();
+        assert_eq!(
+            keymap.get(&[KeyChord::ctrl('e')]),
+            Some(&Action::GotoMiddleVisibleColumn)
+        );
+        assert_eq!(keymap.get(&[KeyChord::char('L')]), Some(&Action::HalfPageRight));
+        assert_eq!(keymap.get(&keymap::parse_key_sequence("zH").unwrap()), None);
+        // Emacs preset still applies to other keys
+        assert_eq!(keymap.get(&[KeyChord::ctrl('n')]), Some(&Action::MoveDown));
+
+        assert!(parse("[keys]\nx = \"fly\"\n").is_err());
+        assert!(parse("[colors]\nx = 1\n").is_err());
+    }
+
+    #[test]
+    fn test_missing_file_is_not_an_error() {
+        let result = Config::load_file(Path::new("/nonexistent/seqtui/config.toml"));
+        assert!(matches!(result, Ok(None)));
+    }
+}
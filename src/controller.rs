@@ -25,8 +25,10 @@ use crossterm::{
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 
-use crate::event::{apply_action, handle_event, poll_event, ActionResult};
-use crate::formats::{parse_file_with_options, FileFormat};
+use crate::cmdline::{self, InputHistory};
+use crate::config::Config;
+use crate::event::{apply_action, handle_event, poll_event, ActionResult, KeyContext};
+use crate::formats::{parse_file_with_block, FileFormat};
 use crate::genetic_code::GeneticCodes;
 use crate::model::{Alignment, AppState, LoadingState, Sequence, SequenceType};
 use crate::ui::{calculate_visible_dimensions, render};
@@ -61,11 +63,21 @@ pub struct App {
     load_receiver: Option<Receiver<LoadMessage>>,
     /// Receiver for background translation messages
     translate_receiver: Option<Receiver<TranslateMessage>>,
+    /// File where command/search history is persisted
+    history_path: Option<PathBuf>,
+    /// Newick tree to show once the alignment is loaded (`--tree`)
+    tree_path: Option<PathBuf>,
+    /// File shown, reloaded to switch NEXUS character blocks (`:block`)
+    file_path: Option<PathBuf>,
+    /// Rows of annotation tracks above the alignment when the viewport was sized
+    annotation_rows: usize,
 }
 
 impl App {
     /// Creates a new application with the given state.
-    pub fn new(state: AppState) -> Result<Self> {
+    pub fn new(mut state: AppState) -> Result<Self> {
+        let history_path = load_history(&mut state);
+
         // Setup terminal
         enable_raw_mode()?;
         let mut stdout = io::stdout();
@@ -79,16 +91,24 @@ impl App {
             tick_rate: Duration::from_millis(50),
             load_receiver: None,
             translate_receiver: None,
+            history_path,
+            tree_path: None,
+            file_path: None,
+            annotation_rows: 0,
         })
     }
 
     /// Creates a new application and starts loading a file in the background.
     /// Optional `preset_translation` is (genetic_code_id, reading_frame) to preset translation settings.
+    /// Optional `block` is the NEXUS character block to show (`--block`).
+    /// Optional `tree_path` is a Newick tree shown next to the alignment.
     pub fn new_with_background_load(
         file_path: PathBuf,
         forced_format: Option<FileFormat>,
+        block: Option<String>,
         preset_translation: Option<(u8, u8)>,
-        fancy_ui: bool,
+        tree_path: Option<PathBuf>,
+        config: &Config,
     ) -> Result<Self> {
         // Extract file name for display
         let file_name = file_path
@@ -99,20 +119,12 @@ impl App {
 
         // Create initial state in loading mode
         let mut state = AppState::new_loading(file_name, file_path.clone());
-        state.fancy_ui = fancy_ui;
+        apply_config(&mut state, config);
+        let history_path = load_history(&mut state);
         
         // Apply preset translation settings if provided
         if let Some((genetic_code, reading_frame)) = preset_translation {
-            // Find the index of the genetic code in the list
-            let codes = GeneticCodes::new();
-            let code_index = codes.all().iter().position(|c| c.id == genetic_code).unwrap_or(0);
-            
-            state.translation_settings.genetic_code_id = genetic_code;
-            state.translation_settings.selected_code_index = code_index;
-            // Convert from 1-based to 0-based frame
-            let frame = (reading_frame.saturating_sub(1) as usize).min(2);
-            state.translation_settings.frame = frame;
-            state.translation_settings.selected_frame = frame;
+            state.translation_settings.preset(genetic_code, reading_frame);
             state.translation_settings.has_translated = true; // Skip the settings dialog
         }
 
@@ -128,8 +140,9 @@ impl App {
 
         // Spawn background thread for loading
         let path_for_error = file_path.clone();
+        let shown_path = file_path.clone();
         thread::spawn(move || {
-            match parse_file_with_options(&file_path, forced_format) {
+            match parse_file_with_block(&file_path, forced_format, block.as_deref()) {
                 Ok(alignment) => {
                     let _ = tx.send(LoadMessage::Complete(alignment));
                 }
@@ -148,6 +161,10 @@ impl App {
             tick_rate: Duration::from_millis(50),
             load_receiver: Some(rx),
             translate_receiver: None,
+            history_path,
+            tree_path,
+            file_path: Some(shown_path),
+            annotation_rows: 0,
         })
     }
 
@@ -220,6 +237,12 @@ impl App {
                     Ok(LoadMessage::Complete(alignment)) => {
                         self.state.set_alignment(alignment);
                         self.load_receiver = None; // Done loading
+                        if let Some(path) = self.tree_path.take() {
+                            if let Err(e) = self.state.load_tree(&path) {
+                                self.state.error_popup = Some(e);
+                            }
+                            self.update_viewport_size()?;
+                        }
                     }
                     Ok(LoadMessage::Error { message, path }) => {
                         self.state.set_loading_error(message, Some(path));
@@ -272,6 +295,11 @@ impl App {
                 self.state.tick_spinner();
             }
 
+            // Annotation tracks (Stockholm) take rows from the sequence panel
+            if self.state.annotation_tracks().len() != self.annotation_rows {
+                self.update_viewport_size()?;
+            }
+
             // Render
             self.terminal.draw(|frame| {
                 render(frame, &self.state);
@@ -279,34 +307,33 @@ impl App {
 
             // Handle events
             if let Some(event) = poll_event(self.tick_rate) {
-                let has_number_prefix = !self.state.number_buffer.is_empty();
-                let has_error_popup = self.state.error_popup.is_some();
-                let has_file_browser = self.state.file_browser.is_some();
-                let action = handle_event(
-                    event, 
-                    &self.state.mode, 
-                    self.state.show_help, 
-                    self.state.pending_g, 
-                    self.state.pending_z, 
-                    has_number_prefix,
-                    has_error_popup,
-                    has_file_browser,
-                );
+                let action = handle_event(event, &KeyContext::from_state(&self.state));
 
                 // Handle resize specially to update viewport
                 if let crate::event::Action::Resize(_, _) = action {
                     self.update_viewport_size()?;
                 }
 
+                let left_width = self.state.left_panels_width();
                 let result = apply_action(&mut self.state, action);
 
+                // Opening or closing the tree panel changes the sequence panel width
+                if self.state.left_panels_width() != left_width {
+                    self.update_viewport_size()?;
+                }
+
                 // Check if translation should be started
                 match result {
                     ActionResult::StartTranslation => {
                         self.start_background_translation();
                     }
                     ActionResult::LoadFile(path) => {
-                        self.start_background_load(path);
+                        self.start_background_load(path, None);
+                    }
+                    ActionResult::LoadBlock(block) => {
+                        if let Some(path) = self.file_path.clone() {
+                            self.start_background_load(path, Some(block));
+                        }
                     }
                     ActionResult::Continue => {}
                 }
@@ -317,11 +344,16 @@ impl App {
             }
         }
 
+        // Persist command/search history (not worth failing the exit for)
+        if let Some(path) = &self.history_path {
+            let _ = self.state.history.save(path);
+        }
+
         Ok(())
     }
 
-    /// Starts a new background load for the given file path.
-    fn start_background_load(&mut self, file_path: PathBuf) {
+    /// Starts a new background load for the given file path (and NEXUS block).
+    fn start_background_load(&mut self, file_path: PathBuf, block: Option<String>) {
         // Close file browser
         self.state.close_file_browser();
         
@@ -330,6 +362,11 @@ impl App {
             .and_then(|n| n.to_str())
             .unwrap_or("Unknown")
             .to_string();
+        self.state.file_name = file_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("alignment")
+            .to_string();
         
         // Reset state for loading
         self.state.loading_state = LoadingState::LoadingFile {
@@ -344,10 +381,11 @@ impl App {
         
         // Store the path for error handling
         let path_for_error = file_path.clone();
+        self.file_path = Some(file_path.clone());
         
         // Spawn background thread for loading
         thread::spawn(move || {
-            match parse_file_with_options(&file_path, None) {
+            match parse_file_with_block(&file_path, None, block.as_deref()) {
                 Ok(alignment) => {
                     let _ = tx.send(LoadMessage::Complete(alignment));
                 }
@@ -366,8 +404,9 @@ impl App {
     /// Updates the viewport size based on terminal dimensions.
     fn update_viewport_size(&mut self) -> Result<()> {
         let size = self.terminal.size()?;
-        let (visible_rows, visible_cols) = calculate_visible_dimensions(size.width, size.height);
-        self.state.update_viewport_size(visible_rows, visible_cols);
+        let (visible_rows, visible_cols) = calculate_visible_dimensions(size.width, size.height, self.state.left_panels_width());
+        self.annotation_rows = self.state.annotation_tracks().len();
+        self.state.update_viewport_size(visible_rows.saturating_sub(self.annotation_rows).max(1), visible_cols);
         Ok(())
     }
 }
@@ -381,6 +420,26 @@ impl Drop for App {
     }
 }
 
+/// Loads the persisted command/search history into the state.
+/// Returns the history file path, to save the history on exit.
+fn load_history(state: &mut AppState) -> Option<PathBuf> {
+    let path = cmdline::history_path()?;
+    state.history = InputHistory::load(&path);
+    Some(path)
+}
+
+/// Applies the display settings, key bindings and translation defaults (shown
+/// in the settings dialog) of a user configuration to the state.
+fn apply_config(state: &mut AppState, config: &Config) {
+    state.translation_settings.preset(config.genetic_code.unwrap_or(1), config.reading_frame.unwrap_or(1));
+    state.fancy_ui = config.fancy.unwrap_or(false);
+    state.palette = config.color_scheme.unwrap_or_default();
+    if let Some(width) = config.name_width {
+        state.name_width = width;
+    }
+    state.keymap = config.build_keymap();
+}
+
 /// Convenience function to run the application with an alignment file.
 pub fn run_app(state: AppState) -> Result<()> {
     let mut app = App::new(state)?;
@@ -389,12 +448,12 @@ pub fn run_app(state: AppState) -> Result<()> {
 
 /// Convenience function to run the application with the file browser open.
 /// Used when no file is provided on command line.
-pub fn run_app_with_file_browser(fancy_ui: bool) -> Result<()> {
+pub fn run_app_with_file_browser(config: &Config) -> Result<()> {
     use crate::model::{Alignment, FileBrowserState};
     
     let start_dir = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."));
     let mut state = AppState::new(Alignment::new(vec![]), "No file".to_string());
-    state.fancy_ui = fancy_ui;
+    apply_config(&mut state, config);
     state.file_browser = Some(FileBrowserState::new(start_dir, "Select a sequence file".to_string()));
     
     let mut app = App::new(state)?;
@@ -402,7 +461,7 @@ pub fn run_app_with_file_browser(fancy_ui: bool) -> Result<()> {
 }
 
 /// Convenience function to run the application with the file browser open in a given directory.
-pub fn run_app_with_file_browser_at(start_dir: PathBuf, fancy_ui: bool) -> Result<()> {
+pub fn run_app_with_file_browser_at(start_dir: PathBuf, config: &Config) -> Result<()> {
     use crate::model::{Alignment, FileBrowserState};
 
     let start_dir = if start_dir.is_dir() {
@@ -411,7 +470,7 @@ pub fn run_app_with_file_browser_at(start_dir: PathBuf, fancy_ui: bool) -> Resul
         std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."))
     };
     let mut state = AppState::new(Alignment::new(vec![]), "No file".to_string());
-    state.fancy_ui = fancy_ui;
+    apply_config(&mut state, config);
     state.file_browser = Some(FileBrowserState::new(start_dir, "Select a sequence file".to_string()));
 
     let mut app = App::new(state)?;
@@ -420,13 +479,17 @@ pub fn run_app_with_file_browser_at(start_dir: PathBuf, fancy_ui: bool) -> Resul
 
 /// Convenience function to run the application with background loading.
 /// Optional `preset_translation` is (genetic_code_id, reading_frame) to preset translation settings.
+/// Optional `block` is the NEXUS character block to show.
+/// Optional `tree_path` is a Newick tree shown next to the alignment.
 pub fn run_app_with_loading(
     file_path: PathBuf,
     forced_format: Option<FileFormat>,
+    block: Option<String>,
     preset_translation: Option<(u8, u8)>,
-    fancy_ui: bool,
+    tree_path: Option<PathBuf>,
+    config: &Config,
 ) -> Result<()> {
-    let mut app = App::new_with_background_load(file_path, forced_format, preset_translation, fancy_ui)?;
+    let mut app = App::new_with_background_load(file_path, forced_format, block, preset_translation, tree_path, config)?;
     app.run()
 }
 
@@ -448,4 +511,17 @@ mod tests {
         assert_eq!(state.alignment.sequence_count(), 2);
         assert!(!state.should_quit);
     }
+
+    #[test]
+    fn test_config_sets_translation_defaults() {
+        let mut state = AppState::new(Alignment::new(vec![Sequence::new("seq1", "ATGAAA")]), "test".to_string());
+        let config = Config { genetic_code: Some(2), reading_frame: Some(3), ..Default::default() };
+        apply_config(&mut state, &config);
+
+        // Defaults of the settings dialog, which is still shown on first use
+        let settings = &state.translation_settings;
+        assert_eq!((settings.genetic_code_id, settings.frame, settings.selected_frame), (2, 2, 2));
+        assert_eq!(settings.selected_code_index, 1);
+        assert!(!settings.has_translated);
+    }
 }
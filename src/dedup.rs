@@ -0,0 +1,172 @@
+//! Identical sequence detection (`--collapse-duplicates`).
+//!
+//! Sequences are compared case-insensitively, optionally ignoring gaps and
+//! treating missing data (`N` or `X`, and `?`) as matching any residue. The first
+//! sequence of each group (in file order) is kept as its representative.
+//!
+//! With wildcards, identity is not transitive (`AN` matches `AC` and `AG`), so
+//! each sequence joins the first group whose representative it matches.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+use crate::model::{is_gap, Alignment};
+
+/// How sequences are compared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DuplicateOptions {
+    /// Compare ungapped sequences
+    pub ignore_gaps: bool,
+    /// `N` (nucleotides) or `X` (amino acids) and `?` match any residue
+    pub wildcards: bool,
+}
+
+/// A representative sequence and the sequences identical to it (indices).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    pub representative: usize,
+    pub duplicates: Vec<usize>,
+}
+
+/// Groups identical sequences. Only groups with at least one duplicate are
+/// returned, ordered by representative.
+pub fn find_duplicates(alignment: &Alignment, options: DuplicateOptions) -> Vec<DuplicateGroup> {
+    let wildcard = if alignment.sequence_type.is_nucleotide() { b'N' } else { b'X' };
+    let normalized: Vec<Vec<u8>> = alignment
+        .sequences
+        .iter()
+        .map(|seq| {
+            seq.as_bytes()
+                .iter()
+                .filter(|&&b| !(options.ignore_gaps && is_gap(b)))
+                .map(|b| match b.to_ascii_uppercase() {
+                    b'?' if options.wildcards => wildcard,
+                    b => b,
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    if options.wildcards {
+        let matches = |a: &[u8], b: &[u8]| {
+            a.len() == b.len()
+                && a.iter().zip(b).all(|(&x, &y)| x == y || x == wildcard || y == wildcard)
+        };
+        for (i, seq) in normalized.iter().enumerate() {
+            match groups.iter_mut().find(|g| matches(&normalized[g.representative], seq)) {
+                Some(group) => group.duplicates.push(i),
+                None => groups.push(DuplicateGroup { representative: i, duplicates: Vec::new() }),
+            }
+        }
+    } else {
+        let mut first: HashMap<&[u8], usize> = HashMap::new();
+        for (i, seq) in normalized.iter().enumerate() {
+            match first.get(seq.as_slice()) {
+                Some(&g) => groups[g].duplicates.push(i),
+                None => {
+                    first.insert(seq, groups.len());
+                    groups.push(DuplicateGroup { representative: i, duplicates: Vec::new() });
+                }
+            }
+        }
+    }
+    groups.retain(|g| !g.duplicates.is_empty());
+    groups
+}
+
+/// Removes the duplicates of each group, keeping representatives in place.
+pub fn collapse(alignment: Alignment, groups: &[DuplicateGroup]) -> Alignment {
+    let mut drop = vec![false; alignment.sequence_count()];
+    for &i in groups.iter().flat_map(|g| &g.duplicates) {
+        drop[i] = true;
+    }
+    let sequence_type = alignment.sequence_type;
+    let sequences: Vec<_> = alignment
+        .sequences
+        .into_iter()
+        .zip(drop)
+        .filter(|(_, d)| !d)
+        .map(|(seq, _)| seq)
+        .collect();
+    let kept: HashSet<&str> = sequences.iter().map(|seq| seq.id.as_str()).collect();
+    let annotations = alignment.annotations.select_sequences(|name| kept.contains(name).then(|| name.to_string()));
+    let mut collapsed = Alignment::new(sequences);
+    collapsed.sequence_type = sequence_type;
+    collapsed.annotations = Box::new(annotations);
+    collapsed
+}
+
+/// Writes the groups as TSV: representative, group size and collapsed IDs
+/// (comma-separated).
+pub fn write_tsv(path: &Path, alignment: &Alignment, groups: &[DuplicateGroup], header: &[String]) -> std::io::Result<()> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    writeln!(file, "# SeqTUI - Identical sequences collapsed into a representative")?;
+    for line in header {
+        writeln!(file, "# {}", line)?;
+    }
+    writeln!(file, "representative\tcount\tcollapsed")?;
+    for group in groups {
+        let ids: Vec<&str> = group.duplicates.iter().map(|&i| alignment.sequences[i].id.as_str()).collect();
+        writeln!(
+            file,
+            "{}\t{}\t{}",
+            alignment.sequences[group.representative].id,
+            group.duplicates.len() + 1,
+            ids.join(",")
+        )?;
+    }
+    file.flush()
+}
+
+/// Sequence names occurring more than once, with their number of occurrences
+/// (in order of first occurrence).
+pub fn duplicate_names(alignment: &Alignment) -> Vec<(&str, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut order = Vec::new();
+    for seq in &alignment.sequences {
+        let count = counts.entry(seq.id.as_str()).or_insert(0);
+        if *count == 0 {
+            order.push(seq.id.as_str());
+        }
+        *count += 1;
+    }
+    order.into_iter().map(|id| (id, counts[id])).filter(|&(_, n)| n > 1).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Sequence;
+
+    fn alignment(seqs: &[(&str, &str)]) -> Alignment {
+        Alignment::new(seqs.iter().map(|(id, s)| Sequence::new(*id, s)).collect())
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let aln = alignment(&[("a", "ACGT-A"), ("b", "acgt-a"), ("c", "ACGTA-"), ("d", "ACNT-A"), ("e", "TTTT-T")]);
+
+        let exact = find_duplicates(&aln, DuplicateOptions::default());
+        assert_eq!(exact, vec![DuplicateGroup { representative: 0, duplicates: vec![1] }]);
+
+        let ungapped = find_duplicates(&aln, DuplicateOptions { ignore_gaps: true, wildcards: false });
+        assert_eq!(ungapped, vec![DuplicateGroup { representative: 0, duplicates: vec![1, 2] }]);
+
+        let all = DuplicateOptions { ignore_gaps: true, wildcards: true };
+        let groups = find_duplicates(&aln, all);
+        assert_eq!(groups, vec![DuplicateGroup { representative: 0, duplicates: vec![1, 2, 3] }]);
+
+        let collapsed = collapse(aln, &groups);
+        let ids: Vec<&str> = collapsed.sequences.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "e"]);
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let aln = alignment(&[("a", "AC"), ("b", "AC"), ("a", "GT"), ("c", "AC"), ("a", "TT"), ("c", "AA")]);
+        assert_eq!(duplicate_names(&aln), vec![("a", 3), ("c", 2)]);
+        assert!(duplicate_names(&alignment(&[("a", "AC"), ("b", "AC")])).is_empty());
+    }
+}
@@ -0,0 +1,373 @@
+//! Pairwise distances and identity between aligned sequences (`--distance`, `:dist`).
+//!
+//! Only unambiguous residues are compared: A, C, G, T (U read as T) for
+//! nucleotides and the 20 standard amino acids. Sites with a gap, missing data
+//! or an ambiguity code are left out for the pair (pairwise deletion), or for
+//! every pair if any sequence has one (complete deletion).
+//!
+//! A distance is undefined (`None`) when no site can be compared, or when a
+//! corrected distance saturates (e.g. p ≥ 0.75 for Jukes-Cantor).
+
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::model::{Alignment, Sequence};
+
+/// What is computed between two sequences.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DistanceModel {
+    /// Proportion of differing sites
+    #[default]
+    P,
+    /// Percentage of identical sites
+    Identity,
+    /// Jukes-Cantor (1969) corrected distance (nucleotides)
+    JukesCantor,
+    /// Kimura 2-parameter distance (nucleotides)
+    K2p,
+}
+
+impl DistanceModel {
+    pub const NAMES: &'static [&'static str] = &["p", "identity", "jc", "k2p"];
+
+    /// Returns true for models only defined for nucleotides.
+    pub fn needs_nucleotides(self) -> bool {
+        matches!(self, DistanceModel::JukesCantor | DistanceModel::K2p)
+    }
+
+    /// Returns true if larger values mean closer sequences.
+    pub fn is_similarity(self) -> bool {
+        self == DistanceModel::Identity
+    }
+
+    /// Value between a sequence and itself.
+    fn self_value(self) -> f64 {
+        if self.is_similarity() {
+            100.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Formats a value (`NA` if undefined).
+    pub fn format(self, value: Option<f64>) -> String {
+        match value {
+            None => "NA".to_string(),
+            Some(v) if self.is_similarity() => format!("{:.2}", v),
+            Some(v) => format!("{:.6}", v),
+        }
+    }
+
+    fn compute(self, c: &Comparison) -> Option<f64> {
+        if c.sites == 0 {
+            return None;
+        }
+        let n = c.sites as f64;
+        let p = c.differences as f64 / n;
+        match self {
+            DistanceModel::P => Some(p),
+            DistanceModel::Identity => Some((1.0 - p) * 100.0),
+            DistanceModel::JukesCantor => {
+                let x = 1.0 - 4.0 / 3.0 * p;
+                // `+ 0.0` writes identical sequences as 0, not -0
+                (x > 0.0).then(|| -0.75 * x.ln() + 0.0)
+            }
+            DistanceModel::K2p => {
+                let transitions = c.transitions as f64 / n;
+                let transversions = (c.differences - c.transitions) as f64 / n;
+                let a = 1.0 - 2.0 * transitions - transversions;
+                let b = 1.0 - 2.0 * transversions;
+                (a > 0.0 && b > 0.0).then(|| -0.5 * a.ln() - 0.25 * b.ln() + 0.0)
+            }
+        }
+    }
+}
+
+impl FromStr for DistanceModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "p" | "pdist" => Ok(DistanceModel::P),
+            "identity" | "id" => Ok(DistanceModel::Identity),
+            "jc" | "jc69" => Ok(DistanceModel::JukesCantor),
+            "k2p" | "k80" => Ok(DistanceModel::K2p),
+            _ => Err(format!("invalid distance '{}' (use {})", s, DistanceModel::NAMES.join(", "))),
+        }
+    }
+}
+
+impl fmt::Display for DistanceModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DistanceModel::P => "p-distance",
+            DistanceModel::Identity => "% identity",
+            DistanceModel::JukesCantor => "Jukes-Cantor",
+            DistanceModel::K2p => "K2P",
+        })
+    }
+}
+
+/// Which sites are left out because of gaps and ambiguous residues.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Deletion {
+    /// Per pair of sequences
+    #[default]
+    Pairwise,
+    /// Sites with a gap or ambiguous residue in any sequence
+    Complete,
+}
+
+impl FromStr for Deletion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "pairwise" => Ok(Deletion::Pairwise),
+            "complete" => Ok(Deletion::Complete),
+            _ => Err(format!("invalid deletion '{}' (use pairwise or complete)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Deletion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Deletion::Pairwise => "pairwise deletion",
+            Deletion::Complete => "complete deletion",
+        })
+    }
+}
+
+/// Sites compared between two sequences.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Comparison {
+    sites: usize,
+    differences: usize,
+    /// Purine-purine or pyrimidine-pyrimidine differences (nucleotides)
+    transitions: usize,
+}
+
+/// Computes distances for one alignment.
+#[derive(Debug, Clone)]
+pub struct DistanceCalculator<'a> {
+    alignment: &'a Alignment,
+    model: DistanceModel,
+    nucleotide: bool,
+    /// Sites kept by complete deletion (all sites with pairwise deletion)
+    mask: Option<Vec<bool>>,
+}
+
+impl<'a> DistanceCalculator<'a> {
+    /// Checks the alignment and model, and prepares complete deletion.
+    pub fn new(alignment: &'a Alignment, model: DistanceModel, deletion: Deletion) -> Result<Self, String> {
+        if !alignment.is_valid_alignment {
+            return Err("Distances require aligned sequences (sequences have different lengths)".to_string());
+        }
+        let nucleotide = alignment.sequence_type.is_nucleotide();
+        if model.needs_nucleotides() && !nucleotide {
+            return Err(format!("{} distances require nucleotide sequences", model));
+        }
+        let mask = (deletion == Deletion::Complete).then(|| {
+            let mut mask = vec![true; alignment.alignment_length()];
+            for seq in &alignment.sequences {
+                for (keep, &b) in mask.iter_mut().zip(seq.as_bytes()) {
+                    *keep &= residue(b, nucleotide).is_some();
+                }
+            }
+            mask
+        });
+        Ok(Self { alignment, model, nucleotide, mask })
+    }
+
+    /// Value between sequences `i` and `j`.
+    pub fn between(&self, i: usize, j: usize) -> Option<f64> {
+        if i == j {
+            return Some(self.model.self_value());
+        }
+        let a = &self.alignment.sequences[i];
+        let b = &self.alignment.sequences[j];
+        self.model.compute(&self.compare(a, b))
+    }
+
+    /// Values between sequence `i` and every sequence (in alignment order).
+    pub fn from_sequence(&self, i: usize) -> Vec<Option<f64>> {
+        (0..self.alignment.sequence_count()).map(|j| self.between(i, j)).collect()
+    }
+
+    /// Full square matrix.
+    pub fn matrix(&self) -> DistanceMatrix {
+        let n = self.alignment.sequence_count();
+        // Upper triangle, mirrored below the diagonal
+        let upper: Vec<Vec<Option<f64>>> = (0..n)
+            .map(|i| (i + 1..n).map(|j| self.between(i, j)).collect())
+            .collect();
+        let values = (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| match i.cmp(&j) {
+                        std::cmp::Ordering::Less => upper[i][j - i - 1],
+                        std::cmp::Ordering::Equal => Some(self.model.self_value()),
+                        std::cmp::Ordering::Greater => upper[j][i - j - 1],
+                    })
+                    .collect()
+            })
+            .collect();
+        DistanceMatrix {
+            ids: self.alignment.sequences.iter().map(|s| s.id.clone()).collect(),
+            model: self.model,
+            values,
+        }
+    }
+
+    fn compare(&self, a: &Sequence, b: &Sequence) -> Comparison {
+        let mut c = Comparison::default();
+        for (pos, (&x, &y)) in a.as_bytes().iter().zip(b.as_bytes()).enumerate() {
+            if self.mask.as_ref().is_some_and(|mask| !mask[pos]) {
+                continue;
+            }
+            let (Some(x), Some(y)) = (residue(x, self.nucleotide), residue(y, self.nucleotide)) else {
+                continue;
+            };
+            c.sites += 1;
+            if x != y {
+                c.differences += 1;
+                if self.nucleotide && is_purine(x) == is_purine(y) {
+                    c.transitions += 1;
+                }
+            }
+        }
+        c
+    }
+}
+
+/// The unambiguous residue (uppercase, U read as T), if any.
+fn residue(b: u8, nucleotide: bool) -> Option<u8> {
+    let b = b.to_ascii_uppercase();
+    if nucleotide {
+        match b {
+            b'A' | b'C' | b'G' | b'T' => Some(b),
+            b'U' => Some(b'T'),
+            _ => None,
+        }
+    } else {
+        b"ACDEFGHIKLMNPQRSTVWY".contains(&b).then_some(b)
+    }
+}
+
+fn is_purine(b: u8) -> bool {
+    matches!(b, b'A' | b'G')
+}
+
+/// Square matrix of pairwise values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceMatrix {
+    pub ids: Vec<String>,
+    pub model: DistanceModel,
+    pub values: Vec<Vec<Option<f64>>>,
+}
+
+impl DistanceMatrix {
+    /// Writes a PHYLIP square matrix: the number of sequences, then one row per
+    /// sequence (name, then values separated by spaces).
+    pub fn write_phylip(&self, out: &mut impl Write) -> std::io::Result<()> {
+        let width = self.ids.iter().map(|id| id.len()).max().unwrap_or(0).max(10);
+        writeln!(out, "{}", self.ids.len())?;
+        for (id, row) in self.ids.iter().zip(&self.values) {
+            let values: Vec<String> = row.iter().map(|&v| self.model.format(v)).collect();
+            writeln!(out, "{:<width$} {}", id, values.join(" "), width = width)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a TSV matrix with a header row of names.
+    pub fn write_tsv(&self, out: &mut impl Write) -> std::io::Result<()> {
+        writeln!(out, "\t{}", self.ids.join("\t"))?;
+        for (id, row) in self.ids.iter().zip(&self.values) {
+            let values: Vec<String> = row.iter().map(|&v| self.model.format(v)).collect();
+            writeln!(out, "{}\t{}", id, values.join("\t"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Orders candidates from closest to farthest (undefined values last).
+pub fn closest_first(model: DistanceModel, entries: &mut [(usize, Option<f64>)]) {
+    entries.sort_by(|(_, a), (_, b)| match (a, b) {
+        (Some(a), Some(b)) if model.is_similarity() => b.total_cmp(a),
+        (Some(a), Some(b)) => a.total_cmp(b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alignment(seqs: &[&str]) -> Alignment {
+        Alignment::new(
+            seqs.iter()
+                .enumerate()
+                .map(|(i, s)| Sequence::new(format!("s{}", i + 1), s))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_models_and_deletion() {
+        // s1/s2: 1 transition (A/G) and 1 transversion (C/A) over 8 sites
+        let aln = alignment(&["ACGTACGT", "GCGTAAGT", "ACGTAC--"]);
+        let p = DistanceCalculator::new(&aln, DistanceModel::P, Deletion::Pairwise).unwrap();
+        assert_eq!(p.between(0, 1), Some(0.25));
+        assert_eq!(p.between(0, 2), Some(0.0));
+
+        let identity = DistanceCalculator::new(&aln, DistanceModel::Identity, Deletion::Pairwise).unwrap();
+        assert_eq!(identity.between(0, 1), Some(75.0));
+        assert_eq!(identity.between(1, 1), Some(100.0));
+
+        let jc = DistanceCalculator::new(&aln, DistanceModel::JukesCantor, Deletion::Pairwise).unwrap();
+        let expected = -0.75 * (1.0f64 - 4.0 / 3.0 * 0.25).ln();
+        assert!((jc.between(0, 1).unwrap() - expected).abs() < 1e-12);
+
+        let k2p = DistanceCalculator::new(&aln, DistanceModel::K2p, Deletion::Pairwise).unwrap();
+        let expected = -0.5 * (1.0f64 - 2.0 * 0.125 - 0.125).ln() - 0.25 * (1.0f64 - 2.0 * 0.125).ln();
+        assert!((k2p.between(0, 1).unwrap() - expected).abs() < 1e-12);
+
+        // Complete deletion drops the last two sites for every pair: 2 differences / 6
+        let complete = DistanceCalculator::new(&aln, DistanceModel::P, Deletion::Complete).unwrap();
+        assert_eq!(complete.between(0, 1), Some(2.0 / 6.0));
+
+        // Saturation and proteins
+        assert_eq!(jc.between(0, 2).map(|d| d.to_string()), Some("0".to_string()));
+        let far = alignment(&["AAAA", "CCCC"]);
+        let jc = DistanceCalculator::new(&far, DistanceModel::JukesCantor, Deletion::Pairwise).unwrap();
+        assert_eq!(jc.between(0, 1), None);
+        let protein = alignment(&["MKWLEQ", "MKWLEE"]);
+        assert!(DistanceCalculator::new(&protein, DistanceModel::K2p, Deletion::Pairwise).is_err());
+        assert!(DistanceCalculator::new(&alignment(&["ACGT", "AC"]), DistanceModel::P, Deletion::Pairwise).is_err());
+    }
+
+    #[test]
+    fn test_matrix_output_and_ordering() {
+        let aln = alignment(&["ACGT", "ACGA", "TTTT"]);
+        let matrix = DistanceCalculator::new(&aln, DistanceModel::P, Deletion::Pairwise).unwrap().matrix();
+        let mut out = Vec::new();
+        matrix.write_phylip(&mut out).unwrap();
+        let phylip = String::from_utf8(out).unwrap();
+        assert_eq!(phylip.lines().next(), Some("3"));
+        assert_eq!(phylip.lines().nth(2), Some("s2         0.250000 0.000000 1.000000"));
+
+        let mut out = Vec::new();
+        matrix.write_tsv(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with("\ts1\ts2\ts3\ns1\t0.000000\t0.250000\t0.750000\n"));
+
+        let mut entries = vec![(0, Some(0.5)), (1, None), (2, Some(0.1))];
+        closest_first(DistanceModel::P, &mut entries);
+        assert_eq!(entries.iter().map(|e| e.0).collect::<Vec<_>>(), vec![2, 0, 1]);
+        closest_first(DistanceModel::Identity, &mut entries);
+        assert_eq!(entries.iter().map(|e| e.0).collect::<Vec<_>>(), vec![0, 2, 1]);
+    }
+}
@@ -21,11 +21,15 @@
 //! - `?`: search backward
 //! - `n`: find next match
 //! - `N`: find previous match
+//!
+//! Normal-mode bindings come from the `Keymap` (see `keymap.rs`) and can be
+//! changed in the config file or with `:map` / `:unmap`.
 
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use std::time::Duration;
 
-use crate::model::{AppMode, AppState};
+use crate::keymap::{KeyChord, KeyLookup, Keymap};
+use crate::model::{AppMode, AppState, CommandOutcome};
 
 /// Actions that can be triggered by keyboard input.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -88,8 +92,8 @@ pub enum Action {
     GotoLastVisibleColumn,
     /// Go to specific column (number|)
     GotoColumn(usize),
-    /// Pending 'g' key for g-commands
-    PendingG,
+    /// Key that starts a multi-key binding (e.g. 'g' in "gm")
+    PendingKey(KeyChord),
     /// Accumulate a digit for number prefix
     AccumulateDigit(char),
     /// Execute pending number with | (go to column)
@@ -120,8 +124,6 @@ pub enum Action {
     PageUp,
     /// Move full page down (PageDown)
     PageDown,
-    /// Pending 'z' key for z-commands
-    PendingZ,
     /// Move to start of next word (w)
     WordForward,
     /// Move to start of previous word (b)
@@ -142,6 +144,80 @@ pub enum Action {
     FileBrowserToggleAll,
     /// File browser: quit application
     FileBrowserQuit,
+    /// Command/search input: move cursor left
+    InputCursorLeft,
+    /// Command/search input: move cursor right
+    InputCursorRight,
+    /// Command/search input: move cursor to start (Home or Ctrl+A)
+    InputCursorHome,
+    /// Command/search input: move cursor to end (End or Ctrl+E)
+    InputCursorEnd,
+    /// Command/search input: delete character under cursor
+    InputDelete,
+    /// Command/search input: delete word before cursor (Ctrl+W)
+    InputDeleteWord,
+    /// Command/search input: delete to start of line (Ctrl+U)
+    InputDeleteToStart,
+    /// Command/search input: recall older history entry (Up)
+    HistoryPrevious,
+    /// Command/search input: recall newer history entry (Down)
+    HistoryNext,
+    /// Command input: complete / next candidate (Tab)
+    CompleteNext,
+    /// Command input: previous candidate (Shift+Tab)
+    CompletePrevious,
+    /// Open the fuzzy sequence finder (Ctrl+P)
+    OpenSequenceFinder,
+    /// Sequence finder: type a character
+    FinderInput(char),
+    /// Sequence finder: delete last character
+    FinderBackspace,
+    /// Sequence finder: clear the query (Ctrl+U)
+    FinderClearQuery,
+    /// Sequence finder: move selection up
+    FinderUp,
+    /// Sequence finder: move selection down
+    FinderDown,
+    /// Sequence finder: jump to the selected sequence
+    FinderSelect,
+    /// Sequence finder: close without jumping
+    FinderCancel,
+    /// Distance panel: move selection up
+    DistanceUp,
+    /// Distance panel: move selection down
+    DistanceDown,
+    /// Distance panel: jump to the selected sequence
+    DistanceSelect,
+    /// Distance panel: close without jumping
+    DistanceClose,
+    /// Block picker: move selection up
+    BlockPickerUp,
+    /// Block picker: move selection down
+    BlockPickerDown,
+    /// Block picker: show the selected character matrix
+    BlockPickerSelect,
+    /// Block picker: close without switching
+    BlockPickerClose,
+    /// Move the current row up (Alt+Up)
+    MoveRowUp,
+    /// Move the current row down (Alt+Down)
+    MoveRowDown,
+    /// Pin/unpin the current row (gp)
+    TogglePin,
+    /// Select the clade of the current row, then larger clades (gc)
+    SelectClade,
+    /// Collapse the selected clade to its top row (zc)
+    CollapseClade,
+    /// Expand the collapsed clade on the current row (zo)
+    ExpandClade,
+    /// Expand all collapsed clades (zR)
+    ExpandAllClades,
+    /// Move to the start of the next partition (]p)
+    NextPartition,
+    /// Move to the start of the current or previous partition ([p)
+    PreviousPartition,
+    /// Hide or show the insert columns of A2M/A3M files (zi)
+    ToggleInserts,
 }
 
 /// Polls for keyboard events with a timeout.
@@ -155,32 +231,56 @@ pub fn poll_event(timeout: Duration) -> Option<Event> {
     }
 }
 
+/// Input state needed to interpret a key press.
+pub struct KeyContext<'a> {
+    /// Current application mode
+    pub mode: &'a AppMode,
+    /// Whether the help overlay is shown
+    pub show_help: bool,
+    /// Keys typed so far of a multi-key binding
+    pub pending_keys: &'a [KeyChord],
+    /// Whether digits of a <number>| prefix have been typed
+    pub has_number_prefix: bool,
+    /// Whether an error popup is shown
+    pub has_error_popup: bool,
+    /// Whether the file browser is open
+    pub has_file_browser: bool,
+    /// Whether the sequence finder is open
+    pub has_sequence_finder: bool,
+    /// Whether the distance panel is open
+    pub has_distance_panel: bool,
+    /// Whether the NEXUS block picker is open
+    pub has_block_picker: bool,
+    /// Normal-mode key bindings
+    pub keymap: &'a Keymap,
+}
+
+impl<'a> KeyContext<'a> {
+    /// Builds the key context from the application state.
+    pub fn from_state(state: &'a AppState) -> Self {
+        Self {
+            mode: &state.mode,
+            show_help: state.show_help,
+            pending_keys: &state.pending_keys,
+            has_number_prefix: !state.number_buffer.is_empty(),
+            has_error_popup: state.error_popup.is_some(),
+            has_file_browser: state.file_browser.is_some(),
+            has_sequence_finder: state.sequence_finder.is_some(),
+            has_distance_panel: state.distance_panel.is_some(),
+            has_block_picker: state.block_picker.is_some(),
+            keymap: &state.keymap,
+        }
+    }
+}
+
 /// Converts a crossterm event to an Action based on current app mode.
-pub fn handle_event(
-    event: Event, 
-    mode: &AppMode, 
-    show_help: bool, 
-    pending_g: bool, 
-    pending_z: bool, 
-    has_number_prefix: bool,
-    has_error_popup: bool,
-    has_file_browser: bool,
-) -> Action {
+pub fn handle_event(event: Event, ctx: &KeyContext) -> Action {
     match event {
         Event::Key(key_event) => {
             if key_event.kind != KeyEventKind::Press {
                 return Action::None;
             }
-            handle_key_event(
-                key_event,
-                mode,
-                show_help,
-                pending_g,
-                pending_z,
-                has_number_prefix,
-                has_error_popup,
-                has_file_browser,
-            )
+            handle_key_event(key_event, ctx)
         }
         Event::Resize(width, height) => Action::Resize(width, height),
         _ => Action::None,
@@ -188,14 +288,14 @@ pub fn handle_event(
 }
 
 /// Handles a key event based on the current application mode.
-fn handle_key_event(key: KeyEvent, mode: &AppMode, show_help: bool, pending_g: bool, pending_z: bool, has_number_prefix: bool, has_error_popup: bool, has_file_browser: bool) -> Action {
+fn handle_key_event(key: KeyEvent, ctx: &KeyContext) -> Action {
     // Error popup takes priority - any key dismisses it
-    if has_error_popup {
+    if ctx.has_error_popup {
         return Action::DismissErrorPopup;
     }
     
     // File browser takes priority over normal mode
-    if has_file_browser {
+    if ctx.has_file_browser {
         return match key.code {
             KeyCode::Up | KeyCode::Char('k') => Action::FileBrowserUp,
             KeyCode::Down | KeyCode::Char('j') => Action::FileBrowserDown,
@@ -207,8 +307,35 @@ fn handle_key_event(key: KeyEvent, mode: &AppMode, show_help: bool, pending_g: b
         };
     }
     
+    // Sequence finder captures typing
+    if ctx.has_sequence_finder {
+        return handle_sequence_finder(key);
+    }
+
+    // Distance panel: navigate, jump or close
+    if ctx.has_distance_panel {
+        return match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Action::DistanceUp,
+            KeyCode::Down | KeyCode::Char('j') => Action::DistanceDown,
+            KeyCode::Enter => Action::DistanceSelect,
+            KeyCode::Esc | KeyCode::Char('q') => Action::DistanceClose,
+            _ => Action::None,
+        };
+    }
+
+    // Block picker: choose a character matrix or close
+    if ctx.has_block_picker {
+        return match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Action::BlockPickerUp,
+            KeyCode::Down | KeyCode::Char('j') => Action::BlockPickerDown,
+            KeyCode::Enter => Action::BlockPickerSelect,
+            KeyCode::Esc | KeyCode::Char('q') => Action::BlockPickerClose,
+            _ => Action::None,
+        };
+    }
+    
     // If help is shown, handle tab navigation or dismiss
-    if show_help {
+    if ctx.show_help {
         return match key.code {
             KeyCode::Right | KeyCode::Char('l') | KeyCode::Tab => Action::HelpNextTab,
             KeyCode::Left | KeyCode::Char('h') | KeyCode::BackTab => Action::HelpPrevTab,
@@ -216,165 +343,121 @@ fn handle_key_event(key: KeyEvent, mode: &AppMode, show_help: bool, pending_g: b
         };
     }
 
-    // Handle pending g-command
-    if pending_g {
-        return handle_g_command(key);
-    }
-
-    // Handle pending z-command
-    if pending_z {
-        return handle_z_command(key);
-    }
-
-    match mode {
-        AppMode::Normal => handle_normal_mode(key, has_number_prefix),
+    match ctx.mode {
+        AppMode::Normal => handle_normal_mode(key, ctx),
         AppMode::Command(_) => handle_command_mode(key),
         AppMode::Search(_) | AppMode::SearchBackward(_) => handle_search_mode(key),
         AppMode::TranslationSettings => handle_translation_settings_mode(key),
     }
 }
 
-/// Handles g-prefix commands (g0, gm, g$).
-fn handle_g_command(key: KeyEvent) -> Action {
-    match key.code {
-        KeyCode::Char('0') => Action::GotoFirstVisibleColumn,
-        KeyCode::Char('m') => Action::GotoMiddleVisibleColumn,
-        KeyCode::Char('$') => Action::GotoLastVisibleColumn,
-        _ => Action::None, // Unknown g-command, cancel
-    }
-}
-
-/// Handles z-prefix commands (zH, zL for half-screen horizontal scroll).
-fn handle_z_command(key: KeyEvent) -> Action {
-    match key.code {
-        KeyCode::Char('H') => Action::HalfPageLeft,
-        KeyCode::Char('L') => Action::HalfPageRight,
-        _ => Action::None, // Unknown z-command, cancel
-    }
-}
-
-/// Handles key events in normal mode (Vim-style navigation).
-fn handle_normal_mode(key: KeyEvent, has_number_prefix: bool) -> Action {
-    // Handle Ctrl+C for emergency quit
+/// Handles key events in normal mode by looking them up in the keymap.
+///
+/// Returns `Action::None` when the keys typed so far match no binding,
+/// which also cancels any pending multi-key sequence.
+fn handle_normal_mode(key: KeyEvent, ctx: &KeyContext) -> Action {
+    // Handle Ctrl+C for emergency quit (not remappable)
     if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
         return Action::Quit;
     }
 
-    // Handle Ctrl+F for search (Cmd+F is not sent as a modifier in most terminal emulators)
-    // Note: Cmd+F cannot be intercepted in most terminal environments due to OS/terminal limitations.
-    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('f') {
-        return Action::EnterSearchMode;
-    }
-
-    // Handle Ctrl+U for half page up
-    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('u') {
-        return Action::HalfPageUp;
-    }
+    let chord = KeyChord::from(key);
 
-    // Handle Ctrl+D for half page down
-    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('d') {
-        return Action::HalfPageDown;
+    // Number prefix for <number>| command
+    // '0' goes to first column only if not accumulating a number (e.g., 500|)
+    if ctx.pending_keys.is_empty() && chord.modifiers.is_empty() {
+        match chord.code {
+            KeyCode::Char(c @ '1'..='9') => return Action::AccumulateDigit(c),
+            KeyCode::Char('0') if ctx.has_number_prefix => return Action::AccumulateDigit('0'),
+            _ => {}
+        }
     }
 
-    // Handle Ctrl+Left or Shift+Left for half page left
-    if (key.modifiers.contains(KeyModifiers::CONTROL) || key.modifiers.contains(KeyModifiers::SHIFT))
-        && key.code == KeyCode::Left {
-        return Action::HalfPageLeft;
+    let mut keys = ctx.pending_keys.to_vec();
+    keys.push(chord);
+    match ctx.keymap.lookup(&keys) {
+        KeyLookup::Action(action) => action.clone(),
+        KeyLookup::Prefix => Action::PendingKey(chord),
+        KeyLookup::Unbound => Action::None,
     }
+}
 
-    // Handle Ctrl+Right or Shift+Right for half page right
-    if (key.modifiers.contains(KeyModifiers::CONTROL) || key.modifiers.contains(KeyModifiers::SHIFT))
-        && key.code == KeyCode::Right {
-        return Action::HalfPageRight;
+/// Handles key events in the sequence finder popup.
+fn handle_sequence_finder(key: KeyEvent) -> Action {
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return match key.code {
+            KeyCode::Char('p') | KeyCode::Char('k') => Action::FinderUp,
+            KeyCode::Char('n') | KeyCode::Char('j') => Action::FinderDown,
+            KeyCode::Char('u') => Action::FinderClearQuery,
+            KeyCode::Char('c') => Action::FinderCancel,
+            _ => Action::None,
+        };
     }
-
-    // Handle Ctrl+Up or Shift+Up for page up
-    if (key.modifiers.contains(KeyModifiers::CONTROL) || key.modifiers.contains(KeyModifiers::SHIFT))
-        && key.code == KeyCode::Up {
-        return Action::PageUp;
+    match key.code {
+        KeyCode::Up | KeyCode::BackTab => Action::FinderUp,
+        KeyCode::Down | KeyCode::Tab => Action::FinderDown,
+        KeyCode::Enter => Action::FinderSelect,
+        KeyCode::Esc => Action::FinderCancel,
+        KeyCode::Backspace => Action::FinderBackspace,
+        KeyCode::Char(c) => Action::FinderInput(c),
+        _ => Action::None,
     }
+}
 
-    // Handle Ctrl+Down or Shift+Down for page down
-    if (key.modifiers.contains(KeyModifiers::CONTROL) || key.modifiers.contains(KeyModifiers::SHIFT))
-        && key.code == KeyCode::Down {
-        return Action::PageDown;
+/// Handles line-editing keys shared by command and search modes
+/// (cursor movement, deletion, history).
+fn handle_input_editing(key: KeyEvent) -> Option<Action> {
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return match key.code {
+            KeyCode::Char('a') => Some(Action::InputCursorHome),
+            KeyCode::Char('e') => Some(Action::InputCursorEnd),
+            KeyCode::Char('b') => Some(Action::InputCursorLeft),
+            KeyCode::Char('f') => Some(Action::InputCursorRight),
+            KeyCode::Char('w') => Some(Action::InputDeleteWord),
+            KeyCode::Char('u') => Some(Action::InputDeleteToStart),
+            KeyCode::Char('p') => Some(Action::HistoryPrevious),
+            KeyCode::Char('n') => Some(Action::HistoryNext),
+            _ => None,
+        };
     }
-
     match key.code {
-        // Vim-style navigation (as per spec: j=down, k=up, l=right, h=left)
-        KeyCode::Char('j') => Action::MoveDown,
-        KeyCode::Char('k') => Action::MoveUp,
-        KeyCode::Char('l') => Action::MoveRight,
-        KeyCode::Char('h') => Action::MoveLeft,
-
-        // Word movements (gaps and stops are delimiters)
-        KeyCode::Char('w') => Action::WordForward,
-        KeyCode::Char('b') => Action::WordBackward,
-        KeyCode::Char('e') => Action::WordEnd,
-
-        // Alternative arrow keys for convenience
-        KeyCode::Up => Action::MoveUp,
-        KeyCode::Down => Action::MoveDown,
-        KeyCode::Right => Action::MoveRight,
-        KeyCode::Left => Action::MoveLeft,
-
-        // Jump to start/end of line (Vim-style)
-        // '0' goes to first column only if not accumulating a number (e.g., 500|)
-        KeyCode::Char('0') if !has_number_prefix => Action::GotoFirstColumn,
-        KeyCode::Char('0') => Action::AccumulateDigit('0'),
-        KeyCode::Char('$') => Action::GotoLastColumn,
-        KeyCode::Home => Action::GotoFirstColumn,
-        KeyCode::End => Action::GotoLastColumn,
-
-        // Page navigation
-        KeyCode::PageUp => Action::PageUp,
-        KeyCode::PageDown => Action::PageDown,
-
-        // g-prefix commands (handled via pending state)
-        KeyCode::Char('g') => Action::PendingG,
-
-        // z-prefix commands (handled via pending state)
-        KeyCode::Char('z') => Action::PendingZ,
-
-        // Number prefix for <number>| command
-        KeyCode::Char(c @ '1'..='9') => Action::AccumulateDigit(c),
-        KeyCode::Char('|') => Action::ExecuteGotoColumn,
-
-        // Command mode
-        KeyCode::Char(':') => Action::EnterCommandMode,
-
-        // Search mode (Vim-style)
-        KeyCode::Char('/') => Action::EnterSearchMode,
-        KeyCode::Char('?') => Action::EnterSearchBackward,
-        KeyCode::Char('n') => Action::FindNext,
-        KeyCode::Char('N') => Action::FindPrevious,
-
-        // Quick quit with 'q' in normal mode (optional convenience)
-        // Commented out to strictly follow spec (use :q)
-        // KeyCode::Char('q') => Action::Quit,
-
-        _ => Action::None,
+        KeyCode::Left => Some(Action::InputCursorLeft),
+        KeyCode::Right => Some(Action::InputCursorRight),
+        KeyCode::Home => Some(Action::InputCursorHome),
+        KeyCode::End => Some(Action::InputCursorEnd),
+        KeyCode::Delete => Some(Action::InputDelete),
+        KeyCode::Up => Some(Action::HistoryPrevious),
+        KeyCode::Down => Some(Action::HistoryNext),
+        _ => None,
     }
 }
 
 /// Handles key events in command mode.
 fn handle_command_mode(key: KeyEvent) -> Action {
+    if let Some(action) = handle_input_editing(key) {
+        return action;
+    }
     match key.code {
         KeyCode::Enter => Action::ExecuteCommand,
         KeyCode::Esc => Action::CancelCommand,
         KeyCode::Backspace => Action::CommandBackspace,
-        KeyCode::Char(c) => Action::CommandChar(c),
+        KeyCode::Tab => Action::CompleteNext,
+        KeyCode::BackTab => Action::CompletePrevious,
+        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => Action::CommandChar(c),
         _ => Action::None,
     }
 }
 
 /// Handles key events in search mode.
 fn handle_search_mode(key: KeyEvent) -> Action {
+    if let Some(action) = handle_input_editing(key) {
+        return action;
+    }
     match key.code {
         KeyCode::Enter => Action::ExecuteSearch,
         KeyCode::Esc => Action::CancelSearch,
         KeyCode::Backspace => Action::SearchBackspace,
-        KeyCode::Char(c) => Action::SearchChar(c),
+        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => Action::SearchChar(c),
         _ => Action::None,
     }
 }
@@ -407,12 +490,19 @@ pub enum ActionResult {
     StartTranslation,
     /// Load a file from the file browser
     LoadFile(std::path::PathBuf),
+    /// Reload the file with another NEXUS character block
+    LoadBlock(String),
 }
 
 /// Applies an action to the application state.
 ///
 /// Returns ActionResult indicating if any follow-up action is needed.
 pub fn apply_action(state: &mut AppState, action: Action) -> ActionResult {
+    // Any other key ends a pending multi-key sequence (matched or not)
+    if !matches!(action, Action::PendingKey(_) | Action::Resize(_, _)) {
+        state.pending_keys.clear();
+    }
+
     match action {
         Action::None => {}
         Action::Quit => {
@@ -436,11 +526,12 @@ pub fn apply_action(state: &mut AppState, action: Action) -> ActionResult {
         Action::CommandChar(c) => {
             state.command_input(c);
         }
-        Action::ExecuteCommand => {
-            if state.execute_command() {
-                return ActionResult::StartTranslation;
-            }
-        }
+        Action::ExecuteCommand => match state.execute_command() {
+            CommandOutcome::None => {}
+            CommandOutcome::StartTranslation => return ActionResult::StartTranslation,
+            CommandOutcome::LoadFile(path) => return ActionResult::LoadFile(path),
+            CommandOutcome::LoadBlock(block) => return ActionResult::LoadBlock(block),
+        },
         Action::CancelCommand => {
             state.cancel_command();
         }
@@ -501,8 +592,8 @@ pub fn apply_action(state: &mut AppState, action: Action) -> ActionResult {
         Action::GotoColumn(col) => {
             state.goto_column(col);
         }
-        Action::PendingG => {
-            state.set_pending_g();
+        Action::PendingKey(key) => {
+            state.push_pending_key(key);
         }
         Action::AccumulateDigit(c) => {
             state.accumulate_digit(c);
@@ -554,9 +645,6 @@ pub fn apply_action(state: &mut AppState, action: Action) -> ActionResult {
         Action::PageDown => {
             state.page_down();
         }
-        Action::PendingZ => {
-            state.set_pending_z();
-        }
         Action::WordForward => {
             state.word_forward();
         }
@@ -589,6 +677,121 @@ pub fn apply_action(state: &mut AppState, action: Action) -> ActionResult {
         Action::FileBrowserQuit => {
             state.file_browser_quit();
         }
+        Action::OpenSequenceFinder => {
+            state.open_sequence_finder("");
+        }
+        Action::FinderInput(c) => {
+            state.finder_input(c);
+        }
+        Action::FinderBackspace => {
+            state.finder_backspace();
+        }
+        Action::FinderClearQuery => {
+            state.finder_clear_query();
+        }
+        Action::FinderUp => {
+            state.finder_up();
+        }
+        Action::FinderDown => {
+            state.finder_down();
+        }
+        Action::FinderSelect => {
+            state.finder_select();
+        }
+        Action::FinderCancel => {
+            state.close_sequence_finder();
+        }
+        Action::DistanceUp => {
+            state.distance_up();
+        }
+        Action::DistanceDown => {
+            state.distance_down();
+        }
+        Action::DistanceSelect => {
+            state.distance_select();
+        }
+        Action::DistanceClose => {
+            state.close_distance_panel();
+        }
+        Action::BlockPickerUp => {
+            state.block_picker_up();
+        }
+        Action::BlockPickerDown => {
+            state.block_picker_down();
+        }
+        Action::BlockPickerSelect => {
+            if let Some(block) = state.block_picker_select() {
+                return ActionResult::LoadBlock(block);
+            }
+        }
+        Action::BlockPickerClose => {
+            state.block_picker = None;
+        }
+        Action::MoveRowUp => {
+            state.move_row(true);
+        }
+        Action::MoveRowDown => {
+            state.move_row(false);
+        }
+        Action::TogglePin => {
+            state.toggle_pin();
+        }
+        Action::SelectClade => {
+            state.select_clade();
+        }
+        Action::CollapseClade => {
+            state.collapse_clade();
+        }
+        Action::ExpandClade => {
+            state.expand_clade();
+        }
+        Action::ExpandAllClades => {
+            state.expand_all_clades();
+        }
+        Action::NextPartition => {
+            state.next_partition();
+        }
+        Action::PreviousPartition => {
+            state.previous_partition();
+        }
+        Action::ToggleInserts => {
+            if state.toggle_inserts() == CommandOutcome::StartTranslation {
+                return ActionResult::StartTranslation;
+            }
+        }
+        Action::InputCursorLeft => {
+            state.input_cursor_left();
+        }
+        Action::InputCursorRight => {
+            state.input_cursor_right();
+        }
+        Action::InputCursorHome => {
+            state.input_cursor_home();
+        }
+        Action::InputCursorEnd => {
+            state.input_cursor_end();
+        }
+        Action::InputDelete => {
+            state.input_delete();
+        }
+        Action::InputDeleteWord => {
+            state.input_delete_word();
+        }
+        Action::InputDeleteToStart => {
+            state.input_delete_to_start();
+        }
+        Action::HistoryPrevious => {
+            state.history_previous();
+        }
+        Action::HistoryNext => {
+            state.history_next();
+        }
+        Action::CompleteNext => {
+            state.complete_input(true);
+        }
+        Action::CompletePrevious => {
+            state.complete_input(false);
+        }
     }
 
     ActionResult::Continue
@@ -598,29 +801,47 @@ pub fn apply_action(state: &mut AppState, action: Action) -> ActionResult {
 mod tests {
     use super::*;
 
+    /// Maps a key press with the default keymap.
+    fn press(key: KeyEvent, mode: &AppMode, show_help: bool, pending_keys: &[KeyChord], has_number_prefix: bool) -> Action {
+        let keymap = Keymap::default();
+        let ctx = KeyContext {
+            mode,
+            show_help,
+            pending_keys,
+            has_number_prefix,
+            has_error_popup: false,
+            has_file_browser: false,
+            has_sequence_finder: false,
+            has_distance_panel: false,
+            has_block_picker: false,
+            keymap: &keymap,
+        };
+        handle_key_event(key, &ctx)
+    }
+
     #[test]
     fn test_normal_mode_navigation() {
         let mode = AppMode::Normal;
 
         // Test movement keys (Vim-style: h=left, j=down, k=up, l=right)
         let key = KeyEvent::new(KeyCode::Char('h'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::MoveLeft);
+        assert_eq!(press(key, &mode, false, &[], false), Action::MoveLeft);
 
         let key = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::MoveDown);
+        assert_eq!(press(key, &mode, false, &[], false), Action::MoveDown);
 
         let key = KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::MoveUp);
+        assert_eq!(press(key, &mode, false, &[], false), Action::MoveUp);
 
         let key = KeyEvent::new(KeyCode::Char('l'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::MoveRight);
+        assert_eq!(press(key, &mode, false, &[], false), Action::MoveRight);
     }
 
     #[test]
     fn test_enter_command_mode() {
         let mode = AppMode::Normal;
         let key = KeyEvent::new(KeyCode::Char(':'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::EnterCommandMode);
+        assert_eq!(press(key, &mode, false, &[], false), Action::EnterCommandMode);
     }
 
     #[test]
@@ -628,20 +849,20 @@ mod tests {
         let mode = AppMode::Command(String::new());
 
         let key = KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::CommandChar('q'));
+        assert_eq!(press(key, &mode, false, &[], false), Action::CommandChar('q'));
 
         let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::ExecuteCommand);
+        assert_eq!(press(key, &mode, false, &[], false), Action::ExecuteCommand);
 
         let key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::CancelCommand);
+        assert_eq!(press(key, &mode, false, &[], false), Action::CancelCommand);
     }
 
     #[test]
     fn test_ctrl_c_quit() {
         let mode = AppMode::Normal;
         let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::Quit);
+        assert_eq!(press(key, &mode, false, &[], false), Action::Quit);
     }
 
     #[test]
@@ -650,17 +871,17 @@ mod tests {
 
         // Test entering search modes
         let key = KeyEvent::new(KeyCode::Char('/'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::EnterSearchMode);
+        assert_eq!(press(key, &mode, false, &[], false), Action::EnterSearchMode);
 
         let key = KeyEvent::new(KeyCode::Char('?'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::EnterSearchBackward);
+        assert_eq!(press(key, &mode, false, &[], false), Action::EnterSearchBackward);
 
         // Test find next/previous
         let key = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::FindNext);
+        assert_eq!(press(key, &mode, false, &[], false), Action::FindNext);
 
         let key = KeyEvent::new(KeyCode::Char('N'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::FindPrevious);
+        assert_eq!(press(key, &mode, false, &[], false), Action::FindPrevious);
     }
 
     #[test]
@@ -668,16 +889,16 @@ mod tests {
         let mode = AppMode::Search(String::new());
 
         let key = KeyEvent::new(KeyCode::Char('A'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::SearchChar('A'));
+        assert_eq!(press(key, &mode, false, &[], false), Action::SearchChar('A'));
 
         let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::ExecuteSearch);
+        assert_eq!(press(key, &mode, false, &[], false), Action::ExecuteSearch);
 
         let key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::CancelSearch);
+        assert_eq!(press(key, &mode, false, &[], false), Action::CancelSearch);
 
         let key = KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::SearchBackspace);
+        assert_eq!(press(key, &mode, false, &[], false), Action::SearchBackspace);
     }
 
     #[test]
@@ -686,26 +907,26 @@ mod tests {
         
         // Left/Right and h/l switch tabs
         let key = KeyEvent::new(KeyCode::Right, KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, true, false, false, false, false, false), Action::HelpNextTab);
+        assert_eq!(press(key, &mode, true, &[], false), Action::HelpNextTab);
 
         let key = KeyEvent::new(KeyCode::Left, KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, true, false, false, false, false, false), Action::HelpPrevTab);
+        assert_eq!(press(key, &mode, true, &[], false), Action::HelpPrevTab);
 
         let key = KeyEvent::new(KeyCode::Char('l'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, true, false, false, false, false, false), Action::HelpNextTab);
+        assert_eq!(press(key, &mode, true, &[], false), Action::HelpNextTab);
 
         let key = KeyEvent::new(KeyCode::Char('h'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, true, false, false, false, false, false), Action::HelpPrevTab);
+        assert_eq!(press(key, &mode, true, &[], false), Action::HelpPrevTab);
 
         let key = KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, true, false, false, false, false, false), Action::HelpNextTab);
+        assert_eq!(press(key, &mode, true, &[], false), Action::HelpNextTab);
 
         // Other keys dismiss help
         let key = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, true, false, false, false, false, false), Action::DismissHelp);
+        assert_eq!(press(key, &mode, true, &[], false), Action::DismissHelp);
 
         let key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, true, false, false, false, false, false), Action::DismissHelp);
+        assert_eq!(press(key, &mode, true, &[], false), Action::DismissHelp);
     }
 
     #[test]
@@ -714,21 +935,21 @@ mod tests {
 
         // Test 0 key (without number prefix -> go to first column)
         let key = KeyEvent::new(KeyCode::Char('0'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::GotoFirstColumn);
+        assert_eq!(press(key, &mode, false, &[], false), Action::GotoFirstColumn);
 
         // Test 0 key WITH number prefix (e.g., typing 500|) -> accumulate digit
         let key = KeyEvent::new(KeyCode::Char('0'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, true, false, false), Action::AccumulateDigit('0'));
+        assert_eq!(press(key, &mode, false, &[], true), Action::AccumulateDigit('0'));
 
         let key = KeyEvent::new(KeyCode::Char('$'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::GotoLastColumn);
+        assert_eq!(press(key, &mode, false, &[], false), Action::GotoLastColumn);
 
         // Test Home and End keys
         let key = KeyEvent::new(KeyCode::Home, KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::GotoFirstColumn);
+        assert_eq!(press(key, &mode, false, &[], false), Action::GotoFirstColumn);
 
         let key = KeyEvent::new(KeyCode::End, KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::GotoLastColumn);
+        assert_eq!(press(key, &mode, false, &[], false), Action::GotoLastColumn);
     }
 
     #[test]
@@ -737,21 +958,21 @@ mod tests {
 
         // Test g prefix
         let key = KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::PendingG);
+        assert_eq!(press(key, &mode, false, &[], false), Action::PendingKey(KeyChord::char('g')));
 
-        // Test g0, gm, g$ (with pending_g = true)
+        // Test g0, gm, g$ (with 'g' pending)
         let key = KeyEvent::new(KeyCode::Char('0'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, true, false, false, false, false), Action::GotoFirstVisibleColumn);
+        assert_eq!(press(key, &mode, false, &[KeyChord::char('g')], false), Action::GotoFirstVisibleColumn);
 
         let key = KeyEvent::new(KeyCode::Char('m'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, true, false, false, false, false), Action::GotoMiddleVisibleColumn);
+        assert_eq!(press(key, &mode, false, &[KeyChord::char('g')], false), Action::GotoMiddleVisibleColumn);
 
         let key = KeyEvent::new(KeyCode::Char('$'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, true, false, false, false, false), Action::GotoLastVisibleColumn);
+        assert_eq!(press(key, &mode, false, &[KeyChord::char('g')], false), Action::GotoLastVisibleColumn);
 
         // Unknown g-command returns None
         let key = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, true, false, false, false, false), Action::None);
+        assert_eq!(press(key, &mode, false, &[KeyChord::char('g')], false), Action::None);
     }
 
     #[test]
@@ -760,18 +981,18 @@ mod tests {
 
         // Test z prefix
         let key = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::PendingZ);
+        assert_eq!(press(key, &mode, false, &[], false), Action::PendingKey(KeyChord::char('z')));
 
-        // Test zH, zL (with pending_z = true)
+        // Test zH, zL (with 'z' pending)
         let key = KeyEvent::new(KeyCode::Char('H'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, true, false, false, false), Action::HalfPageLeft);
+        assert_eq!(press(key, &mode, false, &[KeyChord::char('z')], false), Action::HalfPageLeft);
 
         let key = KeyEvent::new(KeyCode::Char('L'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, true, false, false, false), Action::HalfPageRight);
+        assert_eq!(press(key, &mode, false, &[KeyChord::char('z')], false), Action::HalfPageRight);
 
         // Unknown z-command returns None
         let key = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, true, false, false, false), Action::None);
+        assert_eq!(press(key, &mode, false, &[KeyChord::char('z')], false), Action::None);
     }
 
     #[test]
@@ -780,11 +1001,11 @@ mod tests {
 
         // Test digit accumulation
         let key = KeyEvent::new(KeyCode::Char('5'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::AccumulateDigit('5'));
+        assert_eq!(press(key, &mode, false, &[], false), Action::AccumulateDigit('5'));
 
         // Test | to execute goto column
         let key = KeyEvent::new(KeyCode::Char('|'), KeyModifiers::NONE);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::ExecuteGotoColumn);
+        assert_eq!(press(key, &mode, false, &[], false), Action::ExecuteGotoColumn);
     }
 
     #[test]
@@ -793,26 +1014,113 @@ mod tests {
 
         // Test Ctrl+Left for half page left
         let key = KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::HalfPageLeft);
+        assert_eq!(press(key, &mode, false, &[], false), Action::HalfPageLeft);
 
         // Test Ctrl+Right for half page right
         let key = KeyEvent::new(KeyCode::Right, KeyModifiers::CONTROL);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::HalfPageRight);
+        assert_eq!(press(key, &mode, false, &[], false), Action::HalfPageRight);
 
         // Test Shift+Left for half page left (macOS compatibility)
         let key = KeyEvent::new(KeyCode::Left, KeyModifiers::SHIFT);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::HalfPageLeft);
+        assert_eq!(press(key, &mode, false, &[], false), Action::HalfPageLeft);
 
         // Test Shift+Right for half page right (macOS compatibility)
         let key = KeyEvent::new(KeyCode::Right, KeyModifiers::SHIFT);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::HalfPageRight);
+        assert_eq!(press(key, &mode, false, &[], false), Action::HalfPageRight);
 
         // Test Shift+Up for page up
         let key = KeyEvent::new(KeyCode::Up, KeyModifiers::SHIFT);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::PageUp);
+        assert_eq!(press(key, &mode, false, &[], false), Action::PageUp);
 
         // Test Shift+Down for page down
         let key = KeyEvent::new(KeyCode::Down, KeyModifiers::SHIFT);
-        assert_eq!(handle_key_event(key, &mode, false, false, false, false, false, false), Action::PageDown);
+        assert_eq!(press(key, &mode, false, &[], false), Action::PageDown);
+    }
+
+    #[test]
+    fn test_custom_binding() {
+        let mode = AppMode::Normal;
+        let mut keymap = Keymap::default();
+        keymap.bind(vec![KeyChord::char('L')], Action::GotoLastColumn);
+        keymap.bind(vec![KeyChord::ctrl('c')], Action::MoveDown);
+        let ctx = KeyContext {
+            mode: &mode,
+            show_help: false,
+            pending_keys: &[],
+            has_number_prefix: false,
+            has_error_popup: false,
+            has_file_browser: false,
+            has_sequence_finder: false,
+            has_distance_panel: false,
+            has_block_picker: false,
+            keymap: &keymap,
+        };
+        let key = KeyEvent::new(KeyCode::Char('L'), KeyModifiers::SHIFT);
+        assert_eq!(handle_key_event(key, &ctx), Action::GotoLastColumn);
+
+        // Ctrl+C cannot be remapped away
+        let key = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(handle_key_event(key, &ctx), Action::Quit);
+    }
+
+    #[test]
+    fn test_unknown_sequence_clears_pending_keys() {
+        let alignment = crate::model::Alignment::new(vec![crate::model::Sequence::new("seq1", "ACGT")]);
+        let mut state = AppState::new(alignment, "test".to_string());
+
+        apply_action(&mut state, Action::PendingKey(KeyChord::char('g')));
+        assert_eq!(state.pending_keys, vec![KeyChord::char('g')]);
+
+        // 'gx' is not bound: the pending 'g' must not stick
+        apply_action(&mut state, Action::None);
+        assert!(state.pending_keys.is_empty());
+    }
+
+    #[test]
+    fn test_command_line_editing_keys() {
+        let mode = AppMode::Command("w out".to_string());
+        let key = KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(press(key, &mode, false, &[], false), Action::CompleteNext);
+        let key = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(press(key, &mode, false, &[], false), Action::CompletePrevious);
+        let key = KeyEvent::new(KeyCode::Up, KeyModifiers::NONE);
+        assert_eq!(press(key, &mode, false, &[], false), Action::HistoryPrevious);
+        let key = KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(press(key, &mode, false, &[], false), Action::InputDeleteWord);
+
+        // Search shares the editing keys
+        let mode = AppMode::Search("ACG".to_string());
+        let key = KeyEvent::new(KeyCode::Left, KeyModifiers::NONE);
+        assert_eq!(press(key, &mode, false, &[], false), Action::InputCursorLeft);
+        let key = KeyEvent::new(KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(press(key, &mode, false, &[], false), Action::HistoryNext);
+    }
+
+    #[test]
+    fn test_sequence_finder_keys() {
+        let mode = AppMode::Normal;
+        let key = KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL);
+        assert_eq!(press(key, &mode, false, &[], false), Action::OpenSequenceFinder);
+
+        let keymap = Keymap::default();
+        let ctx = KeyContext {
+            mode: &mode,
+            show_help: false,
+            pending_keys: &[],
+            has_number_prefix: false,
+            has_error_popup: false,
+            has_file_browser: false,
+            has_sequence_finder: true,
+            has_distance_panel: false,
+            has_block_picker: false,
+            keymap: &keymap,
+        };
+        // Letters are typed into the query, not interpreted as motions
+        let key = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE);
+        assert_eq!(handle_key_event(key, &ctx), Action::FinderInput('j'));
+        let key = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL);
+        assert_eq!(handle_key_event(key, &ctx), Action::FinderDown);
+        let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(handle_key_event(key, &ctx), Action::FinderSelect);
     }
 }
@@ -0,0 +1,167 @@
+//! Sequence filtering by length, gap content, ambiguity and stop codons.
+//!
+//! Used in CLI mode to drop unreliable sequences before writing output.
+//! Length, gap and ambiguity checks are applied to the input sequences;
+//! the internal stop check is applied after translation.
+
+use std::collections::HashSet;
+
+use crate::model::{is_gap, Alignment, Sequence};
+use crate::rows::{gap_fraction, ungapped_length};
+
+/// Thresholds for dropping sequences. `None` disables a check.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SequenceFilter {
+    /// Minimum number of non-gap residues
+    pub min_length: Option<usize>,
+    /// Maximum proportion of gap characters (0-1)
+    pub max_gap_fraction: Option<f64>,
+    /// Maximum proportion of ambiguous residues among non-gap residues (0-1)
+    pub max_ambiguity: Option<f64>,
+    /// Drop sequences with a stop codon (`*`) before their last residue
+    pub drop_internal_stops: bool,
+}
+
+/// A sequence removed by a filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dropped {
+    pub id: String,
+    pub reason: String,
+}
+
+impl SequenceFilter {
+    /// Returns true if any check is enabled.
+    pub fn is_active(&self) -> bool {
+        self.min_length.is_some()
+            || self.max_gap_fraction.is_some()
+            || self.max_ambiguity.is_some()
+            || self.drop_internal_stops
+    }
+
+    /// Returns the reason a sequence fails the length, gap or ambiguity checks.
+    ///
+    /// Ambiguous residues are `N` for nucleotides and `X` for amino acids.
+    pub fn check(&self, seq: &Sequence, nucleotide: bool) -> Option<String> {
+        let length = ungapped_length(seq);
+        if let Some(min) = self.min_length {
+            if length < min {
+                return Some(format!("length {} < {}", length, min));
+            }
+        }
+        if let Some(max) = self.max_gap_fraction {
+            let gaps = gap_fraction(seq);
+            if gaps > max {
+                return Some(format!("gap fraction {:.3} > {}", gaps, max));
+            }
+        }
+        if let Some(max) = self.max_ambiguity {
+            let ambiguity = ambiguity_fraction(seq, nucleotide);
+            if ambiguity > max {
+                return Some(format!("ambiguity {:.3} > {}", ambiguity, max));
+            }
+        }
+        None
+    }
+
+    /// Removes sequences failing the length, gap or ambiguity checks.
+    pub fn apply(&self, alignment: Alignment) -> (Alignment, Vec<Dropped>) {
+        let nucleotide = alignment.sequence_type.is_nucleotide();
+        retain(alignment, |seq| self.check(seq, nucleotide))
+    }
+
+    /// Returns the reason a (translated) sequence fails the internal stop check, if enabled.
+    pub fn check_stops(&self, seq: &Sequence) -> Option<String> {
+        if !self.drop_internal_stops {
+            return None;
+        }
+        internal_stop(seq).map(|pos| format!("internal stop at position {}", pos + 1))
+    }
+
+    /// Removes sequences with internal stop codons, if enabled.
+    pub fn apply_stops(&self, alignment: Alignment) -> (Alignment, Vec<Dropped>) {
+        if !self.drop_internal_stops {
+            return (alignment, Vec::new());
+        }
+        retain(alignment, |seq| self.check_stops(seq))
+    }
+}
+
+/// Keeps sequences for which `reason` returns `None`, preserving the sequence type.
+fn retain(alignment: Alignment, reason: impl Fn(&Sequence) -> Option<String>) -> (Alignment, Vec<Dropped>) {
+    let sequence_type = alignment.sequence_type;
+    let mut kept = Vec::with_capacity(alignment.sequences.len());
+    let mut dropped = Vec::new();
+    for seq in alignment.sequences {
+        match reason(&seq) {
+            Some(reason) => dropped.push(Dropped { id: seq.id.clone(), reason }),
+            None => kept.push(seq),
+        }
+    }
+    let names: HashSet<&str> = kept.iter().map(|seq| seq.id.as_str()).collect();
+    let annotations = alignment.annotations.select_sequences(|name| names.contains(name).then(|| name.to_string()));
+    let mut filtered = Alignment::new(kept);
+    filtered.sequence_type = sequence_type;
+    filtered.annotations = Box::new(annotations);
+    (filtered, dropped)
+}
+
+/// Proportion of ambiguous residues among non-gap residues (0.0 if there are none).
+pub fn ambiguity_fraction(seq: &Sequence, nucleotide: bool) -> f64 {
+    let length = ungapped_length(seq);
+    if length == 0 {
+        return 0.0;
+    }
+    let ambiguous = if nucleotide { b'N' } else { b'X' };
+    let count = seq.as_bytes().iter().filter(|b| b.to_ascii_uppercase() == ambiguous).count();
+    count as f64 / length as f64
+}
+
+/// Position (0-based column) of the first stop codon (`*`) that is followed by
+/// another residue. Terminal stops, including those followed only by gaps, are allowed.
+pub fn internal_stop(seq: &Sequence) -> Option<usize> {
+    let bytes = seq.as_bytes();
+    let last_residue = bytes.iter().rposition(|&b| !is_gap(b))?;
+    bytes[..last_residue].iter().position(|&b| b == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seq(id: &str, data: &str) -> Sequence {
+        Sequence::new(id, data)
+    }
+
+    #[test]
+    fn test_checks() {
+        let filter = SequenceFilter {
+            min_length: Some(4),
+            max_gap_fraction: Some(0.5),
+            max_ambiguity: Some(0.25),
+            drop_internal_stops: false,
+        };
+        assert!(filter.is_active());
+        assert_eq!(filter.check(&seq("a", "ACGT----"), true), None);
+        assert_eq!(filter.check(&seq("b", "ACG-----"), true).unwrap(), "length 3 < 4");
+        assert!(filter.check(&seq("c", "A-C-G-T--"), true).unwrap().starts_with("gap fraction"));
+        assert!(filter.check(&seq("d", "ACNNT"), true).unwrap().starts_with("ambiguity"));
+        // N is asparagine in proteins; X is the ambiguous residue
+        assert_eq!(filter.check(&seq("e", "MNNNK"), false), None);
+        assert!(filter.check(&seq("f", "MXXXK"), false).is_some());
+        assert!(!SequenceFilter::default().is_active());
+    }
+
+    #[test]
+    fn test_internal_stops() {
+        assert_eq!(internal_stop(&seq("a", "MK*")), None);
+        assert_eq!(internal_stop(&seq("b", "MK*--")), None);
+        assert_eq!(internal_stop(&seq("c", "M*K")), Some(1));
+        assert_eq!(internal_stop(&seq("d", "---")), None);
+
+        let filter = SequenceFilter { drop_internal_stops: true, ..Default::default() };
+        let alignment = Alignment::new(vec![seq("a", "MK*"), seq("b", "M*K")]);
+        let (kept, dropped) = filter.apply_stops(alignment);
+        assert_eq!(kept.sequence_count(), 1);
+        assert_eq!(dropped, vec![Dropped { id: "b".into(), reason: "internal stop at position 2".into() }]);
+    }
+}
@@ -0,0 +1,168 @@
+//! A2M/A3M parser.
+//!
+//! Reads the alignments of HHblits, HMMER (`--outformat A2M`) and jackhmmer,
+//! FASTA files where insert states are marked by case.
+//!
+//! ## A2M/A3M Format
+//!
+//! Uppercase residues and `-` are match states (one per HMM column); lowercase
+//! residues and `.` are inserts. A2M pads inserts with `.` so that sequences
+//! have the same length; A3M leaves them out:
+//! ```text
+//! >query
+//! MK-LV
+//! >hit1
+//! MKaaLLV
+//! >hit2
+//! MrK-LV
+//! ```
+//!
+//! Insert columns are expanded so that the sequences are aligned (`M-K---LV`,
+//! `M-KaaLLV`, `MrK---LV`), and recorded in a `#=GC RF` annotation (`x` match,
+//! `.` insert) used to hide and colour them in the viewer.
+
+use thiserror::Error;
+
+use crate::model::{Alignment, Sequence};
+
+/// Errors that can occur during A2M/A3M parsing.
+#[derive(Error, Debug)]
+pub enum A3mError {
+    #[error("Empty A3M file")]
+    EmptyFile,
+
+    #[error("Sequence without header at line {0}")]
+    SequenceWithoutHeader(usize),
+
+    #[error("Empty sequence identifier at line {0}")]
+    EmptyIdentifier(usize),
+
+    #[error("Sequence '{name}' has {len} match states, expected {expected} (like '{first}')")]
+    MatchLength { name: String, len: usize, expected: usize, first: String },
+}
+
+/// Result type for A2M/A3M operations.
+pub type A3mResult<T> = Result<T, A3mError>;
+
+/// Returns true if a residue is an insert state (lowercase or `.`).
+fn is_insert(b: u8) -> bool {
+    b.is_ascii_lowercase() || b == b'.'
+}
+
+/// A sequence split into match states and the inserts before each of them.
+struct Row<'a> {
+    name: &'a str,
+    matches: Vec<u8>,
+    /// Inserts before each match state, then after the last one
+    inserts: Vec<Vec<u8>>,
+}
+
+impl<'a> Row<'a> {
+    fn new(name: &'a str) -> Self {
+        Self { name, matches: Vec::new(), inserts: vec![Vec::new()] }
+    }
+
+    fn push_line(&mut self, line: &str) {
+        for b in line.bytes().filter(|b| !b.is_ascii_whitespace()) {
+            if is_insert(b) {
+                self.inserts.last_mut().expect("one insert run per match state").push(b);
+            } else {
+                self.matches.push(b);
+                self.inserts.push(Vec::new());
+            }
+        }
+    }
+}
+
+/// Parses A2M or A3M content from a string, expanding the insert columns.
+pub fn parse_a3m_str(content: &str) -> A3mResult<Alignment> {
+    let mut rows: Vec<Row> = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        // '#' lines before the first sequence are HH-suite comments
+        if line.is_empty() || (rows.is_empty() && line.starts_with('#')) {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('>') {
+            let name = header.split_whitespace().next().ok_or(A3mError::EmptyIdentifier(i + 1))?;
+            rows.push(Row::new(name));
+        } else {
+            rows.last_mut().ok_or(A3mError::SequenceWithoutHeader(i + 1))?.push_line(line);
+        }
+    }
+
+    let first = rows.first().ok_or(A3mError::EmptyFile)?;
+    let (first, expected) = (first.name.to_string(), first.matches.len());
+    if let Some(row) = rows.iter().find(|row| row.matches.len() != expected) {
+        return Err(A3mError::MatchLength { name: row.name.to_string(), len: row.matches.len(), expected, first });
+    }
+
+    // Width of each insert run: the longest one across sequences
+    let widths: Vec<usize> = (0..=expected)
+        .map(|k| rows.iter().map(|row| row.inserts[k].len()).max().unwrap_or(0))
+        .collect();
+
+    let mut reference = Vec::with_capacity(expected + widths.iter().sum::<usize>());
+    for (k, &width) in widths.iter().enumerate() {
+        reference.extend(std::iter::repeat_n(b'.', width));
+        if k < expected {
+            reference.push(b'x');
+        }
+    }
+
+    let sequences = rows
+        .iter()
+        .map(|row| {
+            let mut data = Vec::with_capacity(reference.len());
+            for (k, &width) in widths.iter().enumerate() {
+                let inserts = &row.inserts[k];
+                data.extend(inserts.iter().map(|&b| if b == b'.' { b'-' } else { b }));
+                data.extend(std::iter::repeat_n(b'-', width - inserts.len()));
+                if let Some(&b) = row.matches.get(k) {
+                    data.push(b);
+                }
+            }
+            Sequence::from_bytes(row.name, data)
+        })
+        .collect();
+
+    let mut alignment = Alignment::new(sequences);
+    alignment.annotations.column.push(("RF".to_string(), reference));
+    Ok(alignment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_a3m() {
+        let content = "#A3M#\n>query desc\nMK-LV\n>hit1\nMKaa\nLLV\n>hit2\nMrK-LV\n";
+        let alignment = parse_a3m_str(content).unwrap();
+        assert!(alignment.is_valid_alignment);
+        assert_eq!(alignment.get(0).unwrap().as_str(), "M-K---LV");
+        assert_eq!(alignment.get(1).unwrap().as_str(), "M-KaaLLV");
+        assert_eq!(alignment.get(2).unwrap().as_str(), "MrK---LV");
+        assert_eq!(alignment.annotations.column("RF"), Some(b"x.x..xxx".as_slice()));
+        assert_eq!(alignment.annotations.match_columns(), Some(vec![0, 2, 5, 6, 7]));
+    }
+
+    #[test]
+    fn test_parse_a2m() {
+        // A2M pads inserts with '.', which keeps their layout
+        let content = ">query\nMK..LV\n>hit1\nMKa.LV\n>hit2\nM-.bL-\n";
+        let alignment = parse_a3m_str(content).unwrap();
+        assert_eq!(alignment.get(1).unwrap().as_str(), "MKa-LV");
+        assert_eq!(alignment.get(2).unwrap().as_str(), "M--bL-");
+        assert_eq!(alignment.annotations.column("RF"), Some(b"xx..xx".as_slice()));
+    }
+
+    #[test]
+    fn test_a3m_errors() {
+        assert!(matches!(parse_a3m_str("\n"), Err(A3mError::EmptyFile)));
+        assert!(matches!(parse_a3m_str("MKLV\n"), Err(A3mError::SequenceWithoutHeader(1))));
+        let content = ">query\nMK-LV\n>hit1\nMKaaLV\n";
+        assert!(matches!(parse_a3m_str(content), Err(A3mError::MatchLength { len: 4, expected: 5, .. })));
+    }
+}
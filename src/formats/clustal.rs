@@ -0,0 +1,154 @@
+//! Clustal format parser.
+//!
+//! Reads the `.aln` output of Clustal W/X/Omega, MUSCLE and similar tools.
+//!
+//! ## Clustal Format
+//!
+//! A `CLUSTAL` header line, then blocks separated by blank lines. Each block has
+//! one line per sequence (name, residues and an optional residue count) and a
+//! conservation line:
+//! ```text
+//! CLUSTAL W (1.83) multiple sequence alignment
+//!
+//! seq1      ACGTACGTAC 10
+//! seq2      ACGTTCGTAC 10
+//!           **** *****
+//!
+//! seq1      GGTT 14
+//! seq2      GG-T 13
+//!           ** *
+//! ```
+//!
+//! ## Relaxed Parsing
+//!
+//! - Headers from MUSCLE and PROBCONS (`MUSCLE (3.8) multiple sequence alignment`)
+//! - Any whitespace between names and residues; residue counts are optional
+//! - Conservation lines (starting with whitespace) are ignored
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::model::{Alignment, Sequence};
+
+/// Errors that can occur during Clustal parsing.
+#[derive(Error, Debug)]
+pub enum ClustalError {
+    #[error("Empty Clustal file")]
+    EmptyFile,
+
+    #[error("Not a Clustal file (must start with a CLUSTAL header)")]
+    NotClustal,
+
+    #[error("No sequence data found after header")]
+    NoSequenceData,
+
+    #[error("Line {line}: sequence '{name}' is not in the first block")]
+    UnknownSequence { line: usize, name: String },
+
+    #[error("Line {line}: missing residues for '{name}'")]
+    MissingResidues { line: usize, name: String },
+}
+
+/// Result type for Clustal operations.
+pub type ClustalResult<T> = Result<T, ClustalError>;
+
+/// Returns true if a line is a Clustal header (`CLUSTAL ...`, or a MUSCLE or
+/// PROBCONS header).
+pub fn is_clustal_header(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("CLUSTAL")
+        || ((line.starts_with("MUSCLE") || line.starts_with("PROBCONS"))
+            && line.contains("multiple sequence alignment"))
+}
+
+/// Parses Clustal content from a string.
+pub fn parse_clustal_str(content: &str) -> ClustalResult<Alignment> {
+    let mut lines = content.lines().enumerate().skip_while(|(_, line)| line.trim().is_empty());
+    let (_, header) = lines.next().ok_or(ClustalError::EmptyFile)?;
+    if !is_clustal_header(header) {
+        return Err(ClustalError::NotClustal);
+    }
+
+    let mut sequences: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    // Names are collected from the first block only
+    let mut first_block = true;
+
+    for (i, line) in lines {
+        // Conservation lines start with whitespace; blank lines end a block
+        if line.trim().is_empty() {
+            if !sequences.is_empty() {
+                first_block = false;
+            }
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+
+        let mut fields = line.split_whitespace();
+        let name = fields.next().unwrap_or_default();
+        let residues = fields.next().ok_or_else(|| ClustalError::MissingResidues {
+            line: i + 1,
+            name: name.to_string(),
+        })?;
+
+        match index.get(name) {
+            Some(&idx) => sequences[idx].1.extend(residues.bytes()),
+            None if first_block => {
+                index.insert(name.to_string(), sequences.len());
+                sequences.push((name.to_string(), residues.as_bytes().to_vec()));
+            }
+            None => {
+                return Err(ClustalError::UnknownSequence { line: i + 1, name: name.to_string() });
+            }
+        }
+    }
+
+    if sequences.is_empty() {
+        return Err(ClustalError::NoSequenceData);
+    }
+
+    let sequences = sequences
+        .into_iter()
+        .map(|(name, data)| Sequence::from_bytes(name, data))
+        .collect();
+    Ok(Alignment::new(sequences))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clustal() {
+        let content = "CLUSTAL W (1.83) multiple sequence alignment\n\n\
+                       seq1      ACGTACGTAC 10\n\
+                       seq2      ACGTTCGTAC 10\n\
+                       \x20         **** *****\n\n\
+                       seq1      GGTT 14\n\
+                       seq2      GG-T 13\n\
+                       \x20         ** *\n";
+        let alignment = parse_clustal_str(content).unwrap();
+        assert_eq!(alignment.sequence_count(), 2);
+        assert_eq!(alignment.get(0).unwrap().as_str(), "ACGTACGTACGGTT");
+        assert_eq!(alignment.get(1).unwrap().as_str(), "ACGTTCGTACGG-T");
+        assert!(alignment.is_valid_alignment);
+    }
+
+    #[test]
+    fn test_muscle_header_without_counts() {
+        let content = "MUSCLE (3.8) multiple sequence alignment\n\n\nA  MK-L\nB  MKAL\n   ** *\n";
+        let alignment = parse_clustal_str(content).unwrap();
+        assert_eq!(alignment.get(0).unwrap().as_str(), "MK-L");
+    }
+
+    #[test]
+    fn test_clustal_errors() {
+        assert!(matches!(parse_clustal_str(">seq1\nACGT\n"), Err(ClustalError::NotClustal)));
+        assert!(matches!(parse_clustal_str("CLUSTAL W\n\n"), Err(ClustalError::NoSequenceData)));
+        let content = "CLUSTAL W\n\nseq1 ACGT\n\nseq1 AC\nseq3 GG\n";
+        assert!(matches!(parse_clustal_str(content), Err(ClustalError::UnknownSequence { line: 6, .. })));
+    }
+}
@@ -172,7 +172,7 @@ pub fn parse_fasta<R: BufRead>(reader: R) -> FastaResult<Alignment> {
             continue;
         }
 
-        if line.starts_with('>') {
+        if let Some(header) = line.strip_prefix('>') {
             // Save previous sequence if exists
             if let Some(id) = current_id.take() {
                 if !current_seq.is_empty() {
@@ -183,7 +183,6 @@ pub fn parse_fasta<R: BufRead>(reader: R) -> FastaResult<Alignment> {
             }
 
             // Parse new header - take everything after '>' and before first space as ID
-            let header = &line[1..];
             let id = header
                 .split_whitespace()
                 .next()
@@ -4,15 +4,23 @@
 //! - FASTA (.fasta, .fa, .fna, .faa, .fas)
 //! - PHYLIP (.phy, .phylip) - sequential and interleaved
 //! - NEXUS (.nex, .nexus, .nxs)
+//! - Clustal (.aln, .clw, .clustal) - conservation lines are ignored
+//! - MSF (.msf)
+//! - Stockholm (.sto, .stk) - with `#=GF`, `#=GS`, `#=GR` and `#=GC` markup
+//! - A2M/A3M (.a2m, .a3m) - insert columns are expanded (by extension or -f only)
 //!
 //! Format detection priority:
 //! 1. Explicit format specification (-f option)
 //! 2. File extension
 //! 3. Content-based detection
 
+pub mod a3m;
+pub mod clustal;
 pub mod fasta;
+pub mod msf;
 pub mod nexus;
 pub mod phylip;
+pub mod stockholm;
 
 use std::ffi::OsStr;
 use std::fs::File;
@@ -30,6 +38,10 @@ pub enum FileFormat {
     Fasta,
     Phylip,
     Nexus,
+    Clustal,
+    Msf,
+    Stockholm,
+    A3m,
 }
 
 impl std::fmt::Display for FileFormat {
@@ -38,6 +50,10 @@ impl std::fmt::Display for FileFormat {
             FileFormat::Fasta => write!(f, "FASTA"),
             FileFormat::Phylip => write!(f, "PHYLIP"),
             FileFormat::Nexus => write!(f, "NEXUS"),
+            FileFormat::Clustal => write!(f, "Clustal"),
+            FileFormat::Msf => write!(f, "MSF"),
+            FileFormat::Stockholm => write!(f, "Stockholm"),
+            FileFormat::A3m => write!(f, "A2M/A3M"),
         }
     }
 }
@@ -55,7 +71,11 @@ pub enum ParseError {
              Hint: Use -f/--format to specify the format explicitly:\n  \
              seqtui -f fasta <file>   # FASTA format\n  \
              seqtui -f nexus <file>   # NEXUS format\n  \
-             seqtui -f phylip <file>  # PHYLIP format")]
+             seqtui -f phylip <file>  # PHYLIP format\n  \
+             seqtui -f clustal <file> # Clustal format\n  \
+             seqtui -f msf <file>     # MSF format\n  \
+             seqtui -f stockholm <file> # Stockholm format\n  \
+             seqtui -f a3m <file>     # A2M/A3M format")]
     UnknownFormat,
 
     #[error("Ambiguous file format (could be {possible}).\n\
@@ -71,6 +91,21 @@ pub enum ParseError {
 
     #[error("NEXUS error: {0}")]
     NexusError(#[from] nexus::NexusError),
+
+    #[error("Clustal error: {0}")]
+    ClustalError(#[from] clustal::ClustalError),
+
+    #[error("MSF error: {0}")]
+    MsfError(#[from] msf::MsfError),
+
+    #[error("Stockholm error: {0}")]
+    StockholmError(#[from] stockholm::StockholmError),
+
+    #[error("A2M/A3M error: {0}")]
+    A3mError(#[from] a3m::A3mError),
+
+    #[error("Choosing a character block requires a NEXUS file (got {0})")]
+    BlockNotNexus(FileFormat),
 }
 
 /// Result type for parsing operations.
@@ -86,6 +121,12 @@ pub fn detect_format_from_extension<P: AsRef<Path>>(path: P) -> Option<FileForma
         "nex" | "nexus" | "nxs" => Some(FileFormat::Nexus),
         // PHYLIP extensions
         "phy" | "phylip" | "ph" => Some(FileFormat::Phylip),
+        // Clustal and MSF extensions
+        "aln" | "clw" | "clustal" => Some(FileFormat::Clustal),
+        "msf" => Some(FileFormat::Msf),
+        "sto" | "stk" | "stockholm" => Some(FileFormat::Stockholm),
+        // A2M/A3M look like FASTA: only known by extension
+        "a2m" | "a3m" => Some(FileFormat::A3m),
         _ => None,
     }
 }
@@ -107,13 +148,29 @@ pub fn detect_format_from_content(content: &str) -> Option<FileFormat> {
         if trimmed.starts_with('>') {
             return Some(FileFormat::Fasta);
         }
+
+        // Stockholm: # STOCKHOLM 1.0 header
+        if stockholm::is_stockholm_header(trimmed) {
+            return Some(FileFormat::Stockholm);
+        }
+
+        // Clustal: CLUSTAL (or MUSCLE) header
+        if clustal::is_clustal_header(trimmed) {
+            return Some(FileFormat::Clustal);
+        }
+
+        // MSF: MSF: header line before a // separator
+        if msf::looks_like_msf(content) {
+            return Some(FileFormat::Msf);
+        }
         
         // PHYLIP: first line is "ntax nchar" (two integers)
         let parts: Vec<&str> = trimmed.split_whitespace().collect();
-        if parts.len() >= 2 {
-            if parts[0].parse::<usize>().is_ok() && parts[1].parse::<usize>().is_ok() {
-                return Some(FileFormat::Phylip);
-            }
+        if parts.len() >= 2
+            && parts[0].parse::<usize>().is_ok()
+            && parts[1].parse::<usize>().is_ok()
+        {
+            return Some(FileFormat::Phylip);
         }
         
         // First non-empty line doesn't match any known format
@@ -124,11 +181,11 @@ pub fn detect_format_from_content(content: &str) -> Option<FileFormat> {
 }
 
 /// Tries to parse with multiple formats, returning the first success.
-fn try_parse_formats(content: &str, formats: &[FileFormat]) -> ParseResult<(Alignment, FileFormat)> {
+fn try_parse_formats(content: &str, formats: &[FileFormat], block: Option<&str>) -> ParseResult<(Alignment, FileFormat)> {
     let mut last_error = None;
     
     for &format in formats {
-        match parse_content(content, format) {
+        match parse_content(content, format, block) {
             Ok(alignment) => return Ok((alignment, format)),
             Err(e) => last_error = Some(e),
         }
@@ -137,12 +194,18 @@ fn try_parse_formats(content: &str, formats: &[FileFormat]) -> ParseResult<(Alig
     Err(last_error.unwrap_or(ParseError::UnknownFormat))
 }
 
-/// Parses content with a specific format.
-fn parse_content(content: &str, format: FileFormat) -> ParseResult<Alignment> {
+/// Parses content with a specific format, keeping the NEXUS character block
+/// `block` if given.
+fn parse_content(content: &str, format: FileFormat, block: Option<&str>) -> ParseResult<Alignment> {
     match format {
+        FileFormat::Nexus => nexus::parse_nexus_block(content, block).map_err(ParseError::NexusError),
+        _ if block.is_some() => Err(ParseError::BlockNotNexus(format)),
         FileFormat::Fasta => parse_fasta_fast(content).map_err(ParseError::FastaError),
         FileFormat::Phylip => phylip::parse_phylip_str(content).map_err(ParseError::PhylipError),
-        FileFormat::Nexus => nexus::parse_nexus_str(content).map_err(ParseError::NexusError),
+        FileFormat::Clustal => clustal::parse_clustal_str(content).map_err(ParseError::ClustalError),
+        FileFormat::Msf => msf::parse_msf_str(content).map_err(ParseError::MsfError),
+        FileFormat::Stockholm => stockholm::parse_stockholm_str(content).map_err(ParseError::StockholmError),
+        FileFormat::A3m => a3m::parse_a3m_str(content).map_err(ParseError::A3mError),
     }
 }
 
@@ -152,10 +215,20 @@ fn parse_content(content: &str, format: FileFormat) -> ParseResult<Alignment> {
 /// 1. Explicit format (if provided)
 /// 2. File extension
 /// 3. Content-based detection
-/// 4. Try all formats (FASTA first as most common, then NEXUS, then PHYLIP)
+/// 4. Try all formats (FASTA first as most common, then NEXUS, Clustal, MSF, Stockholm, then PHYLIP)
 pub fn parse_file_with_options<P: AsRef<Path>>(
     path: P,
     forced_format: Option<FileFormat>,
+) -> ParseResult<Alignment> {
+    parse_file_with_block(path, forced_format, None)
+}
+
+/// Parses a sequence file like `parse_file_with_options`, keeping the NEXUS
+/// character block `block` (TITLE or 1-based number) instead of the first one.
+pub fn parse_file_with_block<P: AsRef<Path>>(
+    path: P,
+    forced_format: Option<FileFormat>,
+    block: Option<&str>,
 ) -> ParseResult<Alignment> {
     let file = File::open(&path)?;
     let metadata = file.metadata()?;
@@ -171,12 +244,12 @@ pub fn parse_file_with_options<P: AsRef<Path>>(
     
     // 1. Use explicit format if provided
     if let Some(format) = forced_format {
-        return parse_content(&content, format);
+        return parse_content(&content, format, block);
     }
     
     // 2. Try to detect from extension
     if let Some(format) = detect_format_from_extension(&path) {
-        match parse_content(&content, format) {
+        match parse_content(&content, format, block) {
             Ok(alignment) => return Ok(alignment),
             Err(_) => {
                 // Extension didn't work, try content detection
@@ -186,7 +259,7 @@ pub fn parse_file_with_options<P: AsRef<Path>>(
     
     // 3. Try content-based detection
     if let Some(format) = detect_format_from_content(&content) {
-        match parse_content(&content, format) {
+        match parse_content(&content, format, block) {
             Ok(alignment) => return Ok(alignment),
             Err(e) => return Err(e),
         }
@@ -194,9 +267,9 @@ pub fn parse_file_with_options<P: AsRef<Path>>(
     
     // 4. Last resort: try all formats in order of likelihood
     // FASTA is most common and has clear markers
-    // NEXUS has clear #NEXUS header
+    // NEXUS has clear #NEXUS header, Clustal, MSF and Stockholm have their own headers
     // PHYLIP is most ambiguous
-    match try_parse_formats(&content, &[FileFormat::Fasta, FileFormat::Nexus, FileFormat::Phylip]) {
+    match try_parse_formats(&content, &[FileFormat::Fasta, FileFormat::Nexus, FileFormat::Clustal, FileFormat::Msf, FileFormat::Stockholm, FileFormat::Phylip], block) {
         Ok((alignment, _)) => Ok(alignment),
         Err(_) => Err(ParseError::UnknownFormat),
     }
@@ -264,7 +337,30 @@ mod tests {
         assert_eq!(detect_format_from_extension("test.phy"), Some(FileFormat::Phylip));
         assert_eq!(detect_format_from_extension("test.phylip"), Some(FileFormat::Phylip));
         assert_eq!(detect_format_from_extension("test.txt"), None);
-        assert_eq!(detect_format_from_extension("test.aln"), None);
+        assert_eq!(detect_format_from_extension("test.aln"), Some(FileFormat::Clustal));
+        assert_eq!(detect_format_from_extension("test.msf"), Some(FileFormat::Msf));
+        assert_eq!(detect_format_from_extension("test.a3m"), Some(FileFormat::A3m));
+        assert_eq!(detect_format_from_extension("test.a2m"), Some(FileFormat::A3m));
+    }
+
+    #[test]
+    fn test_detect_clustal_and_msf() {
+        let content = "CLUSTAL O(1.2.4) multiple sequence alignment\n\nseq1 ACGT\n";
+        assert_eq!(detect_format_from_content(content), Some(FileFormat::Clustal));
+
+        let content = "pileup.msf  MSF: 4  Type: N  Check: 1  ..\n\n Name: seq1 Len: 4\n\n//\n\nseq1 ACGT\n";
+        assert_eq!(detect_format_from_content(content), Some(FileFormat::Msf));
+    }
+
+    #[test]
+    fn test_detect_stockholm() {
+        assert_eq!(detect_format_from_extension("PF00001.sto"), Some(FileFormat::Stockholm));
+        let content = "# STOCKHOLM 1.0
+#=GF ID test
+seq1 ACGT
+//
+";
+        assert_eq!(detect_format_from_content(content), Some(FileFormat::Stockholm));
     }
 
     #[test]
@@ -0,0 +1,164 @@
+//! MSF format parser.
+//!
+//! Reads GCG MSF files (written by PileUp, Clustal, T-Coffee and others).
+//!
+//! ## MSF Format
+//!
+//! A header with the `MSF:` line and one `Name:` line per sequence, a `//`
+//! separator, then blocks of residues in groups of ten:
+//! ```text
+//! !!NA_MULTIPLE_ALIGNMENT 1.0
+//!
+//!  example.msf  MSF: 14  Type: N  Check: 1234  ..
+//!
+//!  Name: seq1  Len: 14  Check: 1111  Weight: 1.00
+//!  Name: seq2  Len: 14  Check: 2222  Weight: 1.00
+//!
+//! //
+//!
+//!             1                                   14
+//! seq1        ACGTACGTAC GGTT
+//! seq2        ACGTTCGTAC GG.T
+//! ```
+//!
+//! ## Supported Features
+//!
+//! - `.` and `~` gaps, normalized to `-`
+//! - `Type: N` / `Type: P` sets the sequence type
+//! - Position lines between blocks are ignored
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::model::{Alignment, Sequence, SequenceType};
+
+/// Errors that can occur during MSF parsing.
+#[derive(Error, Debug)]
+pub enum MsfError {
+    #[error("Empty MSF file")]
+    EmptyFile,
+
+    #[error("Not an MSF file (missing '//' after the header)")]
+    MissingSeparator,
+
+    #[error("No 'Name:' lines in the MSF header")]
+    NoNames,
+
+    #[error("Duplicate sequence name: '{0}'")]
+    DuplicateName(String),
+}
+
+/// Result type for MSF operations.
+pub type MsfResult<T> = Result<T, MsfError>;
+
+/// Returns true if content looks like MSF: an `MSF:` header line (or a `!!` /
+/// `PileUp` first line) before a `//` separator.
+pub fn looks_like_msf(content: &str) -> bool {
+    let mut header = content.lines().take_while(|line| line.trim() != "//");
+    let first = header.by_ref().find(|line| !line.trim().is_empty()).unwrap_or_default();
+    let msf_header = first.starts_with("!!") || first.trim() == "PileUp" || first.contains("MSF:")
+        || header.any(|line| line.contains("MSF:"));
+    msf_header && content.lines().any(|line| line.trim() == "//")
+}
+
+/// Parses MSF content from a string.
+pub fn parse_msf_str(content: &str) -> MsfResult<Alignment> {
+    if content.trim().is_empty() {
+        return Err(MsfError::EmptyFile);
+    }
+
+    let mut lines = content.lines();
+    let mut names: Vec<String> = Vec::new();
+    let mut sequence_type = None;
+    let mut separated = false;
+
+    // Header: sequence type and names, up to '//'
+    for line in lines.by_ref() {
+        let trimmed = line.trim();
+        if trimmed == "//" {
+            separated = true;
+            break;
+        }
+        let words: Vec<&str> = trimmed.split_whitespace().collect();
+        if let Some(pos) = words.iter().position(|&w| w == "Type:") {
+            sequence_type = match words.get(pos + 1).map(|t| t.to_ascii_uppercase()) {
+                Some(t) if t == "N" => Some(SequenceType::NUCLEOTIDE),
+                Some(t) if t == "P" => Some(SequenceType::AMINO_ACID),
+                _ => sequence_type,
+            };
+        }
+        if words.first() == Some(&"Name:") {
+            if let Some(name) = words.get(1) {
+                if names.iter().any(|n| n == name) {
+                    return Err(MsfError::DuplicateName(name.to_string()));
+                }
+                names.push(name.to_string());
+            }
+        }
+    }
+    if !separated {
+        return Err(MsfError::MissingSeparator);
+    }
+    if names.is_empty() {
+        return Err(MsfError::NoNames);
+    }
+
+    // Alignment blocks: lines starting with a known name; others (positions) are skipped
+    let index: HashMap<&str, usize> = names.iter().enumerate().map(|(i, n)| (n.as_str(), i)).collect();
+    let mut data: Vec<Vec<u8>> = vec![Vec::new(); names.len()];
+    for line in lines {
+        let mut fields = line.split_whitespace();
+        let Some(&idx) = fields.next().and_then(|name| index.get(name)) else {
+            continue;
+        };
+        for chunk in fields {
+            data[idx].extend(chunk.bytes().map(|b| match b {
+                b'.' | b'~' => b'-',
+                b => b,
+            }));
+        }
+    }
+
+    let sequences = names
+        .into_iter()
+        .zip(data)
+        .map(|(name, data)| Sequence::from_bytes(name, data))
+        .collect();
+    let mut alignment = Alignment::new(sequences);
+    if let Some(sequence_type) = sequence_type {
+        alignment.sequence_type = sequence_type;
+    }
+    Ok(alignment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "!!NA_MULTIPLE_ALIGNMENT 1.0\n\n example.msf  MSF: 14  Type: N  Check: 1234  ..\n\n \
+                           Name: seq1  Len: 14  Check: 1111  Weight: 1.00\n \
+                           Name: seq2  Len: 14  Check: 2222  Weight: 1.00\n\n//\n\n            \
+                           1                                   14\n\
+                           seq1        ACGTACGTAC GGTT\n\
+                           seq2        ACGTTCGTAC GG.T\n";
+
+    #[test]
+    fn test_parse_msf() {
+        assert!(looks_like_msf(EXAMPLE));
+        let alignment = parse_msf_str(EXAMPLE).unwrap();
+        assert_eq!(alignment.sequence_count(), 2);
+        assert_eq!(alignment.get(0).unwrap().as_str(), "ACGTACGTACGGTT");
+        assert_eq!(alignment.get(1).unwrap().as_str(), "ACGTTCGTACGG-T");
+        assert_eq!(alignment.sequence_type, SequenceType::NUCLEOTIDE);
+    }
+
+    #[test]
+    fn test_msf_errors() {
+        assert!(!looks_like_msf(">seq1\nACGT\n//\n"));
+        assert!(matches!(parse_msf_str("PileUp\n MSF: 4\n"), Err(MsfError::MissingSeparator)));
+        assert!(matches!(parse_msf_str("PileUp\n MSF: 4\n//\n"), Err(MsfError::NoNames)));
+        let duplicate = "PileUp\n Name: a Len: 4\n Name: a Len: 4\n//\n";
+        assert!(matches!(parse_msf_str(duplicate), Err(MsfError::DuplicateName(_))));
+    }
+}
@@ -20,10 +20,20 @@
 //!
 //! ## Supported Features
 //!
-//! - DATA and CHARACTERS blocks
+//! - DATA and CHARACTERS blocks; with several matrices, one is chosen by TITLE or
+//!   number (`parse_nexus_block`) and the titles are kept in `Alignment::blocks`;
+//!   the other matrices that cannot be read are reported with a warning
+//! - TAXA blocks (TITLE, TAXLABELS) linked with LINK TAXA: NTAX, taxon numbers as
+//!   row names, taxa of transposed matrices
 //! - DIMENSIONS command (NTAX, NCHAR)
-//! - FORMAT command (DATATYPE, GAP, MISSING, INTERLEAVE)
-//! - MATRIX command (sequential and interleaved)
+//! - FORMAT command: DATATYPE (sets the sequence type), GAP and MISSING (normalized
+//!   to `-` and `?`), MATCHCHAR, EQUATE, RESPECTCASE, INTERLEAVE and TRANSPOSE
+//! - TAXLABELS command (taxon names of a transposed matrix)
+//! - MATRIX command (sequential, interleaved and transposed); rows are checked
+//!   against NCHAR, and polymorphic or uncertain states (`{01}`, `(AG)`) are read
+//!   as one state (IUPAC code for nucleotides, X for proteins, else `?`)
+//! - SETS blocks (`charset`, `charpartition`, `taxset`), kept in `Alignment::sets`;
+//!   a SETS block that cannot be read is ignored with a warning
 //!
 //! ## Relaxed Parsing
 //!
@@ -33,7 +43,8 @@
 
 use thiserror::Error;
 
-use crate::model::{Alignment, Sequence};
+use crate::model::{Alignment, Sequence, SequenceType};
+use crate::partition;
 
 /// Errors that can occur during NEXUS parsing.
 #[derive(Error, Debug)]
@@ -72,6 +83,22 @@ pub enum NexusError {
     #[error("Unterminated MATRIX (missing ';')")]
     UnterminatedMatrix,
 
+    #[error("Unterminated state set in MATRIX (missing '}}' or ')')")]
+    UnterminatedStateSet,
+
+    #[error("No character block '{name}' (available: {available})")]
+    UnknownBlock { name: String, available: String },
+
+    #[error("TRANSPOSE requires taxon names (TAXLABELS)")]
+    MissingTaxlabels,
+
+    #[error("Row {row} of the transposed matrix has {found} states, expected {expected} (NTAX)")]
+    TransposedRowMismatch {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+
     #[error("Duplicate sequence name: '{0}'")]
     DuplicateName(String),
 
@@ -82,14 +109,146 @@ pub enum NexusError {
 /// Result type for NEXUS operations.
 pub type NexusResult<T> = Result<T, NexusError>;
 
-/// Parses NEXUS content from a string.
+/// A character matrix (DATA or CHARACTERS block) of a NEXUS file.
+#[derive(Debug, Clone)]
+pub struct NexusMatrix {
+    /// TITLE of the block, or `Matrix n` for an untitled block
+    pub title: String,
+    pub alignment: Alignment,
+}
+
+/// Taxon labels of a TAXA block.
+#[derive(Debug, Clone, Default)]
+struct TaxaBlock {
+    title: Option<String>,
+    labels: Vec<String>,
+}
+
+/// Parses NEXUS content from a string (the first character matrix).
 pub fn parse_nexus_str(content: &str) -> NexusResult<Alignment> {
-    let lines: Vec<&str> = content.lines().collect();
-    
-    if lines.is_empty() {
-        return Err(NexusError::EmptyFile);
+    parse_nexus_block(content, None)
+}
+
+/// Parses NEXUS content, keeping the character matrix named `block` (its TITLE,
+/// or its 1-based number), or the first one.
+///
+/// When the file has several matrices, their titles are kept in `Alignment::blocks`.
+/// Only the chosen matrix must be readable: errors in the others become warnings.
+pub fn parse_nexus_block(content: &str, block: Option<&str>) -> NexusResult<Alignment> {
+    let blocks = find_blocks(content)?;
+    let taxa = taxa_blocks(&blocks);
+    let matrices: Vec<&str> = matrix_blocks(&blocks).collect();
+    if matrices.is_empty() {
+        return Err(NexusError::NoDataBlock);
+    }
+
+    let titles: Vec<String> = matrices.iter().enumerate().map(|(i, content)| block_title(content, i)).collect();
+    let index = match block {
+        None => 0,
+        Some(name) => titles
+            .iter()
+            .position(|t| t.eq_ignore_ascii_case(name))
+            .or_else(|| name.parse::<usize>().ok().filter(|n| (1..=titles.len()).contains(n)).map(|n| n - 1))
+            .ok_or_else(|| NexusError::UnknownBlock { name: name.to_string(), available: titles.join(", ") })?,
+    };
+    let mut alignment = parse_data_block(matrices[index], &taxa)?;
+
+    // The other matrices are only checked, so that they can be chosen later
+    for (i, content) in matrices.iter().enumerate().filter(|&(i, _)| i != index) {
+        if let Err(e) = parse_data_block(content, &taxa) {
+            let warning = format!("Unreadable block '{}': {}", titles[i], e);
+            alignment.warning = Some(match alignment.warning.take() {
+                Some(w) => format!("{}; {}", w, warning),
+                None => warning,
+            });
+        }
+    }
+    if titles.len() > 1 {
+        alignment.blocks = titles;
+        alignment.block = index;
     }
 
+    // Read the SETS blocks, if any, against the selected matrix
+    let sets_content: String = blocks
+        .iter()
+        .filter(|(name, _)| name == "SETS")
+        .flat_map(|(_, content)| content.lines())
+        .map(|line| remove_nexus_comments(line) + "\n")
+        .collect();
+    if !sets_content.trim().is_empty() {
+        let taxa: Vec<&str> = alignment.sequences.iter().map(|s| s.id.as_str()).collect();
+        match partition::parse_sets_block(&sets_content, alignment.alignment_length(), &taxa) {
+            Ok(sets) if !sets.is_empty() => alignment.sets = Some(sets),
+            Ok(_) => {}
+            Err(e) => {
+                let warning = format!("Ignored SETS block: {}", e);
+                alignment.warning = Some(match alignment.warning.take() {
+                    Some(w) => format!("{}; {}", w, warning),
+                    None => warning,
+                });
+            }
+        }
+    }
+    Ok(alignment)
+}
+
+/// Parses all character matrices (DATA and CHARACTERS blocks) of NEXUS content.
+pub fn parse_nexus_matrices(content: &str) -> NexusResult<Vec<NexusMatrix>> {
+    parse_matrices(&find_blocks(content)?)
+}
+
+/// Parses the DATA and CHARACTERS blocks, resolving taxa through TAXA blocks.
+fn parse_matrices(blocks: &[(String, String)]) -> NexusResult<Vec<NexusMatrix>> {
+    let taxa = taxa_blocks(blocks);
+    let matrices = matrix_blocks(blocks)
+        .enumerate()
+        .map(|(i, content)| Ok(NexusMatrix { title: block_title(content, i), alignment: parse_data_block(content, &taxa)? }))
+        .collect::<NexusResult<Vec<_>>>()?;
+
+    if matrices.is_empty() {
+        return Err(NexusError::NoDataBlock);
+    }
+    Ok(matrices)
+}
+
+/// Reads the TAXA blocks.
+fn taxa_blocks(blocks: &[(String, String)]) -> Vec<TaxaBlock> {
+    blocks
+        .iter()
+        .filter(|(name, _)| name == "TAXA")
+        .map(|(_, content)| parse_taxa_block(content))
+        .collect()
+}
+
+/// Contents of the DATA and CHARACTERS blocks, in file order.
+fn matrix_blocks(blocks: &[(String, String)]) -> impl Iterator<Item = &str> {
+    blocks
+        .iter()
+        .filter(|(name, _)| name == "DATA" || name == "CHARACTERS")
+        .map(|(_, content)| content.as_str())
+}
+
+/// TITLE of the `index`-th (0-based) DATA or CHARACTERS block, or `Matrix n`.
+/// Only the commands before MATRIX are read.
+fn block_title(content: &str, index: usize) -> String {
+    let header: String = content
+        .lines()
+        .take_while(|line| !line.trim_start().to_uppercase().starts_with("MATRIX"))
+        .map(|line| line.to_string() + "\n")
+        .collect();
+    normalize_nexus_commands(&header)
+        .lines()
+        .map(str::trim)
+        .find(|line| line.to_uppercase().starts_with("TITLE"))
+        .and_then(|line| command_words(line).get(1).cloned())
+        .unwrap_or_else(|| format!("Matrix {}", index + 1))
+}
+
+/// Splits NEXUS content into blocks: uppercased name and content lines (trimmed,
+/// comments kept). Checks the `#NEXUS` header.
+fn find_blocks(content: &str) -> NexusResult<Vec<(String, String)>> {
+    let lines: Vec<&str> = content.lines().collect();
+
     // Verify #NEXUS header
     let first_non_empty = lines
         .iter()
@@ -100,53 +259,240 @@ pub fn parse_nexus_str(content: &str) -> NexusResult<Alignment> {
         return Err(NexusError::NotNexus);
     }
 
-    // Find DATA or CHARACTERS block and extract its content
-    let block_content = find_data_block(&lines)?;
-    
-    // Parse the block
-    parse_data_block(&block_content)
-}
-
-/// Finds and extracts the content of a DATA or CHARACTERS block.
-fn find_data_block(lines: &[&str]) -> NexusResult<String> {
+    let mut blocks: Vec<(String, String)> = Vec::new();
     let mut in_block = false;
-    let mut block_lines: Vec<&str> = Vec::new();
 
     for line in lines {
         let trimmed = line.trim();
         let upper = trimmed.to_uppercase();
+        let words: Vec<&str> = upper
+            .split(|c: char| c.is_whitespace() || c == ';')
+            .filter(|w| !w.is_empty())
+            .collect();
 
         if !in_block {
-            // Look for BEGIN DATA; or BEGIN CHARACTERS;
-            if upper.starts_with("BEGIN") && (upper.contains("DATA") || upper.contains("CHARACTERS")) {
+            // Look for BEGIN name;
+            if words.len() >= 2 && words[0] == "BEGIN" {
+                blocks.push((words[1].to_string(), String::new()));
                 in_block = true;
             }
+        } else if matches!(words.first(), Some(&"END") | Some(&"ENDBLOCK")) {
+            in_block = false;
+        } else if let Some((_, content)) = blocks.last_mut() {
+            content.push_str(trimmed);
+            content.push('\n');
+        }
+    }
+
+    Ok(blocks)
+}
+
+/// Parses a TAXA block (TITLE and TAXLABELS).
+fn parse_taxa_block(content: &str) -> TaxaBlock {
+    let mut taxa = TaxaBlock::default();
+    for line in normalize_nexus_commands(content).lines() {
+        let upper = line.trim().to_uppercase();
+        if upper.starts_with("TITLE") {
+            taxa.title = command_words(line).get(1).cloned();
+        } else if upper.starts_with("TAXLABELS") {
+            taxa.labels = tokenize_matrix(&line.trim()["TAXLABELS".len()..]).iter().map(|t| unquote(t)).collect();
+        }
+    }
+    taxa
+}
+
+/// Settings of the FORMAT command.
+#[derive(Debug, Clone, PartialEq)]
+struct Format {
+    /// DATATYPE, uppercased (None if not given)
+    datatype: Option<String>,
+    gap: u8,
+    missing: u8,
+    matchchar: Option<u8>,
+    /// EQUATE macros: symbol and the state it stands for
+    equate: Vec<(u8, u8)>,
+    interleave: bool,
+    respect_case: bool,
+    transpose: bool,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Self {
+            datatype: None,
+            gap: b'-',
+            missing: b'?',
+            matchchar: None,
+            equate: Vec::new(),
+            interleave: false,
+            respect_case: false,
+            transpose: false,
+        }
+    }
+}
+
+impl Format {
+    /// Sequence type given by DATATYPE (None for STANDARD, MIXED, etc.).
+    fn sequence_type(&self) -> Option<SequenceType> {
+        match self.datatype.as_deref()? {
+            "DNA" | "RNA" | "NUCLEOTIDE" => Some(SequenceType::NUCLEOTIDE),
+            "PROTEIN" => Some(SequenceType::AMINO_ACID),
+            _ => None,
+        }
+    }
+
+    /// Byte lookup table applying EQUATE and normalizing GAP and MISSING to `-`
+    /// and `?`. Symbols are case-insensitive unless RESPECTCASE is set.
+    fn symbol_table(&self) -> [u8; 256] {
+        let mut table = [0u8; 256];
+        for (i, b) in table.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let mut set = |symbol: u8, to: u8| {
+            table[symbol as usize] = to;
+            if !self.respect_case {
+                table[symbol.to_ascii_lowercase() as usize] = to;
+                table[symbol.to_ascii_uppercase() as usize] = to;
+            }
+        };
+        for &(symbol, state) in &self.equate {
+            set(symbol, state);
+        }
+        set(self.missing, b'?');
+        set(self.gap, b'-');
+        table
+    }
+
+    /// State standing for a set of states (`{AG}` or `(AG)`) in EQUATE: its
+    /// IUPAC code for nucleotides, X for proteins, else missing.
+    fn ambiguity(&self, states: &str) -> u8 {
+        if let [state] = states.as_bytes() {
+            return *state;
+        }
+        match self.sequence_type() {
+            Some(t) if t == SequenceType::NUCLEOTIDE => {
+                let mut bases: Vec<u8> = states
+                    .bytes()
+                    .map(|b| match b.to_ascii_uppercase() {
+                        b'U' => b'T',
+                        b => b,
+                    })
+                    .collect();
+                bases.sort_unstable();
+                bases.dedup();
+                match bases.as_slice() {
+                    b"AG" => b'R',
+                    b"CT" => b'Y',
+                    b"CG" => b'S',
+                    b"AT" => b'W',
+                    b"GT" => b'K',
+                    b"AC" => b'M',
+                    b"CGT" => b'B',
+                    b"AGT" => b'D',
+                    b"ACT" => b'H',
+                    b"ACG" => b'V',
+                    _ => b'N',
+                }
+            }
+            Some(_) => b'X',
+            None => b'?',
+        }
+    }
+}
+
+/// Splits a command into words, `=` signs and quoted strings (unquoted).
+fn command_words(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut chars = command.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || c == ';' {
+            chars.next();
+        } else if c == '=' {
+            chars.next();
+            words.push("=".to_string());
+        } else if c == '"' || c == '\'' {
+            chars.next();
+            let mut word = String::new();
+            for q in chars.by_ref() {
+                if q == c {
+                    break;
+                }
+                word.push(q);
+            }
+            words.push(word);
         } else {
-            // Check for END;
-            if upper.starts_with("END") || upper.starts_with("ENDBLOCK") {
-                break;
+            let mut word = String::new();
+            while let Some(&c) = chars.peek().filter(|c| !c.is_whitespace() && !matches!(c, '=' | ';')) {
+                word.push(c);
+                chars.next();
             }
-            block_lines.push(trimmed);
+            words.push(word);
         }
     }
+    words
+}
 
-    if block_lines.is_empty() && !in_block {
-        return Err(NexusError::NoDataBlock);
+/// Parses a FORMAT command (`FORMAT DATATYPE=DNA GAP=- ...`).
+fn parse_format(command: &str) -> Format {
+    let mut format = Format::default();
+    let words = command_words(command);
+    let mut equate = None;
+    let mut i = 1;
+    while i < words.len() {
+        let key = words[i].to_ascii_uppercase();
+        let value = if words.get(i + 1).map(String::as_str) == Some("=") {
+            i += 2;
+            words.get(i).cloned()
+        } else {
+            None
+        };
+        i += 1;
+        let symbol = value.as_ref().and_then(|v| v.bytes().next());
+        match key.as_str() {
+            "DATATYPE" => format.datatype = value.map(|v| v.to_ascii_uppercase()),
+            "GAP" => format.gap = symbol.unwrap_or(b'-'),
+            "MISSING" => format.missing = symbol.unwrap_or(b'?'),
+            "MATCHCHAR" => format.matchchar = symbol,
+            "EQUATE" => equate = value,
+            "INTERLEAVE" => format.interleave = !value.is_some_and(|v| v.eq_ignore_ascii_case("no")),
+            "RESPECTCASE" => format.respect_case = true,
+            "TRANSPOSE" => format.transpose = true,
+            _ => {}
+        }
     }
 
-    Ok(block_lines.join("\n"))
+    // EQUATE="R={AG} Y={CT} ..." (read once DATATYPE is known)
+    if let Some(equate) = equate {
+        let words = command_words(&equate);
+        for pair in words.windows(3).filter(|w| w[1] == "=") {
+            if let [symbol] = pair[0].as_bytes() {
+                let states = pair[2].trim_matches(|c| matches!(c, '{' | '}' | '(' | ')'));
+                if !states.is_empty() {
+                    let state = format.ambiguity(states);
+                    format.equate.push((*symbol, state));
+                }
+            }
+        }
+    }
+    format
 }
 
-/// Parses the content of a DATA or CHARACTERS block.
-fn parse_data_block(content: &str) -> NexusResult<Alignment> {
+/// Parses the content of a DATA or CHARACTERS block into its matrix (the TITLE
+/// is read by `block_title`).
+///
+/// Without its own TAXLABELS, the block takes the taxa of the TAXA block given by
+/// LINK TAXA (or of the first one): NTAX, taxon numbers used as row names, and
+/// the taxon order of a transposed matrix.
+fn parse_data_block(content: &str, taxa: &[TaxaBlock]) -> NexusResult<Alignment> {
     // First, normalize the content by joining multi-line commands
     // NEXUS commands end with ';', so we join lines until we see ';'
     let normalized = normalize_nexus_commands(content);
     
     let mut ntax: Option<usize> = None;
     let mut nchar: Option<usize> = None;
-    let mut interleave = false;
-    let mut matchchar: Option<char> = None;
+    let mut format = Format::default();
+    let mut taxlabels: Vec<String> = Vec::new();
+    let mut link: Option<String> = None;
     let mut matrix_content = String::new();
     let mut in_matrix = false;
 
@@ -173,12 +519,18 @@ fn parse_data_block(content: &str) -> NexusResult<Alignment> {
                 nchar = n.parse().ok();
             }
         } else if upper.starts_with("FORMAT") {
-            // Check for INTERLEAVE in the full FORMAT command
-            interleave = upper.contains("INTERLEAVE");
-            // Check for MATCHCHAR
-            if let Some(mc) = extract_param(&upper, "MATCHCHAR") {
-                matchchar = mc.chars().next();
-            }
+            format = parse_format(trimmed);
+        } else if upper.starts_with("TAXLABELS") {
+            taxlabels = tokenize_matrix(&trimmed["TAXLABELS".len()..]).iter().map(|t| unquote(t)).collect();
+        } else if upper.starts_with("TITLE") && !in_matrix {
+            // Read by block_title
+        } else if upper.starts_with("LINK") && !in_matrix {
+            // LINK TAXA = name;
+            let words = command_words(trimmed);
+            link = words
+                .windows(3)
+                .find(|w| w[0].eq_ignore_ascii_case("TAXA") && w[1] == "=")
+                .map(|w| w[2].clone());
         } else if upper.starts_with("MATRIX") || in_matrix {
             in_matrix = true;
             // This is matrix content - preserve everything including comments
@@ -205,18 +557,31 @@ fn parse_data_block(content: &str) -> NexusResult<Alignment> {
         }
     }
 
+    // Taxa of the linked TAXA block, unless the block has its own
+    if taxlabels.is_empty() {
+        let linked = match &link {
+            Some(link) => taxa.iter().find(|t| t.title.as_ref().is_some_and(|title| title.eq_ignore_ascii_case(link))),
+            None => taxa.first(),
+        };
+        taxlabels = linked.map(|t| t.labels.clone()).unwrap_or_default();
+    }
+
     // Use ntax and nchar if available
-    let expected_ntax = ntax.unwrap_or(0);
+    let expected_ntax = ntax.unwrap_or(taxlabels.len());
     let expected_nchar = nchar.unwrap_or(0);
 
     // Parse the matrix using token-based approach
-    let sequences = parse_matrix(&matrix_content, expected_ntax, expected_nchar, interleave, matchchar)?;
+    let sequences = parse_matrix(&matrix_content, expected_ntax, expected_nchar, &format, &taxlabels)?;
 
     if sequences.is_empty() {
         return Err(NexusError::NoDataBlock);
     }
 
-    Ok(Alignment::new(sequences))
+    let mut alignment = Alignment::new(sequences);
+    if let Some(sequence_type) = format.sequence_type() {
+        alignment.sequence_type = sequence_type;
+    }
+    Ok(alignment)
 }
 
 /// Normalizes NEXUS commands by joining multi-line commands into single lines.
@@ -348,25 +713,52 @@ fn extract_param<'a>(line: &'a str, param: &str) -> Option<&'a str> {
 /// In sequential format: name1 seq1 name2 seq2 ... (each seq can span multiple lines)
 /// We use NTAX to know how many sequences to expect, and NCHAR to know sequence length.
 /// If matchchar is specified, it will be replaced with the corresponding character from the first sequence.
-fn parse_matrix(content: &str, ntax: usize, nchar: usize, interleave: bool, matchchar: Option<char>) -> NexusResult<Vec<Sequence>> {
-    // Tokenize: split by whitespace, remove comments
-    let tokens = tokenize_matrix(content);
+/// A transposed matrix has one row per character, taxa in the order of `taxlabels`;
+/// rows named by taxon number take the label.
+/// Polymorphic and uncertain states (`{01}`, `(AG)`) count as one character.
+fn parse_matrix(content: &str, ntax: usize, nchar: usize, format: &Format, taxlabels: &[String]) -> NexusResult<Vec<Sequence>> {
+    // Tokenize: split by whitespace, remove comments, collapse state sets
+    let tokens = collapse_state_sets(tokenize_matrix(content), format)?;
     
     if tokens.is_empty() {
         return Ok(Vec::new());
     }
     
     // Parse into (name, data) pairs first
-    let mut raw_sequences = if interleave && ntax > 0 {
+    let mut raw_sequences = if format.transpose {
+        parse_transposed_tokens_raw(&tokens, taxlabels, nchar)?
+    } else if format.interleave && ntax > 0 {
         parse_interleaved_tokens_raw(&tokens, ntax, nchar)?
     } else {
         parse_sequential_tokens_raw(&tokens, ntax, nchar)?
     };
+    if nchar > 0 {
+        if let Some((name, data)) = raw_sequences.iter().find(|(_, data)| data.len() != nchar) {
+            return Err(NexusError::SequenceLengthMismatch { name: name.clone(), expected: nchar, found: data.len() });
+        }
+    }
     
+    // Rows named by taxon number take the taxon label
+    for (name, _) in &mut raw_sequences {
+        if let Ok(n) = name.parse::<usize>() {
+            if (1..=taxlabels.len()).contains(&n) && !taxlabels.contains(name) {
+                *name = taxlabels[n - 1].clone();
+            }
+        }
+    }
+
     // Apply MATCHCHAR substitution if specified
-    if let Some(mc) = matchchar {
+    if let Some(mc) = format.matchchar {
         apply_matchchar_raw(&mut raw_sequences, mc);
     }
+
+    // Apply EQUATE, then normalize GAP and MISSING symbols
+    let table = format.symbol_table();
+    for (_, data) in &mut raw_sequences {
+        for byte in data.iter_mut() {
+            *byte = table[*byte as usize];
+        }
+    }
     
     // Shrink excess capacity from sequence building
     for (_, data) in &mut raw_sequences {
@@ -383,12 +775,11 @@ fn parse_matrix(content: &str, ntax: usize, nchar: usize, interleave: bool, matc
 }
 
 /// Apply MATCHCHAR substitution on raw sequence data
-fn apply_matchchar_raw(sequences: &mut [(String, Vec<u8>)], matchchar: char) {
+fn apply_matchchar_raw(sequences: &mut [(String, Vec<u8>)], matchchar_byte: u8) {
     if sequences.len() < 2 {
         return;
     }
     
-    let matchchar_byte = matchchar as u8;
     let first_seq_data: Vec<u8> = sequences[0].1.clone();
     
     for (_, seq_data) in sequences.iter_mut().skip(1) {
@@ -453,6 +844,46 @@ fn tokenize_matrix(content: &str) -> Vec<String> {
     tokens
 }
 
+/// Replaces each state set (`{01}` or `(AG)`, possibly split by whitespace) with
+/// one symbol given by `Format::ambiguity`. Quoted tokens (names) are kept.
+fn collapse_state_sets(tokens: Vec<String>, format: &Format) -> NexusResult<Vec<String>> {
+    let mut result = Vec::with_capacity(tokens.len());
+    // Token being built and the states of the open set
+    let mut open: Option<(String, String)> = None;
+
+    for token in tokens {
+        if open.is_none() && (token.starts_with('\'') || token.starts_with('"') || !token.contains(['{', '('])) {
+            result.push(token);
+            continue;
+        }
+        let (mut current, mut states) = match open.take() {
+            Some((current, states)) => (current, Some(states)),
+            None => (String::new(), None),
+        };
+        for c in token.chars() {
+            match (&mut states, c) {
+                (None, '{' | '(') => states = Some(String::new()),
+                (Some(set), '}' | ')') => {
+                    current.push(format.ambiguity(set) as char);
+                    states = None;
+                }
+                (Some(_), ',') => {}
+                (Some(set), c) => set.push(c),
+                (None, c) => current.push(c),
+            }
+        }
+        match states {
+            Some(states) => open = Some((current, states)),
+            None => result.push(current),
+        }
+    }
+
+    if open.is_some() {
+        return Err(NexusError::UnterminatedStateSet);
+    }
+    Ok(result)
+}
+
 /// Parse sequential format tokens into raw (name, data) pairs.
 /// Pattern: name1 seqdata1 [seqdata1...] name2 seqdata2 [seqdata2...] ...
 /// We use NTAX and NCHAR to determine boundaries.
@@ -544,6 +975,37 @@ fn parse_interleaved_tokens_raw(tokens: &[String], ntax: usize, nchar: usize) ->
     Ok(sequences)
 }
 
+/// Parse transposed tokens (one row per character: a character label, then
+/// one state per taxon) into raw (name, data) pairs.
+fn parse_transposed_tokens_raw(tokens: &[String], taxlabels: &[String], nchar: usize) -> NexusResult<Vec<(String, Vec<u8>)>> {
+    if taxlabels.is_empty() {
+        return Err(NexusError::MissingTaxlabels);
+    }
+    let ntax = taxlabels.len();
+    let mut data: Vec<Vec<u8>> = vec![Vec::with_capacity(nchar); ntax];
+    let mut i = 0;
+    let mut row = 0;
+
+    while i < tokens.len() && (nchar == 0 || row < nchar) {
+        // Skip the character label
+        i += 1;
+        let mut states = Vec::with_capacity(ntax);
+        while i < tokens.len() && states.len() < ntax {
+            states.extend(tokens[i].as_bytes());
+            i += 1;
+        }
+        row += 1;
+        if states.len() != ntax {
+            return Err(NexusError::TransposedRowMismatch { row, expected: ntax, found: states.len() });
+        }
+        for (seq, state) in data.iter_mut().zip(states) {
+            seq.push(state);
+        }
+    }
+
+    Ok(taxlabels.iter().cloned().zip(data).collect())
+}
+
 /// Remove quotes from a string if present
 fn unquote(s: &str) -> String {
     let s = s.trim();
@@ -781,4 +1243,200 @@ END;
         // Position 0: T, 1: C(seq1[1]), 2: T, 3: T(seq1[3]), 4: T, 5: C(seq1[5]), 6: T, 7: T(seq1[7]), 8: T, 9: C(seq1[9])
         assert_eq!(alignment.get(2).unwrap().as_str(), "TCTTTCTTTC");
     }
+
+    #[test]
+    fn test_sets_block() {
+        let content = r#"#NEXUS
+BEGIN DATA;
+  DIMENSIONS NTAX=2 NCHAR=10;
+  FORMAT DATATYPE=DNA GAP=-;
+  MATRIX
+    seq1 ACGTACGTAC
+    seq2 ACGTTGGTAC
+  ;
+END;
+BEGIN SETS;
+  [coding region]
+  CHARSET cds = 1-6;
+  CHARSET utr = 7-.;
+  TAXSET pair = seq1 seq2;
+END;
+"#;
+        let alignment = parse_nexus_str(content).unwrap();
+        let sets = alignment.sets.unwrap();
+        assert_eq!(sets.charsets.len(), 2);
+        assert_eq!(sets.charsets[1].columns(), vec![6, 7, 8, 9]);
+        assert_eq!(sets.taxsets[0].1, vec!["seq1", "seq2"]);
+        assert!(alignment.warning.is_none());
+
+        // An unreadable SETS block is ignored with a warning
+        let broken = content.replace("7-.", "7-20");
+        let alignment = parse_nexus_str(&broken).unwrap();
+        assert!(alignment.sets.is_none());
+        assert!(alignment.warning.unwrap().contains("Ignored SETS block"));
+    }
+
+    #[test]
+    fn test_format_symbols() {
+        let content = r#"#NEXUS
+BEGIN DATA;
+  DIMENSIONS NTAX=2 NCHAR=8;
+  FORMAT DATATYPE=DNA GAP=. MISSING=n MATCHCHAR=: EQUATE="R={AG} Y=(CT) U=T";
+  MATRIX
+    seq1 ACGT..UR
+    seq2 ::NNrY:-
+  ;
+END;
+"#;
+        let alignment = parse_nexus_str(content).unwrap();
+        // GAP and MISSING become - and ?, EQUATE symbols are expanded (case-insensitive)
+        assert_eq!(alignment.get(0).unwrap().as_str(), "ACGT--TR");
+        assert_eq!(alignment.get(1).unwrap().as_str(), "AC??RYT-");
+        assert_eq!(alignment.sequence_type, SequenceType::NUCLEOTIDE);
+
+        // RESPECTCASE: only the given case is a symbol
+        let respect = content.replace("DATATYPE=DNA", "DATATYPE=DNA RESPECTCASE");
+        assert_eq!(parse_nexus_str(&respect).unwrap().get(1).unwrap().as_str(), "ACNNrYT-");
+    }
+
+    #[test]
+    fn test_protein_datatype() {
+        // Only A, C, G and T residues, but DATATYPE says protein
+        let content = "#NEXUS\nBEGIN DATA;\nDIMENSIONS NTAX=2 NCHAR=4;\nFORMAT DATATYPE=PROTEIN INTERLEAVE=NO;\nMATRIX\na ACGT\nb ACGA\n;\nEND;\n";
+        let alignment = parse_nexus_str(content).unwrap();
+        assert_eq!(alignment.sequence_type, SequenceType::AMINO_ACID);
+
+        let format = parse_format("FORMAT datatype = protein equate = \"B = (DN)\" interleave;");
+        assert_eq!(format.equate, vec![(b'B', b'X')]);
+        assert!(format.interleave);
+    }
+
+    #[test]
+    fn test_transposed_matrix() {
+        let content = r#"#NEXUS
+BEGIN DATA;
+  DIMENSIONS NTAX=3 NCHAR=4;
+  FORMAT DATATYPE=DNA TRANSPOSE;
+  TAXLABELS seq1 'seq two' seq3;
+  MATRIX
+    c1 AAC
+    c2 CC-
+    c3 G G ?
+    c4 TTA
+  ;
+END;
+"#;
+        let alignment = parse_nexus_str(content).unwrap();
+        assert_eq!(alignment.get(1).unwrap().id, "seq two");
+        assert_eq!(alignment.get(0).unwrap().as_str(), "ACGT");
+        assert_eq!(alignment.get(2).unwrap().as_str(), "C-?A");
+
+        let no_labels = content.replace("  TAXLABELS seq1 'seq two' seq3;\n", "");
+        assert!(matches!(parse_nexus_str(&no_labels), Err(NexusError::MissingTaxlabels)));
+    }
+
+    #[test]
+    fn test_taxa_and_several_matrices() {
+        let content = r#"#NEXUS
+BEGIN TAXA;
+  TITLE Taxa;
+  DIMENSIONS NTAX=3;
+  TAXLABELS 'Homo sapiens' Pan Gorilla;
+END;
+BEGIN CHARACTERS;
+  TITLE DNA;
+  LINK TAXA = Taxa;
+  DIMENSIONS NCHAR=4;
+  FORMAT DATATYPE=DNA INTERLEAVE;
+  MATRIX
+    'Homo sapiens' AC
+    Pan AC
+    Gorilla AT
+    'Homo sapiens' GT
+    Pan GA
+    Gorilla GA
+  ;
+END;
+BEGIN CHARACTERS;
+  DIMENSIONS NCHAR=3;
+  FORMAT DATATYPE=STANDARD SYMBOLS="0 1";
+  MATRIX
+    1 010
+    2 011
+    3 110
+  ;
+END;
+"#;
+        let matrices = parse_nexus_matrices(content).unwrap();
+        assert_eq!(matrices.iter().map(|m| m.title.as_str()).collect::<Vec<_>>(), ["DNA", "Matrix 2"]);
+        // NTAX comes from the TAXA block (needed for interleaved matrices)
+        assert_eq!(matrices[0].alignment.get(2).unwrap().as_str(), "ATGA");
+
+        let alignment = parse_nexus_str(content).unwrap();
+        assert_eq!(alignment.blocks, ["DNA", "Matrix 2"]);
+        assert_eq!(alignment.block, 0);
+
+        // Rows given by taxon number take the TAXA labels
+        let alignment = parse_nexus_block(content, Some("2")).unwrap();
+        assert_eq!(alignment.block, 1);
+        assert_eq!(alignment.get(0).unwrap().id, "Homo sapiens");
+        assert_eq!(alignment.get(2).unwrap().as_str(), "110");
+        assert_eq!(parse_nexus_block(content, Some("dna")).unwrap().block, 0);
+        assert!(matches!(parse_nexus_block(content, Some("morphology")), Err(NexusError::UnknownBlock { .. })));
+    }
+
+    #[test]
+    fn test_state_sets_count_as_one_character() {
+        let content = "#NEXUS\nBEGIN DATA;\nDIMENSIONS NTAX=2 NCHAR=3;\nFORMAT DATATYPE=STANDARD;\nMATRIX\nA 0{01}1\nB 1(0 1)0\n;\nEND;\n";
+        let alignment = parse_nexus_str(content).unwrap();
+        assert_eq!(alignment.get(0).unwrap().as_str(), "0?1");
+        assert_eq!(alignment.get(1).unwrap().as_str(), "1?0");
+
+        // Nucleotide sets become IUPAC codes
+        let content = "#NEXUS\nBEGIN DATA;\nDIMENSIONS NTAX=2 NCHAR=4;\nFORMAT DATATYPE=DNA;\nMATRIX\nA AC{AG}T\nB A( C T )GT\n;\nEND;\n";
+        let alignment = parse_nexus_str(content).unwrap();
+        assert_eq!(alignment.get(0).unwrap().as_str(), "ACRT");
+        assert_eq!(alignment.get(1).unwrap().as_str(), "AYGT");
+
+        let unterminated = content.replace("( C T )", "( C T");
+        assert!(matches!(parse_nexus_str(&unterminated), Err(NexusError::UnterminatedStateSet)));
+    }
+
+    #[test]
+    fn test_row_length_checked_against_nchar() {
+        let content = "#NEXUS\nBEGIN DATA;\nDIMENSIONS NTAX=2 NCHAR=3;\nMATRIX\nA 0101\nB 110\n;\nEND;\n";
+        let err = parse_nexus_str(content).unwrap_err();
+        assert!(matches!(err, NexusError::SequenceLengthMismatch { expected: 3, found: 4, .. }), "{}", err);
+    }
+
+    #[test]
+    fn test_unreadable_other_block_is_a_warning() {
+        let content = r#"#NEXUS
+BEGIN DATA;
+  DIMENSIONS NTAX=2 NCHAR=4;
+  FORMAT DATATYPE=DNA;
+  MATRIX
+    seq1 ACGT
+    seq2 ACGA
+  ;
+END;
+BEGIN CHARACTERS;
+  TITLE Traits;
+  DIMENSIONS NCHAR=2;
+  FORMAT DATATYPE=CONTINUOUS TRANSPOSE;
+  MATRIX
+    length 1.5 2.0
+  ;
+END;
+"#;
+        let alignment = parse_nexus_str(content).unwrap();
+        assert_eq!(alignment.blocks, ["Matrix 1", "Traits"]);
+        assert_eq!(alignment.get(1).unwrap().as_str(), "ACGA");
+        let warning = alignment.warning.unwrap();
+        assert!(warning.contains("Unreadable block 'Traits'"), "{}", warning);
+        assert!(warning.contains("TRANSPOSE requires taxon names"), "{}", warning);
+
+        // The chosen block must be readable
+        assert!(matches!(parse_nexus_block(content, Some("traits")), Err(NexusError::MissingTaxlabels)));
+    }
 }
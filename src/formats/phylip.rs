@@ -162,7 +162,7 @@ fn split_name_and_sequence(line: &str) -> (Option<String>, String) {
         if name_words.len() == 1  // Single word name (with padding)
             && !name_trimmed.is_empty() 
             && !seq_chars.is_empty() 
-            && seq_chars.chars().all(|c| is_sequence_char(c)) 
+            && seq_chars.chars().all(is_sequence_char) 
         {
             return (Some(name_trimmed.to_string()), seq_chars);
         }
@@ -175,14 +175,14 @@ fn split_name_and_sequence(line: &str) -> (Option<String>, String) {
         let seq: String = rest.chars().filter(|c| !c.is_whitespace()).collect();
         
         // Check if this looks like a name + sequence
-        if !name.is_empty() && !seq.is_empty() && seq.chars().all(|c| is_sequence_char(c)) {
+        if !name.is_empty() && !seq.is_empty() && seq.chars().all(is_sequence_char) {
             return (Some(name.to_string()), seq);
         }
     }
     
     // This line might be just sequence data (continuation line)
     let seq: String = line.chars().filter(|c| !c.is_whitespace()).collect();
-    if !seq.is_empty() && seq.chars().all(|c| is_sequence_char(c)) {
+    if !seq.is_empty() && seq.chars().all(is_sequence_char) {
         return (None, seq);
     }
     
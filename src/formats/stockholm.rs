@@ -0,0 +1,308 @@
+//! Stockholm format parser and writer.
+//!
+//! Reads the alignments of Pfam, Rfam, HMMER and Infernal with their markup.
+//!
+//! ## Stockholm Format
+//!
+//! A `# STOCKHOLM 1.0` header, sequence lines (name and residues, possibly in
+//! several blocks), markup lines and a `//` terminator:
+//! ```text
+//! # STOCKHOLM 1.0
+//! #=GF ID    example
+//! #=GS seq1  AC P12345
+//!
+//! seq1         ACGU.ACGU
+//! #=GR seq1 SS <<<..>>>.
+//! seq2         ACGUUACGU
+//! #=GC SS_cons <<<..>>>.
+//! #=GC RF      xxxx.xxxx
+//! //
+//! ```
+//!
+//! ## Supported Features
+//!
+//! - `#=GF`, `#=GS`, `#=GR` and `#=GC` markup, kept in `Alignment::annotations`
+//! - `.` and `-` gaps in sequences, normalized to `-` (markup is kept as is)
+//! - Interleaved blocks; other `#` lines are comments
+//! - Only the first alignment of a multi-alignment file is read (with a warning)
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use thiserror::Error;
+
+use crate::model::{Alignment, Annotations, Sequence};
+
+/// Errors that can occur during Stockholm parsing.
+#[derive(Error, Debug)]
+pub enum StockholmError {
+    #[error("Empty Stockholm file")]
+    EmptyFile,
+
+    #[error("Not a Stockholm file (must start with '# STOCKHOLM 1.0')")]
+    NotStockholm,
+
+    #[error("No sequence data found after header")]
+    NoSequenceData,
+
+    #[error("Line {line}: missing residues for '{name}'")]
+    MissingResidues { line: usize, name: String },
+
+    #[error("Line {line}: incomplete {markup} line")]
+    IncompleteMarkup { line: usize, markup: String },
+
+    #[error("Line {line}: annotation for unknown sequence '{name}'")]
+    UnknownSequence { line: usize, name: String },
+
+    #[error("{markup} {tag} has {len} columns, expected {expected}")]
+    AnnotationLength { markup: String, tag: String, len: usize, expected: usize },
+}
+
+/// Result type for Stockholm operations.
+pub type StockholmResult<T> = Result<T, StockholmError>;
+
+/// Returns true if a line is a Stockholm header (`# STOCKHOLM 1.0`).
+pub fn is_stockholm_header(line: &str) -> bool {
+    line.trim_start().starts_with("# STOCKHOLM")
+}
+
+/// Appends residues to the data of `key`, in first-seen order.
+fn append(entries: &mut Vec<(String, Vec<u8>)>, index: &mut HashMap<String, usize>, key: &str, data: &str) {
+    match index.get(key) {
+        Some(&i) => entries[i].1.extend(data.bytes()),
+        None => {
+            index.insert(key.to_string(), entries.len());
+            entries.push((key.to_string(), data.as_bytes().to_vec()));
+        }
+    }
+}
+
+/// Parses Stockholm content from a string (the first alignment of the file).
+pub fn parse_stockholm_str(content: &str) -> StockholmResult<Alignment> {
+    let mut lines = content.lines().enumerate().skip_while(|(_, line)| line.trim().is_empty());
+    let (_, header) = lines.next().ok_or(StockholmError::EmptyFile)?;
+    if !is_stockholm_header(header) {
+        return Err(StockholmError::NotStockholm);
+    }
+
+    let mut sequences: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut annotations = Annotations::default();
+    // Per-residue and per-column markup may also be split across blocks
+    let mut residue: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut residue_index: HashMap<String, usize> = HashMap::new();
+    let mut column: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut column_index: HashMap<String, usize> = HashMap::new();
+    // Line of the first markup naming each sequence, checked once all are read
+    let mut named: Vec<(usize, String)> = Vec::new();
+    let mut rest = "";
+
+    for (i, line) in lines {
+        let trimmed = line.trim();
+        if trimmed == "//" {
+            rest = content.lines().skip(i + 1).find(|line| is_stockholm_header(line)).unwrap_or_default();
+            break;
+        }
+        if trimmed.is_empty() {
+            continue;
+        }
+        let incomplete = |markup: &str| StockholmError::IncompleteMarkup { line: i + 1, markup: markup.to_string() };
+
+        if let Some(markup) = trimmed.strip_prefix("#=GF") {
+            let (tag, text) = split_word(markup).ok_or_else(|| incomplete("#=GF"))?;
+            annotations.file.push((tag.to_string(), text.to_string()));
+        } else if let Some(markup) = trimmed.strip_prefix("#=GS") {
+            let (name, more) = split_word(markup).ok_or_else(|| incomplete("#=GS"))?;
+            let (tag, text) = split_word(more).ok_or_else(|| incomplete("#=GS"))?;
+            named.push((i + 1, name.to_string()));
+            annotations.sequence.push((name.to_string(), tag.to_string(), text.to_string()));
+        } else if let Some(markup) = trimmed.strip_prefix("#=GR") {
+            let mut fields = markup.split_whitespace();
+            let (Some(name), Some(tag), Some(data)) = (fields.next(), fields.next(), fields.next()) else {
+                return Err(incomplete("#=GR"));
+            };
+            named.push((i + 1, name.to_string()));
+            append(&mut residue, &mut residue_index, &format!("{} {}", name, tag), data);
+        } else if let Some(markup) = trimmed.strip_prefix("#=GC") {
+            let mut fields = markup.split_whitespace();
+            let (Some(tag), Some(data)) = (fields.next(), fields.next()) else {
+                return Err(incomplete("#=GC"));
+            };
+            append(&mut column, &mut column_index, tag, data);
+        } else if trimmed.starts_with('#') {
+            continue;
+        } else {
+            let mut fields = trimmed.split_whitespace();
+            let name = fields.next().unwrap_or_default();
+            let residues = fields.next().ok_or_else(|| StockholmError::MissingResidues {
+                line: i + 1,
+                name: name.to_string(),
+            })?;
+            append(&mut sequences, &mut index, name, residues);
+        }
+    }
+
+    if sequences.is_empty() {
+        return Err(StockholmError::NoSequenceData);
+    }
+    if let Some((line, name)) = named.into_iter().find(|(_, name)| !index.contains_key(name)) {
+        return Err(StockholmError::UnknownSequence { line, name });
+    }
+
+    // Markup keeps its own symbols ('.' is meaningful in SS_cons)
+    for (key, data) in residue {
+        let (name, tag) = key.split_once(' ').unwrap_or_default();
+        let expected = sequences[index[name]].1.len();
+        if data.len() != expected {
+            let tag = format!("{} {}", name, tag);
+            return Err(StockholmError::AnnotationLength { markup: "#=GR".to_string(), tag, len: data.len(), expected });
+        }
+        annotations.residue.push((name.to_string(), tag.to_string(), data));
+    }
+    let width = sequences[0].1.len();
+    for (tag, data) in column {
+        if data.len() != width {
+            return Err(StockholmError::AnnotationLength { markup: "#=GC".to_string(), tag, len: data.len(), expected: width });
+        }
+        annotations.column.push((tag, data));
+    }
+
+    let sequences = sequences
+        .into_iter()
+        .map(|(name, mut data)| {
+            for b in data.iter_mut().filter(|b| **b == b'.') {
+                *b = b'-';
+            }
+            Sequence::from_bytes(name, data)
+        })
+        .collect();
+    let mut alignment = Alignment::new(sequences);
+    alignment.annotations = Box::new(annotations);
+    if !rest.is_empty() {
+        let warning = "Only the first alignment of the Stockholm file is shown".to_string();
+        alignment.warning = Some(match alignment.warning.take() {
+            Some(w) => format!("{}; {}", w, warning),
+            None => warning,
+        });
+    }
+    Ok(alignment)
+}
+
+/// Splits the first word from the rest of a markup line.
+fn split_word(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    let (word, rest) = text.split_at(end);
+    (!word.is_empty()).then(|| (word, rest.trim()))
+}
+
+/// Writes sequences as a Stockholm alignment, one line per sequence, with the
+/// annotations of the written sequences.
+pub fn write_stockholm<'a>(
+    out: &mut impl Write,
+    sequences: impl IntoIterator<Item = &'a Sequence>,
+    annotations: &Annotations,
+) -> std::io::Result<()> {
+    let sequences: Vec<&Sequence> = sequences.into_iter().collect();
+    let written = |name: &str| sequences.iter().any(|seq| seq.id == name);
+
+    // Names, #=GR and #=GC labels share one column
+    let width = sequences
+        .iter()
+        .map(|seq| seq.id.len())
+        .chain(annotations.residue.iter().filter(|(name, ..)| written(name)).map(|(name, tag, _)| name.len() + tag.len() + 6))
+        .chain(annotations.column.iter().map(|(tag, _)| tag.len() + 5))
+        .max()
+        .unwrap_or(0);
+
+    writeln!(out, "# STOCKHOLM 1.0")?;
+    for (tag, text) in &annotations.file {
+        writeln!(out, "#=GF {} {}", tag, text)?;
+    }
+    for (name, tag, text) in annotations.sequence.iter().filter(|(name, ..)| written(name)) {
+        writeln!(out, "#=GS {} {} {}", name, tag, text)?;
+    }
+    writeln!(out)?;
+    for seq in &sequences {
+        writeln!(out, "{:width$} {}", seq.id, seq.as_str())?;
+        for (_, tag, data) in annotations.residue.iter().filter(|(name, ..)| *name == seq.id) {
+            let label = format!("#=GR {} {}", seq.id, tag);
+            writeln!(out, "{:width$} {}", label, String::from_utf8_lossy(data))?;
+        }
+    }
+    for (tag, data) in &annotations.column {
+        let label = format!("#=GC {}", tag);
+        writeln!(out, "{:width$} {}", label, String::from_utf8_lossy(data))?;
+    }
+    writeln!(out, "//")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "# STOCKHOLM 1.0\n#=GF ID    example\n#=GF CC    first line\n\
+                           #=GS seq1  AC P12345\n\n\
+                           seq1         ACGU.ACGU\n\
+                           #=GR seq1 SS <<<..>>>.\n\
+                           seq2         ACGUUACGU\n\
+                           #=GC SS_cons <<<..>>>.\n\n\
+                           seq1         AA\n\
+                           seq2         A-\n\
+                           #=GR seq1 SS ..\n\
+                           #=GC SS_cons ..\n\
+                           //\n";
+
+    #[test]
+    fn test_parse_stockholm() {
+        let alignment = parse_stockholm_str(EXAMPLE).unwrap();
+        assert_eq!(alignment.sequence_count(), 2);
+        assert_eq!(alignment.get(0).unwrap().as_str(), "ACGU-ACGUAA");
+        assert_eq!(alignment.get(1).unwrap().as_str(), "ACGUUACGUA-");
+        assert!(alignment.warning.is_none());
+
+        let annotations = &alignment.annotations;
+        assert_eq!(annotations.file[0], ("ID".to_string(), "example".to_string()));
+        assert_eq!(annotations.file[1].1, "first line");
+        assert_eq!(annotations.sequence[0], ("seq1".to_string(), "AC".to_string(), "P12345".to_string()));
+        assert_eq!(annotations.residue[0].2, b"<<<..>>>...");
+        assert_eq!(annotations.tracks(), [("SS_cons", b"<<<..>>>...".as_slice())]);
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let alignment = parse_stockholm_str(EXAMPLE).unwrap();
+        let mut out = Vec::new();
+        write_stockholm(&mut out, &alignment.sequences, &alignment.annotations).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        assert!(written.contains("#=GR seq1 SS <<<..>>>...\n"));
+        assert!(written.ends_with("#=GC SS_cons <<<..>>>...\n//\n"));
+
+        let reread = parse_stockholm_str(&written).unwrap();
+        assert_eq!(reread.get(0).unwrap().as_str(), "ACGU-ACGUAA");
+        assert_eq!(reread.annotations, alignment.annotations);
+
+        // Annotations of sequences left out are not written
+        let mut out = Vec::new();
+        write_stockholm(&mut out, &alignment.sequences[1..], &alignment.annotations).unwrap();
+        let written = String::from_utf8(out).unwrap();
+        assert!(!written.contains("seq1"));
+        assert!(parse_stockholm_str(&written).is_ok());
+    }
+
+    #[test]
+    fn test_stockholm_errors() {
+        assert!(matches!(parse_stockholm_str(">seq1\nACGT\n"), Err(StockholmError::NotStockholm)));
+        assert!(matches!(parse_stockholm_str("# STOCKHOLM 1.0\n//\n"), Err(StockholmError::NoSequenceData)));
+        let content = "# STOCKHOLM 1.0\nseq1 ACGT\n#=GR seq9 SS ....\n//\n";
+        assert!(matches!(parse_stockholm_str(content), Err(StockholmError::UnknownSequence { line: 3, .. })));
+        let content = "# STOCKHOLM 1.0\nseq1 ACGT\n#=GC RF xx\n//\n";
+        assert!(matches!(parse_stockholm_str(content), Err(StockholmError::AnnotationLength { len: 2, expected: 4, .. })));
+
+        // Only the first alignment is read
+        let content = "# STOCKHOLM 1.0\nseq1 ACGT\n//\n# STOCKHOLM 1.0\nseq2 GG\n//\n";
+        let alignment = parse_stockholm_str(content).unwrap();
+        assert_eq!(alignment.sequence_count(), 1);
+        assert!(alignment.warning.unwrap().contains("first alignment"));
+    }
+}
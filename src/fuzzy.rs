@@ -0,0 +1,175 @@
+//! Fuzzy matching of sequence names (fzf-style).
+//!
+//! A pattern matches a name when its characters appear in order, not
+//! necessarily contiguously (`hsap` matches `Homo_sapiens`). Matching is
+//! case-insensitive unless the pattern contains an uppercase letter.
+//!
+//! Scores favour contiguous runs and matches at word boundaries
+//! (start of name, after `_`, `-`, `.`, `|`, space, or a lower→upper case change),
+//! and penalise gaps between matched characters.
+
+/// Score for each matched character.
+const SCORE_MATCH: i64 = 16;
+/// Bonus when a matched character directly follows the previous one.
+const BONUS_CONSECUTIVE: i64 = 8;
+/// Bonus when a matched character starts a word.
+const BONUS_BOUNDARY: i64 = 8;
+/// Extra bonus when the first pattern character matches the start of the name.
+const BONUS_FIRST_CHAR: i64 = 8;
+/// Penalty for a gap between two matched characters.
+const PENALTY_GAP_START: i64 = 3;
+/// Additional penalty per unmatched character beyond the first in a gap.
+const PENALTY_GAP: i64 = 1;
+
+/// A successful fuzzy match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    /// Higher is better
+    pub score: i64,
+    /// Char indices of the matched characters in the name (for highlighting)
+    pub positions: Vec<usize>,
+}
+
+/// Matches `pattern` against `text`.
+///
+/// Returns `None` if the pattern characters do not all appear in order.
+/// An empty pattern matches everything with a score of 0.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let case_sensitive = pattern.chars().any(|c| c.is_uppercase());
+    let fold = |c: char| if case_sensitive { c } else { c.to_ascii_lowercase() };
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).map(fold).collect();
+    let chars: Vec<char> = text.chars().collect();
+
+    if pattern.is_empty() {
+        return Some(FuzzyMatch { score: 0, positions: Vec::new() });
+    }
+
+    // Forward pass: find where the first complete match ends
+    let mut p = 0;
+    let mut end = None;
+    for (i, &c) in chars.iter().enumerate() {
+        if fold(c) == pattern[p] {
+            p += 1;
+            if p == pattern.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    // Backward pass from the end: tightens the span (e.g. "ab" in "a_xab" -> "ab")
+    let mut positions = vec![0; pattern.len()];
+    let mut p = pattern.len();
+    for i in (0..=end).rev() {
+        if fold(chars[i]) == pattern[p - 1] {
+            p -= 1;
+            positions[p] = i;
+            if p == 0 {
+                break;
+            }
+        }
+    }
+
+    Some(FuzzyMatch { score: score(&chars, &positions), positions })
+}
+
+/// Scores matched positions within a name.
+///
+/// Characters in a contiguous run share the boundary bonus of the run's first
+/// character, so `sap` in `Homo_sapiens` beats the scattered match in `Saimiri_sp`.
+fn score(chars: &[char], positions: &[usize]) -> i64 {
+    let mut score = 0;
+    let mut run_bonus = 0;
+    for (n, &pos) in positions.iter().enumerate() {
+        score += SCORE_MATCH;
+        let boundary = if is_boundary(chars, pos) { BONUS_BOUNDARY } else { 0 };
+        if n > 0 && positions[n - 1] + 1 == pos {
+            run_bonus = run_bonus.max(boundary);
+            score += BONUS_CONSECUTIVE + run_bonus;
+        } else {
+            run_bonus = boundary;
+            score += boundary;
+            if n > 0 {
+                score -= PENALTY_GAP_START + (pos - positions[n - 1] - 2) as i64 * PENALTY_GAP;
+            }
+        }
+    }
+    if positions.first() == Some(&0) {
+        score += BONUS_FIRST_CHAR;
+    }
+    score
+}
+
+/// Returns true if the character at `pos` starts a word.
+fn is_boundary(chars: &[char], pos: usize) -> bool {
+    if pos == 0 {
+        return true;
+    }
+    let prev = chars[pos - 1];
+    let cur = chars[pos];
+    !prev.is_alphanumeric() || (prev.is_lowercase() && cur.is_uppercase())
+        || (prev.is_alphabetic() && cur.is_ascii_digit())
+}
+
+/// Ranks names against a pattern, best match first.
+///
+/// Returns `(index, match)` pairs. Ties are broken by shorter name, then original order.
+/// An empty pattern returns every name in its original order.
+pub fn rank<'a>(pattern: &str, names: impl IntoIterator<Item = &'a str>) -> Vec<(usize, FuzzyMatch)> {
+    let mut matches: Vec<(usize, usize, FuzzyMatch)> = names
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, name)| fuzzy_match(pattern, name).map(|m| (i, name.chars().count(), m)))
+        .collect();
+    matches.sort_by(|a, b| b.2.score.cmp(&a.2.score).then(a.1.cmp(&b.1)).then(a.0.cmp(&b.0)));
+    matches.into_iter().map(|(i, _, m)| (i, m)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subsequence_match() {
+        let m = fuzzy_match("hsap", "Homo_sapiens").unwrap();
+        assert_eq!(m.positions, vec![0, 5, 6, 7]);
+        assert!(fuzzy_match("hsx", "Homo_sapiens").is_none());
+        assert!(fuzzy_match("", "anything").is_some());
+    }
+
+    #[test]
+    fn test_smart_case() {
+        assert!(fuzzy_match("homo", "Homo_sapiens").is_some());
+        assert!(fuzzy_match("Homo", "Homo_sapiens").is_some());
+        assert!(fuzzy_match("HOMO", "Homo_sapiens").is_none());
+    }
+
+    #[test]
+    fn test_backward_pass_tightens_span() {
+        let m = fuzzy_match("ab", "a_xab").unwrap();
+        assert_eq!(m.positions, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_rank_prefers_contiguous_and_boundaries() {
+        let names = ["Mus_musculus", "Homo_sapiens", "Saimiri_sp", "Pan_troglodytes"];
+        let ranked = rank("sap", names.iter().copied());
+        assert_eq!(ranked[0].0, 1);
+        // "Saimiri_sp" also matches, but scattered
+        assert!(ranked.iter().any(|(i, _)| *i == 2));
+        assert!(!ranked.iter().any(|(i, _)| *i == 0));
+
+        // Prefix match beats a later contiguous match
+        let ranked = rank("mus", names.iter().copied());
+        assert_eq!(ranked[0].0, 0);
+        assert_eq!(ranked[0].1.positions, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_rank_empty_pattern_keeps_order() {
+        let names = ["b", "a", "c"];
+        let ranked: Vec<usize> = rank("", names.iter().copied()).into_iter().map(|(i, _)| i).collect();
+        assert_eq!(ranked, vec![0, 1, 2]);
+    }
+}
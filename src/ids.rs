@@ -0,0 +1,172 @@
+//! Sequence subsets and renaming from ID lists and mapping tables.
+//!
+//! ID lists have one ID per line; mapping tables have an old and a new ID per
+//! line, separated by a tab (or other whitespace). Blank lines and lines starting
+//! with `#` are ignored, and a leading `>` is stripped so FASTA headers can be pasted.
+//!
+//! IDs are matched against the key extracted from each sequence name (see `-d`/`-f`
+//! in CLI mode), and renaming is applied to that key.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// Which sequences to keep, and how to rename them.
+#[derive(Debug, Clone, Default)]
+pub struct IdSelection {
+    /// Only keep these IDs (all if `None`)
+    keep: Option<Vec<String>>,
+    /// Drop these IDs
+    exclude: Vec<String>,
+    /// Old ID -> new ID, in file order
+    rename: Vec<(String, String)>,
+    keep_set: HashSet<String>,
+    exclude_set: HashSet<String>,
+    rename_map: HashMap<String, String>,
+}
+
+impl IdSelection {
+    /// Builds a selection from in-memory lists.
+    pub fn new(keep: Option<Vec<String>>, exclude: Vec<String>, rename: Vec<(String, String)>) -> Result<Self> {
+        let mut rename_map = HashMap::new();
+        let mut new_names = HashSet::new();
+        for (old, new) in &rename {
+            if rename_map.insert(old.clone(), new.clone()).is_some() {
+                anyhow::bail!("ID '{}' is renamed more than once", old);
+            }
+            if !new_names.insert(new.as_str()) {
+                anyhow::bail!("Several IDs are renamed to '{}'", new);
+            }
+        }
+        Ok(Self {
+            keep_set: keep.iter().flatten().cloned().collect(),
+            exclude_set: exclude.iter().cloned().collect(),
+            keep,
+            exclude,
+            rename,
+            rename_map,
+        })
+    }
+
+    /// Loads the ID lists and mapping table given on the command line.
+    pub fn load(keep: Option<&Path>, exclude: Option<&Path>, rename: Option<&Path>) -> Result<Self> {
+        let keep = keep.map(read_id_list).transpose()?;
+        let exclude = exclude.map(read_id_list).transpose()?.unwrap_or_default();
+        let rename = rename.map(read_rename_table).transpose()?.unwrap_or_default();
+        Self::new(keep, exclude, rename)
+    }
+
+    /// Returns true if any list or table was given.
+    pub fn is_active(&self) -> bool {
+        self.keep.is_some() || !self.exclude.is_empty() || !self.rename.is_empty()
+    }
+
+    /// Returns the (possibly renamed) output ID for a key, or `None` if the
+    /// sequence is not selected.
+    pub fn apply(&self, key: &str) -> Option<String> {
+        if self.keep.is_some() && !self.keep_set.contains(key) {
+            return None;
+        }
+        if self.exclude_set.contains(key) {
+            return None;
+        }
+        Some(self.rename_map.get(key).cloned().unwrap_or_else(|| key.to_string()))
+    }
+
+    /// IDs from the lists that matched none of `seen` keys, as `(list, id)` pairs
+    /// with list one of `keep`, `exclude` or `rename`.
+    pub fn unmatched(&self, seen: &HashSet<String>) -> Vec<(&'static str, &str)> {
+        let keep = self.keep.iter().flatten().map(|id| ("keep", id.as_str()));
+        let exclude = self.exclude.iter().map(|id| ("exclude", id.as_str()));
+        let rename = self.rename.iter().map(|(old, _)| ("rename", old.as_str()));
+        keep.chain(exclude)
+            .chain(rename)
+            .filter(|(_, id)| !seen.contains(*id))
+            .collect()
+    }
+}
+
+/// Strips comments, blank lines and a leading `>`.
+fn data_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(n, line)| (n, line.strip_prefix('>').unwrap_or(line).trim_start()))
+}
+
+/// Reads a list of IDs, one per line.
+pub fn read_id_list(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read ID list {}", path.display()))?;
+    Ok(data_lines(&content).map(|(_, line)| line.to_string()).collect())
+}
+
+/// Reads an old -> new mapping table (two columns).
+pub fn read_rename_table(path: &Path) -> Result<Vec<(String, String)>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read rename table {}", path.display()))?;
+    parse_rename_table(&content).with_context(|| format!("Invalid rename table {}", path.display()))
+}
+
+fn parse_rename_table(content: &str) -> Result<Vec<(String, String)>> {
+    data_lines(content)
+        .map(|(n, line)| {
+            // Tab-separated, so new names may contain spaces; otherwise any whitespace
+            let (old, new) = line
+                .split_once('\t')
+                .or_else(|| line.split_once(char::is_whitespace))
+                .map(|(old, new)| (old.trim(), new.trim()))
+                .filter(|(old, new)| !old.is_empty() && !new.is_empty())
+                .ok_or_else(|| anyhow::anyhow!("line {}: expected 'old<TAB>new' (got '{}')", n, line))?;
+            Ok((old.to_string(), new.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_keep_exclude_rename() {
+        let selection = IdSelection::new(
+            Some(ids(&["a", "b", "c"])),
+            ids(&["b"]),
+            vec![("a".into(), "alpha".into())],
+        )
+        .unwrap();
+        assert!(selection.is_active());
+        assert_eq!(selection.apply("a").as_deref(), Some("alpha"));
+        assert_eq!(selection.apply("b"), None);
+        assert_eq!(selection.apply("c").as_deref(), Some("c"));
+        assert_eq!(selection.apply("d"), None);
+
+        let seen: HashSet<String> = ids(&["a", "b", "d"]).into_iter().collect();
+        assert_eq!(selection.unmatched(&seen), vec![("keep", "c")]);
+
+        assert!(!IdSelection::default().is_active());
+        assert_eq!(IdSelection::default().apply("x").as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn test_rename_table_parsing() {
+        let table = parse_rename_table("# old\tnew\n>a\tHomo sapiens\n\nb  B\n").unwrap();
+        assert_eq!(
+            table,
+            vec![("a".into(), "Homo sapiens".into()), ("b".into(), "B".into())]
+        );
+        let err = parse_rename_table("a\tb\nlonely\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+
+        // Renaming must stay one-to-one
+        assert!(IdSelection::new(None, vec![], vec![("a".into(), "x".into()), ("b".into(), "x".into())]).is_err());
+        assert!(IdSelection::new(None, vec![], vec![("a".into(), "x".into()), ("a".into(), "y".into())]).is_err());
+    }
+}
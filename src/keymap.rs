@@ -0,0 +1,535 @@
+//! Remappable normal-mode key bindings.
+//!
+//! A `Keymap` maps key sequences to `Action` values. Sequences may span several
+//! keys (e.g. `gm`, `zH`): while the keys typed so far are a prefix of a binding,
+//! they are kept in `AppState::pending_keys` and the next key is looked up with them.
+//! An exact match always wins over a longer binding sharing the same prefix.
+//!
+//! Key sequences use Vim notation:
+//! - Plain characters: `j`, `$`, `g0`
+//! - Special keys: `<Left>`, `<Home>`, `<PageDown>`, `<Tab>`, `<Space>`, `<Bar>` (|), `<lt>` (<)
+//! - Modifiers: `<C-d>` (Ctrl), `<A-f>` or `<M-f>` (Alt), `<S-Left>` (Shift)
+//!
+//! Some keys are deliberately not remappable: `Ctrl+C` (emergency quit) and the
+//! digits of the `<number>|` prefix.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::event::Action;
+
+/// A single key press with its modifiers, normalized for lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Creates a normalized chord.
+    ///
+    /// Shift is dropped for characters (it is already part of the character,
+    /// e.g. `H`), and Ctrl/Alt characters are lowercased so `<C-D>` equals `<C-d>`.
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let mut code = code;
+        if let KeyCode::Char(c) = code {
+            modifiers.remove(KeyModifiers::SHIFT);
+            if !modifiers.is_empty() {
+                code = KeyCode::Char(c.to_ascii_lowercase());
+            }
+        }
+        // Shift+Tab is reported as BackTab (sometimes with the Shift modifier)
+        if code == KeyCode::Tab && modifiers.contains(KeyModifiers::SHIFT) {
+            code = KeyCode::BackTab;
+        }
+        if code == KeyCode::BackTab {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self { code, modifiers }
+    }
+
+    /// Creates a chord for a plain character.
+    pub fn char(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    /// Creates a chord for a character with Ctrl held.
+    pub fn ctrl(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self.code {
+            KeyCode::Char('<') => "lt".to_string(),
+            KeyCode::Char('|') => "Bar".to_string(),
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Left => "Left".to_string(),
+            KeyCode::Right => "Right".to_string(),
+            KeyCode::Up => "Up".to_string(),
+            KeyCode::Down => "Down".to_string(),
+            KeyCode::Home => "Home".to_string(),
+            KeyCode::End => "End".to_string(),
+            KeyCode::PageUp => "PageUp".to_string(),
+            KeyCode::PageDown => "PageDown".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::BackTab => "S-Tab".to_string(),
+            KeyCode::Backspace => "BS".to_string(),
+            KeyCode::Enter => "CR".to_string(),
+            KeyCode::Esc => "Esc".to_string(),
+            KeyCode::Delete => "Del".to_string(),
+            KeyCode::Insert => "Insert".to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            other => format!("{:?}", other),
+        };
+
+        let plain_char = matches!(self.code, KeyCode::Char(c) if c != '<' && c != '|' && c != ' ');
+        if self.modifiers.is_empty() && plain_char {
+            return write!(f, "{}", name);
+        }
+
+        write!(f, "<")?;
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "C-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "A-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "S-")?;
+        }
+        write!(f, "{}>", name)
+    }
+}
+
+/// Parses a key sequence in Vim notation (e.g. `gm`, `<C-d>`, `<C-x><C-c>`).
+pub fn parse_key_sequence(text: &str) -> Result<Vec<KeyChord>, String> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = rest
+                .find('>')
+                .ok_or_else(|| format!("unterminated '<' in key sequence '{}'", text))?;
+            keys.push(parse_special_key(&rest[1..end])?);
+            rest = &rest[end + 1..];
+        } else {
+            keys.push(KeyChord::char(c));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    if keys.is_empty() {
+        return Err("empty key sequence".to_string());
+    }
+    Ok(keys)
+}
+
+/// Parses the inside of a `<...>` key (modifiers and key name).
+fn parse_special_key(inner: &str) -> Result<KeyChord, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = inner;
+
+    // Modifier prefixes (a name of length 1 is a key, even if it is "-")
+    while name.len() > 2 && name.as_bytes()[1] == b'-' {
+        match name.as_bytes()[0].to_ascii_uppercase() {
+            b'C' => modifiers |= KeyModifiers::CONTROL,
+            b'A' | b'M' => modifiers |= KeyModifiers::ALT,
+            b'S' => modifiers |= KeyModifiers::SHIFT,
+            _ => break,
+        }
+        name = &name[2..];
+    }
+
+    let code = match name.to_ascii_lowercase().as_str() {
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "tab" => KeyCode::Tab,
+        "bs" | "backspace" => KeyCode::Backspace,
+        "cr" | "enter" | "return" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "del" | "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "space" => KeyCode::Char(' '),
+        "bar" => KeyCode::Char('|'),
+        "lt" => KeyCode::Char('<'),
+        lower => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key '<{}>'", inner)),
+                },
+            }
+        }
+    };
+
+    Ok(KeyChord::new(code, modifiers))
+}
+
+/// Formats a key sequence back to Vim notation.
+pub fn format_key_sequence(keys: &[KeyChord]) -> String {
+    keys.iter().map(|k| k.to_string()).collect()
+}
+
+/// Names of the actions that can be bound, as used in the config and `:map`.
+const ACTION_NAMES: &[(&str, Action)] = &[
+    ("move_up", Action::MoveUp),
+    ("move_down", Action::MoveDown),
+    ("move_left", Action::MoveLeft),
+    ("move_right", Action::MoveRight),
+    ("half_page_up", Action::HalfPageUp),
+    ("half_page_down", Action::HalfPageDown),
+    ("half_page_left", Action::HalfPageLeft),
+    ("half_page_right", Action::HalfPageRight),
+    ("page_up", Action::PageUp),
+    ("page_down", Action::PageDown),
+    ("word_forward", Action::WordForward),
+    ("word_backward", Action::WordBackward),
+    ("word_end", Action::WordEnd),
+    ("goto_first_column", Action::GotoFirstColumn),
+    ("goto_last_column", Action::GotoLastColumn),
+    ("goto_first_visible_column", Action::GotoFirstVisibleColumn),
+    ("goto_middle_visible_column", Action::GotoMiddleVisibleColumn),
+    ("goto_last_visible_column", Action::GotoLastVisibleColumn),
+    ("goto_column", Action::ExecuteGotoColumn),
+    ("command", Action::EnterCommandMode),
+    ("search_forward", Action::EnterSearchMode),
+    ("search_backward", Action::EnterSearchBackward),
+    ("find_next", Action::FindNext),
+    ("find_previous", Action::FindPrevious),
+    ("sequence_finder", Action::OpenSequenceFinder),
+    ("move_row_up", Action::MoveRowUp),
+    ("move_row_down", Action::MoveRowDown),
+    ("toggle_pin", Action::TogglePin),
+    ("select_clade", Action::SelectClade),
+    ("collapse_clade", Action::CollapseClade),
+    ("expand_clade", Action::ExpandClade),
+    ("expand_all_clades", Action::ExpandAllClades),
+    ("next_partition", Action::NextPartition),
+    ("previous_partition", Action::PreviousPartition),
+    ("toggle_inserts", Action::ToggleInserts),
+    ("quit", Action::Quit),
+    ("nop", Action::None),
+];
+
+/// Looks up a bindable action by name.
+pub fn action_from_name(name: &str) -> Option<Action> {
+    ACTION_NAMES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, action)| action.clone())
+}
+
+/// Returns the name of a bindable action.
+pub fn action_name(action: &Action) -> Option<&'static str> {
+    ACTION_NAMES
+        .iter()
+        .find(|(_, a)| a == action)
+        .map(|(name, _)| *name)
+}
+
+/// Returns the names of all bindable actions.
+pub fn action_names() -> impl Iterator<Item = &'static str> {
+    ACTION_NAMES.iter().map(|(name, _)| *name)
+}
+
+/// Built-in sets of bindings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeymapPreset {
+    /// Vim-style bindings (the historical defaults)
+    #[default]
+    Vim,
+    /// Vim bindings plus Emacs-style Ctrl/Alt movements
+    Emacs,
+}
+
+impl KeymapPreset {
+    /// Looks up a preset by its configuration name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "vim" => Some(KeymapPreset::Vim),
+            "emacs" => Some(KeymapPreset::Emacs),
+            _ => None,
+        }
+    }
+}
+
+/// Result of looking up a (partial) key sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyLookup<'a> {
+    /// The sequence is bound to an action
+    Action(&'a Action),
+    /// The sequence is the start of at least one longer binding
+    Prefix,
+    /// Nothing is bound to this sequence
+    Unbound,
+}
+
+/// Normal-mode key bindings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    bindings: HashMap<Vec<KeyChord>, Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::preset(KeymapPreset::Vim)
+    }
+}
+
+impl Keymap {
+    /// Creates a keymap without any binding.
+    pub fn empty() -> Self {
+        Self {
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Creates the keymap of a built-in preset.
+    pub fn preset(preset: KeymapPreset) -> Self {
+        let mut keymap = Self::empty();
+        keymap.bind_all(VIM_BINDINGS);
+        if preset == KeymapPreset::Emacs {
+            keymap.bind_all(EMACS_BINDINGS);
+        }
+        keymap
+    }
+
+    /// Binds a list of (key sequence, action name) pairs. Panics on invalid entries,
+    /// so it is only meant for the built-in tables.
+    fn bind_all(&mut self, table: &[(&str, &str)]) {
+        for (keys, name) in table {
+            let keys = parse_key_sequence(keys).expect("valid built-in key sequence");
+            let action = action_from_name(name).expect("valid built-in action name");
+            self.bind(keys, action);
+        }
+    }
+
+    /// Binds a key sequence to an action, replacing any previous binding.
+    /// Binding to `Action::None` removes the binding.
+    pub fn bind(&mut self, keys: Vec<KeyChord>, action: Action) {
+        if action == Action::None {
+            self.bindings.remove(&keys);
+        } else {
+            self.bindings.insert(keys, action);
+        }
+    }
+
+    /// Removes a binding. Returns false if the sequence was not bound.
+    pub fn unbind(&mut self, keys: &[KeyChord]) -> bool {
+        self.bindings.remove(keys).is_some()
+    }
+
+    /// Returns the action bound to exactly this sequence.
+    pub fn get(&self, keys: &[KeyChord]) -> Option<&Action> {
+        self.bindings.get(keys)
+    }
+
+    /// Looks up a key sequence typed so far.
+    pub fn lookup(&self, keys: &[KeyChord]) -> KeyLookup<'_> {
+        if let Some(action) = self.bindings.get(keys) {
+            return KeyLookup::Action(action);
+        }
+        let is_prefix = self
+            .bindings
+            .keys()
+            .any(|bound| bound.len() > keys.len() && bound.starts_with(keys));
+        if is_prefix {
+            KeyLookup::Prefix
+        } else {
+            KeyLookup::Unbound
+        }
+    }
+
+    /// Returns the number of bindings.
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    /// Returns true if there is no binding.
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+}
+
+/// Default Vim-style bindings.
+const VIM_BINDINGS: &[(&str, &str)] = &[
+    ("h", "move_left"),
+    ("j", "move_down"),
+    ("k", "move_up"),
+    ("l", "move_right"),
+    ("<Left>", "move_left"),
+    ("<Down>", "move_down"),
+    ("<Up>", "move_up"),
+    ("<Right>", "move_right"),
+    ("w", "word_forward"),
+    ("b", "word_backward"),
+    ("e", "word_end"),
+    ("0", "goto_first_column"),
+    ("$", "goto_last_column"),
+    ("<Home>", "goto_first_column"),
+    ("<End>", "goto_last_column"),
+    ("g0", "goto_first_visible_column"),
+    ("gm", "goto_middle_visible_column"),
+    ("g$", "goto_last_visible_column"),
+    ("<Bar>", "goto_column"),
+    ("zH", "half_page_left"),
+    ("zL", "half_page_right"),
+    ("<C-u>", "half_page_up"),
+    ("<C-d>", "half_page_down"),
+    ("<C-Left>", "half_page_left"),
+    ("<S-Left>", "half_page_left"),
+    ("<C-Right>", "half_page_right"),
+    ("<S-Right>", "half_page_right"),
+    ("<PageUp>", "page_up"),
+    ("<PageDown>", "page_down"),
+    ("<C-Up>", "page_up"),
+    ("<S-Up>", "page_up"),
+    ("<C-Down>", "page_down"),
+    ("<S-Down>", "page_down"),
+    (":", "command"),
+    ("/", "search_forward"),
+    ("?", "search_backward"),
+    ("<C-f>", "search_forward"),
+    ("n", "find_next"),
+    ("N", "find_previous"),
+    ("<C-p>", "sequence_finder"),
+    ("<A-Up>", "move_row_up"),
+    ("<A-Down>", "move_row_down"),
+    ("<A-k>", "move_row_up"),
+    ("<A-j>", "move_row_down"),
+    ("gp", "toggle_pin"),
+    ("gc", "select_clade"),
+    ("zc", "collapse_clade"),
+    ("zo", "expand_clade"),
+    ("zR", "expand_all_clades"),
+    ("]p", "next_partition"),
+    ("[p", "previous_partition"),
+    ("zi", "toggle_inserts"),
+];
+
+/// Emacs-style bindings, applied on top of the Vim ones.
+const EMACS_BINDINGS: &[(&str, &str)] = &[
+    ("<C-p>", "move_up"),
+    ("<C-n>", "move_down"),
+    ("<C-b>", "move_left"),
+    ("<C-f>", "move_right"),
+    ("<A-f>", "word_forward"),
+    ("<A-b>", "word_backward"),
+    ("<C-a>", "goto_first_column"),
+    ("<C-e>", "goto_last_column"),
+    ("<C-v>", "page_down"),
+    ("<A-v>", "page_up"),
+    ("<C-s>", "search_forward"),
+    ("<C-r>", "search_backward"),
+    ("<A-x>", "command"),
+    ("<C-x>b", "sequence_finder"),
+    ("<C-x><C-c>", "quit"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key_sequence() {
+        assert_eq!(
+            parse_key_sequence("gm").unwrap(),
+            vec![KeyChord::char('g'), KeyChord::char('m')]
+        );
+        assert_eq!(parse_key_sequence("<C-d>").unwrap(), vec![KeyChord::ctrl('d')]);
+        assert_eq!(parse_key_sequence("<C-D>").unwrap(), vec![KeyChord::ctrl('d')]);
+        assert_eq!(
+            parse_key_sequence("<S-Left>").unwrap(),
+            vec![KeyChord::new(KeyCode::Left, KeyModifiers::SHIFT)]
+        );
+        assert_eq!(parse_key_sequence("<Bar>").unwrap(), vec![KeyChord::char('|')]);
+        assert_eq!(parse_key_sequence("<F5>").unwrap(), vec![KeyChord::new(KeyCode::F(5), KeyModifiers::NONE)]);
+
+        assert!(parse_key_sequence("").is_err());
+        assert!(parse_key_sequence("<C-d").is_err());
+        assert!(parse_key_sequence("<Nope>").is_err());
+    }
+
+    #[test]
+    fn test_format_round_trip() {
+        for text in ["gm", "<C-d>", "<C-x><C-c>", "<S-Left>", "<Bar>", "<lt>", "zH", "<A-f>"] {
+            let keys = parse_key_sequence(text).unwrap();
+            assert_eq!(format_key_sequence(&keys), text);
+        }
+    }
+
+    #[test]
+    fn test_shift_is_part_of_character() {
+        // Terminals may report 'H' with or without the Shift modifier
+        let with_shift = KeyChord::new(KeyCode::Char('H'), KeyModifiers::SHIFT);
+        assert_eq!(with_shift, KeyChord::char('H'));
+    }
+
+    #[test]
+    fn test_lookup_prefix_and_exact() {
+        let keymap = Keymap::default();
+        let g = KeyChord::char('g');
+        assert_eq!(keymap.lookup(&[g]), KeyLookup::Prefix);
+        assert_eq!(
+            keymap.lookup(&[g, KeyChord::char('m')]),
+            KeyLookup::Action(&Action::GotoMiddleVisibleColumn)
+        );
+        assert_eq!(keymap.lookup(&[g, KeyChord::char('x')]), KeyLookup::Unbound);
+    }
+
+    #[test]
+    fn test_bind_and_unbind() {
+        let mut keymap = Keymap::default();
+        let keys = parse_key_sequence("L").unwrap();
+        keymap.bind(keys.clone(), Action::GotoLastColumn);
+        assert_eq!(keymap.get(&keys), Some(&Action::GotoLastColumn));
+
+        assert!(keymap.unbind(&keys));
+        assert!(!keymap.unbind(&keys));
+        assert_eq!(keymap.lookup(&keys), KeyLookup::Unbound);
+
+        // Binding to "nop" removes a default binding
+        let j = parse_key_sequence("j").unwrap();
+        keymap.bind(j.clone(), action_from_name("nop").unwrap());
+        assert_eq!(keymap.get(&j), None);
+    }
+
+    #[test]
+    fn test_emacs_preset() {
+        let keymap = Keymap::preset(KeymapPreset::Emacs);
+        assert_eq!(keymap.get(&[KeyChord::ctrl('n')]), Some(&Action::MoveDown));
+        assert_eq!(keymap.get(&[KeyChord::ctrl('f')]), Some(&Action::MoveRight));
+        assert_eq!(keymap.lookup(&[KeyChord::ctrl('x')]), KeyLookup::Prefix);
+        // Vim bindings remain available
+        assert_eq!(keymap.get(&[KeyChord::char('j')]), Some(&Action::MoveDown));
+    }
+
+    #[test]
+    fn test_action_names() {
+        assert_eq!(action_from_name("goto_last_column"), Some(Action::GotoLastColumn));
+        assert_eq!(action_name(&Action::HalfPageLeft), Some("half_page_left"));
+        assert_eq!(action_from_name("fly"), None);
+    }
+}
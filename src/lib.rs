@@ -7,11 +7,26 @@
 //! The application follows an event-driven architecture with clear separation:
 //! - `model`: Data structures for sequences, viewport, and application state
 //! - `fasta`: FASTA file parsing and validation
-//! - `formats`: Multi-format parsing (FASTA, PHYLIP, NEXUS)
+//! - `formats`: Multi-format parsing (FASTA, PHYLIP, NEXUS, Clustal, MSF, Stockholm, A2M/A3M)
 //! - `event`: Keyboard event handling (Vim-style navigation)
+//! - `keymap`: Remappable normal-mode key bindings
+//! - `cmdline`: Command-line history and tab completion
+//! - `fuzzy`: Fuzzy matching for the sequence finder
+//! - `rows`: Display order of rows (sort, move, hide, pin)
+//! - `trim`: Column trimming (gap thresholds, gappyout, strict) and column maps
+//! - `filter`: Sequence filters (length, gaps, ambiguity, internal stops)
+//! - `ids`: Sequence subsets and renaming from ID lists and mapping tables
+//! - `occupancy`: Taxon x locus occupancy filtering for supermatrices
+//! - `partition`: Partition files (NEXUS, RAxML, IQ-TREE) with codon positions
+//! - `region`: Column regions and sliding windows (aligned or reference coordinates)
+//! - `dedup`: Identical sequence detection and collapsing, duplicate names
+//! - `stats`: Alignment statistics and site classification (variable, informative)
+//! - `distance`: Pairwise distances and identity (p, JC69, K2P) with pairwise/complete deletion
+//! - `tree`: Neighbour-joining trees (NJ, BioNJ), Newick output and leaf order
 //! - `ui`: TUI rendering with ratatui
 //! - `controller`: Orchestration of state transitions
 //! - `genetic_code`: NCBI genetic codes and translation logic
+//! - `config`: Persistent user defaults (global and per-project TOML files)
 //!
 //! ## Supported File Formats
 //!
@@ -19,9 +34,24 @@
 //! - PHYLIP (.phy, .phylip) - sequential and interleaved
 //! - NEXUS (.nex, .nexus, .nxs)
 
+pub mod cmdline;
+pub mod config;
 pub mod controller;
+pub mod dedup;
+pub mod distance;
 pub mod event;
+pub mod filter;
 pub mod formats;
+pub mod fuzzy;
 pub mod genetic_code;
+pub mod ids;
+pub mod keymap;
 pub mod model;
+pub mod occupancy;
+pub mod partition;
+pub mod region;
+pub mod rows;
+pub mod stats;
+pub mod tree;
+pub mod trim;
 pub mod ui;
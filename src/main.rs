@@ -15,6 +15,9 @@
 //! - FASTA (.fasta, .fa, .fna, .faa, .fas)
 //! - PHYLIP (.phy, .phylip)
 //! - NEXUS (.nex, .nexus, .nxs)
+//! - Clustal (.aln, .clw), MSF (.msf)
+//! - Stockholm (.sto, .stk)
+//! - A2M/A3M (.a2m, .a3m)
 //!
 //! ## Navigation (Vim-style)
 //!
@@ -31,17 +34,31 @@ use tikv_jemallocator::Jemalloc;
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
+use std::collections::{HashMap, HashSet};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use clap::{Parser, ValueEnum};
 use rand::Rng;
+use regex::Regex;
 
+use seqtui::config::Config;
 use seqtui::controller::{run_app_with_loading, run_app_with_file_browser, run_app_with_file_browser_at};
-use seqtui::formats::{parse_file_with_options, FileFormat};
+use seqtui::dedup::{self, DuplicateOptions};
+use seqtui::distance::{Deletion, DistanceCalculator, DistanceModel};
+use seqtui::filter::{Dropped, SequenceFilter};
+use seqtui::formats::{detect_format_from_extension, parse_file_with_block, stockholm, FileFormat};
 use seqtui::genetic_code::GeneticCodes;
-use seqtui::model::{Alignment, Sequence, SequenceType};
+use seqtui::ids::IdSelection;
+use seqtui::model::{is_gap, Alignment, Sequence, SequenceType};
+use seqtui::occupancy::OccupancyMatrix;
+use seqtui::region::{Coordinates, Region, Windows};
+use seqtui::partition::{self, Partition, PartitionFormat, PartitionModels, PartitionScheme};
+use seqtui::rows;
+use seqtui::stats::{self, AlignmentStats};
+use seqtui::tree::{self, Tree, TreeMethod};
+use seqtui::trim::{self, TrimMethod};
 
 /// Generates a log file path based on the output file (if any) with a random suffix.
 /// 
@@ -76,18 +93,175 @@ fn generate_log_path(output: Option<&str>, suffix_hint: &str) -> PathBuf {
     }
 }
 
-/// Runs CLI mode: parse file, optionally translate, and write to output.
-fn run_cli_mode(
-    file_path: &PathBuf,
-    forced_format: Option<FileFormat>,
-    output: &str,
+/// Options of single-file CLI mode (`-o` with one input file).
+#[derive(Debug)]
+struct CliOptions {
+    /// Translate to amino acids (`-t`) with this genetic code and reading frame (1-3)
     translate: bool,
     genetic_code: u8,
     reading_frame: u8,
+    /// Key matched by --keep/--exclude/--rename
+    key_extractor: KeyExtractor,
+    selection: IdSelection,
+    /// Column range (`--region`), in ungapped positions of `region_ref` if given
+    region: Option<Region>,
+    region_ref: Option<String>,
+    filter: SequenceFilter,
+    gaps: GapOutput,
+    /// Keep only the match-state columns of A2M/A3M files
+    hide_inserts: bool,
+    trim_method: Option<TrimMethod>,
+    /// File listing the kept columns after trimming
+    column_map: Option<PathBuf>,
+    duplicates: Option<DuplicateOptions>,
+    /// Proceed despite warnings (duplicate names, suspect sequence types)
     force: bool,
+}
+
+impl Default for CliOptions {
+    fn default() -> Self {
+        Self {
+            translate: false,
+            genetic_code: 1,
+            reading_frame: 1,
+            key_extractor: KeyExtractor::Full,
+            selection: IdSelection::default(),
+            region: None,
+            region_ref: None,
+            filter: SequenceFilter::default(),
+            gaps: GapOutput::Keep,
+            hide_inserts: false,
+            trim_method: None,
+            column_map: None,
+            duplicates: None,
+            force: false,
+        }
+    }
+}
+
+/// Path of a report that belongs with the output (overwritten on each run):
+/// `<stem>.<suffix>` next to the output file, or `seqtui.<suffix>` in the current
+/// directory when writing to stdout.
+fn report_path(output: &str, suffix: &str) -> PathBuf {
+    match output {
+        "-" => PathBuf::from(format!("seqtui.{}", suffix)),
+        path => {
+            let output_path = Path::new(path);
+            let parent = output_path.parent().unwrap_or(Path::new("."));
+            let stem = output_path.file_stem().and_then(|s| s.to_str()).unwrap_or("seqtui");
+            parent.join(format!("{}.{}", stem, suffix))
+        }
+    }
+}
+
+/// Runs CLI mode: parse file, optionally translate and trim, and write to output.
+fn run_cli_mode(
+    file_path: &PathBuf,
+    forced_format: Option<FileFormat>,
+    block: Option<&str>,
+    output: &str,
+    options: &CliOptions,
 ) -> Result<()> {
+    let &CliOptions {
+        translate,
+        genetic_code,
+        reading_frame,
+        ref key_extractor,
+        ref selection,
+        region,
+        ref region_ref,
+        ref filter,
+        gaps,
+        hide_inserts,
+        trim_method,
+        ref column_map,
+        duplicates,
+        force,
+    } = options;
+
     // Parse the input file
-    let alignment = parse_file_with_options(file_path, forced_format)?;
+    let alignment = parse_file_with_block(file_path, forced_format, block)?;
+
+    // Keep the match-state columns of A2M/A3M files (--region then counts them only)
+    let alignment = match alignment.annotations.match_columns() {
+        Some(kept) if hide_inserts => {
+            eprintln!("Hid {} insert columns", alignment.alignment_length() - kept.len());
+            trim::apply(&alignment, &kept)
+        }
+        _ => alignment,
+    };
+
+    // Extract a column range (before translation: coordinates refer to the input)
+    let alignment = match region {
+        Some(region) => extract_region(&alignment, region, region_ref.as_deref())?,
+        None => alignment,
+    };
+
+    // Subset and rename sequences (matched on the extracted key)
+    let alignment = if selection.is_active() {
+        let file_name = file_path.display().to_string();
+        let sequence_type = alignment.sequence_type;
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut selected = Vec::with_capacity(alignment.sequences.len());
+        let mut names: HashMap<String, String> = HashMap::new(); // kept sequences: old -> new name
+        for mut seq in alignment.sequences {
+            let key = key_extractor.extract(&seq.id, &file_name)?;
+            if let Some(new_id) = selection.apply(&key) {
+                let new_id = if new_id != key { new_id } else { seq.id.clone() };
+                names.insert(std::mem::replace(&mut seq.id, new_id.clone()), new_id);
+                selected.push(seq);
+            }
+            seen.insert(key);
+        }
+        report_unmatched_ids(selection, &seen, output)?;
+        if selected.is_empty() {
+            anyhow::bail!("No sequences left after --keep/--exclude");
+        }
+        let mut selected = Alignment::new(selected);
+        selected.sequence_type = sequence_type;
+        selected.annotations = Box::new(alignment.annotations.select_sequences(|name| names.get(name).cloned()));
+        selected
+    } else {
+        alignment
+    };
+    let input_count = alignment.sequence_count();
+
+    // Duplicate names are kept, but listed IDs and the duplicates mapping cannot
+    // tell such sequences apart (NEXUS files already reject them)
+    let duplicate_names = dedup::duplicate_names(&alignment);
+    if !duplicate_names.is_empty() {
+        if (selection.is_active() || duplicates.is_some()) && !force {
+            let list: Vec<String> = duplicate_names.iter().map(|(id, n)| format!("'{}' ({}x)", id, n)).collect();
+            anyhow::bail!(
+                "Duplicate sequence name(s) in {}: {}\n\
+                 --keep/--exclude/--rename and --collapse-duplicates require unique names. Use --force to proceed anyway.",
+                file_path.display(),
+                list.join(", ")
+            );
+        }
+        let log_path = generate_log_path(Some(output), "duplicate_names");
+        let mut log_file = std::fs::File::create(&log_path)?;
+        writeln!(log_file, "# SeqTUI - Duplicate sequence names in {}", file_path.display())?;
+        writeln!(log_file, "#")?;
+        writeln!(log_file, "# Name\tCount")?;
+        for (id, n) in &duplicate_names {
+            writeln!(log_file, "{}\t{}", id, n)?;
+        }
+        eprintln!(
+            "Warning: {} sequence name(s) occur more than once (details: {})",
+            duplicate_names.len(),
+            log_path.display()
+        );
+    }
+
+    // Drop sequences failing length, gap or ambiguity filters
+    let (alignment, mut dropped) = filter.apply(alignment);
+
+    // Back to raw sequences (before translation: codons are read from the ungapped sequence)
+    let alignment = match gaps {
+        GapOutput::Degap { frameshifts } => alignment.degapped(frameshifts),
+        _ => alignment,
+    };
 
     // Translate if requested
     let output_alignment = if translate {
@@ -137,6 +311,73 @@ fn run_cli_mode(
         alignment
     };
 
+    // Drop sequences with internal stops (after translation)
+    let (output_alignment, stops) = filter.apply_stops(output_alignment);
+    dropped.extend(stops);
+    if filter.is_active() {
+        eprintln!("Filtered: kept {} of {} sequences", output_alignment.sequence_count(), input_count);
+        if !dropped.is_empty() {
+            let log_path = generate_log_path(Some(output), "filter");
+            let mut log_file = std::fs::File::create(&log_path)?;
+            writeln!(log_file, "# SeqTUI - Sequences removed by filters")?;
+            writeln!(log_file, "# {} of {} sequences removed", dropped.len(), input_count)?;
+            writeln!(log_file, "#")?;
+            writeln!(log_file, "# ID\tReason")?;
+            for d in &dropped {
+                writeln!(log_file, "{}\t{}", d.id, d.reason)?;
+            }
+            eprintln!("Removed sequences written to: {}", log_path.display());
+        }
+        if output_alignment.sequences.is_empty() {
+            anyhow::bail!("All sequences were removed by filters");
+        }
+    }
+
+    // Drop all-gap columns (e.g. left by a subset of sequences)
+    let output_alignment = if gaps == GapOutput::RemoveGapColumns {
+        if !output_alignment.is_valid_alignment {
+            anyhow::bail!("--remove-gap-columns requires aligned sequences (sequences have different lengths)");
+        }
+        let (trimmed, kept) = trim::trim_alignment(&output_alignment, TrimMethod::AllGaps, &[], None);
+        eprintln!(
+            "Removed {} all-gap columns",
+            output_alignment.alignment_length() - kept.len()
+        );
+        trimmed
+    } else {
+        output_alignment
+    };
+
+    // Trim columns if requested (whole codons for untranslated nucleotides)
+    let output_alignment = match trim_method {
+        Some(method) => {
+            if !output_alignment.is_valid_alignment {
+                anyhow::bail!("--trim requires aligned sequences (sequences have different lengths)");
+            }
+            let codon_frame = (!translate && output_alignment.sequence_type.is_nucleotide())
+                .then_some((reading_frame - 1) as usize);
+            let (trimmed, kept) = trim::trim_alignment(&output_alignment, method, &[], codon_frame);
+            eprintln!(
+                "Trimmed ({}): kept {} of {} sites",
+                method,
+                kept.len(),
+                output_alignment.alignment_length()
+            );
+            if let Some(map_path) = column_map {
+                trim::write_column_map(map_path, &kept)?;
+                eprintln!("Wrote column map to {}", map_path.display());
+            }
+            trimmed
+        }
+        None => output_alignment,
+    };
+
+    // Keep one representative per group of identical sequences
+    let output_alignment = match duplicates {
+        Some(options) => collapse_duplicates(output_alignment, options, output)?,
+        None => output_alignment,
+    };
+
     // Write output
     if output == "-" {
         // Write to stdout
@@ -147,12 +388,17 @@ fn run_cli_mode(
             writeln!(handle, "{}", seq.as_str())?;
         }
     } else {
-        // Write to file
-        let mut file = std::fs::File::create(output)?;
-        for seq in &output_alignment.sequences {
-            writeln!(file, ">{}", seq.id)?;
-            writeln!(file, "{}", seq.as_str())?;
+        // Write to file (Stockholm for .sto/.stk, keeping the annotations)
+        let mut file = io::BufWriter::new(std::fs::File::create(output)?);
+        if detect_format_from_extension(output) == Some(FileFormat::Stockholm) {
+            stockholm::write_stockholm(&mut file, &output_alignment.sequences, &output_alignment.annotations)?;
+        } else {
+            for seq in &output_alignment.sequences {
+                writeln!(file, ">{}", seq.id)?;
+                writeln!(file, "{}", seq.as_str())?;
+            }
         }
+        file.flush()?;
         eprintln!(
             "Wrote {} sequences to {}",
             output_alignment.sequence_count(),
@@ -164,6 +410,330 @@ fn run_cli_mode(
     )
 }
 
+/// Coordinate system of `--region`/`--windows`: alignment columns, or ungapped
+/// positions of the `--region-ref` sequence.
+fn region_coordinates<'a>(alignment: &'a Alignment, reference: Option<&str>) -> Result<Coordinates<'a>> {
+    if !alignment.is_valid_alignment {
+        anyhow::bail!("--region and --windows require aligned sequences (sequences have different lengths)");
+    }
+    match reference {
+        None => Ok(Coordinates::Aligned(alignment.alignment_length())),
+        Some(name) => alignment
+            .sequences
+            .iter()
+            .find(|seq| seq.id == name)
+            .map(Coordinates::Reference)
+            .ok_or_else(|| anyhow::anyhow!("Reference sequence '{}' not found (--region-ref)", name)),
+    }
+}
+
+/// Extracts the columns of a region (see `region_coordinates`).
+fn extract_region(alignment: &Alignment, region: Region, reference: Option<&str>) -> Result<Alignment> {
+    let columns = region_coordinates(alignment, reference)?
+        .columns(region)
+        .map_err(|e| anyhow::anyhow!("--region: {}", e))?;
+    eprintln!("Region {}: alignment columns {}-{}", region, columns.start + 1, columns.end);
+    Ok(trim::apply(alignment, &columns.collect::<Vec<_>>()))
+}
+
+/// Runs windows mode: write one alignment per sliding window (within `region` if given).
+fn run_windows_mode(
+    file_path: &PathBuf,
+    forced_format: Option<FileFormat>,
+    block: Option<&str>,
+    output_dir: &str,
+    windows: Windows,
+    region: Option<Region>,
+    reference: Option<&str>,
+) -> Result<()> {
+    let alignment = parse_file_with_block(file_path, forced_format, block)?;
+    let coordinates = region_coordinates(&alignment, reference)?;
+    let span = match region {
+        Some(region) => region,
+        None => coordinates.span().ok_or_else(|| anyhow::anyhow!("--windows: the alignment is empty"))?,
+    };
+    let windows = windows.within(span);
+    if windows.is_empty() {
+        anyhow::bail!("--windows: no window fits in {} ({} positions)", span, span.end - span.start + 1);
+    }
+
+    let output_dir = PathBuf::from(output_dir);
+    std::fs::create_dir_all(&output_dir)?;
+    let stem = locus_name(file_path);
+    for window in &windows {
+        let columns = coordinates.columns(*window).map_err(|e| anyhow::anyhow!("--windows: {}", e))?;
+        let path = output_dir.join(format!("{}_{}.fasta", stem, window));
+        let mut file = io::BufWriter::new(std::fs::File::create(&path)?);
+        for seq in &alignment.sequences {
+            writeln!(file, ">{}", seq.id)?;
+            file.write_all(&seq.as_bytes()[columns.clone()])?;
+            writeln!(file)?;
+        }
+        file.flush()?;
+    }
+    eprintln!("Wrote {} windows to {}", windows.len(), output_dir.display());
+
+    Ok(())
+}
+
+/// Warns about IDs from `--keep`, `--exclude` or `--rename` that matched no sequence,
+/// and lists them in a log file.
+fn report_unmatched_ids(selection: &IdSelection, seen: &HashSet<String>, output: &str) -> Result<()> {
+    let unmatched = selection.unmatched(seen);
+    if unmatched.is_empty() {
+        return Ok(());
+    }
+    let log_path = generate_log_path(Some(output), "unmatched");
+    let mut log_file = std::fs::File::create(&log_path)?;
+    writeln!(log_file, "# SeqTUI - IDs from --keep/--exclude/--rename matching no sequence")?;
+    writeln!(log_file, "#")?;
+    writeln!(log_file, "# List\tID")?;
+    for (list, id) in &unmatched {
+        writeln!(log_file, "{}\t{}", list, id)?;
+    }
+    eprintln!(
+        "Warning: {} listed ID(s) matched no sequence (details: {})",
+        unmatched.len(),
+        log_path.display()
+    );
+    Ok(())
+}
+
+/// Runs stats mode: summary statistics of each file, written to `output` (or stdout).
+///
+/// A single file is reported with one row per sequence; several files with one
+/// row per file. GC3 uses third codon positions of the reading frame.
+fn run_stats_mode(
+    files: &[PathBuf],
+    forced_format: Option<FileFormat>,
+    block: Option<&str>,
+    output: Option<&str>,
+    format: StatsFormat,
+    reading_frame: u8,
+) -> Result<()> {
+    let frame = (reading_frame - 1) as usize;
+    let mut rows = Vec::with_capacity(files.len());
+    for file_path in files {
+        let alignment = parse_file_with_block(file_path, forced_format, block)?;
+        rows.push((file_path.display().to_string(), AlignmentStats::compute(&alignment, frame)));
+    }
+
+    let mut out: Box<dyn Write> = match output {
+        Some(path) if path != "-" => Box::new(io::BufWriter::new(std::fs::File::create(path)?)),
+        _ => Box::new(io::stdout().lock()),
+    };
+    match (format, rows.as_slice()) {
+        (StatsFormat::Tsv, [(file, stats)]) => stats.write_tsv(&mut out, file)?,
+        (StatsFormat::Tsv, _) => stats::write_summary_tsv(&mut out, &rows)?,
+        (StatsFormat::Json, [(file, stats)]) => writeln!(out, "{}", stats.to_json(file, true))?,
+        (StatsFormat::Json, _) => {
+            let objects: Vec<String> = rows.iter().map(|(file, stats)| stats.to_json(file, false)).collect();
+            writeln!(out, "[\n{}\n]", objects.join(",\n"))?;
+        }
+    }
+    out.flush()?;
+    if let Some(path) = output.filter(|&path| path != "-") {
+        eprintln!("Wrote statistics for {} file(s) to {}", rows.len(), path);
+    }
+    Ok(())
+}
+
+/// Runs distance mode: writes the pairwise matrix of one alignment to `output`
+/// (or stdout).
+fn run_distance_mode(
+    file_path: &Path,
+    forced_format: Option<FileFormat>,
+    block: Option<&str>,
+    output: Option<&str>,
+    model: DistanceModel,
+    deletion: Deletion,
+    format: MatrixFormat,
+) -> Result<()> {
+    let alignment = parse_file_with_block(file_path, forced_format, block)?;
+    let matrix = DistanceCalculator::new(&alignment, model, deletion)
+        .map_err(|e| anyhow::anyhow!("{}: {}", file_path.display(), e))?
+        .matrix();
+
+    let mut out: Box<dyn Write> = match output {
+        Some(path) if path != "-" => Box::new(io::BufWriter::new(std::fs::File::create(path)?)),
+        _ => Box::new(io::stdout().lock()),
+    };
+    match format {
+        MatrixFormat::Phylip => matrix.write_phylip(&mut out)?,
+        MatrixFormat::Tsv => matrix.write_tsv(&mut out)?,
+    }
+    out.flush()?;
+    let undefined = matrix.values.iter().flatten().filter(|v| v.is_none()).count() / 2;
+    if undefined > 0 {
+        eprintln!("Warning: {} pair(s) have no comparable sites or a saturated distance (written as NA)", undefined);
+    }
+    if let Some(path) = output.filter(|&path| path != "-") {
+        eprintln!("Wrote {} matrix ({}, {} sequences) to {}", model, deletion, matrix.ids.len(), path);
+    }
+    Ok(())
+}
+
+/// Runs tree mode: builds a neighbour-joining tree of one alignment and writes
+/// it as Newick to `tree_path`. The matrix is also written to `matrix_output`
+/// (`-` for stdout) when `--distance` is given.
+#[allow(clippy::too_many_arguments)]
+fn run_tree_mode(
+    file_path: &Path,
+    forced_format: Option<FileFormat>,
+    block: Option<&str>,
+    tree_path: &Path,
+    matrix_output: Option<(&str, MatrixFormat)>,
+    model: DistanceModel,
+    deletion: Deletion,
+    method: TreeMethod,
+) -> Result<()> {
+    let alignment = parse_file_with_block(file_path, forced_format, block)?;
+    if alignment.sequence_count() < 3 {
+        anyhow::bail!("{}: a tree requires at least 3 sequences", file_path.display());
+    }
+    let matrix = DistanceCalculator::new(&alignment, model, deletion)
+        .map_err(|e| anyhow::anyhow!("{}: {}", file_path.display(), e))?
+        .matrix();
+    if let Some((path, format)) = matrix_output {
+        let mut out: Box<dyn Write> = match path {
+            "-" => Box::new(io::stdout().lock()),
+            _ => Box::new(io::BufWriter::new(std::fs::File::create(path)?)),
+        };
+        match format {
+            MatrixFormat::Phylip => matrix.write_phylip(&mut out)?,
+            MatrixFormat::Tsv => matrix.write_tsv(&mut out)?,
+        }
+        out.flush()?;
+    }
+
+    let (distances, undefined) = tree::fill_undefined(&matrix.values);
+    if undefined > 0 {
+        eprintln!(
+            "Warning: {} pair(s) have no comparable sites or a saturated distance (set to the largest distance)",
+            undefined
+        );
+    }
+    let tree = Tree::neighbor_joining(&distances, method);
+    std::fs::write(tree_path, tree.to_newick(&matrix.ids) + "\n")?;
+    eprintln!(
+        "Wrote {} tree ({} distance, {} sequences) to {}",
+        method,
+        model,
+        matrix.ids.len(),
+        tree_path.display()
+    );
+    Ok(())
+}
+
+/// Collapses identical sequences and writes the representative -> collapsed
+/// IDs mapping next to the output (`<stem>.duplicates.tsv`).
+fn collapse_duplicates(alignment: Alignment, options: DuplicateOptions, output: &str) -> Result<Alignment> {
+    let groups = dedup::find_duplicates(&alignment, options);
+    if groups.is_empty() {
+        eprintln!("No identical sequences found");
+        return Ok(alignment);
+    }
+    let collapsed: usize = groups.iter().map(|g| g.duplicates.len()).sum();
+    let tsv_path = report_path(output, "duplicates.tsv");
+    let header = vec![
+        format!("Output: {}", output),
+        format!(
+            "Comparison: {}, {}",
+            if options.ignore_gaps { "gaps ignored" } else { "gaps compared" },
+            if options.wildcards { "N/X/? match any residue" } else { "exact residues" }
+        ),
+    ];
+    dedup::write_tsv(&tsv_path, &alignment, &groups, &header)?;
+    eprintln!(
+        "Collapsed {} identical sequence(s) into {} representative(s) (mapping: {})",
+        collapsed,
+        groups.len(),
+        tsv_path.display()
+    );
+    Ok(dedup::collapse(alignment, &groups))
+}
+
+/// What happens to gaps in CLI mode output.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum GapOutput {
+    /// Keep the alignment as is
+    #[default]
+    Keep,
+    /// Strip `-` and `.` (and `!` if `frameshifts`) before translation (`--degap`)
+    Degap { frameshifts: bool },
+    /// Drop columns that are gaps in every sequence (`--remove-gap-columns`)
+    RemoveGapColumns,
+}
+
+/// How sequence IDs are turned into matching keys.
+#[derive(Debug, Clone, Default)]
+enum KeyExtractor {
+    /// Full ID
+    #[default]
+    Full,
+    /// Fields of the ID split on a delimiter (see `extract_key`)
+    Fields { delimiter: String, fields: Option<Vec<usize>> },
+    /// Capture groups of a regular expression (see `extract_key_regex`)
+    Regex(Regex),
+}
+
+impl KeyExtractor {
+    /// Extracts the key of a sequence ID, with detailed errors naming the file.
+    fn extract(&self, id: &str, file_name: &str) -> Result<String> {
+        match self {
+            KeyExtractor::Full => Ok(id.to_string()),
+            KeyExtractor::Fields { delimiter, fields } => {
+                extract_key(id, Some(delimiter), fields.as_deref(), file_name)
+            }
+            KeyExtractor::Regex(re) => extract_key_regex(id, re, file_name),
+        }
+    }
+
+    /// Hint for IDs that do not match across files, naming the options in use.
+    fn hint(&self) -> &'static str {
+        match self {
+            KeyExtractor::Full => "Check if you need -d/--delimiter or --id-regex to extract a common prefix",
+            KeyExtractor::Fields { .. } => "Check that -d/--delimiter and -f/--fields extract a common prefix",
+            KeyExtractor::Regex(_) => "Check that --id-regex extracts a common prefix",
+        }
+    }
+}
+
+/// Extracts the matching key from a sequence ID using a regular expression.
+///
+/// Named groups that matched are joined with `_` (in pattern order); without
+/// named groups, the first capture group is used, or the whole match if the
+/// pattern has no group.
+///
+/// # Returns
+/// The extracted key, or an error with detailed context if the ID does not match
+fn extract_key_regex(id: &str, re: &Regex, file_name: &str) -> Result<String> {
+    let Some(caps) = re.captures(id) else {
+        anyhow::bail!(
+            "Cannot extract key from sequence '{}' in file '{}'\n\
+             The ID does not match --id-regex '{}'",
+            id, file_name, re.as_str()
+        );
+    };
+    let names: Vec<&str> = re.capture_names().flatten().collect();
+    let key = if !names.is_empty() {
+        let parts: Vec<&str> = names.iter().filter_map(|n| caps.name(n)).map(|m| m.as_str()).collect();
+        parts.join("_")
+    } else if re.captures_len() > 1 {
+        caps.get(1).map_or("", |m| m.as_str()).to_string()
+    } else {
+        caps[0].to_string()
+    };
+    if key.is_empty() {
+        anyhow::bail!(
+            "Cannot extract key from sequence '{}' in file '{}'\n\
+             --id-regex '{}' matched, but its capture group(s) are empty",
+            id, file_name, re.as_str()
+        );
+    }
+    Ok(key)
+}
+
 /// Extracts the matching key from a sequence ID using delimiter and field indices.
 /// 
 /// # Arguments
@@ -214,8 +784,9 @@ fn extract_key(
     }
 }
 
-/// Extracts the file basename (without extension) for use as CHROM name
-fn get_chrom_name(file_path: &PathBuf) -> String {
+/// Locus name of an input file: its basename without extension (VCF CHROM,
+/// partition and occupancy matrix names, window file prefix).
+fn locus_name(file_path: &Path) -> String {
     file_path
         .file_stem()
         .and_then(|s| s.to_str())
@@ -227,10 +798,10 @@ fn get_chrom_name(file_path: &PathBuf) -> String {
 fn run_vcf_mode(
     files: &[PathBuf],
     forced_format: Option<FileFormat>,
+    block: Option<&str>,
     output: &str,
     min_dist: usize,
-    delimiter: Option<&str>,
-    fields: Option<&[usize]>,
+    key_extractor: &KeyExtractor,
     force: bool,
 ) -> Result<()> {
     use std::collections::{HashMap, HashSet};
@@ -256,7 +827,7 @@ fn run_vcf_mode(
             eprint!(".");
             let _ = std::io::stderr().flush();
         }
-        let alignment = parse_file_with_options(file_path, forced_format)?;
+        let alignment = parse_file_with_block(file_path, forced_format, block)?;
         
         // Validate: must be a valid alignment
         if !alignment.is_valid_alignment {
@@ -279,7 +850,7 @@ fn run_vcf_mode(
         // Collect all keys
         let file_name = file_path.display().to_string();
         for seq in &alignment.sequences {
-            let key = extract_key(&seq.id, delimiter, fields, &file_name)?;
+            let key = key_extractor.extract(&seq.id, &file_name)?;
             if !seen_keys.contains(&key) {
                 seen_keys.insert(key.clone());
                 all_keys.push(key);
@@ -351,45 +922,23 @@ fn run_vcf_mode(
             eprint!(".");
             let _ = std::io::stderr().flush();
         }
-        let alignment = parse_file_with_options(file_path, forced_format)?;
-        let chrom = get_chrom_name(file_path);
+        let alignment = parse_file_with_block(file_path, forced_format, block)?;
+        let chrom = locus_name(file_path);
         let aln_len = alignment.alignment_length();
         
         // Build map: key -> sequence bytes
         let file_name = file_path.display().to_string();
         let mut seq_map: HashMap<String, &[u8]> = HashMap::new();
         for seq in &alignment.sequences {
-            let key = extract_key(&seq.id, delimiter, fields, &file_name)?;
+            let key = key_extractor.extract(&seq.id, &file_name)?;
             seq_map.insert(key, seq.as_bytes());
         }
         
-        // Single pass: collect alleles using bit flags and track if site has only real NTs
-        // Bit flags: A=1, C=2, G=4, T=8
-        let mut real_nt_only: Vec<bool> = vec![true; aln_len];
-        let mut seen_nt: Vec<u8> = vec![0; aln_len];
-        
-        for seq in &alignment.sequences {
-            let seq_bytes = seq.as_bytes();
-            for pos in 0..aln_len {
-                if real_nt_only[pos] {
-                    match seq_bytes[pos].to_ascii_uppercase() {
-                        b'A' => seen_nt[pos] |= 1,
-                        b'C' => seen_nt[pos] |= 2,
-                        b'G' => seen_nt[pos] |= 4,
-                        b'T' => seen_nt[pos] |= 8,
-                        b'N' | b'?' => {} // Missing data, ignore
-                        _ => real_nt_only[pos] = false, // Gap or other -> exclude
-                    }
-                }
-            }
-        }
+        // Alleles seen at each site; sites with gaps or ambiguity codes are excluded
+        let sites = stats::classify_sites(&alignment, true);
         
-        // Derive polymorphic sites: !real_nt_only OR seen_nt has >1 bit set
-        // Polymorphic sites reset the flanking distance counter
-        let mut reset: Vec<bool> = vec![false; aln_len];
-        for pos in 0..aln_len {
-            reset[pos] = !real_nt_only[pos] || seen_nt[pos].count_ones() > 1;
-        }
+        // Polymorphic sites (excluded or with >1 allele) reset the flanking distance counter
+        let reset: Vec<bool> = sites.iter().map(|site| !site.clean || site.states() > 1).collect();
         
         // Compute distLeft: distance to nearest polymorphic site on the left
         let mut dist_left: Vec<usize> = vec![0; aln_len];
@@ -404,23 +953,17 @@ fn run_vcf_mode(
         }
         
         // Write VCF lines for biallelic sites with sufficient flanking distance
-        // Biallelic = real_nt_only AND seen_nt has exactly 2 bits set
+        // Biallelic = clean site with exactly 2 alleles
         let mut snp_count = 0;
         for pos in 0..aln_len {
-            // Check biallelic: real_nt_only AND exactly 2 alleles AND sufficient flanking distance
-            if !real_nt_only[pos] || seen_nt[pos].count_ones() != 2 
+            // Check biallelic: clean AND exactly 2 alleles AND sufficient flanking distance
+            if !sites[pos].clean || sites[pos].states() != 2 
                || dist_left[pos] < min_dist || dist_right[pos] < min_dist {
                 continue;
             }
             
-            // Decode alleles from bit flags (alphabetical order: A, C, G, T)
-            let allele_bits = seen_nt[pos];
-            let alleles: Vec<u8> = [b'A', b'C', b'G', b'T']
-                .iter()
-                .zip([1u8, 2, 4, 8])
-                .filter(|(_, bit)| allele_bits & bit != 0)
-                .map(|(base, _)| *base)
-                .collect();
+            // Alleles in alphabetical order
+            let alleles = sites[pos].residues();
             
             // REF = first sample (alphabetically) with an actual ACGT at this position
             // ALT = the other allele
@@ -507,21 +1050,80 @@ fn run_vcf_mode(
     Ok(())
 }
 
+/// Options of concatenation mode (several input files).
+#[derive(Debug)]
+struct ConcatOptions {
+    /// Translate to amino acids (`-t`) with this genetic code and reading frame (1-3)
+    translate: bool,
+    genetic_code: u8,
+    reading_frame: u8,
+    /// Key matching sequences across files (and by --keep/--exclude/--rename)
+    key_extractor: KeyExtractor,
+    selection: IdSelection,
+    /// Fill character of missing sequences (`-s`); None concatenates raw sequences
+    gap_char: Option<char>,
+    /// Drops unreliable sequences of each file before concatenation
+    filter: SequenceFilter,
+    /// Partition file to write (`-p`), with its format and models
+    partitions_file: Option<String>,
+    partition_scheme: PartitionScheme,
+    /// Occupancy filters (0 disables them)
+    min_taxa_per_locus: usize,
+    min_loci_per_taxon: usize,
+    trim_method: Option<TrimMethod>,
+    /// File listing the kept columns after trimming
+    column_map: Option<PathBuf>,
+    /// Proceed despite warnings (ID mismatches, suspect sequence types)
+    force: bool,
+}
+
+impl Default for ConcatOptions {
+    fn default() -> Self {
+        Self {
+            translate: false,
+            genetic_code: 1,
+            reading_frame: 1,
+            key_extractor: KeyExtractor::Full,
+            selection: IdSelection::default(),
+            gap_char: None,
+            filter: SequenceFilter::default(),
+            partitions_file: None,
+            partition_scheme: PartitionScheme::default(),
+            min_taxa_per_locus: 0,
+            min_loci_per_taxon: 0,
+            trim_method: None,
+            column_map: None,
+            force: false,
+        }
+    }
+}
+
 /// Runs concatenation mode: parse multiple files and concatenate sequences by ID.
 fn run_concatenation_mode(
     files: &[PathBuf],
     forced_format: Option<FileFormat>,
+    block: Option<&str>,
     output: &str,
-    translate: bool,
-    genetic_code: u8,
-    reading_frame: u8,
-    delimiter: Option<&str>,
-    fields: Option<&[usize]>,
-    gap_char: Option<char>,  // None = no gap filling, Some(c) = fill with c
-    partitions_file: Option<&str>,
-    force: bool,
+    options: &ConcatOptions,
 ) -> Result<()> {
-    use std::collections::{HashMap, HashSet};
+    use std::collections::HashMap;
+
+    let &ConcatOptions {
+        translate,
+        genetic_code,
+        reading_frame,
+        ref key_extractor,
+        ref selection,
+        gap_char,
+        ref filter,
+        ref partitions_file,
+        ref partition_scheme,
+        min_taxa_per_locus,
+        min_loci_per_taxon,
+        trim_method,
+        ref column_map,
+        force,
+    } = options;
     
     let codes = GeneticCodes::new();
     let code = codes.get(genetic_code).ok_or_else(|| {
@@ -536,13 +1138,17 @@ fn run_concatenation_mode(
     // Pass 1: Collect all sequence IDs and validate alignments (if supermatrix)
     let mut all_keys: Vec<String> = Vec::new();
     let mut seen_keys: HashSet<String> = HashSet::new();
-    let mut key_file_count: HashMap<String, usize> = HashMap::new(); // count files per key
+    let mut file_keys: Vec<HashMap<String, usize>> = Vec::new(); // per file: key -> non-gap residues
     let mut file_lengths: Vec<usize> = Vec::new(); // alignment length per file
+    let mut matched_keys: HashSet<String> = HashSet::new(); // keys before --keep/--exclude/--rename
+    let mut file_passed: Vec<Vec<bool>> = Vec::new(); // per file: sequence passes the filters
+    let mut dropped: Vec<(String, Dropped)> = Vec::new(); // (file, sequence removed by filters)
+    let mut selected_count = 0;
     
     eprintln!("Pass 1: Scanning {} files...", files.len());
     
     for file_path in files {
-        let alignment = parse_file_with_options(file_path, forced_format)?;
+        let alignment = parse_file_with_block(file_path, forced_format, block)?;
         
         // Validate alignment if supermatrix mode (gap filling enabled)
         if gap_char.is_some() && !alignment.is_valid_alignment {
@@ -570,61 +1176,137 @@ fn run_concatenation_mode(
         };
         file_lengths.push(aln_len);
         
-        // Collect keys and track which files they appear in
+        // Collect keys and their non-gap length in this file (first occurrence)
         let file_name = file_path.display().to_string();
-        let mut keys_in_this_file: HashSet<String> = HashSet::new();
-        for seq in &alignment.sequences {
-            let key = extract_key(&seq.id, delimiter, fields, &file_name)?;
+        let nucleotide = alignment.sequence_type.is_nucleotide();
+        let mut keys_in_this_file: HashMap<String, usize> = HashMap::new();
+        let mut passed = vec![true; alignment.sequences.len()];
+        for (i, seq) in alignment.sequences.iter().enumerate() {
+            let key = key_extractor.extract(&seq.id, &file_name)?;
+            matched_keys.insert(key.clone());
+            let Some(key) = selection.apply(&key) else {
+                continue;
+            };
+            selected_count += 1;
+
+            // Drop sequences failing the filters (internal stops after translation)
+            let reason = filter.check(seq, nucleotide).or_else(|| {
+                if translate && filter.drop_internal_stops {
+                    let translated = code.translate_sequence(seq.as_bytes(), frame);
+                    filter.check_stops(&Sequence::from_bytes(seq.id.clone(), translated))
+                } else {
+                    filter.check_stops(seq)
+                }
+            });
+            if let Some(reason) = reason {
+                passed[i] = false;
+                dropped.push((file_name.clone(), Dropped { id: seq.id.clone(), reason }));
+                continue;
+            }
             if !seen_keys.contains(&key) {
                 seen_keys.insert(key.clone());
                 all_keys.push(key.clone());
             }
-            keys_in_this_file.insert(key);
+            keys_in_this_file.entry(key).or_insert_with(|| rows::ungapped_length(seq));
+        }
+        file_keys.push(keys_in_this_file);
+        file_passed.push(passed);
+    }
+    
+    if selection.is_active() {
+        report_unmatched_ids(selection, &matched_keys, output)?;
+        if selected_count == 0 {
+            anyhow::bail!("No sequences left after --keep/--exclude");
+        }
+    }
+    if filter.is_active() {
+        eprintln!("Filtered: kept {} of {} sequences", selected_count - dropped.len(), selected_count);
+        if !dropped.is_empty() {
+            let log_path = generate_log_path(Some(output), "filter");
+            let mut log_file = std::fs::File::create(&log_path)?;
+            writeln!(log_file, "# SeqTUI - Sequences removed by filters")?;
+            writeln!(log_file, "# {} of {} sequences removed", dropped.len(), selected_count)?;
+            writeln!(log_file, "#")?;
+            writeln!(log_file, "# File\tID\tReason")?;
+            for (file, d) in &dropped {
+                writeln!(log_file, "{}\t{}\t{}", file, d.id, d.reason)?;
+            }
+            eprintln!("Removed sequences written to: {}", log_path.display());
         }
-        // Increment file count for each key seen in this file
-        for key in keys_in_this_file {
-            *key_file_count.entry(key).or_insert(0) += 1;
+        if all_keys.is_empty() {
+            anyhow::bail!("All sequences were removed by filters");
         }
     }
     
     // Sort keys alphabetically for canonical output
     all_keys.sort();
     
-    let output_count = all_keys.len();
-    // Count IDs that appear in only one file (orphans)
-    let orphan_count = key_file_count.values().filter(|&&c| c == 1).count();
+    // Taxon x locus occupancy: drop sparse loci and taxa, and report the matrix
+    let locus_names: Vec<String> = files.iter().map(|f| locus_name(f)).collect();
+    let mut occupancy = OccupancyMatrix::new(all_keys.clone(), locus_names);
+    for (t, key) in all_keys.iter().enumerate() {
+        for (l, keys) in file_keys.iter().enumerate() {
+            if let Some(&length) = keys.get(key) {
+                occupancy.set(t, l, length);
+            }
+        }
+    }
+    let rounds = occupancy.filter(min_taxa_per_locus, min_loci_per_taxon);
+    let (dropped_loci, dropped_taxa) = (occupancy.dropped_loci().len(), occupancy.dropped_taxa().len());
+    if dropped_loci > 0 || dropped_taxa > 0 {
+        eprintln!("Occupancy filters: dropped {} loci and {} taxa", dropped_loci, dropped_taxa);
+    }
+
+    // The occupancy matrix is also the log: settings, per-file statistics and
+    // warnings are written as comments above it
+    let occupancy_path = report_path(output, "occupancy.tsv");
+    let mut report = vec![format!("Output: {}", output)];
+    if translate {
+        report.push(format!("Translation: code {}, frame +{}", genetic_code, frame + 1));
+    }
+    report.push(match gap_char {
+        Some(fill_char) => format!("Mode: supermatrix (missing sequences filled with '{}')", fill_char),
+        None => "Mode: concatenation (no gap filling)".to_string(),
+    });
+    if !dropped.is_empty() {
+        report.push(format!("Sequence filters: removed {} of {} sequences", dropped.len(), selected_count));
+    }
+    if min_taxa_per_locus > 0 || min_loci_per_taxon > 0 {
+        report.push(format!(
+            "Filters: min taxa per locus {}, min loci per taxon {} ({} rounds)",
+            min_taxa_per_locus, min_loci_per_taxon, rounds
+        ));
+    }
+
+    let kept_files: Vec<usize> = (0..files.len()).filter(|&l| occupancy.kept_loci[l]).collect();
+    let kept_taxa: Vec<usize> = (0..all_keys.len()).filter(|&t| occupancy.kept_taxa[t]).collect();
+    if kept_files.is_empty() || kept_taxa.is_empty() {
+        occupancy.write_tsv(&occupancy_path, &report)?;
+        anyhow::bail!(
+            "No {} left after occupancy filtering (see {})",
+            if kept_files.is_empty() { "locus" } else { "taxon" },
+            occupancy_path.display()
+        );
+    }
+    // Count IDs that appear in only one (kept) file (orphans)
+    let orphan_count = kept_taxa.iter().filter(|&&t| occupancy.loci_of_taxon(t) == 1).count();
+    let all_keys: Vec<String> = kept_taxa.into_iter().map(|t| all_keys[t].clone()).collect();
     
+    let output_count = all_keys.len();
     eprintln!("Found {} output sequence IDs ({} appear in only one file)", 
               output_count, orphan_count);
     
     // Check for suspicious ID matching: if >30% of output IDs are orphans (only in 1 file)
     let orphan_ratio = orphan_count as f64 / output_count as f64;
     if orphan_ratio > 0.30 && !force {
-        // Write log file with all output IDs
-        let log_path = generate_log_path(Some(output), "ids");
-        let mut log_file = std::fs::File::create(&log_path)?;
-        use std::io::Write;
-        writeln!(log_file, "# SeqTUI - Output sequence IDs from concatenation")?;
-        writeln!(log_file, "# {} IDs total, {} appear in only one file ({:.1}%)", 
-                 output_count, orphan_count, orphan_ratio * 100.0)?;
-        writeln!(log_file, "# IDs marked with * appear in only one file")?;
-        writeln!(log_file, "#")?;
-        for key in &all_keys {
-            let count = key_file_count.get(key).unwrap_or(&0);
-            if *count == 1 {
-                writeln!(log_file, "{}*", key)?;
-            } else {
-                writeln!(log_file, "{}", key)?;
-            }
-        }
-        
+        occupancy.write_tsv(&occupancy_path, &report)?;
         anyhow::bail!(
             "Suspicious ID matching: {:.0}% of output IDs appear in only one file ({} / {}).\n\
             This often means sequence names don't match across files.\n\
-            - Check if you need -d/--delimiter to extract a common prefix\n\
-            - List of output IDs written to: {} (orphans marked with *)\n\
-            - Use --force to proceed anyway",
-            orphan_ratio * 100.0, orphan_count, output_count, log_path.display()
+            - {}\n\
+            - Taxon x locus matrix written to: {} (orphans have 1 in the loci column)\n\
+            - Use --min-loci-per-taxon 2 to drop them, or --force to proceed anyway",
+            orphan_ratio * 100.0, orphan_count, output_count, key_extractor.hint(), occupancy_path.display()
         );
     }
     
@@ -636,27 +1318,31 @@ fn run_concatenation_mode(
     }
     
     // Track partitions
-    let mut partitions: Vec<(String, usize, usize)> = Vec::new(); // (name, start, end)
+    let mut partitions: Vec<Partition> = Vec::new();
     let mut current_pos: usize = 1; // 1-based for partition file
     
-    // Collect statistics and warnings for log file
+    // Collect statistics and warnings for the occupancy report
     let mut file_stats: Vec<(String, usize, usize)> = Vec::new(); // (file, seqs, sites)
     let mut warnings: Vec<String> = Vec::new();
     
-    // Log file for per-file details (always created for concatenation)
-    let log_path = generate_log_path(Some(output), "concat");
-    
     eprintln!("Pass 2: Concatenating sequences...");
     
-    for (file_idx, file_path) in files.iter().enumerate() {
-        let alignment = parse_file_with_options(file_path, forced_format)?;
+    for &file_idx in &kept_files {
+        let file_path = &files[file_idx];
+        let alignment = parse_file_with_block(file_path, forced_format, block)?;
         let expected_len = file_lengths[file_idx];
         
-        // Build map of key -> sequence for this file
+        // Build map of key -> sequence for this file (dropped taxa are skipped below)
         let file_name = file_path.display().to_string();
         let mut file_seqs: HashMap<String, Vec<u8>> = HashMap::new();
-        for seq in &alignment.sequences {
-            let key = extract_key(&seq.id, delimiter, fields, &file_name)?;
+        for (i, seq) in alignment.sequences.iter().enumerate() {
+            if !file_passed[file_idx][i] {
+                continue;
+            }
+            let key = key_extractor.extract(&seq.id, &file_name)?;
+            let Some(key) = selection.apply(&key) else {
+                continue;
+            };
             
             // Translate if needed
             let seq_data = if translate {
@@ -694,21 +1380,19 @@ fn run_concatenation_mode(
         }
         
         // Record partition
-        let gene_name = file_path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("unknown");
         let end_pos = current_pos + expected_len - 1;
-        partitions.push((gene_name.to_string(), current_pos, end_pos));
+        let nucleotide = !translate && alignment.sequence_type.is_nucleotide();
+        partitions.push(Partition::new(&locus_name(file_path), current_pos, end_pos, nucleotide));
         current_pos = end_pos + 1;
         
         // Record file stats
-        file_stats.push((file_path.display().to_string(), file_seqs.len(), expected_len));
+        let seqs = all_keys.iter().filter(|key| file_seqs.contains_key(*key)).count();
+        file_stats.push((file_path.display().to_string(), seqs, expected_len));
     }
     
     // Validate: if not filling gaps, check that all sequences have the same length
     if gap_char.is_none() {
-        let total_len: usize = file_lengths.iter().sum();
+        let total_len: usize = kept_files.iter().map(|&l| file_lengths[l]).sum();
         for (key, data) in &seq_data {
             if data.len() != total_len {
                 let warning = format!(
@@ -724,38 +1408,81 @@ fn run_concatenation_mode(
         }
     }
     
-    // Write log file with per-file statistics and warnings
-    {
-        let mut log_file = std::fs::File::create(&log_path)?;
-        writeln!(log_file, "# SeqTUI concatenation log")?;
-        writeln!(log_file, "# Output: {}", output)?;
-        if translate {
-            writeln!(log_file, "# Translation: code {}, frame +{}", genetic_code, frame + 1)?;
-        }
-        if let Some(fill_char) = gap_char {
-            writeln!(log_file, "# Mode: supermatrix (missing sequences filled with '{}')", fill_char)?;
-        } else {
-            writeln!(log_file, "# Mode: concatenation (no gap filling)")?;
+    if partitions_file.is_some() && partition_scheme.format != PartitionFormat::Nexus {
+        partition_scheme.models.check_types(&partitions).map_err(|e| anyhow::anyhow!("--partition-model: {}", e))?;
+    }
+
+    // Codon position of each column, from the untrimmed partitions
+    let mut codon_positions = (partitions_file.is_some() && partition_scheme.split_codons)
+        .then(|| partition::codon_positions(&partitions, frame, partitions.last().map_or(0, |p| p.end)));
+
+    // Trim columns of the whole matrix if requested, and remap the partitions.
+    // Untranslated nucleotides are trimmed by whole codons, the frame starting at each file.
+    let mut trim_summary: Option<(usize, usize)> = None; // (kept, total)
+    if let Some(method) = trim_method {
+        let total_len: usize = kept_files.iter().map(|&l| file_lengths[l]).sum();
+        if seq_data.values().any(|data| data.len() != total_len) {
+            anyhow::bail!("--trim requires aligned sequences of equal length (use -s/--supermatrix)");
         }
-        writeln!(log_file, "#")?;
-        writeln!(log_file, "# Per-file statistics:")?;
-        writeln!(log_file, "# File\tSequences\tSites")?;
-        for (file, seqs, sites) in &file_stats {
-            writeln!(log_file, "{}\t{}\t{}", file, seqs, sites)?;
+        let matrix = Alignment::new(
+            all_keys
+                .iter()
+                .map(|key| Sequence::from_bytes(key.clone(), seq_data.remove(key).unwrap_or_default()))
+                .collect(),
+        );
+        let segments: Vec<std::ops::Range<usize>> =
+            partitions.iter().map(|p| p.start - 1..p.end).collect();
+        let codon_frame = (!translate && matrix.sequence_type.is_nucleotide()).then_some(frame);
+        let (trimmed, kept) = trim::trim_alignment(&matrix, method, &segments, codon_frame);
+        for seq in trimmed.sequences {
+            seq_data.insert(seq.id.clone(), seq.into_bytes());
         }
-        if !warnings.is_empty() {
-            writeln!(log_file, "#")?;
-            writeln!(log_file, "# Warnings ({}):", warnings.len())?;
-            for warning in &warnings {
-                writeln!(log_file, "# WARNING: {}", warning)?;
+
+        let mut remapped = Vec::with_capacity(partitions.len());
+        for p in partitions {
+            match trim::remap_range(&kept, p.start, p.end) {
+                Some((start, end)) => remapped.push(Partition { start, end, ..p }),
+                None => {
+                    let warning = format!("Partition '{}' has no site left after trimming (dropped)", p.name);
+                    eprintln!("Warning: {}", warning);
+                    warnings.push(warning);
+                }
             }
         }
-        writeln!(log_file, "#")?;
-        let total_sites: usize = file_stats.iter().map(|(_, _, s)| s).sum();
-        writeln!(log_file, "# Summary: {} files, {} output sequences, {} total sites", 
-                 file_stats.len(), all_keys.len(), total_sites)?;
+        partitions = remapped;
+        if let Some(positions) = &mut codon_positions {
+            *positions = kept.iter().map(|&col| positions[col]).collect();
+        }
+
+        if let Some(map_path) = column_map {
+            trim::write_column_map(map_path, &kept)?;
+            eprintln!("Wrote column map to {}", map_path.display());
+        }
+        eprintln!("Trimmed ({}): kept {} of {} sites", method, kept.len(), total_len);
+        trim_summary = Some((kept.len(), total_len));
+    }
+
+    // Write the occupancy matrix with per-file statistics and warnings
+    if let (Some(method), Some((kept, total))) = (trim_method, trim_summary) {
+        report.push(format!("Trimming: {} ({} of {} sites kept)", method, kept, total));
+    }
+    report.push("Per-file statistics (file, sequences, sites):".to_string());
+    for (file, seqs, sites) in &file_stats {
+        report.push(format!("{}\t{}\t{}", file, seqs, sites));
     }
-    eprintln!("Log written to: {}", log_path.display());
+    if !warnings.is_empty() {
+        report.push(format!("Warnings ({}):", warnings.len()));
+        report.extend(warnings.iter().map(|warning| format!("WARNING: {}", warning)));
+    }
+    let total_sites: usize = file_stats.iter().map(|(_, _, s)| s).sum();
+    report.push(format!(
+        "Summary: {} files, {} output sequences, {} total sites",
+        file_stats.len(),
+        all_keys.len(),
+        total_sites
+    ));
+    occupancy.write_tsv(&occupancy_path, &report)?;
+    eprintln!("Occupancy matrix and log written to: {}", occupancy_path.display());
     
     // Write output
     let seq_count = all_keys.len();
@@ -778,23 +1505,114 @@ fn run_concatenation_mode(
         eprintln!("Wrote {} sequences to {}", seq_count, output);
     }
     
-    // Write partitions file if requested (NEXUS format for IQtree)
+    // Write partitions file if requested
     if let Some(part_file) = partitions_file {
-        let mut file = std::fs::File::create(part_file)?;
-        writeln!(file, "#nexus")?;
-        writeln!(file, "begin sets;")?;
-        for (name, start, end) in &partitions {
-            // Sanitize partition name: replace spaces with underscores
-            let sanitized_name = name.replace(' ', "_");
-            writeln!(file, "    charset {} = {}-{};", sanitized_name, start, end)?;
+        let unknown = partition_scheme.models.unknown_names(&partitions);
+        if !unknown.is_empty() {
+            eprintln!("Warning: --partition-model names matching no partition: {}", unknown.join(", "));
         }
-        writeln!(file, "end;")?;
-        eprintln!("Wrote NEXUS partition file with {} partitions to {}", partitions.len(), part_file);
+        let mut file = std::io::BufWriter::new(std::fs::File::create(part_file)?);
+        let count = partition::write_partitions(&mut file, &partitions, partition_scheme, codon_positions.as_deref())?;
+        file.flush()?;
+        eprintln!("Wrote {} partition file with {} partitions to {}", partition_scheme.format, count, part_file);
     }
     
     Ok(())
 }
 
+/// Runs split mode: write one alignment per partition of a supermatrix.
+///
+/// Partitions are read from a NEXUS `charset` or RAxML partition file. With `drop_empty`,
+/// taxa with only gaps or missing data (N, or X for amino acids) in a partition are left out.
+fn run_split_mode(
+    file_path: &PathBuf,
+    forced_format: Option<FileFormat>,
+    block: Option<&str>,
+    partitions_path: &Path,
+    output_dir: &str,
+    drop_empty: bool,
+) -> Result<()> {
+    let content = std::fs::read_to_string(partitions_path)
+        .map_err(|e| anyhow::anyhow!("Cannot read partition file {}: {}", partitions_path.display(), e))?;
+    let sets = partition::parse_partition_file(&content)
+        .map_err(|e| anyhow::anyhow!("Invalid partition file {}: {}", partitions_path.display(), e))?;
+
+    let alignment = parse_file_with_block(file_path, forced_format, block)?;
+    if !alignment.is_valid_alignment {
+        anyhow::bail!("--split requires an aligned supermatrix (sequences have different lengths)");
+    }
+    let length = alignment.alignment_length();
+    let missing = if alignment.sequence_type.is_nucleotide() { b'N' } else { b'X' };
+
+    // Keep file names portable, and distinct on case-insensitive file systems
+    let file_names: Vec<String> = sets
+        .iter()
+        .map(|set| {
+            set.name
+                .chars()
+                .map(|c| if c.is_alphanumeric() || matches!(c, '_' | '-' | '.') { c } else { '_' })
+                .collect()
+        })
+        .collect();
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    for (set, file_name) in sets.iter().zip(&file_names) {
+        if let Some(other) = seen.insert(file_name.to_lowercase(), &set.name) {
+            anyhow::bail!(
+                "Partitions '{}' and '{}' would both be written to {}.fasta (rename one in the partition file)",
+                other, set.name, file_name
+            );
+        }
+    }
+
+    let output_dir = PathBuf::from(output_dir);
+    std::fs::create_dir_all(&output_dir)?;
+
+    let mut written = 0;
+    for (set, file_name) in sets.iter().zip(&file_names) {
+        let columns = set.columns();
+        if let Some(&last) = columns.last() {
+            if last >= length {
+                anyhow::bail!(
+                    "Partition '{}' goes up to column {}, but the matrix has {} columns",
+                    set.name, last + 1, length
+                );
+            }
+        }
+
+        let mut dropped = 0;
+        let mut records: Vec<(&str, Vec<u8>)> = Vec::with_capacity(alignment.sequences.len());
+        for seq in &alignment.sequences {
+            let bytes = seq.as_bytes();
+            let data: Vec<u8> = columns.iter().map(|&c| bytes[c]).collect();
+            if drop_empty && data.iter().all(|&b| is_gap(b) || b.to_ascii_uppercase() == missing) {
+                dropped += 1;
+                continue;
+            }
+            records.push((&seq.id, data));
+        }
+        if records.is_empty() {
+            eprintln!("Warning: partition '{}' has no data for any taxon (skipped)", set.name);
+            continue;
+        }
+
+        let path = output_dir.join(format!("{}.fasta", file_name));
+        let mut file = io::BufWriter::new(std::fs::File::create(&path)?);
+        for (id, data) in &records {
+            writeln!(file, ">{}", id)?;
+            file.write_all(data)?;
+            writeln!(file)?;
+        }
+        file.flush()?;
+        written += 1;
+        if dropped > 0 {
+            eprintln!("{}: {} sites, {} taxa ({} empty dropped)", path.display(), columns.len(), records.len(), dropped);
+        }
+    }
+    eprintln!("Wrote {} partition alignments to {}", written, output_dir.display());
+
+    Ok(())
+}
+
 /// File format specification for command line
 #[derive(Debug, Clone, Copy, ValueEnum)]
 enum FormatArg {
@@ -804,6 +1622,15 @@ enum FormatArg {
     Nexus,
     /// PHYLIP format
     Phylip,
+    /// Clustal format (.aln)
+    Clustal,
+    /// GCG MSF format
+    Msf,
+    /// Stockholm format (Pfam, Rfam)
+    Stockholm,
+    /// A2M/A3M format (HMMER, HH-suite)
+    #[value(alias = "a2m")]
+    A3m,
     /// Auto-detect from extension and content
     Auto,
 }
@@ -814,11 +1641,62 @@ impl From<FormatArg> for Option<FileFormat> {
             FormatArg::Fasta => Some(FileFormat::Fasta),
             FormatArg::Nexus => Some(FileFormat::Nexus),
             FormatArg::Phylip => Some(FileFormat::Phylip),
+            FormatArg::Clustal => Some(FileFormat::Clustal),
+            FormatArg::Msf => Some(FileFormat::Msf),
+            FormatArg::Stockholm => Some(FileFormat::Stockholm),
+            FormatArg::A3m => Some(FileFormat::A3m),
             FormatArg::Auto => None,
         }
     }
 }
 
+/// Parses a proportion between 0 and 1 (for filter thresholds).
+fn parse_fraction(s: &str) -> std::result::Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(v) if (0.0..=1.0).contains(&v) => Ok(v),
+        _ => Err(format!("expected a number between 0 and 1 (got '{}')", s)),
+    }
+}
+
+/// Partition file format for command line
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum PartitionFormatArg {
+    /// NEXUS charset blocks
+    Nexus,
+    /// RAxML / RAxML-NG (MODEL, name = ranges)
+    Raxml,
+    /// NEXUS charsets with an IQ-TREE charpartition (one model per partition)
+    Iqtree,
+}
+
+impl From<PartitionFormatArg> for PartitionFormat {
+    fn from(arg: PartitionFormatArg) -> Self {
+        match arg {
+            PartitionFormatArg::Nexus => PartitionFormat::Nexus,
+            PartitionFormatArg::Raxml => PartitionFormat::Raxml,
+            PartitionFormatArg::Iqtree => PartitionFormat::Iqtree,
+        }
+    }
+}
+
+/// Output format of `--distance`.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum MatrixFormat {
+    /// Square matrix preceded by the number of sequences
+    Phylip,
+    /// Tab-separated values with a header row
+    Tsv,
+}
+
+/// Output format of `--stats`.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum StatsFormat {
+    /// Tab-separated values
+    Tsv,
+    /// JSON object (one file) or array (several files)
+    Json,
+}
+
 /// SeqTUI - A Vim-style terminal viewer for sequence alignments
 ///
 /// When run without -o/--output, opens an interactive TUI viewer.
@@ -839,7 +1717,7 @@ struct Args {
 
     // ==================== Input/Output ====================
     
-    /// Output file in sorted FASTA format (triggers CLI mode). Use "-" for stdout.
+    /// Output file in sorted FASTA format, or Stockholm for .sto/.stk (triggers CLI mode). Use "-" for stdout.
     #[arg(short = 'o', long = "output", help_heading = "Input/Output")]
     output: Option<String>,
 
@@ -847,6 +1725,10 @@ struct Args {
     #[arg(long = "format", value_enum, default_value = "auto", hide_default_value = true, help_heading = "Input/Output")]
     format: FormatArg,
 
+    /// NEXUS character matrix to read (TITLE or number) when a file has several
+    #[arg(long = "block", value_name = "NAME", help_heading = "Input/Output")]
+    block: Option<String>,
+
     /// Proceed despite warnings (ID mismatches or suspect sequence types)
     #[arg(long = "force", help_heading = "Input/Output")]
     force: bool,
@@ -862,30 +1744,165 @@ struct Args {
     #[arg(short = 'f', long = "fields", help_heading = "ID Extraction")]
     fields: Option<String>,
 
+    /// Regular expression extracting the key from sequence IDs: first capture group,
+    /// or named groups joined with "_". Example: '^([^|]+)\|' with "Homo_sapiens|gene=abc"
+    #[arg(long = "id-regex", value_name = "REGEX", conflicts_with_all = ["delimiter", "fields"], help_heading = "ID Extraction")]
+    id_regex: Option<String>,
+
+    // ==================== Regions ====================
+
+    /// Extract a column range, 1-based inclusive (e.g. 1001-2000)
+    #[arg(long = "region", value_name = "START-END", help_heading = "Regions")]
+    region: Option<Region>,
+
+    /// Sliding windows SIZE:STEP (within --region if given); writes one file per window,
+    /// -o/--output is the output directory
+    #[arg(long = "windows", value_name = "SIZE:STEP", help_heading = "Regions")]
+    windows: Option<Windows>,
+
+    /// Interpret --region/--windows as ungapped positions of this sequence
+    #[arg(long = "region-ref", value_name = "NAME", help_heading = "Regions")]
+    region_ref: Option<String>,
+
+    // ==================== Split ====================
+
+    /// Split a supermatrix into one FASTA file per partition (NEXUS charset or RAxML file).
+    /// -o/--output is the output directory.
+    #[arg(
+        long = "split",
+        value_name = "PARTITION_FILE",
+        conflicts_with_all = ["keep", "exclude", "rename"],
+        help_heading = "Split"
+    )]
+    split: Option<PathBuf>,
+
+    /// With --split, leave out taxa with only gaps or missing data in a partition
+    #[arg(long = "drop-empty", requires = "split", help_heading = "Split")]
+    drop_empty: bool,
+
+    // ==================== Subset & Rename ====================
+
+    /// Keep only sequences whose ID (or key, with -d/-f) is listed in FILE (one per line)
+    #[arg(long = "keep", value_name = "FILE", help_heading = "Subset & Rename")]
+    keep: Option<PathBuf>,
+
+    /// Drop sequences whose ID (or key, with -d/-f) is listed in FILE (one per line)
+    #[arg(long = "exclude", value_name = "FILE", help_heading = "Subset & Rename")]
+    exclude: Option<PathBuf>,
+
+    /// Rename sequences from a two-column table (old<TAB>new), applied to the ID (or key, with -d/-f)
+    #[arg(long = "rename", value_name = "FILE", help_heading = "Subset & Rename")]
+    rename: Option<PathBuf>,
+
     // ==================== Multi-file Concatenation ====================
     
-    /// Fill missing sequences with a character (default: '-').
+    /// Fill missing sequences with a character (default: '-' or config `gap_char`).
     /// Without -s, missing sequences are skipped (raw concatenation).
-    #[arg(short = 's', long = "supermatrix", value_name = "CHAR", default_missing_value = "-", num_args = 0..=1, help_heading = "Multi-file Concatenation")]
+    #[arg(short = 's', long = "supermatrix", value_name = "CHAR", default_missing_value = "", num_args = 0..=1, help_heading = "Multi-file Concatenation")]
     supermatrix: Option<String>,
 
-    /// Write partition file in NEXUS format (IQtree-compatible)
+    /// Write partition file (NEXUS charsets by default, see --partition-format)
     #[arg(short = 'p', long = "partitions", help_heading = "Multi-file Concatenation")]
     partitions: Option<String>,
 
+    /// Partition file format
+    #[arg(long = "partition-format", value_enum, default_value = "nexus", requires = "partitions", help_heading = "Multi-file Concatenation")]
+    partition_format: PartitionFormatArg,
+
+    /// Split nucleotide partitions by codon position (name_pos1, name_pos2, name_pos3)
+    #[arg(long = "codon-partitions", requires = "partitions", help_heading = "Multi-file Concatenation")]
+    codon_partitions: bool,
+
+    /// Partition models (RAxML/IQ-TREE): MODEL for all partitions, DNA=MODEL and AA=MODEL by data type,
+    /// and/or NAME=MODEL, comma-separated (e.g. "LG+G,LOC_2=WAG"). Default: DNA (RAxML) or GTR+G, and LG+G for amino acids
+    #[arg(long = "partition-model", value_name = "MODELS", requires = "partitions", help_heading = "Multi-file Concatenation")]
+    partition_model: Option<PartitionModels>,
+
+    /// Drop loci (input files) with fewer taxa; repeated with --min-loci-per-taxon until stable
+    #[arg(long = "min-taxa-per-locus", value_name = "N", help_heading = "Multi-file Concatenation")]
+    min_taxa_per_locus: Option<usize>,
+
+    /// Drop taxa present in fewer loci; repeated with --min-taxa-per-locus until stable
+    #[arg(long = "min-loci-per-taxon", value_name = "N", help_heading = "Multi-file Concatenation")]
+    min_loci_per_taxon: Option<usize>,
+
     // ==================== Translation ====================
     
     /// Translate nucleotides to amino acids
     #[arg(short = 't', long = "translate", help_heading = "Translation")]
     translate: bool,
 
-    /// Genetic code (1-33, default: 1 = Standard)
-    #[arg(short = 'g', long = "genetic-code", default_value = "1", hide_default_value = true, help_heading = "Translation")]
-    genetic_code: u8,
+    /// Genetic code (1-33, default: 1 = Standard or config `genetic_code`)
+    #[arg(short = 'g', long = "genetic-code", help_heading = "Translation")]
+    genetic_code: Option<u8>,
 
-    /// Reading frame (1-3)
-    #[arg(short = 'r', long = "reading-frame", default_value = "1", hide_default_value = true, help_heading = "Translation")]
-    reading_frame: u8,
+    /// Reading frame (1-3, default: 1 or config `reading_frame`)
+    #[arg(short = 'r', long = "reading-frame", help_heading = "Translation")]
+    reading_frame: Option<u8>,
+
+    // ==================== Filtering ====================
+
+    /// Drop sequences with fewer non-gap residues
+    #[arg(long = "min-length", value_name = "N", help_heading = "Filtering")]
+    min_length: Option<usize>,
+
+    /// Drop sequences with a larger proportion of gaps (0-1)
+    #[arg(long = "max-gap-fraction", value_name = "FRACTION", value_parser = parse_fraction, help_heading = "Filtering")]
+    max_gap_fraction: Option<f64>,
+
+    /// Drop sequences with a larger proportion of ambiguous residues (N, or X for amino acids; 0-1)
+    #[arg(long = "max-ambiguity", value_name = "FRACTION", value_parser = parse_fraction, help_heading = "Filtering")]
+    max_ambiguity: Option<f64>,
+
+    /// Drop translated sequences with a stop codon before the last residue
+    #[arg(long = "drop-internal-stops", requires = "translate", help_heading = "Filtering")]
+    drop_internal_stops: bool,
+
+    // ==================== Gaps ====================
+
+    /// Write raw (unaligned) sequences: strip '-' and '.' before translation,
+    /// so codons are read from the ungapped sequence
+    #[arg(long = "degap", conflicts_with_all = ["remove_gap_columns", "trim"], help_heading = "Gaps")]
+    degap: bool,
+
+    /// With --degap, also strip frameshift marks ('!')
+    #[arg(long = "strip-frameshifts", requires = "degap", help_heading = "Gaps")]
+    strip_frameshifts: bool,
+
+    /// Drop only the columns that are gaps in every sequence
+    #[arg(long = "remove-gap-columns", help_heading = "Gaps")]
+    remove_gap_columns: bool,
+
+    /// Drop the insert columns of A2M/A3M files (lowercase residues), keeping
+    /// the match states
+    #[arg(long = "hide-inserts", help_heading = "Gaps")]
+    hide_inserts: bool,
+
+    // ==================== Duplicates ====================
+
+    /// Keep one representative per group of identical sequences and write the
+    /// representative -> collapsed IDs mapping as TSV
+    #[arg(long = "collapse-duplicates", help_heading = "Duplicates")]
+    collapse_duplicates: bool,
+
+    /// With --collapse-duplicates, compare ungapped sequences
+    #[arg(long = "ignore-gaps", requires = "collapse_duplicates", help_heading = "Duplicates")]
+    ignore_gaps: bool,
+
+    /// With --collapse-duplicates, N (X for amino acids) and '?' match any residue
+    #[arg(long = "wildcards", requires = "collapse_duplicates", help_heading = "Duplicates")]
+    wildcards: bool,
+
+    // ==================== Trimming ====================
+
+    /// Remove alignment columns: allgaps, gappyout, strict, or a maximum gap fraction (e.g. 0.5).
+    /// Untranslated nucleotides are trimmed by whole codons of the reading frame.
+    #[arg(long = "trim", value_name = "METHOD", help_heading = "Trimming")]
+    trim: Option<TrimMethod>,
+
+    /// Write the positions of kept columns (trimmed<TAB>original, 1-based)
+    #[arg(long = "column-map", value_name = "FILE", requires = "trim", help_heading = "Trimming")]
+    column_map: Option<PathBuf>,
 
     // ==================== SNP Extraction ====================
     
@@ -894,29 +1911,116 @@ struct Args {
     #[arg(short = 'v', long = "vcf", value_name = "MIN_DIST", help_heading = "SNP Extraction")]
     vcf: Option<usize>,
 
+    // ==================== Statistics ====================
+
+    /// Print alignment statistics (to stdout, or to -o): one row per sequence
+    /// for a single file, one row per file for several files
+    #[arg(
+        long = "stats",
+        conflicts_with_all = [
+            "translate", "supermatrix", "partitions", "vcf", "split", "windows", "region", "trim",
+            "degap", "remove_gap_columns", "collapse_duplicates", "keep", "exclude", "rename",
+        ],
+        help_heading = "Statistics"
+    )]
+    stats: bool,
+
+    /// Statistics output format
+    #[arg(long = "stats-format", value_enum, default_value = "tsv", requires = "stats", help_heading = "Statistics")]
+    stats_format: StatsFormat,
+
+    // ==================== Distances ====================
+
+    /// Write a pairwise matrix (to stdout, or to -o): p (p-distance), identity
+    /// (percent identity), jc (Jukes-Cantor) or k2p (Kimura 2-parameter)
+    #[arg(
+        long = "distance",
+        value_name = "MODEL",
+        conflicts_with_all = [
+            "translate", "supermatrix", "partitions", "vcf", "split", "windows", "trim", "stats",
+            "degap", "collapse_duplicates", "keep", "exclude", "rename",
+        ],
+        help_heading = "Distances"
+    )]
+    distance: Option<DistanceModel>,
+
+    /// Sites left out for gaps and ambiguous residues: pairwise (per pair) or
+    /// complete (in any sequence)
+    #[arg(long = "deletion", value_name = "MODE", default_value = "pairwise", help_heading = "Distances")]
+    deletion: Deletion,
+
+    /// Distance matrix format
+    #[arg(long = "matrix-format", value_enum, default_value = "phylip", requires = "distance", help_heading = "Distances")]
+    matrix_format: MatrixFormat,
+
+    /// Write a neighbour-joining tree (Newick) of the --distance matrix
+    /// (p-distance by default)
+    #[arg(
+        long = "nj",
+        value_name = "FILE",
+        conflicts_with_all = [
+            "translate", "supermatrix", "partitions", "vcf", "split", "windows", "trim", "stats",
+            "degap", "collapse_duplicates", "keep", "exclude", "rename",
+        ],
+        help_heading = "Distances"
+    )]
+    nj: Option<PathBuf>,
+
+    /// Tree building method: bionj or nj
+    #[arg(long = "tree-method", value_name = "METHOD", default_value = "bionj", requires = "nj", help_heading = "Distances")]
+    tree_method: TreeMethod,
+
+    /// Newick tree to show left of the names in the viewer (rows follow its leaves)
+    #[arg(long = "tree", value_name = "FILE", help_heading = "Display")]
+    tree: Option<PathBuf>,
+
     /// Enable fancy Unicode glyphs in the TUI
-    #[arg(long = "fancy", help_heading = "Display")]
+    #[arg(long = "fancy", overrides_with = "no_fancy", help_heading = "Display")]
     fancy: bool,
+
+    /// Disable fancy glyphs (overrides config `fancy = true`)
+    #[arg(long = "no-fancy", overrides_with = "fancy", help_heading = "Display")]
+    no_fancy: bool,
+
+    // ==================== Configuration ====================
+
+    /// Ignore config files (~/.config/seqtui/config.toml and ./.seqtui.toml)
+    #[arg(long = "no-config", help_heading = "Configuration")]
+    no_config: bool,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
     let forced_format: Option<FileFormat> = args.format.into();
+    let block = args.block.as_deref();
+
+    // Load config files (global, then project); CLI flags override them below
+    let mut config = if args.no_config { Config::default() } else { Config::load()? };
+
+    let genetic_code = args.genetic_code.or(config.genetic_code).unwrap_or(1);
+    let reading_frame = args.reading_frame.or(config.reading_frame).unwrap_or(1);
 
     // Validate reading frame (1-3)
-    if args.reading_frame < 1 || args.reading_frame > 3 {
-        anyhow::bail!("Reading frame must be 1-3 (got {})", args.reading_frame);
+    if !(1..=3).contains(&reading_frame) {
+        anyhow::bail!("Reading frame must be 1-3 (got {})", reading_frame);
     }
 
     // Validate genetic code (1-33, with some gaps)
-    if args.genetic_code < 1 || args.genetic_code > 33 {
-        anyhow::bail!("Genetic code must be 1-33 (got {})", args.genetic_code);
+    if !(1..=33).contains(&genetic_code) {
+        anyhow::bail!("Genetic code must be 1-33 (got {})", genetic_code);
     }
 
-    // Parse and validate supermatrix gap character
+    if args.fancy {
+        config.fancy = Some(true);
+    } else if args.no_fancy {
+        config.fancy = Some(false);
+    }
+
+    // Parse and validate supermatrix gap character (bare -s uses the config default)
     let gap_char: Option<char> = match &args.supermatrix {
         None => None,
+        Some(s) if s.is_empty() => Some(config.gap_char.unwrap_or('-')),
         Some(s) => {
             if s.len() != 1 {
                 anyhow::bail!(
@@ -969,6 +2073,38 @@ fn main() -> Result<()> {
         None
     };
 
+    // Key extraction: --id-regex, -d/-f fields, or the full ID
+    let key_extractor = match (&args.id_regex, effective_delimiter) {
+        (Some(pattern), _) => KeyExtractor::Regex(
+            Regex::new(pattern).map_err(|e| anyhow::anyhow!("Invalid --id-regex '{}': {}", pattern, e))?,
+        ),
+        (None, Some(delimiter)) => KeyExtractor::Fields { delimiter, fields },
+        (None, None) => KeyExtractor::Full,
+    };
+
+    let filter = SequenceFilter {
+        min_length: args.min_length,
+        max_gap_fraction: args.max_gap_fraction,
+        max_ambiguity: args.max_ambiguity,
+        drop_internal_stops: args.drop_internal_stops,
+    };
+    let gaps = if args.degap {
+        GapOutput::Degap { frameshifts: args.strip_frameshifts }
+    } else if args.remove_gap_columns {
+        GapOutput::RemoveGapColumns
+    } else {
+        GapOutput::Keep
+    };
+    let duplicates = args.collapse_duplicates.then_some(DuplicateOptions {
+        ignore_gaps: args.ignore_gaps,
+        wildcards: args.wildcards,
+    });
+
+    // Validate: sequence filters clean the inputs of CLI and concatenation modes only
+    if filter.is_active() && (args.stats || args.split.is_some() || args.vcf.is_some() || args.distance.is_some() || args.nj.is_some()) {
+        anyhow::bail!("Sequence filters (--min-length, --max-gap-fraction, --max-ambiguity, --drop-internal-stops) are incompatible with --stats, --split, -v/--vcf, --distance and --nj");
+    }
+
     // Validate: supermatrix/partitions/delimiter/fields require output mode
     if args.output.is_none() {
         if args.supermatrix.is_some() {
@@ -980,8 +2116,17 @@ fn main() -> Result<()> {
         if args.vcf.is_some() {
             anyhow::bail!("-v/--vcf requires -o/--output");
         }
-        if (args.delimiter.is_some() || args.fields.is_some()) && args.files.len() > 1 {
-            anyhow::bail!("-d/--delimiter or -f/--fields with multiple files requires -o/--output");
+        if args.trim.is_some() {
+            anyhow::bail!("--trim requires -o/--output (use :trim in the viewer)");
+        }
+        if args.keep.is_some() || args.exclude.is_some() || args.rename.is_some() {
+            anyhow::bail!("--keep, --exclude and --rename require -o/--output");
+        }
+        if filter.is_active() {
+            anyhow::bail!("Sequence filters (--min-length, --max-gap-fraction, --max-ambiguity, --drop-internal-stops) require -o/--output");
+        }
+        if (args.delimiter.is_some() || args.fields.is_some() || args.id_regex.is_some()) && args.files.len() > 1 {
+            anyhow::bail!("-d/--delimiter, -f/--fields or --id-regex with multiple files requires -o/--output");
         }
     }
 
@@ -993,6 +2138,109 @@ fn main() -> Result<()> {
         if args.partitions.is_some() {
             anyhow::bail!("-p/--partitions requires multiple input files");
         }
+        if args.min_taxa_per_locus.is_some() || args.min_loci_per_taxon.is_some() {
+            anyhow::bail!("--min-taxa-per-locus and --min-loci-per-taxon require multiple input files");
+        }
+        // A single file only matches IDs against --keep/--exclude/--rename (or samples in VCF mode)
+        if (args.delimiter.is_some() || args.fields.is_some() || args.id_regex.is_some())
+            && args.keep.is_none() && args.exclude.is_none() && args.rename.is_none() && args.vcf.is_none()
+        {
+            anyhow::bail!("-d/--delimiter, -f/--fields and --id-regex with a single input file require --keep, --exclude or --rename");
+        }
+    }
+
+    // Validate: gap options apply to single-file CLI mode
+    if args.degap || args.remove_gap_columns {
+        if args.output.is_none() {
+            anyhow::bail!("--degap and --remove-gap-columns require -o/--output (use :degap in the viewer)");
+        }
+        if args.files.len() != 1 {
+            anyhow::bail!("--degap and --remove-gap-columns require a single input file");
+        }
+        if args.split.is_some() || args.windows.is_some() || args.vcf.is_some() {
+            anyhow::bail!("--degap and --remove-gap-columns are incompatible with --split, --windows and -v/--vcf");
+        }
+    }
+
+    // Validate: insert columns are hidden in single-file CLI mode
+    if args.hide_inserts {
+        if args.output.is_none() {
+            anyhow::bail!("--hide-inserts requires -o/--output (use zi or :inserts in the viewer)");
+        }
+        if args.files.len() != 1 {
+            anyhow::bail!("--hide-inserts requires a single input file");
+        }
+        if args.split.is_some() || args.windows.is_some() || args.vcf.is_some() {
+            anyhow::bail!("--hide-inserts is incompatible with --split, --windows and -v/--vcf");
+        }
+    }
+
+    // Validate: duplicate collapsing applies to single-file CLI mode
+    if args.collapse_duplicates {
+        if args.output.is_none() {
+            anyhow::bail!("--collapse-duplicates requires -o/--output");
+        }
+        if args.files.len() != 1 {
+            anyhow::bail!("--collapse-duplicates requires a single input file");
+        }
+        if args.split.is_some() || args.windows.is_some() || args.vcf.is_some() {
+            anyhow::bail!("--collapse-duplicates is incompatible with --split, --windows and -v/--vcf");
+        }
+    }
+
+    // Validate: distances are computed within one alignment
+    if args.distance.is_some() && args.files.len() > 1 {
+        anyhow::bail!("--distance requires a single input file");
+    }
+    if args.nj.is_some() {
+        if args.files.len() > 1 {
+            anyhow::bail!("--nj requires a single input file");
+        }
+        if args.distance.is_some_and(|model| model.is_similarity()) {
+            anyhow::bail!("--nj requires a distance model (p, jc or k2p), not identity");
+        }
+    }
+    if args.deletion != Deletion::Pairwise && args.distance.is_none() && args.nj.is_none() {
+        anyhow::bail!("--deletion requires --distance or --nj");
+    }
+
+    // Validate: regions and windows apply to a single alignment
+    if args.region.is_some() || args.windows.is_some() || args.region_ref.is_some() {
+        if args.region.is_none() && args.windows.is_none() {
+            anyhow::bail!("--region-ref requires --region or --windows");
+        }
+        if args.output.is_none() {
+            anyhow::bail!("--region and --windows require -o/--output");
+        }
+        if args.files.len() != 1 {
+            anyhow::bail!("--region and --windows require a single input file");
+        }
+        if args.split.is_some() || args.vcf.is_some() {
+            anyhow::bail!("--region and --windows are incompatible with --split and -v/--vcf");
+        }
+    }
+    if args.windows.is_some() {
+        if args.output.as_deref() == Some("-") {
+            anyhow::bail!("--windows requires -o/--output to be a directory");
+        }
+        if args.translate || args.trim.is_some() || filter.is_active()
+            || args.keep.is_some() || args.exclude.is_some() || args.rename.is_some()
+        {
+            anyhow::bail!("--windows is incompatible with -t, --trim, sequence filters and --keep/--exclude/--rename");
+        }
+    }
+
+    // Validate: split mode takes one supermatrix and writes to a directory
+    if args.split.is_some() {
+        if args.output.is_none() || args.output.as_deref() == Some("-") {
+            anyhow::bail!("--split requires -o/--output (output directory)");
+        }
+        if args.files.len() != 1 {
+            anyhow::bail!("--split requires a single input file (the supermatrix)");
+        }
+        if args.translate || args.supermatrix.is_some() || args.partitions.is_some() || args.vcf.is_some() || args.trim.is_some() {
+            anyhow::bail!("--split is incompatible with -t, -s, -p, -v and --trim");
+        }
     }
 
     // Validate: VCF mode is incompatible with other CLI options
@@ -1006,16 +2254,77 @@ fn main() -> Result<()> {
         if args.partitions.is_some() {
             anyhow::bail!("-v/--vcf is incompatible with -p/--partitions");
         }
+        if args.min_taxa_per_locus.is_some() || args.min_loci_per_taxon.is_some() {
+            anyhow::bail!("-v/--vcf is incompatible with --min-taxa-per-locus and --min-loci-per-taxon");
+        }
+        if args.trim.is_some() {
+            anyhow::bail!("-v/--vcf is incompatible with --trim");
+        }
+        if args.keep.is_some() || args.exclude.is_some() || args.rename.is_some() {
+            anyhow::bail!("-v/--vcf is incompatible with --keep, --exclude and --rename");
+        }
     }
 
-    let fancy_ui = args.fancy;
+    // Validate: the tree is only shown by the viewer
+    if args.tree.is_some()
+        && (args.output.is_some() || args.files.len() != 1 || args.stats || args.distance.is_some() || args.nj.is_some())
+    {
+        anyhow::bail!("--tree is only used by the viewer (a single input file, without -o or other modes)");
+    }
 
     // No files provided: open TUI with file browser
     if args.files.is_empty() {
+        if args.stats || args.distance.is_some() || args.nj.is_some() {
+            anyhow::bail!("--stats, --distance and --nj require at least one input file");
+        }
         if args.output.is_some() {
             anyhow::bail!("CLI mode (-o/--output) requires at least one input file");
         }
-        return run_app_with_file_browser(fancy_ui);
+        return run_app_with_file_browser(&config);
+    }
+
+    // Stats mode: summary statistics, no alignment output
+    if args.stats {
+        return run_stats_mode(&args.files, forced_format, block, args.output.as_deref(), args.stats_format, reading_frame);
+    }
+
+    // Tree mode: neighbour-joining tree, optionally with its matrix
+    if let Some(tree_path) = &args.nj {
+        return run_tree_mode(
+            &args.files[0],
+            forced_format,
+            block,
+            tree_path,
+            args.distance.map(|_| (args.output.as_deref().unwrap_or("-"), args.matrix_format)),
+            args.distance.unwrap_or_default(),
+            args.deletion,
+            args.tree_method,
+        );
+    }
+
+    // Distance mode: pairwise matrix, no alignment output
+    if let Some(model) = args.distance {
+        return run_distance_mode(
+            &args.files[0],
+            forced_format,
+            block,
+            args.output.as_deref(),
+            model,
+            args.deletion,
+            args.matrix_format,
+        );
+    }
+
+    // Split mode: one alignment per partition
+    if let Some(partitions_path) = &args.split {
+        let output = args.output.as_ref().unwrap(); // Already validated above
+        return run_split_mode(&args.files[0], forced_format, block, partitions_path, output, args.drop_empty);
+    }
+
+    // Windows mode: one alignment per sliding window
+    if let Some(windows) = args.windows {
+        let output = args.output.as_ref().unwrap(); // Already validated above
+        return run_windows_mode(&args.files[0], forced_format, block, output, windows, args.region, args.region_ref.as_deref());
     }
 
     // VCF mode: extract biallelic SNPs
@@ -1024,62 +2333,91 @@ fn main() -> Result<()> {
         return run_vcf_mode(
             &args.files,
             forced_format,
+            block,
             output,
             min_dist,
-            effective_delimiter.as_deref(),
-            fields.as_deref(),
+            &key_extractor,
             args.force,
         );
     }
 
+    let partition_scheme = PartitionScheme {
+        format: args.partition_format.into(),
+        models: args.partition_model.clone().unwrap_or_default(),
+        split_codons: args.codon_partitions,
+    };
+    if args.partition_model.is_some() && partition_scheme.format == PartitionFormat::Nexus {
+        anyhow::bail!("--partition-model requires --partition-format raxml or iqtree");
+    }
+    if args.codon_partitions && args.translate {
+        anyhow::bail!("--codon-partitions is incompatible with -t/--translate");
+    }
+
+    // ID lists and rename table (CLI and concatenation modes)
+    let selection = IdSelection::load(args.keep.as_deref(), args.exclude.as_deref(), args.rename.as_deref())?;
+
     // Multiple files: concatenation mode (requires -o)
     if args.files.len() > 1 {
         let output = args.output.ok_or_else(|| {
             anyhow::anyhow!("Multiple input files require -o/--output for concatenation")
         })?;
         
-        run_concatenation_mode(
-            &args.files,
-            forced_format,
-            &output,
-            args.translate,
-            args.genetic_code,
-            args.reading_frame,
-            effective_delimiter.as_deref(),
-            fields.as_deref(),
+        let options = ConcatOptions {
+            translate: args.translate,
+            genetic_code,
+            reading_frame,
+            key_extractor,
+            selection,
             gap_char,
-            args.partitions.as_deref(),
-            args.force,
-        )?;
+            filter,
+            partitions_file: args.partitions,
+            partition_scheme,
+            min_taxa_per_locus: args.min_taxa_per_locus.unwrap_or(0),
+            min_loci_per_taxon: args.min_loci_per_taxon.unwrap_or(0),
+            trim_method: args.trim,
+            column_map: args.column_map,
+            force: args.force,
+        };
+        run_concatenation_mode(&args.files, forced_format, block, &output, &options)?;
     } else {
         // Single file mode
         let file_path = &args.files[0];
         
         if let Some(output) = args.output {
             // CLI mode: output to file/stdout
-            run_cli_mode(
-                file_path,
-                forced_format,
-                &output,
-                args.translate,
-                args.genetic_code,
-                args.reading_frame,
-                args.force,
-            )?;
+            let options = CliOptions {
+                translate: args.translate,
+                genetic_code,
+                reading_frame,
+                key_extractor,
+                selection,
+                region: args.region,
+                region_ref: args.region_ref,
+                filter,
+                gaps,
+                hide_inserts: args.hide_inserts,
+                trim_method: args.trim,
+                column_map: args.column_map,
+                duplicates,
+                force: args.force,
+            };
+            run_cli_mode(file_path, forced_format, block, &output, &options)?;
         } else {
             // TUI mode with optional preset translation settings
             if file_path.is_dir() {
-                return run_app_with_file_browser_at(file_path.clone(), fancy_ui);
+                return run_app_with_file_browser_at(file_path.clone(), &config);
             }
             run_app_with_loading(
                 file_path.clone(),
                 forced_format,
-                if args.translate || args.genetic_code != 1 || args.reading_frame != 1 {
-                    Some((args.genetic_code, args.reading_frame))
+                args.block.clone(),
+                if args.translate || args.genetic_code.is_some() || args.reading_frame.is_some() {
+                    Some((genetic_code, reading_frame))
                 } else {
                     None
                 },
-                fancy_ui,
+                args.tree.clone(),
+                &config,
             )?;
         }
     }
@@ -1090,6 +2428,7 @@ fn main() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use seqtui::formats::parse_file_with_options;
     use std::io::BufRead;
     use std::sync::atomic::{AtomicUsize, Ordering};
     
@@ -1104,13 +2443,13 @@ mod tests {
         // Use unique temp file per test to avoid race conditions
         let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
         let tmp_output = format!("/tmp/seqtui_test_vcf_{}.vcf", test_id);
-        run_vcf_mode(&file_paths, None, &tmp_output, min_dist, None, None, false).unwrap();
+        run_vcf_mode(&file_paths, None, None, &tmp_output, min_dist, &KeyExtractor::Full, false).unwrap();
         
         // Read and return data lines (skip header)
         let file = std::fs::File::open(&tmp_output).unwrap();
         let reader = std::io::BufReader::new(file);
         let lines: Vec<String> = reader.lines()
-            .filter_map(|l| l.ok())
+            .map_while(Result::ok)
             .filter(|l| !l.starts_with('#'))
             .collect();
         
@@ -1133,10 +2472,10 @@ mod tests {
         let mut dl = 0;
         let mut dr = 0;
         for field in info.split(';') {
-            if field.starts_with("DL=") {
-                dl = field[3..].parse().unwrap();
-            } else if field.starts_with("DR=") {
-                dr = field[3..].parse().unwrap();
+            if let Some(value) = field.strip_prefix("DL=") {
+                dl = value.parse().unwrap();
+            } else if let Some(value) = field.strip_prefix("DR=") {
+                dr = value.parse().unwrap();
             }
         }
         
@@ -1272,6 +2611,13 @@ mod tests {
         assert!(log_str.ends_with(".log"), "Log should end with .log");
     }
     
+    #[test]
+    fn test_report_path_is_next_to_output() {
+        assert_eq!(report_path("/tmp/subdir/result.fa", "occupancy.tsv"), PathBuf::from("/tmp/subdir/result.occupancy.tsv"));
+        assert_eq!(report_path("result.fa", "duplicates.tsv"), PathBuf::from("result.duplicates.tsv"));
+        assert_eq!(report_path("-", "occupancy.tsv"), PathBuf::from("seqtui.occupancy.tsv"));
+    }
+
     #[test]
     fn test_generate_log_path_uniqueness() {
         // Test that multiple calls generate different paths
@@ -1282,7 +2628,7 @@ mod tests {
     }
     
     #[test]
-    fn test_concatenation_creates_log_file() {
+    fn test_concatenation_writes_occupancy_report() {
         let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
         let tmp_output = format!("/tmp/seqtui_test_concat_{}.fasta", test_id);
         
@@ -1292,51 +2638,224 @@ mod tests {
             PathBuf::from("test_data/LOC_01790.nex"),
         ];
         
-        let result = run_concatenation_mode(
-            &files,
-            None,           // auto-detect format
-            &tmp_output,
-            false,          // no translation
-            1,              // genetic code (unused)
-            1,              // frame (unused)
-            None,           // no delimiter
-            None,           // no fields
-            Some('-'),      // supermatrix mode with gap filling
-            None,           // no partitions file
-            true,           // force (skip ID mismatch check)
-        );
+        let options = ConcatOptions {
+            gap_char: Some('-'), // supermatrix mode with gap filling
+            force: true,         // skip ID mismatch check
+            ..Default::default()
+        };
+        let result = run_concatenation_mode(&files, None, None, &tmp_output, &options);
         
         assert!(result.is_ok(), "Concatenation should succeed");
-        
-        // Find the log file that was created
-        let tmp_dir = std::path::Path::new("/tmp");
+
+        // The occupancy matrix next to the output doubles as the log
+        let report = format!("/tmp/seqtui_test_concat_{}.occupancy.tsv", test_id);
+        let content = std::fs::read_to_string(&report).expect("Occupancy report should be written");
+        assert!(content.contains("# SeqTUI"), "Report should have SeqTUI header");
+        assert!(content.contains("# Per-file statistics"), "Report should have per-file statistics");
+        assert!(content.contains("alignment.fasta") && content.contains("LOC_01790"), "Report should list input files");
+
+        // No separate log file
         let output_stem = format!("seqtui_test_concat_{}", test_id);
-        let mut found_log = false;
-        
-        if let Ok(entries) = std::fs::read_dir(tmp_dir) {
+        let logs = std::fs::read_dir("/tmp")
+            .unwrap()
+            .flatten()
+            .filter(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                name.starts_with(&output_stem) && name.ends_with(".log")
+            })
+            .count();
+        assert_eq!(logs, 0);
+
+        let _ = std::fs::remove_file(&report);
+        let _ = std::fs::remove_file(&tmp_output);
+    }
+    
+    #[test]
+    fn test_cli_filters_write_dropped_log() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let tmp_input = PathBuf::from(format!("/tmp/seqtui_test_filter_{}_in.fasta", test_id));
+        let tmp_output = format!("/tmp/seqtui_test_filter_{}.fasta", test_id);
+        std::fs::write(
+            &tmp_input,
+            ">good\nATGAAATTT\n>short\nATG------\n>ambig\nATGNNNNNT\n>stop\nATGTAATTT\n",
+        )
+        .unwrap();
+
+        let filter = SequenceFilter {
+            min_length: Some(6),
+            max_ambiguity: Some(0.5),
+            drop_internal_stops: true,
+            ..Default::default()
+        };
+        let options = CliOptions { translate: true, filter, ..Default::default() };
+        let result = run_cli_mode(&tmp_input, None, None, &tmp_output, &options);
+        assert!(result.is_ok(), "Filtering should succeed: {:?}", result.err());
+
+        let kept = parse_file_with_options(Path::new(&tmp_output), None).unwrap();
+        let ids: Vec<&str> = kept.sequences.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["good"]);
+
+        let mut log = String::new();
+        if let Ok(entries) = std::fs::read_dir("/tmp") {
             for entry in entries.flatten() {
                 let name = entry.file_name().to_string_lossy().to_string();
-                if name.starts_with(&output_stem) && name.ends_with(".log") {
-                    found_log = true;
-                    // Verify log content has expected structure
-                    if let Ok(content) = std::fs::read_to_string(entry.path()) {
-                        assert!(content.contains("# SeqTUI"), "Log should have SeqTUI header");
-                        assert!(content.contains("alignment.fasta") || content.contains("LOC_01790"), 
-                                "Log should list input files");
+                if name.starts_with(&format!("seqtui_test_filter_{}_", test_id)) && name.ends_with(".log") {
+                    log = std::fs::read_to_string(entry.path()).unwrap();
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+        assert!(log.contains("short\tlength 3 < 6"));
+        assert!(log.contains("ambig\tambiguity"));
+        assert!(log.contains("stop\tinternal stop at position 2"));
+
+        let _ = std::fs::remove_file(&tmp_input);
+        let _ = std::fs::remove_file(&tmp_output);
+    }
+
+    #[test]
+    fn test_concatenation_keep_and_rename_extracted_keys() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let prefix = format!("/tmp/seqtui_test_ids_{}", test_id);
+        let files = vec![PathBuf::from(format!("{}_a.fasta", prefix)), PathBuf::from(format!("{}_b.fasta", prefix))];
+        std::fs::write(&files[0], ">x_1\nAAA\n>y_1\nCCC\n>z_1\nGGG\n").unwrap();
+        std::fs::write(&files[1], ">x_2\nTTT\n>y_2\nAAA\n").unwrap();
+        let tmp_output = format!("{}.fasta", prefix);
+
+        let selection = IdSelection::new(
+            Some(vec!["x".into(), "y".into(), "w".into()]),
+            vec![],
+            vec![("x".into(), "X".into())],
+        )
+        .unwrap();
+        let key_extractor = KeyExtractor::Fields { delimiter: "_".into(), fields: Some(vec![1]) };
+        let options = ConcatOptions { key_extractor, selection, gap_char: Some('-'), ..Default::default() };
+        let result = run_concatenation_mode(&files, None, None, &tmp_output, &options);
+        assert!(result.is_ok(), "Concatenation should succeed: {:?}", result.err());
+
+        let output = parse_file_with_options(Path::new(&tmp_output), None).unwrap();
+        let records: Vec<(&str, &str)> = output.sequences.iter().map(|s| (s.id.as_str(), s.as_str())).collect();
+        assert_eq!(records, vec![("X", "AAATTT"), ("y", "CCCAAA")]);
+
+        // "w" matched nothing and is reported in a log file
+        let mut unmatched = String::new();
+        if let Ok(entries) = std::fs::read_dir("/tmp") {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with(&format!("seqtui_test_ids_{}_", test_id)) {
+                    if name.ends_with(".log") {
+                        let content = std::fs::read_to_string(entry.path()).unwrap();
+                        if content.contains("matching no sequence") {
+                            unmatched = content;
+                        }
                     }
-                    // Clean up
                     let _ = std::fs::remove_file(entry.path());
-                    break;
                 }
             }
         }
-        
-        // Clean up output file
         let _ = std::fs::remove_file(&tmp_output);
-        
-        assert!(found_log, "Concatenation should create a log file");
+        let _ = std::fs::remove_file(report_path(&tmp_output, "occupancy.tsv"));
+        assert!(unmatched.contains("keep\tw"));
     }
-    
+
+    #[test]
+    fn test_concatenation_applies_sequence_filters() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let prefix = format!("/tmp/seqtui_test_concat_filter_{}", test_id);
+        let files = vec![PathBuf::from(format!("{}_a.fasta", prefix)), PathBuf::from(format!("{}_b.fasta", prefix))];
+        std::fs::write(&files[0], ">s1\nACGTACGTAC\n>s2\nAC--------\n").unwrap();
+        std::fs::write(&files[1], ">s1\nACGTAC\n>s2\nACGTAA\n").unwrap();
+        let tmp_output = format!("{}.fasta", prefix);
+
+        let filter = SequenceFilter { min_length: Some(5), max_gap_fraction: Some(0.1), ..Default::default() };
+        let options = ConcatOptions { filter, gap_char: Some('-'), force: true, ..Default::default() };
+        let result = run_concatenation_mode(&files, None, None, &tmp_output, &options);
+        assert!(result.is_ok(), "Concatenation should succeed: {:?}", result.err());
+
+        // s2 is dropped from the first file only, and filled with gaps there
+        let output = parse_file_with_options(Path::new(&tmp_output), None).unwrap();
+        let records: Vec<(&str, &str)> = output.sequences.iter().map(|s| (s.id.as_str(), s.as_str())).collect();
+        assert_eq!(records, vec![("s1", "ACGTACGTACACGTAC"), ("s2", "----------ACGTAA")]);
+
+        let mut log = String::new();
+        if let Ok(entries) = std::fs::read_dir("/tmp") {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with(&format!("seqtui_test_concat_filter_{}", test_id)) {
+                    if name.ends_with(".log") {
+                        log = std::fs::read_to_string(entry.path()).unwrap();
+                    }
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+        assert!(log.contains(&format!("{}\ts2\tlength 2 < 5", files[0].display())), "{}", log);
+    }
+
+    #[test]
+    fn test_concatenation_orphans_hint_names_id_regex() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let prefix = format!("/tmp/seqtui_test_orphans_{}", test_id);
+        let files = vec![PathBuf::from(format!("{}_a.fasta", prefix)), PathBuf::from(format!("{}_b.fasta", prefix))];
+        std::fs::write(&files[0], ">a|1\nAAA\n>b|1\nCCC\n").unwrap();
+        std::fs::write(&files[1], ">c|2\nTTT\n>d|2\nAAA\n").unwrap();
+        let tmp_output = format!("{}.fasta", prefix);
+
+        let key_extractor = KeyExtractor::Regex(Regex::new(r"^([^|]+)\|").unwrap());
+        let options = ConcatOptions { key_extractor, gap_char: Some('-'), ..Default::default() };
+        let err = run_concatenation_mode(&files, None, None, &tmp_output, &options).unwrap_err().to_string();
+        for file in &files {
+            let _ = std::fs::remove_file(file);
+        }
+        let _ = std::fs::remove_file(report_path(&tmp_output, "occupancy.tsv"));
+        assert!(err.contains("Check that --id-regex extracts a common prefix"), "{}", err);
+        assert!(!err.contains("-d/--delimiter"), "{}", err);
+    }
+
+    #[test]
+    fn test_concatenation_occupancy_filters() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let prefix = format!("/tmp/seqtui_test_occ_{}", test_id);
+        let files: Vec<PathBuf> = ["a", "b", "c"].iter().map(|l| PathBuf::from(format!("{}_{}.fasta", prefix, l))).collect();
+        // Locus c only has taxa y and z; z is only in locus c
+        std::fs::write(&files[0], ">x\nAAA\n>y\nCC-\n").unwrap();
+        std::fs::write(&files[1], ">x\nTTT\n>y\nGGG\n").unwrap();
+        std::fs::write(&files[2], ">y\nAAA\n>z\nCCC\n").unwrap();
+        let tmp_output = format!("{}_out.fasta", prefix);
+
+        let options = ConcatOptions {
+            gap_char: Some('-'),
+            min_taxa_per_locus: 2,
+            min_loci_per_taxon: 2,
+            force: true,
+            ..Default::default()
+        };
+        let result = run_concatenation_mode(&files, None, None, &tmp_output, &options);
+        assert!(result.is_ok(), "Concatenation should succeed: {:?}", result.err());
+
+        // z has a single locus: dropped, then c has a single taxon: dropped
+        let output = parse_file_with_options(Path::new(&tmp_output), None).unwrap();
+        let records: Vec<(&str, &str)> = output.sequences.iter().map(|s| (s.id.as_str(), s.as_str())).collect();
+        assert_eq!(records, vec![("x", "AAATTT"), ("y", "CC-GGG")]);
+
+        let mut matrix = String::new();
+        if let Ok(entries) = std::fs::read_dir("/tmp") {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with(&format!("seqtui_test_occ_{}_", test_id)) {
+                    if name.ends_with(".tsv") {
+                        matrix = std::fs::read_to_string(entry.path()).unwrap();
+                    }
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+        let rows: Vec<&str> = matrix.lines().filter(|l| !l.starts_with('#')).collect();
+        let header = format!("taxon\tloci\tseqtui_test_occ_{0}_a\tseqtui_test_occ_{0}_b\tseqtui_test_occ_{0}_c", test_id);
+        assert_eq!(rows, vec![header.as_str(), "x\t2\t3\t3\t0", "y\t2\t2\t3\t3", "z\t0\t0\t0\t3"]);
+        assert!(matrix.contains("# Dropped loci (1)"));
+    }
+
     // ==================== Field Extraction Tests ====================
     
     #[test]
@@ -1416,6 +2935,44 @@ mod tests {
         assert!(err.contains("1-based"), "Error should mention 1-based indexing");
     }
     
+    #[test]
+    fn test_selection_conflicts_with_matrix_modes() {
+        for mode in [&["--split", "parts.nex"][..], &["--distance", "p"], &["--nj", "tree.nwk"], &["--stats"]] {
+            for option in ["--keep", "--exclude", "--rename"] {
+                let mut argv = vec!["seqtui", "in.fasta", "-o", "out", option, "ids.txt"];
+                argv.extend_from_slice(mode);
+                let err = Args::try_parse_from(&argv).unwrap_err();
+                assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict, "{:?}", argv);
+            }
+        }
+        assert!(Args::try_parse_from(["seqtui", "in.fasta", "-o", "out", "--keep", "ids.txt"]).is_ok());
+    }
+
+    #[test]
+    fn test_extract_key_regex() {
+        // First capture group
+        let re = Regex::new(r"^([^|]+)\|").unwrap();
+        assert_eq!(extract_key_regex("Homo_sapiens|gene=abc|len=300", &re, "test.fa").unwrap(), "Homo_sapiens");
+
+        // Named groups joined with "_", skipping optional groups that did not match
+        let re = Regex::new(r"^(?P<sp>[a-z]+)_(?P<acc>\d+)(?:\.(?P<ver>\d+))?").unwrap();
+        assert_eq!(extract_key_regex("sp_12345.1", &re, "test.fa").unwrap(), "sp_12345_1");
+        assert_eq!(extract_key_regex("sp_12345", &re, "test.fa").unwrap(), "sp_12345");
+
+        // No group: whole match
+        let re = Regex::new(r"^[A-Z][a-z]*_[a-z]+").unwrap();
+        assert_eq!(extract_key_regex("Ae_bicornis_contig1", &re, "test.fa").unwrap(), "Ae_bicornis");
+    }
+
+    #[test]
+    fn test_extract_key_regex_no_match() {
+        let re = Regex::new(r"gene=(\w+)").unwrap();
+        let err = extract_key_regex("Homo_sapiens|len=300", &re, "test.fa").unwrap_err().to_string();
+        assert!(err.contains("Homo_sapiens|len=300"), "Error should include full sequence name");
+        assert!(err.contains("test.fa"), "Error should include file name");
+        assert!(err.contains("gene=(\\w+)"), "Error should include the pattern");
+    }
+
     #[test]
     fn test_extract_key_different_delimiter() {
         // Use different delimiter
@@ -1439,19 +2996,13 @@ mod tests {
             PathBuf::from("examples/LOC_39310.fasta"),
         ];
         
-        let result = run_concatenation_mode(
-            &files,
-            None,                           // auto-detect format
-            &tmp_output,
-            true,                           // translate
-            1,                              // genetic code
-            1,                              // frame
-            None,                           // no delimiter
-            None,                           // no fields
-            Some('-'),                      // supermatrix mode
-            Some(&tmp_partition),           // partition file
-            false,                          // no force
-        );
+        let options = ConcatOptions {
+            translate: true,
+            gap_char: Some('-'), // supermatrix mode
+            partitions_file: Some(tmp_partition.clone()),
+            ..Default::default()
+        };
+        let result = run_concatenation_mode(&files, None, None, &tmp_output, &options);
         
         assert!(result.is_ok(), "Concatenation with partitions should succeed");
         
@@ -1471,6 +3022,7 @@ end;
         
         // Clean up
         let _ = std::fs::remove_file(&tmp_output);
+        let _ = std::fs::remove_file(report_path(&tmp_output, "occupancy.tsv"));
         let _ = std::fs::remove_file(&tmp_partition);
         // Clean up log file
         if let Ok(entries) = std::fs::read_dir("/tmp") {
@@ -1483,6 +3035,406 @@ end;
         }
     }
     
+    #[test]
+    fn test_trim_remaps_partitions_by_codons() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let tmp_output = format!("/tmp/seqtui_test_trim_{}.fasta", test_id);
+        let tmp_partition = format!("/tmp/seqtui_test_trim_{}.nex", test_id);
+        let tmp_map = PathBuf::from(format!("/tmp/seqtui_test_trim_{}.map", test_id));
+
+        let files = vec![
+            PathBuf::from("examples/LOC_01790.nex"),
+            PathBuf::from("examples/LOC_11070.fasta"),
+            PathBuf::from("examples/LOC_39310.fasta"),
+        ];
+        let lengths: Vec<usize> = files
+            .iter()
+            .map(|f| parse_file_with_options(f, None).unwrap().alignment_length())
+            .collect();
+
+        // Nucleotides are kept: codon-aware trimming
+        let options = ConcatOptions {
+            gap_char: Some('-'),
+            partitions_file: Some(tmp_partition.clone()),
+            trim_method: Some(TrimMethod::Gappyout),
+            column_map: Some(tmp_map.clone()),
+            ..Default::default()
+        };
+        let result = run_concatenation_mode(&files, None, None, &tmp_output, &options);
+        assert!(result.is_ok(), "Trimmed concatenation should succeed: {:?}", result.err());
+
+        // Partitions are contiguous, cover the trimmed matrix, and lost whole codons only
+        let trimmed = parse_file_with_options(Path::new(&tmp_output), None).unwrap();
+        let content = std::fs::read_to_string(&tmp_partition).unwrap();
+        let ranges: Vec<(usize, usize)> = content
+            .lines()
+            .filter_map(|l| l.trim().strip_prefix("charset "))
+            .map(|l| {
+                let range = l.split(" = ").nth(1).unwrap().trim_end_matches(';');
+                let (a, b) = range.split_once('-').unwrap();
+                (a.parse().unwrap(), b.parse().unwrap())
+            })
+            .collect();
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges[0].0, 1);
+        for w in ranges.windows(2) {
+            assert_eq!(w[0].1 + 1, w[1].0);
+        }
+        assert_eq!(ranges[2].1, trimmed.alignment_length());
+        for ((start, end), orig) in ranges.iter().zip(&lengths) {
+            assert_eq!((orig - (end - start + 1)) % 3, 0, "whole codons removed");
+        }
+
+        // Column map has one line per kept column (plus header)
+        let map = std::fs::read_to_string(&tmp_map).unwrap();
+        assert_eq!(map.lines().count(), trimmed.alignment_length() + 1);
+
+        let _ = std::fs::remove_file(&tmp_output);
+        let _ = std::fs::remove_file(report_path(&tmp_output, "occupancy.tsv"));
+        let _ = std::fs::remove_file(&tmp_partition);
+        let _ = std::fs::remove_file(&tmp_map);
+        if let Ok(entries) = std::fs::read_dir("/tmp") {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with(&format!("seqtui_test_trim_{}_", test_id)) {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_codon_partitions_raxml() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let tmp_output = format!("/tmp/seqtui_test_codon_{}.fasta", test_id);
+        let tmp_partition = format!("/tmp/seqtui_test_codon_{}.txt", test_id);
+        let files = vec![PathBuf::from("examples/LOC_11070.fasta"), PathBuf::from("examples/LOC_39310.fasta")];
+        let scheme = PartitionScheme {
+            format: PartitionFormat::Raxml,
+            models: "LOC_39310=HKY+G".parse().unwrap(),
+            split_codons: true,
+        };
+
+        let options = ConcatOptions {
+            gap_char: Some('-'),
+            partitions_file: Some(tmp_partition.clone()),
+            partition_scheme: scheme,
+            force: true,
+            ..Default::default()
+        };
+        let result = run_concatenation_mode(&files, None, None, &tmp_output, &options);
+        assert!(result.is_ok(), "Concatenation should succeed: {:?}", result.err());
+
+        let content = std::fs::read_to_string(&tmp_partition).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[0].starts_with("DNA, LOC_11070_pos1 = 1-"));
+        assert!(lines[1].starts_with("DNA, LOC_11070_pos2 = 2-"));
+        assert!(lines[3].starts_with("HKY+G, LOC_39310_pos1 = 1051-"), "{}", lines[3]);
+        assert!(lines.iter().all(|l| l.ends_with("\\3")));
+
+        let _ = std::fs::remove_file(&tmp_output);
+        let _ = std::fs::remove_file(report_path(&tmp_output, "occupancy.tsv"));
+        let _ = std::fs::remove_file(&tmp_partition);
+        if let Ok(entries) = std::fs::read_dir("/tmp") {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with(&format!("seqtui_test_codon_{}_", test_id)) {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_inverts_concatenation() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let tmp_output = format!("/tmp/seqtui_test_split_{}.fasta", test_id);
+        let tmp_partition = PathBuf::from(format!("/tmp/seqtui_test_split_{}.nex", test_id));
+        let tmp_dir = format!("/tmp/seqtui_test_split_{}_dir", test_id);
+        let files = vec![
+            PathBuf::from("examples/LOC_01790.nex"),
+            PathBuf::from("examples/LOC_11070.fasta"),
+            PathBuf::from("examples/LOC_39310.fasta"),
+        ];
+        let options = ConcatOptions {
+            gap_char: Some('-'),
+            partitions_file: tmp_partition.to_str().map(String::from),
+            force: true,
+            ..Default::default()
+        };
+        let result = run_concatenation_mode(&files, None, None, &tmp_output, &options);
+        assert!(result.is_ok(), "Concatenation should succeed: {:?}", result.err());
+
+        let result = run_split_mode(&PathBuf::from(&tmp_output), None, None, &tmp_partition, &tmp_dir, true);
+        assert!(result.is_ok(), "Split should succeed: {:?}", result.err());
+
+        // Each locus comes back with its taxa (gap-filled taxa dropped) and sequences
+        for file in &files {
+            let original = parse_file_with_options(file, None).unwrap();
+            let name = file.file_stem().unwrap().to_str().unwrap();
+            let split = parse_file_with_options(Path::new(&tmp_dir).join(format!("{}.fasta", name)), None).unwrap();
+            assert_eq!(split.sequence_count(), original.sequence_count(), "{}", name);
+            for seq in &split.sequences {
+                let orig = original.sequences.iter().find(|s| s.id == seq.id).unwrap();
+                assert_eq!(seq.as_bytes(), orig.as_bytes());
+            }
+        }
+
+        let _ = std::fs::remove_dir_all(&tmp_dir);
+        let _ = std::fs::remove_file(&tmp_output);
+        let _ = std::fs::remove_file(report_path(&tmp_output, "occupancy.tsv"));
+        let _ = std::fs::remove_file(&tmp_partition);
+        if let Ok(entries) = std::fs::read_dir("/tmp") {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.starts_with(&format!("seqtui_test_split_{}_", test_id)) {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_rejects_colliding_file_names() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let tmp_input = format!("/tmp/seqtui_test_split_names_{}.fasta", test_id);
+        let tmp_partition = PathBuf::from(format!("/tmp/seqtui_test_split_names_{}.txt", test_id));
+        let tmp_dir = format!("/tmp/seqtui_test_split_names_{}_dir", test_id);
+        std::fs::write(&tmp_input, ">a\nACGTAC\n>b\nACGTAA\n").unwrap();
+        std::fs::write(&tmp_partition, "DNA, gene/1 = 1-3\nDNA, gene:1 = 4-6\n").unwrap();
+
+        let result = run_split_mode(&PathBuf::from(&tmp_input), None, None, &tmp_partition, &tmp_dir, false);
+        let _ = std::fs::remove_file(&tmp_input);
+        let _ = std::fs::remove_file(&tmp_partition);
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("'gene/1' and 'gene:1'") && err.contains("gene_1.fasta"), "{}", err);
+        assert!(!Path::new(&tmp_dir).exists(), "Nothing should be written");
+    }
+
+    #[test]
+    fn test_region_and_windows() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let tmp_input = PathBuf::from(format!("/tmp/seqtui_test_region_{}_in.fasta", test_id));
+        let tmp_output = format!("/tmp/seqtui_test_region_{}.fasta", test_id);
+        let tmp_dir = format!("/tmp/seqtui_test_region_{}_dir", test_id);
+        std::fs::write(&tmp_input, ">ref\n--ACG-TA\n>other\nGGACGTTA\n").unwrap();
+
+        // Reference positions 2-4 (C, G, T) are columns 4-7
+        let region: Region = "2-4".parse().unwrap();
+        let options = CliOptions { region: Some(region), region_ref: Some("ref".to_string()), ..Default::default() };
+        let result = run_cli_mode(&tmp_input, None, None, &tmp_output, &options);
+        assert!(result.is_ok(), "Region extraction should succeed: {:?}", result.err());
+        let output = parse_file_with_options(Path::new(&tmp_output), None).unwrap();
+        assert_eq!(output.sequences[0].as_str(), "CG-T");
+        assert_eq!(output.sequences[1].as_str(), "CGTT");
+
+        // Aligned windows of 4 columns every 2 columns
+        let windows: Windows = "4:2".parse().unwrap();
+        run_windows_mode(&tmp_input, None, None, &tmp_dir, windows, None, None).unwrap();
+        let mut names: Vec<String> = std::fs::read_dir(&tmp_dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        let stem = format!("seqtui_test_region_{}_in", test_id);
+        assert_eq!(names, vec![format!("{}_1-4.fasta", stem), format!("{}_3-6.fasta", stem), format!("{}_5-8.fasta", stem)]);
+
+        assert!(run_windows_mode(&tmp_input, None, None, &tmp_dir, windows, None, Some("missing")).is_err());
+
+        let _ = std::fs::remove_dir_all(&tmp_dir);
+        let _ = std::fs::remove_file(&tmp_input);
+        let _ = std::fs::remove_file(&tmp_output);
+    }
+
+    #[test]
+    fn test_degap_before_translation_and_gap_columns() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let tmp_input = PathBuf::from(format!("/tmp/seqtui_test_degap_{}_in.fasta", test_id));
+        let tmp_output = format!("/tmp/seqtui_test_degap_{}.fasta", test_id);
+        // The gap in s1 breaks the frame: ATG-AA.ATGG reads ATG AAA TGG once degapped
+        std::fs::write(&tmp_input, ">s1\nATG-AA.ATGG-\n>s2\nATGGAAAATGG-\n").unwrap();
+        let run = |translate: bool, gaps: GapOutput| {
+            let options = CliOptions { translate, gaps, ..Default::default() };
+            run_cli_mode(&tmp_input, None, None, &tmp_output, &options).unwrap();
+            std::fs::read_to_string(&tmp_output).unwrap()
+        };
+
+        assert_eq!(run(false, GapOutput::Degap { frameshifts: false }), ">s1\nATGAAATGG\n>s2\nATGGAAAATGG\n");
+        assert_eq!(run(true, GapOutput::Degap { frameshifts: false }), ">s1\nMKW\n>s2\nMEN\n");
+        assert_eq!(run(false, GapOutput::RemoveGapColumns), ">s1\nATG-AA.ATGG\n>s2\nATGGAAAATGG\n");
+
+        let _ = std::fs::remove_file(&tmp_input);
+        let _ = std::fs::remove_file(&tmp_output);
+    }
+
+    #[test]
+    fn test_keep_and_rename_follow_stockholm_annotations() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let tmp_input = PathBuf::from(format!("/tmp/seqtui_test_sto_{}_in.sto", test_id));
+        let tmp_output = format!("/tmp/seqtui_test_sto_{}.sto", test_id);
+        std::fs::write(
+            &tmp_input,
+            "# STOCKHOLM 1.0\n#=GF ID fam\n#=GS a AC P1\n#=GS b AC P2\na ACGU\n#=GR a SS <..>\nb ACGA\n#=GR b SS <>..\n#=GC RF xxxx\n//\n",
+        )
+        .unwrap();
+
+        let selection = IdSelection::new(Some(vec!["b".into()]), vec![], vec![("b".into(), "B".into())]).unwrap();
+        let options = CliOptions { selection, ..Default::default() };
+        run_cli_mode(&tmp_input, None, None, &tmp_output, &options).unwrap();
+        let written = std::fs::read_to_string(&tmp_output).unwrap();
+        assert_eq!(
+            written,
+            "# STOCKHOLM 1.0\n#=GF ID fam\n#=GS B AC P2\n\nB         ACGA\n#=GR B SS <>..\n#=GC RF   xxxx\n//\n"
+        );
+
+        let _ = std::fs::remove_file(&tmp_input);
+        let _ = std::fs::remove_file(&tmp_output);
+    }
+
+    #[test]
+    fn test_collapse_duplicates_keeps_stockholm_annotations() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let tmp_input = PathBuf::from(format!("/tmp/seqtui_test_sto_dups_{}_in.sto", test_id));
+        let tmp_output = format!("/tmp/seqtui_test_sto_dups_{}.sto", test_id);
+        std::fs::write(
+            &tmp_input,
+            "# STOCKHOLM 1.0\n#=GF ID fam\n#=GS a AC P1\n#=GS b AC P2\na ACGU\n#=GR a SS <..>\nb ACGU\n#=GR b SS <>..\n#=GC RF xxxx\n//\n",
+        )
+        .unwrap();
+
+        let duplicates = Some(DuplicateOptions::default());
+        let options = CliOptions { duplicates, ..Default::default() };
+        run_cli_mode(&tmp_input, None, None, &tmp_output, &options).unwrap();
+        let written = std::fs::read_to_string(&tmp_output).unwrap();
+        let _ = std::fs::remove_file(&tmp_input);
+        let _ = std::fs::remove_file(&tmp_output);
+        let _ = std::fs::remove_file(report_path(&tmp_output, "duplicates.tsv"));
+        assert_eq!(
+            written,
+            "# STOCKHOLM 1.0\n#=GF ID fam\n#=GS a AC P1\n\na         ACGU\n#=GR a SS <..>\n#=GC RF   xxxx\n//\n"
+        );
+    }
+
+    #[test]
+    fn test_a3m_inserts() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let tmp_input = PathBuf::from(format!("/tmp/seqtui_test_a3m_{}_in.a3m", test_id));
+        let tmp_output = format!("/tmp/seqtui_test_a3m_{}.fasta", test_id);
+        std::fs::write(&tmp_input, ">query\nMK-LV\n>hit\nMKaaLLV\n").unwrap();
+        let run = |hide_inserts: bool| {
+            let options = CliOptions { hide_inserts, ..Default::default() };
+            run_cli_mode(&tmp_input, None, None, &tmp_output, &options).unwrap();
+            std::fs::read_to_string(&tmp_output).unwrap()
+        };
+
+        assert_eq!(run(false), ">query\nMK---LV\n>hit\nMKaaLLV\n");
+        assert_eq!(run(true), ">query\nMK-LV\n>hit\nMKLLV\n");
+
+        let _ = std::fs::remove_file(&tmp_input);
+        let _ = std::fs::remove_file(&tmp_output);
+    }
+
+    #[test]
+    fn test_collapse_duplicates_and_duplicate_names() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let tmp_input = PathBuf::from(format!("/tmp/seqtui_test_dups_{}_in.fasta", test_id));
+        let tmp_output = format!("/tmp/seqtui_test_dups_{}.fasta", test_id);
+        let run = |duplicates: Option<DuplicateOptions>, force: bool| {
+            let options = CliOptions { duplicates, force, ..Default::default() };
+            run_cli_mode(&tmp_input, None, None, &tmp_output, &options)
+        };
+
+        std::fs::write(&tmp_input, ">a\nACGT-\n>b\nACGT-\n>c\nAC-GT\n>d\nTTTTT\n").unwrap();
+        let options = DuplicateOptions { ignore_gaps: true, wildcards: false };
+        run(Some(options), false).unwrap();
+        assert_eq!(std::fs::read_to_string(&tmp_output).unwrap(), ">a\nACGT-\n>d\nTTTTT\n");
+        let tsv = report_path(&tmp_output, "duplicates.tsv");
+        let content = std::fs::read_to_string(&tsv).unwrap();
+        assert!(content.contains("a\t3\tb,c\n"), "Unexpected mapping: {}", content);
+
+        // Duplicate names are logged, and only an error when they make IDs ambiguous
+        std::fs::write(&tmp_input, ">a\nACGT\n>a\nTTTT\n").unwrap();
+        run(None, false).unwrap();
+        assert_eq!(std::fs::read_to_string(&tmp_output).unwrap(), ">a\nACGT\n>a\nTTTT\n");
+        let logs: Vec<PathBuf> = std::fs::read_dir("/tmp")
+            .unwrap()
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| {
+                let name = p.file_name().unwrap().to_string_lossy();
+                name.starts_with(&format!("seqtui_test_dups_{}_", test_id)) && name.ends_with(".log")
+            })
+            .collect();
+        assert_eq!(logs.len(), 1);
+        assert!(std::fs::read_to_string(&logs[0]).unwrap().contains("a\t2\n"));
+        let err = run(Some(options), false).unwrap_err();
+        assert!(err.to_string().contains("'a' (2x)"), "Unexpected error: {}", err);
+        assert!(run(Some(options), true).is_ok());
+
+        let _ = std::fs::remove_file(&tsv);
+        for entry in std::fs::read_dir("/tmp").unwrap().flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with(&format!("seqtui_test_dups_{}_", test_id)) && name.ends_with(".log") {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+        let _ = std::fs::remove_file(&tmp_input);
+        let _ = std::fs::remove_file(&tmp_output);
+    }
+
+    #[test]
+    fn test_stats_mode_one_row_per_file() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let tmp_a = PathBuf::from(format!("/tmp/seqtui_test_stats_{}_a.fasta", test_id));
+        let tmp_b = PathBuf::from(format!("/tmp/seqtui_test_stats_{}_b.fasta", test_id));
+        let tmp_output = format!("/tmp/seqtui_test_stats_{}.tsv", test_id);
+        std::fs::write(&tmp_a, ">s1\nACGT\n>s2\nACGA\n>s3\nTCGA\n>s4\nTCGT\n").unwrap();
+        std::fs::write(&tmp_b, ">s1\nAC\n>s2\nACG\n").unwrap();
+
+        let files = vec![tmp_a.clone(), tmp_b.clone()];
+        run_stats_mode(&files, None, None, Some(&tmp_output), StatsFormat::Tsv, 1).unwrap();
+        let content = std::fs::read_to_string(&tmp_output).unwrap();
+        let rows: Vec<&str> = content.lines().collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].starts_with("file\tsequences\tlength"));
+        assert!(rows[1].ends_with("\t4\t4\ttrue\tnucleotide\t50.00\t100.00\t2\t2\t0.00"), "{}", rows[1]);
+        assert!(rows[2].contains("\tfalse\t") && rows[2].contains("\tNA\tNA\t"), "{}", rows[2]);
+
+        run_stats_mode(&files[..1], None, None, Some(&tmp_output), StatsFormat::Json, 1).unwrap();
+        let json = std::fs::read_to_string(&tmp_output).unwrap();
+        assert!(json.starts_with('{') && json.contains("\"per_sequence\""));
+
+        let _ = std::fs::remove_file(&tmp_a);
+        let _ = std::fs::remove_file(&tmp_b);
+        let _ = std::fs::remove_file(&tmp_output);
+    }
+
+    #[test]
+    fn test_tree_mode_writes_newick_and_matrix() {
+        let test_id = TEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let tmp_input = PathBuf::from(format!("/tmp/seqtui_test_nj_{}.fasta", test_id));
+        let tmp_tree = PathBuf::from(format!("/tmp/seqtui_test_nj_{}.nwk", test_id));
+        let tmp_matrix = format!("/tmp/seqtui_test_nj_{}.tsv", test_id);
+        std::fs::write(&tmp_input, ">a\nAAAAAAAA\n>b\nAAAAAAAT\n>c\nCCCCCCCC\n>d\nCCCCCCCT\n").unwrap();
+
+        run_tree_mode(&tmp_input, None, None, &tmp_tree, None, DistanceModel::P, Deletion::Pairwise, TreeMethod::BioNj).unwrap();
+        let newick = std::fs::read_to_string(&tmp_tree).unwrap();
+        assert!(newick.ends_with(");\n"), "{}", newick);
+        assert!(newick.contains("(a:0.093750,b:0.031250)"), "{}", newick);
+        assert!(!std::path::Path::new(&tmp_matrix).exists());
+
+        let matrix = Some((tmp_matrix.as_str(), MatrixFormat::Tsv));
+        run_tree_mode(&tmp_input, None, None, &tmp_tree, matrix, DistanceModel::JukesCantor, Deletion::Pairwise, TreeMethod::Nj)
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&tmp_matrix).unwrap().lines().count(), 5);
+
+        let _ = std::fs::remove_file(&tmp_input);
+        let _ = std::fs::remove_file(&tmp_tree);
+        let _ = std::fs::remove_file(&tmp_matrix);
+    }
+
     #[test]
     fn test_partition_name_sanitization() {
         // Test verifies that partition names with spaces are sanitized
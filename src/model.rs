@@ -10,7 +10,24 @@
 //! and translation between nucleotides and amino acids.
 
 use std::ops::Range;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::cmdline::{self, Completion, InputHistory};
+use crate::distance::{self, Deletion, DistanceCalculator, DistanceModel};
+use crate::formats::{self, FileFormat};
+use crate::fuzzy;
+use crate::keymap::{self, KeyChord, Keymap};
+use crate::partition::{self, ColumnSet, NexusSets};
+use crate::rows::{RowView, SortKey};
+use crate::tree::Tree;
+use crate::trim::{self, TrimMethod};
+
+/// Default width of the sequence names panel (border included).
+pub const DEFAULT_NAME_WIDTH: u16 = 20;
+
+/// Width of the tree panel (border included).
+pub const TREE_PANEL_WIDTH: u16 = 24;
 
 /// Type of biological sequence with nucleotide ratio.
 /// The ratio indicates the proportion of nucleotide characters (ACGTUN) found.
@@ -62,9 +79,10 @@ impl SequenceType {
 }
 
 /// Loading state for async operations.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub enum LoadingState {
     /// No loading in progress, alignment is ready
+    #[default]
     Ready,
     /// Loading file from disk
     LoadingFile {
@@ -86,12 +104,6 @@ pub enum LoadingState {
     },
 }
 
-impl Default for LoadingState {
-    fn default() -> Self {
-        LoadingState::Ready
-    }
-}
-
 impl LoadingState {
     /// Returns true if currently loading/processing.
     pub fn is_loading(&self) -> bool {
@@ -213,7 +225,8 @@ impl FileBrowserState {
                         Some(
                             "fasta" | "fa" | "fna" | "faa" | "fas" |
                             "phy" | "phylip" | "aln" | "ali" |
-                            "nex" | "nexus" | "nxs"
+                            "nex" | "nexus" | "nxs" | "clw" | "msf" |
+                            "sto" | "stk" | "a2m" | "a3m"
                         )
                     ) {
                         files.push(entry);
@@ -222,8 +235,8 @@ impl FileBrowserState {
             }
 
             // Sort directories and files separately, then combine
-            dirs.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-            files.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+            dirs.sort_by_key(|e| e.name.to_lowercase());
+            files.sort_by_key(|e| e.name.to_lowercase());
 
             self.entries.extend(dirs);
             self.entries.extend(files);
@@ -283,6 +296,254 @@ impl FileBrowserState {
     }
 }
 
+/// A sequence listed in the sequence finder.
+#[derive(Debug, Clone)]
+pub struct FinderMatch {
+    /// Display row of the sequence (0-based)
+    pub row: usize,
+    /// Char indices of the matched characters in the name
+    pub positions: Vec<usize>,
+}
+
+/// State for the fuzzy sequence finder popup.
+#[derive(Debug, Clone, Default)]
+pub struct SequenceFinderState {
+    /// Text typed so far
+    pub query: String,
+    /// Matching sequences, best first
+    pub matches: Vec<FinderMatch>,
+    /// Currently selected index in `matches` (0-based)
+    pub selected: usize,
+    /// Scroll offset for long lists
+    pub scroll_offset: usize,
+}
+
+impl SequenceFinderState {
+    /// Creates a finder with the given query, ranked against the row names.
+    pub fn new(query: &str, names: &[&str]) -> Self {
+        let mut finder = Self {
+            query: query.to_string(),
+            ..Self::default()
+        };
+        finder.refresh(names);
+        finder
+    }
+
+    /// Re-ranks the row names (in display order) against the current query.
+    pub fn refresh(&mut self, names: &[&str]) {
+        self.matches = fuzzy::rank(&self.query, names.iter().copied())
+            .into_iter()
+            .map(|(row, m)| FinderMatch { row, positions: m.positions })
+            .collect();
+        self.selected = 0;
+        self.scroll_offset = 0;
+    }
+
+    /// Moves selection up.
+    pub fn select_prev(&mut self) {
+        if self.selected > 0 {
+            self.selected -= 1;
+            if self.selected < self.scroll_offset {
+                self.scroll_offset = self.selected;
+            }
+        }
+    }
+
+    /// Moves selection down.
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Returns the row of the selected sequence, if any.
+    pub fn selected_row(&self) -> Option<usize> {
+        self.matches.get(self.selected).map(|m| m.row)
+    }
+}
+
+/// State for the NEXUS character block picker (`:block`).
+#[derive(Debug, Clone)]
+pub struct BlockPickerState {
+    /// Selected index in `AppState::blocks`
+    pub selected: usize,
+}
+
+/// State for the closest-relatives panel (`:dist`).
+#[derive(Debug, Clone)]
+pub struct DistancePanelState {
+    /// Sequence index the values are computed from
+    pub reference: usize,
+    pub model: DistanceModel,
+    pub deletion: Deletion,
+    /// Other visible sequences as (sequence index, value), closest first
+    pub entries: Vec<(usize, Option<f64>)>,
+    /// Currently selected index in `entries` (0-based)
+    pub selected: usize,
+}
+
+impl DistancePanelState {
+    /// Moves selection up.
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Moves selection down.
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+}
+
+/// Partitions shown in the viewer (NEXUS SETS block or `:sets file`).
+#[derive(Debug, Clone)]
+pub struct PartitionView {
+    /// Partition names
+    pub names: Vec<String>,
+    /// Partition of each column of the loaded file (the first set containing it)
+    pub by_column: Vec<Option<usize>>,
+    /// Where the partitions were read from
+    pub source: String,
+}
+
+impl PartitionView {
+    /// Builds the column lookup for `len` columns.
+    pub fn new(sets: &[ColumnSet], len: usize, source: String) -> Self {
+        let mut by_column = vec![None; len];
+        for (i, set) in sets.iter().enumerate() {
+            for column in set.columns() {
+                if let Some(slot) = by_column.get_mut(column) {
+                    slot.get_or_insert(i);
+                }
+            }
+        }
+        Self {
+            names: sets.iter().map(|set| set.name.clone()).collect(),
+            by_column,
+            source,
+        }
+    }
+
+    /// Status message shown when the partitions are loaded.
+    fn load_message(&self) -> String {
+        format!(
+            "{} partitions from the {} (]p / [p: next / previous)",
+            self.names.len(),
+            self.source
+        )
+    }
+
+    /// Partitions of an alignment read from its SETS block, if any.
+    fn from_alignment(alignment: &Alignment) -> Option<Self> {
+        let sets = alignment.sets.as_ref()?.partitions();
+        (!sets.is_empty())
+            .then(|| Self::new(sets, alignment.alignment_length(), "SETS block".to_string()))
+    }
+}
+
+/// A clade collapsed to its top row (`zc`).
+#[derive(Debug, Clone)]
+pub struct CollapsedClade {
+    /// Tree node of the clade
+    pub node: usize,
+    /// Sequence left visible for the clade
+    pub representative: usize,
+    /// Sequences hidden by the collapse
+    pub hidden: Vec<usize>,
+}
+
+/// Newick tree shown left of the names panel (`--tree`, `:tree`).
+#[derive(Debug, Clone)]
+pub struct TreePanelState {
+    /// File the tree was read from
+    pub path: PathBuf,
+    pub tree: Tree,
+    /// Parent of each tree node
+    pub parents: Vec<Option<usize>>,
+    /// Tree node of each leaf
+    pub leaf_nodes: Vec<usize>,
+    /// Sequence index of each leaf (None if no sequence has its name)
+    pub leaf_seqs: Vec<Option<usize>>,
+    /// Leaf of each sequence (None if the sequence is not in the tree)
+    pub seq_leaves: Vec<Option<usize>>,
+    /// Highlighted clade (`gc`)
+    pub selected: Option<usize>,
+    /// Collapsed clades, in collapse order
+    pub collapsed: Vec<CollapsedClade>,
+}
+
+impl TreePanelState {
+    /// Matches the leaves to sequence names (exactly, or with `_` for spaces as
+    /// written by many tree programs).
+    pub fn new(path: PathBuf, tree: Tree, labels: &[String], alignment: &Alignment) -> Self {
+        let mut by_name: HashMap<String, usize> = HashMap::new();
+        for (i, seq) in alignment.sequences.iter().enumerate().rev() {
+            by_name.insert(seq.id.clone(), i);
+        }
+        let mut by_underscored: HashMap<String, usize> = HashMap::new();
+        for (i, seq) in alignment.sequences.iter().enumerate().rev() {
+            by_underscored.insert(seq.id.replace(' ', "_"), i);
+        }
+        let mut seq_leaves = vec![None; alignment.sequence_count()];
+        let mut leaf_seqs = Vec::with_capacity(labels.len());
+        for (leaf, label) in labels.iter().enumerate() {
+            let seq = by_name
+                .get(label)
+                .or_else(|| by_underscored.get(&label.replace(' ', "_")))
+                .copied()
+                .filter(|&i| seq_leaves[i].is_none());
+            if let Some(i) = seq {
+                seq_leaves[i] = Some(leaf);
+            }
+            leaf_seqs.push(seq);
+        }
+        Self {
+            path,
+            parents: tree.parents(),
+            leaf_nodes: tree.leaf_nodes(),
+            tree,
+            leaf_seqs,
+            seq_leaves,
+            selected: None,
+            collapsed: Vec::new(),
+        }
+    }
+
+    /// Number of sequences found in the tree.
+    pub fn matched_count(&self) -> usize {
+        self.leaf_seqs.iter().flatten().count()
+    }
+
+    /// Sequences in leaf order, followed by those not in the tree (file order).
+    pub fn sequence_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = self.tree.leaf_order().into_iter().filter_map(|leaf| self.leaf_seqs[leaf]).collect();
+        order.extend((0..self.seq_leaves.len()).filter(|&i| self.seq_leaves[i].is_none()));
+        order
+    }
+
+    /// Sequences of the clade below `node`, in tree order.
+    pub fn clade_sequences(&self, node: usize) -> Vec<usize> {
+        self.tree.leaves_under(node).into_iter().filter_map(|leaf| self.leaf_seqs[leaf]).collect()
+    }
+
+    /// Smallest clade (above the leaf) containing a sequence.
+    pub fn parent_clade(&self, seq: usize) -> Option<usize> {
+        self.parents[self.leaf_nodes[self.seq_leaves[seq]?]]
+    }
+
+    /// Collapsed clade whose representative is `seq` (the last collapsed one).
+    pub fn collapsed_at(&self, seq: usize) -> Option<&CollapsedClade> {
+        self.collapsed.iter().rev().find(|c| c.representative == seq)
+    }
+}
+
+/// Returns true for gap and missing-data characters (`-`, `.`, `?`, space).
+#[inline]
+pub fn is_gap(b: u8) -> bool {
+    matches!(b, b'-' | b'.' | b'?' | b' ')
+}
+
 /// Represents a single sequence with its identifier and data.
 /// 
 /// Sequence data is stored as `Vec<u8>` (ASCII bytes) rather than `String`
@@ -372,6 +633,90 @@ impl Sequence {
     }
 }
 
+/// Column annotations shown as tracks above the alignment, in display order.
+pub const ANNOTATION_TRACKS: [&str; 2] = ["SS_cons", "RF"];
+
+/// Alignment annotations read from Stockholm files.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Annotations {
+    /// Per-file annotations (`#=GF TAG text`), in file order
+    pub file: Vec<(String, String)>,
+    /// Per-sequence annotations (`#=GS name TAG text`)
+    pub sequence: Vec<(String, String, String)>,
+    /// Per-residue annotations (`#=GR name TAG chars`), one byte per column
+    pub residue: Vec<(String, String, Vec<u8>)>,
+    /// Per-column annotations (`#=GC TAG chars`), one byte per column
+    pub column: Vec<(String, Vec<u8>)>,
+}
+
+impl Annotations {
+    /// Returns true if there are no annotations.
+    pub fn is_empty(&self) -> bool {
+        self.file.is_empty() && self.sequence.is_empty() && self.residue.is_empty() && self.column.is_empty()
+    }
+
+    /// Per-column annotation with this tag, if any.
+    pub fn column(&self, tag: &str) -> Option<&[u8]> {
+        self.column.iter().find(|(t, _)| t == tag).map(|(_, data)| data.as_slice())
+    }
+
+    /// Returns true if a column is an insert state: a gap in the `#=GC RF` line
+    /// (as written for A2M/A3M inserts).
+    pub fn is_insert_column(&self, col: usize) -> bool {
+        self.column("RF").and_then(|rf| rf.get(col)).is_some_and(|&b| is_gap(b) || b == b'_' || b == b'~')
+    }
+
+    /// Match-state columns, when the `#=GC RF` line marks some columns as inserts.
+    pub fn match_columns(&self) -> Option<Vec<usize>> {
+        let rf = self.column("RF")?;
+        let kept: Vec<usize> = (0..rf.len()).filter(|&col| !self.is_insert_column(col)).collect();
+        (kept.len() < rf.len()).then_some(kept)
+    }
+
+    /// Column annotations shown above the alignment (`ANNOTATION_TRACKS`).
+    pub fn tracks(&self) -> Vec<(&str, &[u8])> {
+        ANNOTATION_TRACKS
+            .iter()
+            .filter_map(|&tag| Some((tag, self.column(tag)?)))
+            .collect()
+    }
+
+    /// Annotations of the kept columns (per-column and per-residue data follow
+    /// the columns; per-file and per-sequence annotations are kept as is).
+    pub fn select_columns(&self, kept: &[usize]) -> Self {
+        let select = |data: &[u8]| kept.iter().filter_map(|&col| data.get(col).copied()).collect();
+        Self {
+            file: self.file.clone(),
+            sequence: self.sequence.clone(),
+            residue: self
+                .residue
+                .iter()
+                .map(|(name, tag, data)| (name.clone(), tag.clone(), select(data)))
+                .collect(),
+            column: self.column.iter().map(|(tag, data)| (tag.clone(), select(data))).collect(),
+        }
+    }
+
+    /// Annotations of the kept sequences: `rename` gives the new name of a kept
+    /// sequence (`None` drops its per-sequence and per-residue annotations).
+    pub fn select_sequences(&self, rename: impl Fn(&str) -> Option<String>) -> Self {
+        Self {
+            file: self.file.clone(),
+            sequence: self
+                .sequence
+                .iter()
+                .filter_map(|(name, tag, text)| Some((rename(name)?, tag.clone(), text.clone())))
+                .collect(),
+            residue: self
+                .residue
+                .iter()
+                .filter_map(|(name, tag, data)| Some((rename(name)?, tag.clone(), data.clone())))
+                .collect(),
+            column: self.column.clone(),
+        }
+    }
+}
+
 /// Represents an alignment of multiple sequences.
 #[derive(Debug, Clone)]
 pub struct Alignment {
@@ -385,6 +730,14 @@ pub struct Alignment {
     pub warning: Option<String>,
     /// Detected sequence type (nucleotide or amino acid)
     pub sequence_type: SequenceType,
+    /// Sets read from NEXUS SETS blocks (charsets, charpartitions, taxsets)
+    pub sets: Option<NexusSets>,
+    /// Titles of the character matrices of the file, when it has several (NEXUS)
+    pub blocks: Vec<String>,
+    /// Index in `blocks` of the matrix shown
+    pub block: usize,
+    /// Stockholm annotations (`#=GF`, `#=GS`, `#=GR`, `#=GC`), boxed as most files have none
+    pub annotations: Box<Annotations>,
 }
 
 impl Alignment {
@@ -398,6 +751,10 @@ impl Alignment {
             is_valid_alignment: is_valid,
             warning,
             sequence_type,
+            sets: None,
+            blocks: Vec::new(),
+            block: 0,
+            annotations: Box::default(),
         }
     }
 
@@ -482,6 +839,27 @@ impl Alignment {
         self.alignment_length.unwrap_or(0)
     }
 
+    /// Returns the raw sequences: gaps (`-`, `.`) removed, and frameshift
+    /// marks (`!`) too if `frameshifts` is set.
+    pub fn degapped(&self, frameshifts: bool) -> Alignment {
+        let sequences = self
+            .sequences
+            .iter()
+            .map(|seq| {
+                let data = seq
+                    .as_bytes()
+                    .iter()
+                    .copied()
+                    .filter(|&b| !(matches!(b, b'-' | b'.') || (frameshifts && b == b'!')))
+                    .collect();
+                Sequence::from_bytes(seq.id.clone(), data)
+            })
+            .collect();
+        let mut degapped = Alignment::new(sequences);
+        degapped.sequence_type = self.sequence_type;
+        degapped
+    }
+
     /// Returns the maximum identifier length (for display purposes).
     pub fn max_id_length(&self) -> usize {
         self.sequences.iter().map(|s| s.id.len()).max().unwrap_or(0)
@@ -637,6 +1015,19 @@ pub enum AppMode {
     TranslationSettings,
 }
 
+/// Follow-up work requested by a `:` command, carried out by the controller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandOutcome {
+    /// Nothing more to do
+    None,
+    /// Start background translation
+    StartTranslation,
+    /// Load another file
+    LoadFile(PathBuf),
+    /// Reload the file with another NEXUS character block (title or number)
+    LoadBlock(String),
+}
+
 /// View mode for the alignment (nucleotide or translated amino acid).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ViewMode {
@@ -647,6 +1038,32 @@ pub enum ViewMode {
     AminoAcid,
 }
 
+/// Colour palette used for residue backgrounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Palette {
+    /// Seaview-like coloured backgrounds
+    #[default]
+    Seaview,
+    /// No residue colours (low-colour terminals, screenshots for print)
+    Mono,
+}
+
+impl Palette {
+    /// Looks up a palette by its configuration name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "seaview" | "default" => Some(Palette::Seaview),
+            "mono" | "none" => Some(Palette::Mono),
+            _ => None,
+        }
+    }
+
+    /// Returns the names accepted by `from_name`.
+    pub fn all_names() -> &'static [&'static str] {
+        &["seaview", "mono"]
+    }
+}
+
 /// Translation settings for NT to AA conversion.
 #[derive(Debug, Clone)]
 pub struct TranslationSettings {
@@ -677,6 +1094,18 @@ impl Default for TranslationSettings {
     }
 }
 
+impl TranslationSettings {
+    /// Sets the genetic code and reading frame (1-3), also as the selection of the settings dialog.
+    pub fn preset(&mut self, genetic_code: u8, reading_frame: u8) {
+        use crate::genetic_code::GeneticCodes;
+        let codes = GeneticCodes::new();
+        self.genetic_code_id = genetic_code;
+        self.selected_code_index = codes.all().iter().position(|c| c.id == genetic_code).unwrap_or(0);
+        self.frame = (reading_frame.saturating_sub(1) as usize).min(2);
+        self.selected_frame = self.frame;
+    }
+}
+
 /// The complete application state.
 #[derive(Debug)]
 pub struct AppState {
@@ -712,10 +1141,16 @@ pub struct AppState {
     pub show_help: bool,
     /// Current help tab
     pub help_tab: HelpTab,
-    /// Pending 'g' key for g-commands
-    pub pending_g: bool,
-    /// Pending 'z' key for z-commands
-    pub pending_z: bool,
+    /// Keys typed so far of a multi-key binding (e.g. 'g' before 'm')
+    pub pending_keys: Vec<KeyChord>,
+    /// Normal-mode key bindings
+    pub keymap: Keymap,
+    /// Cursor position (byte offset) in the command or search input
+    pub input_cursor: usize,
+    /// Command and search history
+    pub history: InputHistory,
+    /// Active tab completion in the command line
+    pub completion: Option<Completion>,
     /// Number buffer for <number>| command
     pub number_buffer: String,
     /// Loading state for async operations
@@ -726,15 +1161,41 @@ pub struct AppState {
     pub error_popup: Option<String>,
     /// File browser state (shown when file not found)
     pub file_browser: Option<FileBrowserState>,
+    /// Original (1-based in the UI) position of each column after `:trim`
+    pub column_map: Option<Vec<usize>>,
+    /// Aligned sequences and column map stashed by `:degap` (restored by a second `:degap`)
+    pub gapped: Option<(Alignment, Option<Vec<usize>>)>,
+    /// Alignment with its insert columns and column map, stashed while they are hidden (`zi`)
+    pub inserts: Option<(Alignment, Option<Vec<usize>>)>,
+    /// Display order of rows (sorted, moved, hidden, pinned); cursor rows index into it
+    pub rows: RowView,
+    /// Fuzzy sequence finder state (opened with Ctrl+P or :seq)
+    pub sequence_finder: Option<SequenceFinderState>,
+    /// Closest relatives of a sequence (opened with :dist)
+    pub distance_panel: Option<DistancePanelState>,
+    /// Newick tree shown next to the names (opened with --tree or :tree)
+    pub tree_panel: Option<TreePanelState>,
+    /// Column partitions (NEXUS SETS block or :sets file)
+    pub partitions: Option<PartitionView>,
+    /// Titles of the character matrices of the file, when it has several (NEXUS)
+    pub blocks: Vec<String>,
+    /// Index in `blocks` of the matrix shown
+    pub block: usize,
+    /// Character block picker (`:block`)
+    pub block_picker: Option<BlockPickerState>,
     /// Whether to use fancy UI glyphs (non-Windows only)
     pub fancy_ui: bool,
+    /// Colour palette for residues
+    pub palette: Palette,
+    /// Width of the sequence names panel (border included)
+    pub name_width: u16,
 }
 
 impl AppState {
         /// Goes to a specific row (1-indexed for user, like Vim :line command).
         /// Clamps column to alignment length. Returns true if successful, false if out of range.
         fn goto_row_1indexed(&mut self, row: usize) -> bool {
-            let num_seqs = self.active_alignment().sequence_count();
+            let num_seqs = self.rows.len();
             if row > 0 && row <= num_seqs {
                 self.cursor.row = row - 1; // 1-indexed for user
                 // Keep cursor column, but clamp to alignment length
@@ -751,6 +1212,9 @@ impl AppState {
     /// Creates a new application state with the given alignment.
     pub fn new(alignment: Alignment, file_name: String) -> Self {
         let warning = alignment.warning.clone();
+        let rows = RowView::new(alignment.sequence_count());
+        let partitions = PartitionView::from_alignment(&alignment);
+        let (blocks, block) = (alignment.blocks.clone(), alignment.block);
         Self {
             file_name,
             alignment,
@@ -763,19 +1227,36 @@ impl AppState {
             cursor: Cursor::new(),
             mode: AppMode::Normal,
             should_quit: false,
-            status_message: warning,
+            status_message: warning
+                .or_else(|| partitions.as_ref().map(PartitionView::load_message)),
             last_search: None,
             last_search_backward: false,
             show_help: false,
             help_tab: HelpTab::default(),
-            pending_g: false,
-            pending_z: false,
+            pending_keys: Vec::new(),
+            keymap: Keymap::default(),
+            input_cursor: 0,
+            history: InputHistory::default(),
+            completion: None,
             number_buffer: String::new(),
             loading_state: LoadingState::Ready,
             spinner_frame: 0,
             error_popup: None,
             file_browser: None,
+            column_map: None,
+            gapped: None,
+            inserts: None,
+            rows,
+            sequence_finder: None,
+            distance_panel: None,
+            tree_panel: None,
+            partitions,
+            blocks,
+            block,
+            block_picker: None,
             fancy_ui: false,
+            palette: Palette::default(),
+            name_width: DEFAULT_NAME_WIDTH,
         }
     }
 
@@ -798,8 +1279,11 @@ impl AppState {
             last_search_backward: false,
             show_help: false,
             help_tab: HelpTab::default(),
-            pending_g: false,
-            pending_z: false,
+            pending_keys: Vec::new(),
+            keymap: Keymap::default(),
+            input_cursor: 0,
+            history: InputHistory::default(),
+            completion: None,
             number_buffer: String::new(),
             loading_state: LoadingState::LoadingFile {
                 path,
@@ -809,17 +1293,58 @@ impl AppState {
             spinner_frame: 0,
             error_popup: None,
             file_browser: None,
+            column_map: None,
+            gapped: None,
+            inserts: None,
+            rows: RowView::default(),
+            sequence_finder: None,
+            distance_panel: None,
+            tree_panel: None,
+            partitions: None,
+            blocks: Vec::new(),
+            block: 0,
+            block_picker: None,
             fancy_ui: false,
+            palette: Palette::default(),
+            name_width: DEFAULT_NAME_WIDTH,
         }
     }
 
     /// Updates the alignment after async loading completes.
+    /// Resets the cursor, viewport and translated view, as a new file is shown.
     pub fn set_alignment(&mut self, alignment: Alignment) {
         let warning = alignment.warning.clone();
         self.alignment = alignment;
         self.loading_state = LoadingState::Ready;
+        self.translated_alignment = None;
+        self.cached_translation_code_id = None;
+        self.cached_translation_frame = None;
+        self.view_mode = ViewMode::Nucleotide;
+        self.cursor = Cursor::new();
+        self.viewport.first_row = 0;
+        self.viewport.first_col = 0;
+        self.rows = RowView::new(self.alignment.sequence_count());
+        self.column_map = None;
+        self.gapped = None;
+        self.inserts = None;
+        self.sequence_finder = None;
+        self.distance_panel = None;
+        self.tree_panel = None;
+        self.partitions = PartitionView::from_alignment(&self.alignment);
+        self.blocks = self.alignment.blocks.clone();
+        self.block = self.alignment.block;
+        self.block_picker = None;
         if let Some(w) = warning {
             self.status_message = Some(w);
+        } else if let Some(partitions) = &self.partitions {
+            self.status_message = Some(partitions.load_message());
+        } else if self.blocks.len() > 1 {
+            self.status_message = Some(format!(
+                "Matrix '{}' ({} of {}); :block to choose another",
+                self.blocks[self.block],
+                self.block + 1,
+                self.blocks.len()
+            ));
         }
     }
 
@@ -920,6 +1445,73 @@ impl AppState {
         }
     }
 
+    /// Opens the sequence finder, pre-filled with `query`.
+    pub fn open_sequence_finder(&mut self, query: &str) {
+        if self.rows.is_empty() {
+            self.status_message = Some("No sequences loaded".to_string());
+            return;
+        }
+        self.sequence_finder = Some(SequenceFinderState::new(query, &self.row_names()));
+    }
+
+    /// Closes the sequence finder without moving the cursor.
+    pub fn close_sequence_finder(&mut self) {
+        self.sequence_finder = None;
+    }
+
+    /// Appends a character to the finder query.
+    pub fn finder_input(&mut self, c: char) {
+        if let Some(mut finder) = self.sequence_finder.take() {
+            finder.query.push(c);
+            finder.refresh(&self.row_names());
+            self.sequence_finder = Some(finder);
+        }
+    }
+
+    /// Removes the last character of the finder query.
+    pub fn finder_backspace(&mut self) {
+        if let Some(mut finder) = self.sequence_finder.take() {
+            finder.query.pop();
+            finder.refresh(&self.row_names());
+            self.sequence_finder = Some(finder);
+        }
+    }
+
+    /// Clears the finder query.
+    pub fn finder_clear_query(&mut self) {
+        if let Some(mut finder) = self.sequence_finder.take() {
+            finder.query.clear();
+            finder.refresh(&self.row_names());
+            self.sequence_finder = Some(finder);
+        }
+    }
+
+    /// Moves finder selection up.
+    pub fn finder_up(&mut self) {
+        if let Some(finder) = &mut self.sequence_finder {
+            finder.select_prev();
+        }
+    }
+
+    /// Moves finder selection down.
+    pub fn finder_down(&mut self) {
+        if let Some(finder) = &mut self.sequence_finder {
+            finder.select_next();
+        }
+    }
+
+    /// Jumps to the selected sequence, keeping the cursor column, and closes the finder.
+    pub fn finder_select(&mut self) {
+        if let Some(finder) = self.sequence_finder.take() {
+            match finder.selected_row() {
+                Some(row) => {
+                    self.goto_row_1indexed(row + 1);
+                }
+                None => self.status_message = Some(format!("No sequence matches '{}'", finder.query)),
+            }
+        }
+    }
+
     /// Sets the translated alignment after async translation completes.
     pub fn set_translated_alignment(&mut self, alignment: Alignment) {
         self.translated_alignment = Some(alignment);
@@ -1018,6 +1610,37 @@ impl AppState {
         }
     }
 
+    /// Column annotation tracks of the current view (`#=GC SS_cons`, `#=GC RF`),
+    /// shown above the alignment.
+    pub fn annotation_tracks(&self) -> Vec<(&str, &[u8])> {
+        self.active_alignment().annotations.tracks()
+    }
+
+    /// Returns the sequence shown at a display row (see `RowView`).
+    pub fn row_sequence(&self, row: usize) -> Option<&Sequence> {
+        self.rows.seq_index(row).and_then(|i| self.active_alignment().get(i))
+    }
+
+    /// Returns the names of the displayed rows, top to bottom.
+    pub fn row_names(&self) -> Vec<&str> {
+        let alignment = self.active_alignment();
+        self.rows.rows().iter().filter_map(|&i| alignment.get(i)).map(|s| s.id.as_str()).collect()
+    }
+
+    /// Number of pinned rows kept at the top of the screen.
+    /// At least one row is left for scrolling.
+    pub fn frozen_rows(&self) -> usize {
+        self.rows.pinned_count().min(self.viewport.visible_rows.saturating_sub(1))
+    }
+
+    /// Display rows currently on screen: frozen pinned rows, then the scrolled rows.
+    pub fn screen_rows(&self) -> Vec<usize> {
+        let frozen = self.frozen_rows();
+        let first = self.viewport.first_row.max(frozen);
+        let end = (first + self.viewport.visible_rows.saturating_sub(frozen)).min(self.rows.len());
+        (0..frozen).chain(first..end).collect()
+    }
+
     /// Returns whether a translated alignment exists.
     pub fn has_translated_alignment(&self) -> bool {
         self.translated_alignment.is_some()
@@ -1039,7 +1662,7 @@ impl AppState {
 
     /// Moves the cursor down by one row.
     pub fn move_down(&mut self) {
-        if self.cursor.row + 1 < self.active_alignment().sequence_count() {
+        if self.cursor.row + 1 < self.rows.len() {
             self.cursor.row += 1;
             self.ensure_cursor_visible();
         }
@@ -1071,7 +1694,7 @@ impl AppState {
     /// Moves the cursor down by half a page.
     pub fn half_page_down(&mut self) {
         let half_page = self.viewport.visible_rows / 2;
-        let max_row = self.active_alignment().sequence_count().saturating_sub(1);
+        let max_row = self.rows.len().saturating_sub(1);
         self.cursor.row = (self.cursor.row + half_page).min(max_row);
         self.ensure_cursor_visible();
     }
@@ -1103,7 +1726,7 @@ impl AppState {
     /// Moves the cursor down by a full page.
     pub fn page_down(&mut self) {
         let page = self.viewport.visible_rows.saturating_sub(1).max(1);
-        let max_row = self.active_alignment().sequence_count().saturating_sub(1);
+        let max_row = self.rows.len().saturating_sub(1);
         self.cursor.row = (self.cursor.row + page).min(max_row);
         self.ensure_cursor_visible();
     }
@@ -1117,13 +1740,15 @@ impl AppState {
     /// Moves to the start of the next word (w).
     /// Words are delimited by gaps (-) and stops (*).
     pub fn word_forward(&mut self) {
-        let alignment = self.active_alignment();
-        let aln_len = alignment.alignment_length();
-        if aln_len == 0 || self.cursor.row >= alignment.sequence_count() {
+        let aln_len = self.active_alignment().alignment_length();
+        let Some(seq) = self.row_sequence(self.cursor.row) else {
+            return;
+        };
+        if aln_len == 0 {
             return;
         }
 
-        let seq = alignment.sequences[self.cursor.row].as_bytes();
+        let seq = seq.as_bytes();
         let mut col = self.cursor.col;
 
         // If on a non-delimiter, skip to end of current word
@@ -1146,12 +1771,14 @@ impl AppState {
     /// Moves to the start of the previous word (b).
     /// Words are delimited by gaps (-) and stops (*).
     pub fn word_backward(&mut self) {
-        let alignment = self.active_alignment();
-        if alignment.alignment_length() == 0 || self.cursor.row >= alignment.sequence_count() {
+        let Some(seq) = self.row_sequence(self.cursor.row) else {
+            return;
+        };
+        if self.active_alignment().alignment_length() == 0 {
             return;
         }
 
-        let seq = alignment.sequences[self.cursor.row].as_bytes();
+        let seq = seq.as_bytes();
         let mut col = self.cursor.col;
 
         // If at start, nothing to do
@@ -1180,13 +1807,15 @@ impl AppState {
     /// Moves to the end of the current/next word (e).
     /// Words are delimited by gaps (-) and stops (*).
     pub fn word_end(&mut self) {
-        let alignment = self.active_alignment();
-        let aln_len = alignment.alignment_length();
-        if aln_len == 0 || self.cursor.row >= alignment.sequence_count() {
+        let aln_len = self.active_alignment().alignment_length();
+        let Some(seq) = self.row_sequence(self.cursor.row) else {
+            return;
+        };
+        if aln_len == 0 {
             return;
         }
 
-        let seq = alignment.sequences[self.cursor.row].as_bytes();
+        let seq = seq.as_bytes();
         let mut col = self.cursor.col;
 
         // Move forward at least one position
@@ -1209,35 +1838,18 @@ impl AppState {
         self.ensure_cursor_visible();
     }
 
-    /// Ensures the cursor is visible in the viewport, with centering behavior.
-    fn ensure_cursor_visible_center(&mut self) {
-        // Vertical scrolling - keep cursor in view
-        if self.cursor.row < self.viewport.first_row {
-            self.viewport.first_row = self.cursor.row;
-        } else if self.cursor.row >= self.viewport.first_row + self.viewport.visible_rows {
-            self.viewport.first_row = self.cursor.row.saturating_sub(self.viewport.visible_rows - 1);
-        }
-
-        // Horizontal scrolling - center when reaching edge
-        if self.cursor.col < self.viewport.first_col {
-            // Cursor went left of viewport - center it
-            self.center_column();
-        } else if self.cursor.col >= self.viewport.first_col + self.viewport.visible_cols {
-            // Cursor went right of viewport - center it
-            self.center_column();
-        }
-
-        // Clamp viewport to valid bounds
-        self.clamp_viewport();
-    }
-
     /// Ensures the cursor is visible in the viewport, with edge-aligned scrolling.
     fn ensure_cursor_visible(&mut self) {
-        // Vertical scrolling - keep cursor in view
-        if self.cursor.row < self.viewport.first_row {
+        // Vertical scrolling - keep cursor in view (pinned rows never scroll)
+        let frozen = self.frozen_rows();
+        let scroll_rows = self.viewport.visible_rows.saturating_sub(frozen).max(1);
+        self.viewport.first_row = self.viewport.first_row.max(frozen);
+        if self.cursor.row < frozen {
+            // Pinned row: always on screen
+        } else if self.cursor.row < self.viewport.first_row {
             self.viewport.first_row = self.cursor.row;
-        } else if self.cursor.row >= self.viewport.first_row + self.viewport.visible_rows {
-            self.viewport.first_row = self.cursor.row.saturating_sub(self.viewport.visible_rows - 1);
+        } else if self.cursor.row >= self.viewport.first_row + scroll_rows {
+            self.viewport.first_row = self.cursor.row.saturating_sub(scroll_rows - 1);
         }
 
         // Horizontal scrolling - keep cursor on-screen without centering
@@ -1252,24 +1864,18 @@ impl AppState {
         self.clamp_viewport();
     }
 
-    /// Centers the current column in the viewport.
-    fn center_column(&mut self) {
-        if self.viewport.visible_cols > 0 {
-            let half = self.viewport.visible_cols / 2;
-            self.viewport.first_col = self.cursor.col.saturating_sub(half);
-        }
-    }
-
     /// Clamps the viewport to valid alignment bounds.
     fn clamp_viewport(&mut self) {
-        let seq_count = self.active_alignment().sequence_count();
+        let seq_count = self.rows.len();
         let aln_length = self.active_alignment().alignment_length();
         let max_row = seq_count.saturating_sub(1);
         let max_col = aln_length.saturating_sub(1);
 
         // Ensure we don't scroll past the end
-        if self.viewport.first_row + self.viewport.visible_rows > seq_count {
-            self.viewport.first_row = seq_count.saturating_sub(self.viewport.visible_rows);
+        let frozen = self.frozen_rows();
+        let scroll_rows = self.viewport.visible_rows.saturating_sub(frozen);
+        if self.viewport.first_row + scroll_rows > seq_count {
+            self.viewport.first_row = seq_count.saturating_sub(scroll_rows).max(frozen);
         }
 
         if self.viewport.first_col + self.viewport.visible_cols > aln_length {
@@ -1284,80 +1890,319 @@ impl AppState {
     /// Enters command mode.
     pub fn enter_command_mode(&mut self) {
         self.mode = AppMode::Command(String::new());
+        self.reset_input();
     }
 
     /// Handles a character input in command mode.
     pub fn command_input(&mut self, c: char) {
-        if let AppMode::Command(ref mut cmd) = self.mode {
-            cmd.push(c);
+        if matches!(self.mode, AppMode::Command(_)) {
+            self.insert_input_char(c);
         }
     }
 
     /// Handles backspace in command mode.
     pub fn command_backspace(&mut self) {
-        if let AppMode::Command(ref mut cmd) = self.mode {
-            cmd.pop();
-            if cmd.is_empty() {
-                self.mode = AppMode::Normal;
+        if matches!(self.mode, AppMode::Command(_)) {
+            self.input_backspace();
+        }
+    }
+
+    /// Returns the text of the command or search input, if in one of those modes.
+    pub fn input_text(&self) -> Option<&str> {
+        match &self.mode {
+            AppMode::Command(text) | AppMode::Search(text) | AppMode::SearchBackward(text) => {
+                Some(text)
             }
+            _ => None,
         }
     }
 
-    /// Executes the current command.
-    /// Returns true if translation should be started (handled by controller).
-    pub fn execute_command(&mut self) -> bool {
-        let mut start_translation = false;
-        if let AppMode::Command(ref cmd) = self.mode.clone() {
-            match cmd.as_str() {
-                "$" => {
-                    let num_seqs = self.active_alignment().sequence_count();
-                    if num_seqs > 0 {
-                        self.goto_row_1indexed(num_seqs);
-                    } else {
-                        self.status_message = Some("No sequences loaded".to_string());
-                    }
-                }
-                "q" | "quit" => self.should_quit = true,
-                "h" | "help" => self.show_help(),
-                "e" | "edit" => {
-                    // Open file browser to select a new file
-                    let start_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-                    self.file_browser = Some(FileBrowserState::new(start_dir, "Open file".to_string()));
-                    self.mode = AppMode::Normal;
-                    return false;
-                }
-                "asAA" | "asaa" => {
-                    if !self.alignment.sequence_type.is_nucleotide() {
-                        self.status_message = Some("Cannot translate: not a nucleotide sequence".to_string());
-                    } else if self.has_valid_cached_translation() {
-                        // We have a valid cached translation - switch to it directly
-                        self.switch_to_cached_aa_view();
-                    } else if !self.translation_settings.has_translated {
-                        // First time: show settings so user knows the options
-                        self.enter_translation_settings();
-                        return false; // Don't reset to Normal
-                    } else {
-                        // Subsequent times: request translation (controller will start background thread)
-                        start_translation = true;
-                    }
-                }
-                "asNT" | "asnt" => self.switch_to_nucleotide_view(),
-                "setcode" => {
-                    self.enter_translation_settings();
-                    return false; // Don't reset to Normal - enter_translation_settings sets the mode
-                }
+    /// Returns the command or search input buffer for editing.
+    fn input_buffer_mut(&mut self) -> Option<&mut String> {
+        match &mut self.mode {
+            AppMode::Command(text) | AppMode::Search(text) | AppMode::SearchBackward(text) => {
+                Some(text)
+            }
+            _ => None,
+        }
+    }
+
+    /// Resets the input cursor, completion and history navigation.
+    fn reset_input(&mut self) {
+        self.input_cursor = 0;
+        self.completion = None;
+        self.history.commands.reset();
+        self.history.searches.reset();
+    }
+
+    /// Replaces the whole input text and moves the cursor to its end.
+    fn set_input_text(&mut self, text: String) {
+        self.input_cursor = text.len();
+        if let Some(buffer) = self.input_buffer_mut() {
+            *buffer = text;
+        }
+    }
+
+    /// Inserts a character at the input cursor.
+    fn insert_input_char(&mut self, c: char) {
+        self.completion = None;
+        let cursor = self.input_cursor;
+        if let Some(buffer) = self.input_buffer_mut() {
+            let cursor = cursor.min(buffer.len());
+            buffer.insert(cursor, c);
+            self.input_cursor = cursor + c.len_utf8();
+        }
+    }
+
+    /// Deletes the character before the input cursor.
+    /// Leaves the command/search mode when the input becomes empty.
+    fn input_backspace(&mut self) {
+        self.completion = None;
+        let cursor = self.input_cursor;
+        let Some(buffer) = self.input_buffer_mut() else {
+            return;
+        };
+        if let Some((idx, _)) = buffer[..cursor.min(buffer.len())].char_indices().next_back() {
+            buffer.remove(idx);
+            self.input_cursor = idx;
+        }
+        if self.input_text().is_some_and(str::is_empty) {
+            self.mode = AppMode::Normal;
+        }
+    }
+
+    /// Deletes the character under the input cursor (Delete key).
+    pub fn input_delete(&mut self) {
+        self.completion = None;
+        let cursor = self.input_cursor;
+        if let Some(buffer) = self.input_buffer_mut() {
+            if cursor < buffer.len() {
+                buffer.remove(cursor);
+            }
+        }
+    }
+
+    /// Deletes the word before the input cursor (Ctrl+W).
+    pub fn input_delete_word(&mut self) {
+        self.completion = None;
+        let cursor = self.input_cursor;
+        if let Some(buffer) = self.input_buffer_mut() {
+            let head = &buffer[..cursor.min(buffer.len())];
+            let trimmed = head.trim_end();
+            let start = trimmed
+                .rfind(|c: char| c.is_whitespace() || c == '/')
+                .map(|idx| idx + 1)
+                .unwrap_or(0);
+            buffer.replace_range(start..head.len(), "");
+            self.input_cursor = start;
+        }
+    }
+
+    /// Deletes everything before the input cursor (Ctrl+U).
+    pub fn input_delete_to_start(&mut self) {
+        self.completion = None;
+        let cursor = self.input_cursor;
+        if let Some(buffer) = self.input_buffer_mut() {
+            buffer.replace_range(..cursor.min(buffer.len()), "");
+            self.input_cursor = 0;
+        }
+    }
+
+    /// Moves the input cursor one character left.
+    pub fn input_cursor_left(&mut self) {
+        self.completion = None;
+        if let Some(text) = self.input_text() {
+            if let Some((idx, _)) = text[..self.input_cursor.min(text.len())].char_indices().next_back() {
+                self.input_cursor = idx;
+            }
+        }
+    }
+
+    /// Moves the input cursor one character right.
+    pub fn input_cursor_right(&mut self) {
+        self.completion = None;
+        if let Some(text) = self.input_text() {
+            if let Some(c) = text[self.input_cursor.min(text.len())..].chars().next() {
+                self.input_cursor += c.len_utf8();
+            }
+        }
+    }
+
+    /// Moves the input cursor to the start of the input.
+    pub fn input_cursor_home(&mut self) {
+        self.completion = None;
+        self.input_cursor = 0;
+    }
+
+    /// Moves the input cursor to the end of the input.
+    pub fn input_cursor_end(&mut self) {
+        self.completion = None;
+        self.input_cursor = self.input_text().map_or(0, str::len);
+    }
+
+    /// Recalls the previous (older) command or search from history.
+    pub fn history_previous(&mut self) {
+        self.completion = None;
+        let Some(current) = self.input_text().map(str::to_string) else {
+            return;
+        };
+        let history = match self.mode {
+            AppMode::Command(_) => &mut self.history.commands,
+            _ => &mut self.history.searches,
+        };
+        if let Some(entry) = history.older(&current) {
+            self.set_input_text(entry);
+        }
+    }
+
+    /// Recalls the next (newer) command or search from history.
+    pub fn history_next(&mut self) {
+        self.completion = None;
+        let history = match self.mode {
+            AppMode::Command(_) => &mut self.history.commands,
+            AppMode::Search(_) | AppMode::SearchBackward(_) => &mut self.history.searches,
+            _ => return,
+        };
+        if let Some(entry) = history.newer() {
+            self.set_input_text(entry);
+        }
+    }
+
+    /// Tab completion in command mode: inserts the next (or previous) candidate.
+    ///
+    /// A single candidate is inserted directly; several candidates are cycled
+    /// through, coming back to the typed text after the last one.
+    pub fn complete_input(&mut self, forward: bool) {
+        let AppMode::Command(text) = &self.mode else {
+            return;
+        };
+        let cursor = self.input_cursor.min(text.len());
+
+        let mut completion = match self.completion.take() {
+            Some(completion) => completion,
+            None => {
+                let head = &text[..cursor];
+                let Some((start, candidates)) = cmdline::complete_command(head) else {
+                    self.status_message = Some("No completion".to_string());
+                    return;
+                };
+                Completion {
+                    start,
+                    original: head[start..].to_string(),
+                    candidates,
+                    selected: None,
+                }
+            }
+        };
+
+        let count = completion.candidates.len();
+        completion.selected = match (completion.selected, forward) {
+            _ if count == 1 => Some(0),
+            (None, true) => Some(0),
+            (None, false) => Some(count - 1),
+            (Some(i), true) if i + 1 < count => Some(i + 1),
+            (Some(i), false) if i > 0 => Some(i - 1),
+            _ => None,
+        };
+
+        let replacement = match completion.selected {
+            Some(i) => completion.candidates[i].text.clone(),
+            None => completion.original.clone(),
+        };
+        let start = completion.start;
+        if let Some(buffer) = self.input_buffer_mut() {
+            buffer.replace_range(start..cursor, &replacement);
+        }
+        self.input_cursor = start + replacement.len();
+
+        // Keep cycling only when there is a choice to make
+        if count > 1 {
+            self.completion = Some(completion);
+        }
+    }
+
+    /// Executes the current command.
+    /// Returns the follow-up work (translation, file loading) for the controller.
+    pub fn execute_command(&mut self) -> CommandOutcome {
+        let mut outcome = CommandOutcome::None;
+        self.completion = None;
+        if let AppMode::Command(ref cmd) = self.mode.clone() {
+            self.history.commands.push(cmd);
+            let cmd = cmd.trim();
+            match cmd {
+                "$" => {
+                    let num_seqs = self.rows.len();
+                    if num_seqs > 0 {
+                        self.goto_row_1indexed(num_seqs);
+                    } else {
+                        self.status_message = Some("No sequences loaded".to_string());
+                    }
+                }
+                "q" | "quit" => self.should_quit = true,
+                "h" | "help" => self.show_help(),
+                "e" | "edit" => {
+                    // Open file browser to select a new file
+                    let start_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+                    self.file_browser = Some(FileBrowserState::new(start_dir, "Open file".to_string()));
+                    self.mode = AppMode::Normal;
+                    return CommandOutcome::None;
+                }
+                "asAA" | "asaa" => {
+                    if !self.alignment.sequence_type.is_nucleotide() {
+                        self.status_message = Some("Cannot translate: not a nucleotide sequence".to_string());
+                    } else if self.has_valid_cached_translation() {
+                        // We have a valid cached translation - switch to it directly
+                        self.switch_to_cached_aa_view();
+                    } else if !self.translation_settings.has_translated {
+                        // First time: show settings so user knows the options
+                        self.enter_translation_settings();
+                        return CommandOutcome::None; // Don't reset to Normal
+                    } else {
+                        // Subsequent times: request translation (controller will start background thread)
+                        outcome = CommandOutcome::StartTranslation;
+                    }
+                }
+                "asNT" | "asnt" => self.switch_to_nucleotide_view(),
+                "seq" => self.open_sequence_finder(""),
+                "sort" | "sort!" => {
+                    self.status_message = Some(format!("Usage: :sort[!] {}", SortKey::NAMES.join("|")));
+                }
+                "trim" => {
+                    self.status_message = Some("Usage: :trim allgaps|gappyout|strict|<max gap fraction>".to_string());
+                }
+                "colmap" => {
+                    self.status_message = Some("Usage: :colmap filename".to_string());
+                }
+                "degap" => outcome = self.degap_command(),
+                "inserts" => outcome = self.toggle_inserts(),
+                "dist" => self.distance_command(""),
+                "tree" => self.tree_command(""),
+                "sets" => self.sets_command(""),
+                "block" => outcome = self.block_command(""),
+                "hide" => self.hide_rows(None),
+                "unhide" => self.unhide_rows(),
+                "pin" => self.pin_rows(None),
+                "unpin" => self.unpin_rows(None),
+                "setcode" => {
+                    self.enter_translation_settings();
+                    return CommandOutcome::None; // Don't reset to Normal - enter_translation_settings sets the mode
+                }
                 "w" => {
                     // :w alone without filename
                     self.status_message = Some("Usage: :w filename.fasta".to_string());
                 }
                 _ => {
-                    // Handle :w filename - save to FASTA
-                    if cmd.starts_with("w ") {
-                        let filename = cmd[2..].trim();
+                    // Handle :w filename - save to FASTA (Stockholm for .sto/.stk)
+                    if let Some(filename) = cmd.strip_prefix("w ") {
+                        let filename = filename.trim();
                         if filename.is_empty() {
                             self.status_message = Some("Usage: :w filename.fasta".to_string());
                         } else {
-                            match self.write_fasta(filename) {
+                            let written = match formats::detect_format_from_extension(filename) {
+                                Some(FileFormat::Stockholm) => self.write_stockholm(filename),
+                                _ => self.write_fasta(filename),
+                            };
+                            match written {
                                 Ok(count) => {
                                     self.status_message = Some(format!(
                                         "Saved {} sequences to {}", count, filename
@@ -1369,9 +2214,55 @@ impl AppState {
                             }
                         }
                     }
+                    // Handle :e path - open a file (or the file browser for a directory)
+                    else if let Some(path) = cmd.strip_prefix("e ").or_else(|| cmd.strip_prefix("edit ")) {
+                        outcome = self.edit_command(path.trim());
+                    }
+                    // Handle :setcode N - set the genetic code without the dialog
+                    else if let Some(code) = cmd.strip_prefix("setcode ") {
+                        outcome = self.setcode_command(code.trim());
+                    }
+                    // Handle :seq name - open the sequence finder pre-filled
+                    else if let Some(query) = cmd.strip_prefix("seq ") {
+                        self.open_sequence_finder(query.trim());
+                    }
+                    // Handle :trim method - remove columns
+                    else if let Some(method) = cmd.strip_prefix("trim ") {
+                        outcome = self.trim_command(method.trim());
+                    } else if let Some(filename) = cmd.strip_prefix("colmap ") {
+                        self.colmap_command(filename.trim());
+                    } else if let Some(args) = cmd.strip_prefix("dist ") {
+                        self.distance_command(args.trim());
+                    } else if let Some(path) = cmd.strip_prefix("tree ") {
+                        self.tree_command(path.trim());
+                    } else if let Some(path) = cmd.strip_prefix("sets ") {
+                        self.sets_command(path.trim());
+                    } else if let Some(name) = cmd.strip_prefix("block ") {
+                        outcome = self.block_command(name.trim());
+                    }
+                    // Handle :sort key, :hide/:pin/:unpin pattern - row operations
+                    else if let Some(key) = cmd.strip_prefix("sort ") {
+                        self.sort_command(key.trim(), false);
+                    } else if let Some(key) = cmd.strip_prefix("sort! ") {
+                        self.sort_command(key.trim(), true);
+                    } else if let Some(pattern) = cmd.strip_prefix("hide ") {
+                        self.hide_rows(Some(pattern.trim()));
+                    } else if let Some(pattern) = cmd.strip_prefix("pin ") {
+                        self.pin_rows(Some(pattern.trim()));
+                    } else if let Some(pattern) = cmd.strip_prefix("unpin ") {
+                        self.unpin_rows(Some(pattern.trim()));
+                    }
+                    // Handle :map / :unmap for runtime key bindings
+                    else if let Some(args) = cmd.strip_prefix("map ") {
+                        self.map_command(args.trim());
+                    } else if let Some(args) = cmd.strip_prefix("unmap ") {
+                        self.unmap_command(args.trim());
+                    } else if cmd == "map" || cmd == "unmap" {
+                        self.status_message = Some(format!("Usage: :{}", if cmd == "map" { "map {keys} {action}" } else { "unmap {keys}" }));
+                    }
                     // Handle :number for row/sequence navigation (like Vim's :line)
                     else if let Ok(row) = cmd.parse::<usize>() {
-                        let num_seqs = self.active_alignment().sequence_count();
+                        let num_seqs = self.rows.len();
                         if num_seqs > 0 {
                             self.goto_row_1indexed(row);
                         } else {
@@ -1380,11 +2271,860 @@ impl AppState {
                     } else {
                         self.status_message = Some(format!("Unknown command: {}", cmd));
                     }
-                }
+                }
+            }
+        }
+        self.mode = AppMode::Normal;
+        outcome
+    }
+
+    /// Sequence indices whose name contains `pattern` (case-insensitive, like search),
+    /// or the sequence at the cursor when `pattern` is None.
+    fn matching_sequences(&self, pattern: Option<&str>) -> Vec<usize> {
+        match pattern {
+            None => self.rows.seq_index(self.cursor.row).into_iter().collect(),
+            Some(pattern) => {
+                let pattern = pattern.to_uppercase();
+                self.alignment
+                    .sequences
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, seq)| seq.id.to_uppercase().contains(&pattern))
+                    .map(|(i, _)| i)
+                    .collect()
+            }
+        }
+    }
+
+    /// Keeps the cursor on sequence `seq` after the row order changed.
+    /// If it is no longer shown, the cursor stays at the same display row.
+    fn follow_sequence(&mut self, seq: Option<usize>) {
+        if let Some(row) = seq.and_then(|i| self.rows.display_row(i)) {
+            self.cursor.row = row;
+        }
+        self.ensure_cursor_visible();
+    }
+
+    /// Handles `:trim {method}`: removes columns from the alignment.
+    ///
+    /// Nucleotides are trimmed by whole codons of the current reading frame.
+    /// The translation is redone if the amino acid view is shown.
+    fn trim_command(&mut self, arg: &str) -> CommandOutcome {
+        let method = match arg.parse::<TrimMethod>() {
+            Ok(method) => method,
+            Err(e) => {
+                self.status_message = Some(e);
+                return CommandOutcome::None;
+            }
+        };
+        if !self.alignment.is_valid_alignment {
+            self.status_message = Some("Cannot trim: sequences have different lengths".to_string());
+            return CommandOutcome::None;
+        }
+
+        let codon_frame = self
+            .alignment
+            .sequence_type
+            .is_nucleotide()
+            .then_some(self.translation_settings.frame);
+        let (trimmed, kept) = trim::trim_alignment(&self.alignment, method, &[], codon_frame);
+        let total = self.alignment.alignment_length();
+        if kept.is_empty() {
+            self.status_message = Some(format!("Trim ({}) would remove all {} sites; alignment unchanged", method, total));
+            return CommandOutcome::None;
+        }
+
+        // Compose with a previous trim so positions refer to the loaded file
+        self.column_map = Some(match self.column_map.take() {
+            Some(previous) => kept.iter().map(|&c| previous[c]).collect(),
+            None => kept.clone(),
+        });
+        self.alignment = trimmed;
+        self.translated_alignment = None;
+        self.cached_translation_code_id = None;
+        self.cached_translation_frame = None;
+        self.ensure_cursor_visible();
+        self.status_message = Some(format!("Trimmed ({}): kept {} of {} sites", method, kept.len(), total));
+
+        if self.view_mode == ViewMode::AminoAcid {
+            return CommandOutcome::StartTranslation;
+        }
+        CommandOutcome::None
+    }
+
+    /// Handles `:degap`: toggles between the aligned and the raw (ungapped)
+    /// sequences. In AA view the raw sequences are translated, so codons are
+    /// read from the ungapped sequence.
+    fn degap_command(&mut self) -> CommandOutcome {
+        // Keep the cursor on the same residue of its sequence (NT view)
+        let residue_row = (self.view_mode == ViewMode::Nucleotide)
+            .then(|| self.rows.seq_index(self.cursor.row))
+            .flatten();
+        let col = self.cursor.col;
+
+        let (message, new_col) = if let Some((aligned, column_map)) = self.gapped.take() {
+            let new_col = residue_row.and_then(|i| {
+                let residue = self.alignment.get(i)?.as_bytes().len().min(col);
+                aligned.get(i)?.as_bytes().iter().enumerate()
+                    .filter(|(_, &b)| !matches!(b, b'-' | b'.'))
+                    .nth(residue)
+                    .map(|(c, _)| c)
+            });
+            self.alignment = aligned;
+            self.column_map = column_map;
+            ("Aligned view restored".to_string(), new_col)
+        } else {
+            let degapped = self.alignment.degapped(false);
+            let removed = self.alignment.sequences.iter().map(|s| s.len()).sum::<usize>()
+                - degapped.sequences.iter().map(|s| s.len()).sum::<usize>();
+            let new_col = residue_row.and_then(|i| {
+                let seq = self.alignment.get(i)?.as_bytes();
+                Some(seq[..col.min(seq.len())].iter().filter(|&&b| !matches!(b, b'-' | b'.')).count())
+            });
+            let aligned = std::mem::replace(&mut self.alignment, degapped);
+            self.gapped = Some((aligned, self.column_map.take()));
+            (format!("Degapped: removed {} gaps (:degap again to restore)", removed), new_col)
+        };
+
+        let max_col = self.alignment.alignment_length().saturating_sub(1);
+        self.cursor.col = new_col.unwrap_or(col).min(max_col);
+        self.translated_alignment = None;
+        self.cached_translation_code_id = None;
+        self.cached_translation_frame = None;
+        self.ensure_cursor_visible();
+        self.status_message = Some(message);
+
+        if self.view_mode == ViewMode::AminoAcid {
+            return CommandOutcome::StartTranslation;
+        }
+        CommandOutcome::None
+    }
+
+    /// Returns true if a column of the current view is an insert state (A2M/A3M
+    /// lowercase columns, or a gap in the `#=GC RF` line of a Stockholm file).
+    pub fn is_insert_column(&self, col: usize) -> bool {
+        self.active_alignment().annotations.is_insert_column(col)
+    }
+
+    /// Hides the insert columns (`zi` or `:inserts`), or shows them again.
+    /// Hidden columns are removed like `:trim`, so positions still refer to the loaded file;
+    /// match columns trimmed while the inserts were hidden stay removed.
+    pub fn toggle_inserts(&mut self) -> CommandOutcome {
+        let col = self.cursor.col;
+        let (message, new_col) = if let Some((alignment, column_map)) = self.inserts.take() {
+            // Original position of the stashed columns, and of the shown (match) columns
+            let original = |c: usize| column_map.as_ref().map_or(c, |map| map[c]);
+            let shown: HashSet<usize> = self.column_map.iter().flatten().copied().collect();
+            let matches: HashSet<usize> = alignment.annotations.match_columns().unwrap_or_default().into_iter().collect();
+            let kept: Vec<usize> = (0..alignment.alignment_length())
+                .filter(|c| !matches.contains(c) || shown.contains(&original(*c)))
+                .collect();
+            let cursor_original = self.column_map.as_ref().and_then(|map| map.get(col).copied());
+            let new_col = cursor_original.and_then(|orig| kept.iter().position(|&c| original(c) == orig));
+            let trimmed = alignment.alignment_length() - kept.len();
+            if trimmed == 0 {
+                self.alignment = alignment;
+                self.column_map = column_map;
+            } else {
+                self.alignment = trim::apply(&alignment, &kept);
+                self.column_map = Some(kept.iter().map(|&c| original(c)).collect());
+            }
+            self.gapped = None;
+            let message = match trimmed {
+                0 => "Insert columns shown (zi to hide)".to_string(),
+                n => format!("Insert columns shown, {} trimmed columns still removed (zi to hide)", n),
+            };
+            (message, new_col)
+        } else {
+            let Some(kept) = self.alignment.annotations.match_columns() else {
+                self.status_message = Some("No insert columns (A2M/A3M file or #=GC RF line)".to_string());
+                return CommandOutcome::None;
+            };
+            let hidden = self.alignment.alignment_length() - kept.len();
+            let new_col = kept.partition_point(|&c| c < col);
+            let matches = trim::apply(&self.alignment, &kept);
+            let column_map = self.column_map.take();
+            self.column_map = Some(match &column_map {
+                Some(previous) => kept.iter().map(|&c| previous[c]).collect(),
+                None => kept,
+            });
+            let alignment = std::mem::replace(&mut self.alignment, matches);
+            self.inserts = Some((alignment, column_map));
+            (format!("Hid {} insert columns (zi to show)", hidden), Some(new_col))
+        };
+
+        let max_col = self.alignment.alignment_length().saturating_sub(1);
+        self.cursor.col = new_col.unwrap_or(col).min(max_col);
+        self.translated_alignment = None;
+        self.cached_translation_code_id = None;
+        self.cached_translation_frame = None;
+        self.ensure_cursor_visible();
+        self.status_message = Some(message);
+
+        if self.view_mode == ViewMode::AminoAcid {
+            return CommandOutcome::StartTranslation;
+        }
+        CommandOutcome::None
+    }
+
+    /// Handles `:dist [model] [complete]`: lists the visible sequences closest
+    /// to the current row.
+    fn distance_command(&mut self, args: &str) {
+        let mut model = DistanceModel::default();
+        let mut deletion = Deletion::default();
+        for arg in args.split_whitespace() {
+            match (arg.parse::<DistanceModel>(), arg.parse::<Deletion>()) {
+                (Ok(m), _) => model = m,
+                (_, Ok(d)) => deletion = d,
+                (Err(e), _) => {
+                    self.status_message = Some(e);
+                    return;
+                }
+            }
+        }
+        let Some(reference) = self.rows.seq_index(self.cursor.row) else {
+            self.status_message = Some("No sequences loaded".to_string());
+            return;
+        };
+        let calculator = match DistanceCalculator::new(self.active_alignment(), model, deletion) {
+            Ok(calculator) => calculator,
+            Err(e) => {
+                self.status_message = Some(e);
+                return;
+            }
+        };
+        let values = calculator.from_sequence(reference);
+        let mut entries: Vec<(usize, Option<f64>)> = values
+            .into_iter()
+            .enumerate()
+            .filter(|&(i, _)| i != reference && self.rows.display_row(i).is_some())
+            .collect();
+        distance::closest_first(model, &mut entries);
+        if entries.is_empty() {
+            self.status_message = Some("No other visible sequence to compare".to_string());
+            return;
+        }
+        self.distance_panel = Some(DistancePanelState { reference, model, deletion, entries, selected: 0 });
+    }
+
+    /// Moves the distance panel selection up.
+    pub fn distance_up(&mut self) {
+        if let Some(panel) = &mut self.distance_panel {
+            panel.select_prev();
+        }
+    }
+
+    /// Moves the distance panel selection down.
+    pub fn distance_down(&mut self) {
+        if let Some(panel) = &mut self.distance_panel {
+            panel.select_next();
+        }
+    }
+
+    /// Jumps to the selected sequence, keeping the cursor column, and closes the panel.
+    pub fn distance_select(&mut self) {
+        if let Some(panel) = self.distance_panel.take() {
+            let row = panel.entries.get(panel.selected).and_then(|&(i, _)| self.rows.display_row(i));
+            if let Some(row) = row {
+                self.goto_row_1indexed(row + 1);
+            }
+        }
+    }
+
+    /// Closes the distance panel without moving the cursor.
+    pub fn close_distance_panel(&mut self) {
+        self.distance_panel = None;
+    }
+
+    /// Handles `:block [name]`: opens the character block picker, or reloads the
+    /// file with the block named `name` (TITLE or number).
+    fn block_command(&mut self, name: &str) -> CommandOutcome {
+        if self.blocks.len() < 2 {
+            self.status_message = Some("The file has a single character matrix".to_string());
+            return CommandOutcome::None;
+        }
+        if name.is_empty() {
+            self.block_picker = Some(BlockPickerState { selected: self.block });
+            return CommandOutcome::None;
+        }
+        let known = self.blocks.iter().any(|t| t.eq_ignore_ascii_case(name))
+            || name.parse::<usize>().is_ok_and(|n| (1..=self.blocks.len()).contains(&n));
+        if known {
+            CommandOutcome::LoadBlock(name.to_string())
+        } else {
+            self.status_message =
+                Some(format!("No character block '{}' (available: {})", name, self.blocks.join(", ")));
+            CommandOutcome::None
+        }
+    }
+
+    /// Moves the block picker selection up.
+    pub fn block_picker_up(&mut self) {
+        if let Some(picker) = &mut self.block_picker {
+            picker.selected = picker.selected.saturating_sub(1);
+        }
+    }
+
+    /// Moves the block picker selection down.
+    pub fn block_picker_down(&mut self) {
+        let last = self.blocks.len().saturating_sub(1);
+        if let Some(picker) = &mut self.block_picker {
+            picker.selected = (picker.selected + 1).min(last);
+        }
+    }
+
+    /// Closes the block picker and returns the block to load (its number), if it
+    /// is not the one shown.
+    pub fn block_picker_select(&mut self) -> Option<String> {
+        let picker = self.block_picker.take()?;
+        (picker.selected != self.block).then(|| (picker.selected + 1).to_string())
+    }
+
+    /// Column of the loaded file shown at a column of the current view
+    /// (None for degapped sequences).
+    fn original_column(&self, col: usize) -> Option<usize> {
+        if self.gapped.is_some() {
+            return None;
+        }
+        let col = match self.view_mode {
+            ViewMode::Nucleotide => col,
+            ViewMode::AminoAcid => self.translation_settings.frame + 3 * col,
+        };
+        match &self.column_map {
+            Some(map) => map.get(col).copied(),
+            None => Some(col),
+        }
+    }
+
+    /// Index of the partition containing a column of the current view.
+    fn partition_index(&self, col: usize) -> Option<usize> {
+        let partitions = self.partitions.as_ref()?;
+        partitions
+            .by_column
+            .get(self.original_column(col)?)
+            .copied()
+            .flatten()
+    }
+
+    /// Name of the partition containing a column of the current view.
+    pub fn partition_at(&self, col: usize) -> Option<&str> {
+        let index = self.partition_index(col)?;
+        self.partitions.as_ref().map(|p| p.names[index].as_str())
+    }
+
+    /// Moves the cursor to the first column of the next partition (`]p`).
+    pub fn next_partition(&mut self) {
+        let current = self.partition_index(self.cursor.col);
+        let len = self.active_alignment().alignment_length();
+        let target = (self.cursor.col + 1..len)
+            .find(|&c| self.partition_index(c).is_some_and(|i| Some(i) != current));
+        self.jump_to_partition(target, "No next partition");
+    }
+
+    /// Moves the cursor to the first column of the current partition, or of the
+    /// previous one if already there (`[p`).
+    pub fn previous_partition(&mut self) {
+        let start_of = |state: &Self, col: usize| {
+            let index = state.partition_index(col);
+            (0..col)
+                .rev()
+                .take_while(|&c| state.partition_index(c) == index)
+                .last()
+                .unwrap_or(col)
+        };
+        let col = self.cursor.col;
+        let start = start_of(self, col);
+        let target = if start < col && self.partition_index(col).is_some() {
+            Some(start)
+        } else {
+            (0..start)
+                .rev()
+                .find(|&c| self.partition_index(c).is_some())
+                .map(|c| start_of(self, c))
+        };
+        self.jump_to_partition(target, "No previous partition");
+    }
+
+    fn jump_to_partition(&mut self, target: Option<usize>, none_message: &str) {
+        if self.partitions.is_none() {
+            self.status_message =
+                Some("No partitions (NEXUS SETS block or :sets file)".to_string());
+            return;
+        }
+        if self.original_column(0).is_none() {
+            self.status_message =
+                Some("Partitions are not shown for degapped sequences".to_string());
+            return;
+        }
+        match target {
+            Some(col) => {
+                self.cursor.col = col;
+                self.ensure_cursor_visible();
+                self.status_message = Some(format!(
+                    "Partition {}",
+                    self.partition_at(col).unwrap_or_default()
+                ));
+            }
+            None => self.status_message = Some(none_message.to_string()),
+        }
+    }
+
+    /// Handles `:sets [file]`: loads partitions from a NEXUS or RAxML partition
+    /// file, or summarizes the SETS blocks and partitions.
+    fn sets_command(&mut self, path: &str) {
+        if !path.is_empty() {
+            let sets = std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| partition::parse_partition_file(&content));
+            self.status_message = Some(match sets {
+                Ok(sets) => {
+                    let len = self
+                        .gapped
+                        .as_ref()
+                        .map_or(&self.alignment, |(aligned, _)| aligned)
+                        .alignment_length();
+                    let end = sets
+                        .iter()
+                        .flat_map(|set| set.ranges.iter().map(|&(_, end, _)| end))
+                        .max()
+                        .unwrap_or(0);
+                    // After :trim the length of the loaded file is not known
+                    let beyond = self.column_map.is_none() && end > len;
+                    let view = PartitionView::new(&sets, len.max(end), format!("file {}", path));
+                    let message = view.load_message();
+                    self.partitions = Some(view);
+                    if beyond {
+                        format!("{}; some extend beyond column {}", message, len)
+                    } else {
+                        message
+                    }
+                }
+                Err(e) => format!("Cannot read partitions from {}: {}", path, e),
+            });
+            return;
+        }
+
+        let mut parts = Vec::new();
+        if let Some(partitions) = &self.partitions {
+            parts.push(format!(
+                "{} partitions from {}: {}",
+                partitions.names.len(),
+                partitions.source,
+                partitions.names.join(", ")
+            ));
+        }
+        if let Some(sets) = &self.alignment.sets {
+            if !sets.charpartitions.is_empty() {
+                let names: Vec<&str> = sets
+                    .charpartitions
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect();
+                parts.push(format!("charpartitions: {}", names.join(", ")));
+            }
+            if !sets.taxsets.is_empty() {
+                let taxsets: Vec<String> = sets
+                    .taxsets
+                    .iter()
+                    .map(|(name, taxa)| format!("{} ({})", name, taxa.len()))
+                    .collect();
+                parts.push(format!("taxsets: {}", taxsets.join(", ")));
+            }
+        }
+        self.status_message = Some(if parts.is_empty() {
+            "No sets (NEXUS SETS block); :sets file reads a partition file".to_string()
+        } else {
+            parts.join(" | ")
+        });
+    }
+
+    /// Width of the panels left of the sequences (tree and names).
+    pub fn left_panels_width(&self) -> u16 {
+        match self.tree_panel {
+            Some(_) => self.name_width + TREE_PANEL_WIDTH,
+            None => self.name_width,
+        }
+    }
+
+    /// Handles `:tree [file]`: loads a Newick tree, or closes the tree panel.
+    fn tree_command(&mut self, path: &str) {
+        if path.is_empty() {
+            if self.tree_panel.is_none() {
+                self.status_message = Some("Usage: :tree file.nwk".to_string());
+                return;
+            }
+            self.expand_all_clades();
+            self.tree_panel = None;
+            self.status_message = Some("Tree closed".to_string());
+            return;
+        }
+        if let Err(e) = self.load_tree(Path::new(path)) {
+            self.status_message = Some(e);
+        }
+    }
+
+    /// Reads a Newick tree, shows it next to the names and orders the rows by
+    /// its leaves. Sequences not in the tree are listed after the others.
+    pub fn load_tree(&mut self, path: &Path) -> Result<(), String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let (tree, labels) = Tree::parse_newick(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let panel = TreePanelState::new(path.to_path_buf(), tree, &labels, &self.alignment);
+        let matched = panel.matched_count();
+        if matched == 0 {
+            return Err(format!("{}: no tree leaf matches a sequence name", path.display()));
+        }
+
+        if self.tree_panel.is_some() {
+            self.expand_all_clades();
+        }
+        let current = self.rows.seq_index(self.cursor.row);
+        self.rows.set_order(panel.sequence_order());
+        let unknown_leaves = labels.len() - matched;
+        let missing = self.alignment.sequence_count() - matched;
+        self.tree_panel = Some(panel);
+        self.follow_sequence(current);
+        self.status_message = Some(format!(
+            "Tree: {} leaves, {} not in the alignment, {} sequence(s) not in the tree (gc: select clade, zc/zo: collapse/expand)",
+            labels.len(),
+            unknown_leaves,
+            missing
+        ));
+        Ok(())
+    }
+
+    /// Selects the smallest clade containing the current row, or the parent of
+    /// the selected clade if the current row is in it (`gc`). Selecting past the
+    /// root clears the selection. The cursor moves to the top row of the clade.
+    pub fn select_clade(&mut self) {
+        let Some(panel) = &self.tree_panel else {
+            self.status_message = Some("No tree loaded (:tree file.nwk)".to_string());
+            return;
+        };
+        let Some(seq) = self.rows.seq_index(self.cursor.row) else {
+            return;
+        };
+        let node = match panel.selected {
+            Some(selected) if panel.clade_sequences(selected).contains(&seq) => panel.parents[selected],
+            _ => match panel.parent_clade(seq) {
+                Some(node) => Some(node),
+                None => {
+                    self.status_message = Some("Current row is not in the tree".to_string());
+                    return;
+                }
+            },
+        };
+        let clade = node.map(|node| panel.clade_sequences(node)).unwrap_or_default();
+        if let Some(panel) = &mut self.tree_panel {
+            panel.selected = node;
+        }
+        if node.is_none() {
+            self.status_message = Some("Clade selection cleared".to_string());
+            return;
+        }
+        if let Some(top) = clade.iter().filter_map(|&i| self.rows.display_row(i)).min() {
+            self.cursor.row = top;
+            self.ensure_cursor_visible();
+        }
+        self.status_message = Some(format!("Clade of {} sequence(s) (gc: larger clade, zc: collapse)", clade.len()));
+    }
+
+    /// Collapses the selected clade, or the smallest clade containing the
+    /// current row, to its top displayed row (`zc`).
+    pub fn collapse_clade(&mut self) {
+        let Some(panel) = &self.tree_panel else {
+            self.status_message = Some("No tree loaded (:tree file.nwk)".to_string());
+            return;
+        };
+        let current = self.rows.seq_index(self.cursor.row);
+        let node = panel.selected.or_else(|| current.and_then(|seq| panel.parent_clade(seq)));
+        let Some(node) = node else {
+            self.status_message = Some("Current row is not in the tree".to_string());
+            return;
+        };
+        let mut shown: Vec<(usize, usize)> = panel
+            .clade_sequences(node)
+            .into_iter()
+            .filter_map(|i| self.rows.display_row(i).map(|row| (row, i)))
+            .collect();
+        shown.sort_unstable();
+        if shown.len() < 2 {
+            self.status_message = Some("Nothing to collapse".to_string());
+            return;
+        }
+        let representative = shown[0].1;
+        let hidden: Vec<usize> = shown[1..].iter().map(|&(_, i)| i).collect();
+        self.rows.hide(&hidden);
+        self.status_message = Some(format!("Collapsed {} sequence(s) into {} (zo: expand)", hidden.len(), self.alignment.sequences[representative].id));
+        if let Some(panel) = &mut self.tree_panel {
+            panel.selected = None;
+            panel.collapsed.push(CollapsedClade { node, representative, hidden });
+        }
+        self.follow_sequence(Some(representative));
+    }
+
+    /// Expands the collapsed clade shown on the current row (`zo`).
+    pub fn expand_clade(&mut self) {
+        let Some(panel) = &mut self.tree_panel else {
+            self.status_message = Some("No tree loaded (:tree file.nwk)".to_string());
+            return;
+        };
+        let current = self.rows.seq_index(self.cursor.row);
+        let Some(k) = panel.collapsed.iter().rposition(|c| Some(c.representative) == current) else {
+            self.status_message = Some("No collapsed clade on this row".to_string());
+            return;
+        };
+        let clade = panel.collapsed.remove(k);
+        let count = self.rows.unhide(&clade.hidden);
+        self.follow_sequence(current);
+        self.status_message = Some(format!("Expanded {} sequence(s)", count));
+    }
+
+    /// Expands all collapsed clades (`zR`).
+    pub fn expand_all_clades(&mut self) {
+        let Some(panel) = &mut self.tree_panel else {
+            self.status_message = Some("No tree loaded (:tree file.nwk)".to_string());
+            return;
+        };
+        let hidden: Vec<usize> = panel.collapsed.drain(..).flat_map(|c| c.hidden).collect();
+        let current = self.rows.seq_index(self.cursor.row);
+        let count = self.rows.unhide(&hidden);
+        self.follow_sequence(current);
+        self.status_message = Some(format!("Expanded {} sequence(s)", count));
+    }
+
+    /// Handles `:colmap {filename}`: writes the original position of each column.
+    fn colmap_command(&mut self, filename: &str) {
+        let Some(map) = &self.column_map else {
+            self.status_message = Some("No column map: the alignment has not been trimmed".to_string());
+            return;
+        };
+        self.status_message = Some(match trim::write_column_map(std::path::Path::new(filename), map) {
+            Ok(()) => format!("Wrote column map ({} columns) to {}", map.len(), filename),
+            Err(e) => format!("Error saving: {}", e),
+        });
+    }
+
+    /// Handles `:sort[!] {key}`.
+    fn sort_command(&mut self, key: &str, reverse: bool) {
+        let Some(sort_key) = SortKey::from_name(key) else {
+            self.status_message = Some(format!("Unknown sort key: {} (use {})", key, SortKey::NAMES.join(", ")));
+            return;
+        };
+        let current = self.rows.seq_index(self.cursor.row);
+        let reference = current.unwrap_or(0);
+        // With a tree shown, follow its leaves rather than building one
+        if let (SortKey::Tree, Some(panel)) = (sort_key, &self.tree_panel) {
+            let mut order = panel.sequence_order();
+            if reverse {
+                order.reverse();
+            }
+            let message = format!("Sorted by the leaf order of {}", panel.path.display());
+            self.rows.set_order(order);
+            self.follow_sequence(current);
+            self.status_message = Some(message);
+            return;
+        }
+        let alignment = match self.view_mode {
+            ViewMode::Nucleotide => &self.alignment,
+            ViewMode::AminoAcid => self.translated_alignment.as_ref().unwrap_or(&self.alignment),
+        };
+        if sort_key == SortKey::Tree && !alignment.is_valid_alignment {
+            self.status_message = Some("Tree order requires aligned sequences".to_string());
+            return;
+        }
+        self.rows.sort(alignment, sort_key, reference, reverse);
+        self.follow_sequence(current);
+        self.status_message = Some(format!("Sorted by {}{}", sort_key.name(), if reverse { " (reversed)" } else { "" }));
+    }
+
+    /// Moves the current row up or down, with the cursor following it.
+    pub fn move_row(&mut self, up: bool) {
+        if let Some(row) = self.rows.move_row(self.cursor.row, up) {
+            self.cursor.row = row;
+            self.ensure_cursor_visible();
+        }
+    }
+
+    /// Handles `:hide [pattern]`.
+    fn hide_rows(&mut self, pattern: Option<&str>) {
+        let current = self.rows.seq_index(self.cursor.row);
+        let seqs = self.matching_sequences(pattern);
+        if seqs.len() >= self.alignment.sequence_count() {
+            self.status_message = Some("Cannot hide all sequences".to_string());
+            return;
+        }
+        let count = self.rows.hide(&seqs);
+        self.follow_sequence(current);
+        self.status_message = Some(format!("Hid {} sequence(s), {} hidden (:unhide to show)", count, self.rows.hidden_count()));
+    }
+
+    /// Handles `:unhide`.
+    fn unhide_rows(&mut self) {
+        let current = self.rows.seq_index(self.cursor.row);
+        let count = self.rows.unhide_all();
+        if let Some(panel) = &mut self.tree_panel {
+            panel.collapsed.clear();
+        }
+        self.follow_sequence(current);
+        self.status_message = Some(format!("Showing {} hidden sequence(s)", count));
+    }
+
+    /// Handles `:pin [pattern]`.
+    fn pin_rows(&mut self, pattern: Option<&str>) {
+        let current = self.rows.seq_index(self.cursor.row);
+        let seqs = self.matching_sequences(pattern);
+        let count = self.rows.pin(&seqs);
+        self.follow_sequence(current);
+        self.status_message = Some(format!("Pinned {} sequence(s)", count));
+    }
+
+    /// Handles `:unpin [pattern]` (all pinned rows without a pattern).
+    fn unpin_rows(&mut self, pattern: Option<&str>) {
+        let current = self.rows.seq_index(self.cursor.row);
+        let count = match pattern {
+            None => self.rows.unpin_all(),
+            Some(_) => {
+                let seqs = self.matching_sequences(pattern);
+                self.rows.unpin(&seqs)
+            }
+        };
+        self.follow_sequence(current);
+        self.status_message = Some(format!("Unpinned {} sequence(s)", count));
+    }
+
+    /// Pins or unpins the current row.
+    pub fn toggle_pin(&mut self) {
+        match self.rows.seq_index(self.cursor.row) {
+            Some(seq) if self.rows.is_pinned(seq) => {
+                self.rows.unpin(&[seq]);
+                self.follow_sequence(Some(seq));
+            }
+            Some(seq) => {
+                self.rows.pin(&[seq]);
+                self.follow_sequence(Some(seq));
+            }
+            None => {}
+        }
+    }
+
+    /// Handles `:e {path}`: loads a file, or opens the file browser in a directory.
+    fn edit_command(&mut self, path: &str) -> CommandOutcome {
+        let path = match path.strip_prefix("~/") {
+            Some(rest) => match std::env::var_os("HOME") {
+                Some(home) => PathBuf::from(home).join(rest),
+                None => PathBuf::from(path),
+            },
+            None => PathBuf::from(path),
+        };
+        if path.is_dir() {
+            self.file_browser = Some(FileBrowserState::new(path, "Open file".to_string()));
+            CommandOutcome::None
+        } else if path.is_file() {
+            CommandOutcome::LoadFile(path)
+        } else {
+            self.status_message = Some(format!("File not found: {}", path.display()));
+            CommandOutcome::None
+        }
+    }
+
+    /// Handles `:setcode {number|name}`: sets the genetic code directly.
+    /// Retranslates if the amino acid view is shown.
+    fn setcode_command(&mut self, arg: &str) -> CommandOutcome {
+        use crate::genetic_code::GeneticCodes;
+
+        let codes = GeneticCodes::new();
+        let lower = arg.to_lowercase();
+        let found = match arg.parse::<u8>() {
+            Ok(id) => codes.all().iter().position(|c| c.id == id),
+            Err(_) => {
+                // Exact name first, then a unique partial match
+                let exact = codes.all().iter().position(|c| c.name.to_lowercase() == lower);
+                exact.or_else(|| {
+                    let mut partial = codes
+                        .all()
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, c)| c.name.to_lowercase().contains(&lower));
+                    match (partial.next(), partial.next()) {
+                        (Some((idx, _)), None) => Some(idx),
+                        _ => None,
+                    }
+                })
+            }
+        };
+
+        let Some(index) = found else {
+            self.status_message = Some(format!("Unknown genetic code: {}", arg));
+            return CommandOutcome::None;
+        };
+        let code = &codes.all()[index];
+        self.translation_settings.genetic_code_id = code.id;
+        self.translation_settings.selected_code_index = index;
+        self.translation_settings.has_translated = true;
+        self.status_message = Some(format!("Genetic code set to {} ({})", code.id, code.name));
+
+        if self.view_mode == ViewMode::AminoAcid {
+            if self.has_valid_cached_translation() {
+                self.switch_to_cached_aa_view();
+            } else {
+                return CommandOutcome::StartTranslation;
             }
         }
-        self.mode = AppMode::Normal;
-        start_translation
+        CommandOutcome::None
+    }
+
+    /// Handles `:map {keys} [action]`: binds keys, or shows the current binding.
+    fn map_command(&mut self, args: &str) {
+        let mut parts = args.split_whitespace();
+        let (Some(keys_text), action_text, None) = (parts.next(), parts.next(), parts.next()) else {
+            self.status_message = Some("Usage: :map {keys} {action}".to_string());
+            return;
+        };
+        let keys = match keymap::parse_key_sequence(keys_text) {
+            Ok(keys) => keys,
+            Err(e) => {
+                self.status_message = Some(format!("Invalid keys: {}", e));
+                return;
+            }
+        };
+        let keys_display = keymap::format_key_sequence(&keys);
+
+        let Some(action_text) = action_text else {
+            self.status_message = Some(match self.keymap.get(&keys) {
+                Some(action) => format!(
+                    "{} → {}",
+                    keys_display,
+                    keymap::action_name(action).unwrap_or("?")
+                ),
+                None => format!("{} is not mapped", keys_display),
+            });
+            return;
+        };
+
+        match keymap::action_from_name(action_text) {
+            Some(action) => {
+                self.keymap.bind(keys, action);
+                self.status_message = Some(format!("Mapped {} → {}", keys_display, action_text));
+            }
+            None => {
+                self.status_message = Some(format!("Unknown action: {}", action_text));
+            }
+        }
+    }
+
+    /// Handles `:unmap {keys}`.
+    fn unmap_command(&mut self, args: &str) {
+        match keymap::parse_key_sequence(args) {
+            Ok(keys) => {
+                let keys_display = keymap::format_key_sequence(&keys);
+                self.status_message = Some(if self.keymap.unbind(&keys) {
+                    format!("Unmapped {}", keys_display)
+                } else {
+                    format!("{} is not mapped", keys_display)
+                });
+            }
+            Err(e) => self.status_message = Some(format!("Invalid keys: {}", e)),
+        }
     }
 
     /// Enters translation settings mode.
@@ -1406,14 +3146,29 @@ impl AppState {
         let alignment = self.active_alignment();
         let mut file = std::fs::File::create(filename)?;
         
-        for seq in &alignment.sequences {
+        for seq in self.rows.rows().iter().filter_map(|&i| alignment.get(i)) {
             // Write header
             writeln!(file, ">{}", seq.id)?;
             // Write sequence on single line
             writeln!(file, "{}", seq.as_str())?;
         }
         
-        Ok(alignment.sequence_count())
+        Ok(self.rows.len())
+    }
+
+    /// Writes the current view (NT or AA) to a Stockholm file, with the
+    /// annotations of the file (kept through `:trim`, lost by translation and `:degap`).
+    /// Returns the number of sequences written.
+    pub fn write_stockholm(&self, filename: &str) -> std::io::Result<usize> {
+        use std::io::Write;
+
+        let alignment = self.active_alignment();
+        let mut file = std::io::BufWriter::new(std::fs::File::create(filename)?);
+        let sequences = self.rows.rows().iter().filter_map(|&i| alignment.get(i));
+        formats::stockholm::write_stockholm(&mut file, sequences, &alignment.annotations)?;
+        file.flush()?;
+
+        Ok(self.rows.len())
     }
 
     /// Switches to nucleotide view.
@@ -1425,7 +3180,7 @@ impl AppState {
         
         // Convert AA position to NT position: first nucleotide of the codon
         // AA position n corresponds to NT positions (n*3 + frame) to (n*3 + frame + 2)
-        let frame = self.translation_settings.frame as usize;
+        let frame = self.translation_settings.frame;
         let nt_col = self.cursor.col * 3 + frame;
         self.cursor.col = nt_col.min(self.alignment.alignment_length().saturating_sub(1));
         
@@ -1446,7 +3201,7 @@ impl AppState {
         let codes = GeneticCodes::new();
         let code = codes.get(self.translation_settings.genetic_code_id)
             .unwrap_or_else(|| codes.default_code());
-        let frame = self.translation_settings.frame as usize;
+        let frame = self.translation_settings.frame;
         
         // Translate all sequences (single-threaded - fast enough now)
         let translated_seqs: Vec<crate::model::Sequence> = self.alignment.sequences
@@ -1572,29 +3327,19 @@ impl AppState {
 
     /// Clears any pending key state.
     fn clear_pending(&mut self) {
-        self.pending_g = false;
-        self.pending_z = false;
-        self.number_buffer.clear();
-    }
-
-    /// Sets the pending 'g' state for g-commands.
-    pub fn set_pending_g(&mut self) {
-        self.pending_g = true;
-        self.pending_z = false;
+        self.pending_keys.clear();
         self.number_buffer.clear();
     }
 
-    /// Sets the pending 'z' state for z-commands.
-    pub fn set_pending_z(&mut self) {
-        self.pending_z = true;
-        self.pending_g = false;
+    /// Adds a key to the pending multi-key sequence.
+    pub fn push_pending_key(&mut self, key: KeyChord) {
+        self.pending_keys.push(key);
         self.number_buffer.clear();
     }
 
     /// Accumulates a digit for the number prefix.
     pub fn accumulate_digit(&mut self, c: char) {
-        self.pending_g = false;
-        self.pending_z = false;
+        self.pending_keys.clear();
         self.number_buffer.push(c);
     }
 
@@ -1660,20 +3405,10 @@ impl AppState {
         }
     }
 
-    /// Handles a g-command key.
-    pub fn handle_g_command(&mut self, c: char) {
-        self.pending_g = false;
-        match c {
-            '0' => self.goto_first_visible_column(),
-            'm' => self.goto_middle_visible_column(),
-            '$' => self.goto_last_visible_column(),
-            _ => {} // Unknown g-command, ignore
-        }
-    }
-
     /// Cancels command mode and returns to normal mode.
     pub fn cancel_command(&mut self) {
         self.mode = AppMode::Normal;
+        self.reset_input();
     }
 
     /// Enters search mode.
@@ -1683,28 +3418,20 @@ impl AppState {
         } else {
             self.mode = AppMode::Search(String::new());
         }
+        self.reset_input();
     }
 
     /// Handles a character input in search mode.
     pub fn search_input(&mut self, c: char) {
-        match &mut self.mode {
-            AppMode::Search(ref mut pattern) | AppMode::SearchBackward(ref mut pattern) => {
-                pattern.push(c);
-            }
-            _ => {}
+        if matches!(self.mode, AppMode::Search(_) | AppMode::SearchBackward(_)) {
+            self.insert_input_char(c);
         }
     }
 
     /// Handles backspace in search mode.
     pub fn search_backspace(&mut self) {
-        match &mut self.mode {
-            AppMode::Search(ref mut pattern) | AppMode::SearchBackward(ref mut pattern) => {
-                pattern.pop();
-                if pattern.is_empty() {
-                    self.mode = AppMode::Normal;
-                }
-            }
-            _ => {}
+        if matches!(self.mode, AppMode::Search(_) | AppMode::SearchBackward(_)) {
+            self.input_backspace();
         }
     }
 
@@ -1721,6 +3448,7 @@ impl AppState {
             return;
         }
 
+        self.history.searches.push(&pattern);
         self.last_search = Some(pattern.clone());
         self.last_search_backward = backward;
         self.mode = AppMode::Normal;
@@ -1736,6 +3464,7 @@ impl AppState {
     /// Cancels search mode and returns to normal mode.
     pub fn cancel_search(&mut self) {
         self.mode = AppMode::Normal;
+        self.reset_input();
     }
 
     /// Finds the next match (n key).
@@ -1771,9 +3500,8 @@ impl AppState {
         let pattern_upper = pattern.to_uppercase();
         let start_row = self.cursor.row;
         let start_col = self.cursor.col;
-        let alignment = self.active_alignment();
-        let num_rows = alignment.sequence_count();
-        let num_cols = alignment.alignment_length();
+        let num_rows = self.rows.len();
+        let num_cols = self.active_alignment().alignment_length();
 
         if num_rows == 0 || num_cols == 0 {
             self.status_message = Some(format!("Pattern not found: {}", pattern));
@@ -1781,8 +3509,7 @@ impl AppState {
         }
 
         // Search from current position to end of current row (sequence data only)
-        let alignment = self.active_alignment();
-        if let Some(seq) = alignment.get(start_row) {
+        if let Some(seq) = self.row_sequence(start_row) {
             let search_start = start_col + 1; // Start after current position
             if search_start < seq.len() {
                 let seq_str = seq.as_str();
@@ -1798,14 +3525,13 @@ impl AppState {
         // Search remaining rows (name first, then data)
         for row_offset in 1..=num_rows {
             let row = (start_row + row_offset) % num_rows;
-            let alignment = self.active_alignment();
-            if let Some(seq) = alignment.get(row) {
+            if let Some(seq) = self.row_sequence(row) {
                 // First check sequence name
                 if seq.id.to_uppercase().contains(&pattern_upper) {
                     self.cursor.row = row;
                     self.cursor.col = 0; // Position at start when matching name
                     self.ensure_cursor_visible();
-                    let wrapped = row < start_row || (row == start_row);
+                    let wrapped = row <= start_row;
                     if wrapped {
                         self.status_message = Some(format!("/{} (name, wrapped)", pattern));
                     } else {
@@ -1837,9 +3563,8 @@ impl AppState {
         let pattern_upper = pattern.to_uppercase();
         let start_row = self.cursor.row;
         let start_col = self.cursor.col;
-        let alignment = self.active_alignment();
-        let num_rows = alignment.sequence_count();
-        let num_cols = alignment.alignment_length();
+        let num_rows = self.rows.len();
+        let num_cols = self.active_alignment().alignment_length();
 
         if num_rows == 0 || num_cols == 0 {
             self.status_message = Some(format!("Pattern not found: {}", pattern));
@@ -1847,8 +3572,7 @@ impl AppState {
         }
 
         // Search from current position backward in current row (data only)
-        let alignment = self.active_alignment();
-        if let Some(seq) = alignment.get(start_row) {
+        if let Some(seq) = self.row_sequence(start_row) {
             if start_col > 0 {
                 let search_area = &seq.as_str()[..start_col];
                 if let Some(pos) = search_area.to_uppercase().rfind(&pattern_upper) {
@@ -1870,8 +3594,7 @@ impl AppState {
         // Search previous rows (data first from end, then name)
         for row_offset in 1..=num_rows {
             let row = (start_row + num_rows - row_offset) % num_rows;
-            let alignment = self.active_alignment();
-            if let Some(seq) = alignment.get(row) {
+            if let Some(seq) = self.row_sequence(row) {
                 // First check sequence data (from end)
                 if let Some(pos) = seq.as_str().to_uppercase().rfind(&pattern_upper) {
                     self.cursor.row = row;
@@ -1889,7 +3612,7 @@ impl AppState {
                     self.cursor.row = row;
                     self.cursor.col = 0;
                     self.ensure_cursor_visible();
-                    let wrapped = row > start_row || (row == start_row);
+                    let wrapped = row >= start_row;
                     if wrapped {
                         self.status_message = Some(format!("?{} (name, wrapped)", pattern));
                     } else {
@@ -2250,4 +3973,512 @@ mod tests {
         assert_eq!(state.cursor.row, 0);
         assert!(state.status_message.as_ref().unwrap().contains("name"));
     }
+
+    #[test]
+    fn test_map_and_unmap_commands() {
+        let alignment = Alignment::new(vec![Sequence::new("seq1", "ACGT")]);
+        let mut state = AppState::new(alignment, "test".to_string());
+        let keys = keymap::parse_key_sequence("<C-e>").unwrap();
+
+        state.mode = AppMode::Command("map <C-e> goto_last_column".to_string());
+        state.execute_command();
+        assert_eq!(state.keymap.get(&keys), Some(&crate::event::Action::GotoLastColumn));
+        assert_eq!(state.mode, AppMode::Normal);
+
+        state.mode = AppMode::Command("map <C-e> teleport".to_string());
+        state.execute_command();
+        assert!(state.status_message.as_ref().unwrap().contains("Unknown action"));
+
+        state.mode = AppMode::Command("unmap <C-e>".to_string());
+        state.execute_command();
+        assert_eq!(state.keymap.get(&keys), None);
+    }
+
+    #[test]
+    fn test_command_line_editing() {
+        let alignment = Alignment::new(vec![Sequence::new("seq1", "ACGT")]);
+        let mut state = AppState::new(alignment, "test".to_string());
+
+        state.enter_command_mode();
+        for c in "w a.fa".chars() {
+            state.command_input(c);
+        }
+        // Move before "a.fa" and insert "x"
+        for _ in 0..4 {
+            state.input_cursor_left();
+        }
+        state.command_input('x');
+        assert_eq!(state.mode, AppMode::Command("w xa.fa".to_string()));
+
+        state.input_delete();
+        assert_eq!(state.mode, AppMode::Command("w x.fa".to_string()));
+
+        state.input_cursor_end();
+        state.input_delete_word();
+        assert_eq!(state.mode, AppMode::Command("w ".to_string()));
+    }
+
+    #[test]
+    fn test_command_history_recall() {
+        let alignment = Alignment::new(vec![Sequence::new("seq1", "ACGT"), Sequence::new("seq2", "ACGT")]);
+        let mut state = AppState::new(alignment, "test".to_string());
+        state.update_viewport_size(2, 4);
+
+        state.mode = AppMode::Command("2".to_string());
+        state.execute_command();
+        state.enter_search_mode(false);
+        state.search_input('C');
+        state.execute_search();
+
+        state.enter_command_mode();
+        state.history_previous();
+        assert_eq!(state.mode, AppMode::Command("2".to_string()));
+        assert_eq!(state.input_cursor, 1);
+        state.history_next();
+        assert_eq!(state.mode, AppMode::Command(String::new()));
+
+        // Searches have their own history
+        state.enter_search_mode(true);
+        state.history_previous();
+        assert_eq!(state.mode, AppMode::SearchBackward("C".to_string()));
+    }
+
+    #[test]
+    fn test_tab_completion_cycles() {
+        let alignment = Alignment::new(vec![Sequence::new("seq1", "ACGT")]);
+        let mut state = AppState::new(alignment, "test".to_string());
+
+        state.enter_command_mode();
+        state.command_input('a');
+        state.command_input('s');
+        state.complete_input(true);
+        assert_eq!(state.mode, AppMode::Command("asAA".to_string()));
+        state.complete_input(true);
+        assert_eq!(state.mode, AppMode::Command("asNT".to_string()));
+        // Past the last candidate: back to the typed text
+        state.complete_input(true);
+        assert_eq!(state.mode, AppMode::Command("as".to_string()));
+
+        // A single candidate is inserted directly
+        state.enter_command_mode();
+        for c in "setc".chars() {
+            state.command_input(c);
+        }
+        state.complete_input(true);
+        assert_eq!(state.mode, AppMode::Command("setcode".to_string()));
+        assert!(state.completion.is_none());
+    }
+
+    #[test]
+    fn test_setcode_command() {
+        let alignment = Alignment::new(vec![Sequence::new("seq1", "ATGAAA")]);
+        let mut state = AppState::new(alignment, "test".to_string());
+
+        state.mode = AppMode::Command("setcode 5".to_string());
+        assert_eq!(state.execute_command(), CommandOutcome::None);
+        assert_eq!(state.translation_settings.genetic_code_id, 5);
+
+        state.mode = AppMode::Command("setcode vertebrate mito".to_string());
+        state.execute_command();
+        assert!(state.status_message.as_ref().unwrap().contains("Unknown"), "Ambiguous name");
+
+        state.mode = AppMode::Command("setcode yeast mito".to_string());
+        state.execute_command();
+        assert_eq!(state.translation_settings.genetic_code_id, 3);
+
+        // In the AA view, changing the code retranslates
+        state.view_mode = ViewMode::AminoAcid;
+        state.mode = AppMode::Command("setcode 2".to_string());
+        assert_eq!(state.execute_command(), CommandOutcome::StartTranslation);
+    }
+
+    #[test]
+    fn test_sequence_finder_jump_keeps_column() {
+        let alignment = Alignment::new(vec![
+            Sequence::new("Mus_musculus", "ACGTACGT"),
+            Sequence::new("Homo_sapiens", "ACGTACGT"),
+            Sequence::new("Pan_troglodytes", "ACGTACGT"),
+        ]);
+        let mut state = AppState::new(alignment, "test".to_string());
+        state.update_viewport_size(2, 8);
+        state.cursor.col = 5;
+
+        state.open_sequence_finder("");
+        assert_eq!(state.sequence_finder.as_ref().unwrap().matches.len(), 3);
+        for c in "ptro".chars() {
+            state.finder_input(c);
+        }
+        assert_eq!(state.sequence_finder.as_ref().unwrap().selected_row(), Some(2));
+        state.finder_select();
+        assert!(state.sequence_finder.is_none());
+        assert_eq!(state.cursor.row, 2);
+        assert_eq!(state.cursor.col, 5);
+
+        // :seq pre-fills the query; no match leaves the cursor alone
+        state.mode = AppMode::Command("seq xyz".to_string());
+        state.execute_command();
+        assert_eq!(state.sequence_finder.as_ref().unwrap().query, "xyz");
+        state.finder_select();
+        assert_eq!(state.cursor.row, 2);
+        assert!(state.status_message.as_ref().unwrap().contains("No sequence"));
+    }
+
+    #[test]
+    fn test_row_commands_keep_cursor_on_sequence() {
+        let alignment = Alignment::new(vec![
+            Sequence::new("delta", "ACGT--"),
+            Sequence::new("alpha", "ACGTAC"),
+            Sequence::new("charlie", "A-----"),
+            Sequence::new("bravo", "ACGAAC"),
+        ]);
+        let mut state = AppState::new(alignment, "test".to_string());
+        state.update_viewport_size(4, 6);
+        state.cursor.row = 2; // charlie
+
+        state.mode = AppMode::Command("sort name".to_string());
+        state.execute_command();
+        assert_eq!(state.row_names(), vec!["alpha", "bravo", "charlie", "delta"]);
+        assert_eq!(state.row_sequence(state.cursor.row).unwrap().id, "charlie");
+
+        state.mode = AppMode::Command("hide CHAR".to_string());
+        state.execute_command();
+        assert_eq!(state.row_names(), vec!["alpha", "bravo", "delta"]);
+        assert_eq!(state.cursor.row, 2);
+
+        state.mode = AppMode::Command("pin delta".to_string());
+        state.execute_command();
+        assert_eq!(state.row_names(), vec!["delta", "alpha", "bravo"]);
+        assert_eq!(state.row_sequence(state.cursor.row).unwrap().id, "delta");
+
+        state.move_row(false);
+        assert_eq!(state.row_names(), vec!["delta", "alpha", "bravo"], "pinned row stays among pinned");
+        state.cursor.row = 1;
+        state.move_row(false);
+        assert_eq!(state.row_names(), vec!["delta", "bravo", "alpha"]);
+        assert_eq!(state.cursor.row, 2);
+
+        state.mode = AppMode::Command("unhide".to_string());
+        state.execute_command();
+        state.mode = AppMode::Command("unpin".to_string());
+        state.execute_command();
+        state.mode = AppMode::Command("sort! file".to_string());
+        state.execute_command();
+        assert_eq!(state.row_names(), vec!["bravo", "charlie", "alpha", "delta"]);
+    }
+
+    #[test]
+    fn test_pinned_rows_stay_on_screen() {
+        let seqs: Vec<Sequence> = (0..20).map(|i| Sequence::new(format!("s{}", i), "ACGT")).collect();
+        let mut state = AppState::new(Alignment::new(seqs), "test".to_string());
+        state.update_viewport_size(5, 4);
+
+        state.cursor.row = 10;
+        state.toggle_pin();
+        assert_eq!(state.cursor.row, 0);
+        assert_eq!(state.frozen_rows(), 1);
+
+        state.goto_row_1indexed(20);
+        let screen = state.screen_rows();
+        assert_eq!(screen.len(), 5);
+        assert_eq!(screen[0], 0, "pinned row is always shown");
+        assert_eq!(*screen.last().unwrap(), 19);
+
+        state.goto_row_1indexed(2);
+        assert_eq!(state.screen_rows(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_write_fasta_uses_row_order() {
+        let alignment = Alignment::new(vec![
+            Sequence::new("b", "ACGT"),
+            Sequence::new("a", "TTTT"),
+            Sequence::new("c", "GGGG"),
+        ]);
+        let mut state = AppState::new(alignment, "test".to_string());
+        state.mode = AppMode::Command("sort name".to_string());
+        state.execute_command();
+        state.mode = AppMode::Command("hide c".to_string());
+        state.execute_command();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.fa");
+        assert_eq!(state.write_fasta(path.to_str().unwrap()).unwrap(), 2);
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, ">a\nTTTT\n>b\nACGT\n");
+    }
+
+    #[test]
+    fn test_annotations_follow_trim_and_write_stockholm() {
+        let content = "# STOCKHOLM 1.0\n#=GF ID test\nb MK-L\n#=GR b SS HH.H\na MA-L\n#=GC SS_cons HH.H\n//\n";
+        let alignment = formats::stockholm::parse_stockholm_str(content).unwrap();
+        let mut state = AppState::new(alignment, "test".to_string());
+        assert_eq!(state.annotation_tracks(), [("SS_cons", b"HH.H".as_slice())]);
+
+        // :trim keeps the annotations of the kept columns
+        state.mode = AppMode::Command("trim allgaps".to_string());
+        state.execute_command();
+        assert_eq!(state.annotation_tracks(), [("SS_cons", b"HHH".as_slice())]);
+
+        state.mode = AppMode::Command("sort name".to_string());
+        state.execute_command();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.sto");
+        state.mode = AppMode::Command(format!("w {}", path.display()));
+        state.execute_command();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            written,
+            "# STOCKHOLM 1.0\n#=GF ID test\n\na            MAL\nb            MKL\n#=GR b SS    HHH\n#=GC SS_cons HHH\n//\n"
+        );
+    }
+
+    #[test]
+    fn test_toggle_inserts() {
+        let alignment = formats::a3m::parse_a3m_str(">query\nMK-LV\n>hit1\nMKaaLLV\n>hit2\nMrK-LV\n").unwrap();
+        let mut state = AppState::new(alignment, "test".to_string());
+        assert!(state.is_insert_column(1));
+        assert!(!state.is_insert_column(2));
+
+        // Hiding keeps the cursor on its match column and maps positions to the file
+        state.cursor.col = 5;
+        state.mode = AppMode::Command("inserts".to_string());
+        state.execute_command();
+        assert_eq!(state.alignment.get(1).unwrap().as_str(), "MKLLV");
+        assert_eq!(state.column_map.as_deref(), Some([0, 2, 5, 6, 7].as_slice()));
+        assert_eq!(state.cursor.col, 2);
+        assert!(!state.is_insert_column(1));
+
+        assert_eq!(state.toggle_inserts(), CommandOutcome::None);
+        assert_eq!(state.alignment.get(1).unwrap().as_str(), "M-KaaLLV");
+        assert_eq!(state.column_map, None);
+        assert_eq!(state.cursor.col, 5);
+
+        // Nothing to hide without an RF line
+        let mut state = AppState::new(Alignment::new(vec![Sequence::new("a", "ACGT")]), "test".to_string());
+        state.toggle_inserts();
+        assert!(state.status_message.as_deref().unwrap().starts_with("No insert columns"));
+    }
+
+    #[test]
+    fn test_toggle_inserts_keeps_trim() {
+        let a3m = ">query\nMK-LV\n>hit1\nMKaaLLV\n>hit2\nMrK-LV\n";
+
+        // Hide, trim, show: the trimmed match column stays removed
+        let mut state = AppState::new(formats::a3m::parse_a3m_str(a3m).unwrap(), "test".to_string());
+        state.toggle_inserts();
+        state.mode = AppMode::Command("trim 0.5".to_string());
+        state.execute_command();
+        assert_eq!(state.alignment.get(1).unwrap().as_str(), "MKLV");
+        assert_eq!(state.column_map.as_deref(), Some([0, 2, 6, 7].as_slice()));
+        state.cursor.col = 2;
+        state.toggle_inserts();
+        assert_eq!(state.alignment.get(1).unwrap().as_str(), "M-KaaLV");
+        assert_eq!(state.column_map.as_deref(), Some([0, 1, 2, 3, 4, 6, 7].as_slice()));
+        assert_eq!(state.cursor.col, 5);
+        assert!(state.status_message.as_deref().unwrap().contains("1 trimmed columns still removed"));
+
+        // ... and hiding again gives the trimmed match columns
+        state.toggle_inserts();
+        assert_eq!(state.alignment.get(1).unwrap().as_str(), "MKLV");
+        assert_eq!(state.column_map.as_deref(), Some([0, 2, 6, 7].as_slice()));
+
+        // Trim, hide, show: the trimmed alignment comes back
+        let mut state = AppState::new(formats::a3m::parse_a3m_str(a3m).unwrap(), "test".to_string());
+        state.mode = AppMode::Command("trim 0.5".to_string());
+        state.execute_command();
+        let (trimmed, column_map) = (state.alignment.get(1).unwrap().as_str().to_string(), state.column_map.clone());
+        state.toggle_inserts();
+        state.toggle_inserts();
+        assert_eq!(state.alignment.get(1).unwrap().as_str(), trimmed);
+        assert_eq!(state.column_map, column_map);
+    }
+
+    #[test]
+    fn test_trim_command_composes_column_map() {
+        let alignment = Alignment::new(vec![
+            Sequence::new("s1", "ATG---AAACCC"),
+            Sequence::new("s2", "ATG---AAA-CC"),
+        ]);
+        let mut state = AppState::new(alignment, "test".to_string());
+        state.update_viewport_size(2, 12);
+
+        state.mode = AppMode::Command("trim allgaps".to_string());
+        state.execute_command();
+        assert_eq!(state.alignment.sequences[0].as_str(), "ATGAAACCC");
+        assert_eq!(state.column_map, Some(vec![0, 1, 2, 6, 7, 8, 9, 10, 11]));
+
+        // Codon-aware: the codon with a gap in s2 goes as a whole
+        state.mode = AppMode::Command("trim 0".to_string());
+        state.execute_command();
+        assert_eq!(state.alignment.sequences[1].as_str(), "ATGAAA");
+        assert_eq!(state.column_map, Some(vec![0, 1, 2, 6, 7, 8]));
+
+        state.mode = AppMode::Command("trim sometimes".to_string());
+        state.execute_command();
+        assert!(state.status_message.as_ref().unwrap().contains("invalid trim method"));
+    }
+
+    #[test]
+    fn test_degap_command_toggles() {
+        let alignment = Alignment::new(vec![
+            Sequence::new("s1", "AT-G.A!C"),
+            Sequence::new("s2", "ATGGAA-C"),
+        ]);
+        let degapped = alignment.degapped(true);
+        assert_eq!(degapped.sequences[0].as_str(), "ATGAC");
+        assert_eq!(alignment.degapped(false).sequences[0].as_str(), "ATGA!C");
+
+        let mut state = AppState::new(alignment, "test".to_string());
+        state.update_viewport_size(2, 12);
+        state.cursor.col = 5; // second 'A' of s1
+
+        state.mode = AppMode::Command("degap".to_string());
+        state.execute_command();
+        assert_eq!(state.alignment.sequences[0].as_str(), "ATGA!C");
+        assert_eq!(state.alignment.sequences[1].as_str(), "ATGGAAC");
+        assert_eq!(state.cursor.col, 3);
+        assert!(state.gapped.is_some());
+
+        state.mode = AppMode::Command("degap".to_string());
+        state.execute_command();
+        assert_eq!(state.alignment.sequences[0].as_str(), "AT-G.A!C");
+        assert_eq!(state.cursor.col, 5);
+        assert!(state.gapped.is_none());
+    }
+
+    #[test]
+    fn test_distance_panel_lists_closest_rows() {
+        let alignment = Alignment::new(vec![
+            Sequence::new("ref", "ACGTACGTAC"),
+            Sequence::new("far", "TTTTACGTAC"),
+            Sequence::new("near", "ACGTACGTAA"),
+            Sequence::new("hidden", "ACGTACGTAC"),
+        ]);
+        let mut state = AppState::new(alignment, "test".to_string());
+        state.update_viewport_size(4, 10);
+        state.rows.hide(&[3]);
+
+        state.mode = AppMode::Command("dist identity".to_string());
+        state.execute_command();
+        let panel = state.distance_panel.as_ref().expect("panel should open");
+        assert_eq!(panel.entries, vec![(2, Some(90.0)), (1, Some(70.0))]);
+
+        // Jump to the farthest sequence
+        state.distance_down();
+        state.distance_select();
+        assert!(state.distance_panel.is_none());
+        assert_eq!(state.rows.seq_index(state.cursor.row), Some(1));
+
+        state.mode = AppMode::Command("dist k2p nowhere".to_string());
+        state.execute_command();
+        assert!(state.distance_panel.is_none());
+        assert!(state.status_message.as_ref().unwrap().contains("invalid distance"));
+    }
+
+    #[test]
+    fn test_tree_panel_orders_and_collapses_clades() {
+        let alignment = Alignment::new(vec![
+            Sequence::new("a1", "ACGT"),
+            Sequence::new("b1", "ACGT"),
+            Sequence::new("a2", "ACGT"),
+            Sequence::new("other", "ACGT"),
+            Sequence::new("b 2", "ACGT"),
+        ]);
+        let mut state = AppState::new(alignment, "test".to_string());
+        state.update_viewport_size(5, 4);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree.nwk");
+        std::fs::write(&path, "((a1,a2),(b1,b_2),missing);").unwrap();
+
+        state.mode = AppMode::Command(format!("tree {}", path.display()));
+        state.execute_command();
+        assert_eq!(state.rows.rows(), &[0, 2, 1, 4, 3]);
+        assert_eq!(state.left_panels_width(), DEFAULT_NAME_WIDTH + TREE_PANEL_WIDTH);
+
+        // gc from b_2 selects (b1,b_2) and moves to its top row; zc collapses it
+        state.cursor.row = 3;
+        state.select_clade();
+        assert_eq!(state.cursor.row, 2);
+        state.collapse_clade();
+        assert_eq!(state.rows.rows(), &[0, 2, 1, 3]);
+        assert_eq!(state.tree_panel.as_ref().unwrap().collapsed_at(1).unwrap().hidden, vec![4]);
+        state.expand_clade();
+        assert_eq!(state.rows.len(), 5);
+
+        // A second gc on the same clade selects the root (all rows)
+        state.select_clade();
+        state.select_clade();
+        assert_eq!(state.tree_panel.as_ref().unwrap().selected, Some(state.tree_panel.as_ref().unwrap().tree.root()));
+
+        state.mode = AppMode::Command("tree".to_string());
+        state.execute_command();
+        assert!(state.tree_panel.is_none());
+        assert_eq!(state.left_panels_width(), DEFAULT_NAME_WIDTH);
+    }
+
+    #[test]
+    fn test_partition_jumps() {
+        let mut alignment = Alignment::new(vec![Sequence::new("a", "ACGTACGTACGT"), Sequence::new("b", "ACGTACGTACGT")]);
+        alignment.sets = Some(partition::parse_sets_block("charset one = 1-6; charset two = 7-9; charset three = 11-12;", 12, &[]).unwrap());
+        let mut state = AppState::new(alignment, "test".to_string());
+        state.update_viewport_size(5, 6);
+        assert!(state.status_message.as_deref().unwrap().starts_with("3 partitions"));
+        assert_eq!(state.partition_at(0), Some("one"));
+        assert_eq!(state.partition_at(9), None);
+
+        state.next_partition();
+        assert_eq!(state.cursor.col, 6);
+        state.next_partition();
+        assert_eq!(state.cursor.col, 10);
+        state.next_partition();
+        assert_eq!(state.status_message.as_deref(), Some("No next partition"));
+        state.cursor.col = 8;
+        state.previous_partition();
+        assert_eq!(state.cursor.col, 6);
+        state.previous_partition();
+        assert_eq!(state.cursor.col, 0);
+
+        // In the amino acid view, codon 3 covers columns 7-9
+        state.view_mode = ViewMode::AminoAcid;
+        assert_eq!(state.partition_at(2), Some("two"));
+
+        // :sets file replaces the partitions
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sets.txt");
+        std::fs::write(&path, "DNA, first = 1-3\nDNA, rest = 4-12\n").unwrap();
+        state.mode = AppMode::Command(format!("sets {}", path.display()));
+        state.execute_command();
+        assert_eq!(state.partitions.as_ref().unwrap().names, ["first", "rest"]);
+        assert_eq!(state.partition_at(1), Some("rest"));
+    }
+
+    #[test]
+    fn test_block_command_and_picker() {
+        let mut alignment = Alignment::new(vec![Sequence::new("a", "ACGT")]);
+        alignment.blocks = vec!["DNA".to_string(), "Morphology".to_string()];
+        let mut state = AppState::new(Alignment::new(vec![Sequence::new("a", "ACGT")]), "test".to_string());
+
+        state.mode = AppMode::Command("block".to_string());
+        assert_eq!(state.execute_command(), CommandOutcome::None);
+        assert!(state.block_picker.is_none());
+
+        state.set_alignment(alignment);
+        assert!(state.status_message.as_deref().unwrap().contains("Matrix 'DNA' (1 of 2)"));
+        state.mode = AppMode::Command("block morphology".to_string());
+        assert_eq!(state.execute_command(), CommandOutcome::LoadBlock("morphology".to_string()));
+        state.mode = AppMode::Command("block 3".to_string());
+        assert_eq!(state.execute_command(), CommandOutcome::None);
+
+        // The picker starts on the matrix shown; choosing it again loads nothing
+        state.mode = AppMode::Command("block".to_string());
+        state.execute_command();
+        assert_eq!(state.block_picker.as_ref().unwrap().selected, 0);
+        assert_eq!(state.block_picker_select(), None);
+        state.mode = AppMode::Command("block".to_string());
+        state.execute_command();
+        state.block_picker_down();
+        state.block_picker_down();
+        assert_eq!(state.block_picker_select(), Some("2".to_string()));
+        assert!(state.block_picker.is_none());
+    }
 }
@@ -0,0 +1,179 @@
+//! Taxon occupancy of a supermatrix (which taxa are present in which loci).
+//!
+//! Loci with too few taxa and taxa with too few loci are dropped iteratively:
+//! dropping a locus can leave a taxon below its threshold and vice versa, so
+//! both filters are repeated until nothing changes.
+
+use std::io::Write;
+use std::path::Path;
+
+/// Presence and length of each taxon in each locus.
+#[derive(Debug, Clone)]
+pub struct OccupancyMatrix {
+    pub taxa: Vec<String>,
+    pub loci: Vec<String>,
+    /// Non-gap residues, `[taxon][locus]` (`None` = absent)
+    lengths: Vec<Vec<Option<usize>>>,
+    pub kept_taxa: Vec<bool>,
+    pub kept_loci: Vec<bool>,
+    /// Number of filtering rounds run by `filter`
+    rounds: usize,
+}
+
+impl OccupancyMatrix {
+    /// Creates an empty matrix (every taxon absent from every locus).
+    pub fn new(taxa: Vec<String>, loci: Vec<String>) -> Self {
+        Self {
+            lengths: vec![vec![None; loci.len()]; taxa.len()],
+            kept_taxa: vec![true; taxa.len()],
+            kept_loci: vec![true; loci.len()],
+            taxa,
+            loci,
+            rounds: 0,
+        }
+    }
+
+    /// Records that a taxon is present in a locus with `length` non-gap residues.
+    pub fn set(&mut self, taxon: usize, locus: usize, length: usize) {
+        self.lengths[taxon][locus] = Some(length);
+    }
+
+    /// Returns true if a taxon is present in a locus.
+    pub fn is_present(&self, taxon: usize, locus: usize) -> bool {
+        self.lengths[taxon][locus].is_some()
+    }
+
+    /// Number of kept taxa present in a locus.
+    pub fn taxa_in_locus(&self, locus: usize) -> usize {
+        (0..self.taxa.len())
+            .filter(|&t| self.kept_taxa[t] && self.is_present(t, locus))
+            .count()
+    }
+
+    /// Number of kept loci in which a taxon is present.
+    pub fn loci_of_taxon(&self, taxon: usize) -> usize {
+        (0..self.loci.len())
+            .filter(|&l| self.kept_loci[l] && self.is_present(taxon, l))
+            .count()
+    }
+
+    /// Drops loci with fewer than `min_taxa` taxa and taxa present in fewer than
+    /// `min_loci` loci, repeating until stable. Returns the number of rounds.
+    pub fn filter(&mut self, min_taxa: usize, min_loci: usize) -> usize {
+        self.rounds = 0;
+        loop {
+            self.rounds += 1;
+            let mut changed = false;
+            for l in 0..self.loci.len() {
+                if self.kept_loci[l] && self.taxa_in_locus(l) < min_taxa {
+                    self.kept_loci[l] = false;
+                    changed = true;
+                }
+            }
+            for t in 0..self.taxa.len() {
+                if self.kept_taxa[t] && self.loci_of_taxon(t) < min_loci {
+                    self.kept_taxa[t] = false;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return self.rounds;
+            }
+        }
+    }
+
+    /// Names of dropped loci.
+    pub fn dropped_loci(&self) -> Vec<&str> {
+        self.loci.iter().zip(&self.kept_loci).filter(|(_, &k)| !k).map(|(n, _)| n.as_str()).collect()
+    }
+
+    /// Names of dropped taxa.
+    pub fn dropped_taxa(&self) -> Vec<&str> {
+        self.taxa.iter().zip(&self.kept_taxa).filter(|(_, &k)| !k).map(|(n, _)| n.as_str()).collect()
+    }
+
+    /// Writes the matrix as TSV: one row per taxon with its number of kept loci,
+    /// then its length in each locus (0 = absent). Dropped taxa and loci are listed
+    /// in the `#` header.
+    pub fn write_tsv(&self, path: &Path, header: &[String]) -> std::io::Result<()> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(file, "# SeqTUI - Taxon x locus occupancy matrix")?;
+        writeln!(file, "# Values: non-gap residues of each taxon in each locus (0 = absent)")?;
+        for line in header {
+            writeln!(file, "# {}", line)?;
+        }
+        let dropped_loci = self.dropped_loci();
+        let dropped_taxa = self.dropped_taxa();
+        writeln!(file, "# Dropped loci ({}): {}", dropped_loci.len(), dropped_loci.join(", "))?;
+        writeln!(file, "# Dropped taxa ({}): {}", dropped_taxa.len(), dropped_taxa.join(", "))?;
+        write!(file, "taxon\tloci")?;
+        for locus in &self.loci {
+            write!(file, "\t{}", locus)?;
+        }
+        writeln!(file)?;
+        for (t, taxon) in self.taxa.iter().enumerate() {
+            write!(file, "{}\t{}", taxon, self.loci_of_taxon(t))?;
+            for length in &self.lengths[t] {
+                write!(file, "\t{}", length.unwrap_or(0))?;
+            }
+            writeln!(file)?;
+        }
+        file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(n: &[&str]) -> Vec<String> {
+        n.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Taxa A-D, loci L1-L3. D is only in L3, which only has C and D.
+    fn matrix() -> OccupancyMatrix {
+        let mut m = OccupancyMatrix::new(names(&["A", "B", "C", "D"]), names(&["L1", "L2", "L3"]));
+        for (t, l) in [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1), (2, 2), (3, 2)] {
+            m.set(t, l, 10);
+        }
+        m
+    }
+
+    #[test]
+    fn test_filter_is_iterative() {
+        let mut m = matrix();
+        // L3 has 2 taxa < 3: dropped; D is then in 0 loci < 1: dropped
+        let rounds = m.filter(3, 1);
+        assert_eq!(m.dropped_loci(), vec!["L3"]);
+        assert_eq!(m.dropped_taxa(), vec!["D"]);
+        assert_eq!(rounds, 2);
+
+        // Taxa filter first removes D (1 locus), which then empties L3 below 2 taxa
+        let mut m = matrix();
+        m.filter(2, 2);
+        assert_eq!(m.dropped_taxa(), vec!["D"]);
+        assert_eq!(m.dropped_loci(), vec!["L3"]);
+        assert_eq!(m.loci_of_taxon(2), 2);
+
+        let mut m = matrix();
+        assert_eq!(m.filter(0, 0), 1);
+        assert!(m.dropped_loci().is_empty() && m.dropped_taxa().is_empty());
+    }
+
+    #[test]
+    fn test_write_tsv() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("occupancy.tsv");
+        let mut m = matrix();
+        m.set(0, 0, 42);
+        m.filter(3, 1);
+        m.write_tsv(&path, &["Filters: test".to_string()]).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        let rows: Vec<&str> = content.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(rows[0], "taxon\tloci\tL1\tL2\tL3");
+        assert_eq!(rows[1], "A\t2\t42\t10\t0");
+        assert_eq!(rows[4], "D\t0\t0\t0\t10");
+        assert!(content.contains("# Filters: test"));
+        assert!(content.contains("# Dropped loci (1): L3"));
+    }
+}
@@ -0,0 +1,727 @@
+//! Partition files for supermatrices (NEXUS, RAxML, IQ-TREE).
+//!
+//! Each input file of a concatenation is a partition. Nucleotide partitions can
+//! be split by codon position (`LOC_1_pos1 = 1-300\3;`). Positions are derived
+//! from the original columns, so they stay correct after trimming removed
+//! columns: a subset is then written as several ranges.
+//!
+//! Formats:
+//! - `nexus`: `charset` blocks only
+//! - `raxml`: one line per partition, `MODEL, name = ranges` (data type `DNA`
+//!   for nucleotides unless a model is given)
+//! - `iqtree`: NEXUS `charset` blocks plus a `charpartition` with one model per partition
+//!
+//! `parse_partition_file` reads NEXUS `charset` and RAxML files back (for `--split`
+//! and `:sets file`), and `parse_sets_block` reads the SETS blocks of NEXUS
+//! alignments (`charset`, `charpartition` and `taxset`).
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Default RAxML model (data type) for nucleotide partitions.
+const RAXML_DNA: &str = "DNA";
+/// Default IQ-TREE model for nucleotide partitions.
+const DEFAULT_DNA_MODEL: &str = "GTR+G";
+/// Default model for amino acid partitions.
+const DEFAULT_AA_MODEL: &str = "LG+G";
+
+/// A partition of the supermatrix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partition {
+    pub name: String,
+    /// First column (1-based, inclusive)
+    pub start: usize,
+    /// Last column (1-based, inclusive)
+    pub end: usize,
+    /// Nucleotide data (amino acids otherwise)
+    pub nucleotide: bool,
+}
+
+impl Partition {
+    pub fn new(name: &str, start: usize, end: usize, nucleotide: bool) -> Self {
+        // Spaces are not allowed in partition names
+        Self { name: name.replace(' ', "_"), start, end, nucleotide }
+    }
+}
+
+/// Partition file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PartitionFormat {
+    #[default]
+    Nexus,
+    Raxml,
+    Iqtree,
+}
+
+impl std::fmt::Display for PartitionFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PartitionFormat::Nexus => write!(f, "NEXUS"),
+            PartitionFormat::Raxml => write!(f, "RAxML"),
+            PartitionFormat::Iqtree => write!(f, "IQ-TREE"),
+        }
+    }
+}
+
+/// Models per partition: defaults (for all partitions, or per data type with
+/// `DNA=` and `AA=`) and per-name overrides.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PartitionModels {
+    default: Option<String>,
+    dna: Option<String>,
+    aa: Option<String>,
+    by_name: HashMap<String, String>,
+}
+
+impl FromStr for PartitionModels {
+    type Err = String;
+
+    /// Parses a comma-separated list of `MODEL` (default for all partitions),
+    /// `DNA=MODEL` and `AA=MODEL` (defaults by data type) and `NAME=MODEL` items,
+    /// e.g. `DNA=GTR+G,AA=LG+G,LOC_2=WAG`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut models = PartitionModels::default();
+        for item in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            match item.split_once('=') {
+                Some((name, model)) if !name.trim().is_empty() && !model.trim().is_empty() => {
+                    let (name, model) = (name.trim(), model.trim().to_string());
+                    let slot = match name {
+                        "DNA" => &mut models.dna,
+                        "AA" => &mut models.aa,
+                        _ => {
+                            models.by_name.insert(name.to_string(), model);
+                            continue;
+                        }
+                    };
+                    if slot.is_some() {
+                        return Err(format!("several {} partition models (got '{}')", name, item));
+                    }
+                    *slot = Some(model);
+                }
+                Some(_) => return Err(format!("invalid partition model '{}' (expected MODEL or NAME=MODEL)", item)),
+                None => {
+                    if models.default.is_some() {
+                        return Err(format!("several default partition models (got '{}')", item));
+                    }
+                    models.default = Some(item.to_string());
+                }
+            }
+        }
+        Ok(models)
+    }
+}
+
+impl PartitionModels {
+    /// Model of a (sub)partition: by its own name, then by the name of its
+    /// locus (for codon positions), then the default for its data type, then
+    /// the default for all partitions.
+    fn model(&self, charset: &Charset, format: PartitionFormat) -> &str {
+        if let Some(model) = self.by_name.get(&charset.name).or_else(|| self.by_name.get(&charset.locus)) {
+            return model;
+        }
+        let by_type = if charset.nucleotide { &self.dna } else { &self.aa };
+        match (by_type.as_ref().or(self.default.as_ref()), charset.nucleotide, format) {
+            (Some(model), _, _) => model,
+            (None, true, PartitionFormat::Raxml) => RAXML_DNA,
+            (None, true, _) => DEFAULT_DNA_MODEL,
+            (None, false, _) => DEFAULT_AA_MODEL,
+        }
+    }
+
+    /// Checks that a default `MODEL` does not apply to both nucleotide and amino
+    /// acid partitions (a model fits one data type only).
+    pub fn check_types(&self, partitions: &[Partition]) -> Result<(), String> {
+        let Some(default) = &self.default else {
+            return Ok(());
+        };
+        let uses_default = |nucleotide: bool| {
+            (if nucleotide { &self.dna } else { &self.aa }).is_none()
+                && partitions.iter().any(|p| p.nucleotide == nucleotide && !self.by_name.contains_key(&p.name))
+        };
+        if uses_default(true) && uses_default(false) {
+            return Err(format!(
+                "partition model '{}' would apply to both nucleotide and amino acid partitions \
+                 (use DNA=MODEL and AA=MODEL)",
+                default
+            ));
+        }
+        Ok(())
+    }
+
+    /// Names given with `NAME=MODEL` that match no partition or codon subset.
+    pub fn unknown_names<'a>(&'a self, partitions: &[Partition]) -> Vec<&'a str> {
+        let mut names: Vec<&str> = self
+            .by_name
+            .keys()
+            .filter(|name| {
+                !partitions.iter().any(|p| {
+                    p.name == **name || (p.nucleotide && (1..=3).any(|k| format!("{}_pos{}", p.name, k) == **name))
+                })
+            })
+            .map(String::as_str)
+            .collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+/// Options of a partition file.
+#[derive(Debug, Clone, Default)]
+pub struct PartitionScheme {
+    pub format: PartitionFormat,
+    pub models: PartitionModels,
+    /// Split nucleotide partitions by codon position
+    pub split_codons: bool,
+}
+
+/// A named set of columns, as `(start, end, step)` ranges (1-based, inclusive).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Charset {
+    name: String,
+    /// Name of the partition this set comes from
+    locus: String,
+    nucleotide: bool,
+    ranges: Vec<(usize, usize, usize)>,
+}
+
+/// Codon position (0, 1 or 2) of each column of a matrix of `len` columns, with
+/// codons starting `frame` columns after the start of each partition.
+/// Columns outside nucleotide partitions get position 0.
+pub fn codon_positions(partitions: &[Partition], frame: usize, len: usize) -> Vec<u8> {
+    let mut positions = vec![0; len];
+    for p in partitions.iter().filter(|p| p.nucleotide) {
+        for col in p.start..=p.end.min(len) {
+            // 3 + offset - frame avoids underflow for the frame offset columns
+            positions[col - 1] = ((col - p.start + 3 - frame % 3) % 3) as u8;
+        }
+    }
+    positions
+}
+
+/// Compresses sorted columns into `(start, end, step)` ranges of evenly spaced columns.
+fn compress(columns: &[usize], step: usize) -> Vec<(usize, usize, usize)> {
+    let mut ranges: Vec<(usize, usize, usize)> = Vec::new();
+    for &col in columns {
+        match ranges.last_mut() {
+            Some((_, end, _)) if col == *end + step => *end = col,
+            _ => ranges.push((col, col, step)),
+        }
+    }
+    ranges
+}
+
+fn charsets(partitions: &[Partition], codon_positions: Option<&[u8]>) -> Vec<Charset> {
+    let mut sets = Vec::new();
+    for p in partitions {
+        match codon_positions {
+            Some(positions) if p.nucleotide => {
+                for k in 0..3u8 {
+                    let columns: Vec<usize> =
+                        (p.start..=p.end).filter(|&col| positions.get(col - 1) == Some(&k)).collect();
+                    if columns.is_empty() {
+                        continue;
+                    }
+                    sets.push(Charset {
+                        name: format!("{}_pos{}", p.name, k + 1),
+                        locus: p.name.clone(),
+                        nucleotide: true,
+                        ranges: compress(&columns, 3),
+                    });
+                }
+            }
+            _ => sets.push(Charset {
+                name: p.name.clone(),
+                locus: p.name.clone(),
+                nucleotide: p.nucleotide,
+                ranges: vec![(p.start, p.end, 1)],
+            }),
+        }
+    }
+    sets
+}
+
+fn format_range(&(start, end, step): &(usize, usize, usize)) -> String {
+    if start == end {
+        start.to_string()
+    } else if step == 1 {
+        format!("{}-{}", start, end)
+    } else {
+        format!("{}-{}\\{}", start, end, step)
+    }
+}
+
+/// Writes a partition file. Returns the number of partitions written
+/// (codon subsets count separately).
+///
+/// `codon_positions` gives the codon position of each column (see `codon_positions`);
+/// it is used when the scheme splits codons.
+pub fn write_partitions(
+    out: &mut impl Write,
+    partitions: &[Partition],
+    scheme: &PartitionScheme,
+    codon_positions: Option<&[u8]>,
+) -> io::Result<usize> {
+    let sets = charsets(partitions, codon_positions.filter(|_| scheme.split_codons));
+    match scheme.format {
+        PartitionFormat::Raxml => {
+            for set in &sets {
+                let ranges: Vec<String> = set.ranges.iter().map(format_range).collect();
+                writeln!(out, "{}, {} = {}", scheme.models.model(set, scheme.format), set.name, ranges.join(", "))?;
+            }
+        }
+        PartitionFormat::Nexus | PartitionFormat::Iqtree => {
+            writeln!(out, "#nexus")?;
+            writeln!(out, "begin sets;")?;
+            for set in &sets {
+                let ranges: Vec<String> = set.ranges.iter().map(format_range).collect();
+                writeln!(out, "    charset {} = {};", set.name, ranges.join(" "))?;
+            }
+            if scheme.format == PartitionFormat::Iqtree {
+                let models: Vec<String> = sets
+                    .iter()
+                    .map(|set| format!("{}:{}", scheme.models.model(set, scheme.format), set.name))
+                    .collect();
+                writeln!(out, "    charpartition models = {};", models.join(", "))?;
+            }
+            writeln!(out, "end;")?;
+        }
+    }
+    Ok(sets.len())
+}
+
+/// A named set of columns read from a partition file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSet {
+    pub name: String,
+    /// `(start, end, step)` ranges, 1-based and inclusive
+    pub ranges: Vec<(usize, usize, usize)>,
+}
+
+impl ColumnSet {
+    /// Columns of the set (0-based), sorted and without duplicates.
+    pub fn columns(&self) -> Vec<usize> {
+        let mut columns: Vec<usize> = self
+            .ranges
+            .iter()
+            .flat_map(|&(start, end, step)| (start..=end).step_by(step).map(|c| c - 1))
+            .collect();
+        columns.sort_unstable();
+        columns.dedup();
+        columns
+    }
+}
+
+/// Parses a range item: `12`, `1-300` or `1-300\3`.
+fn parse_range(item: &str) -> Result<(usize, usize, usize), String> {
+    let (range, step) = match item.split_once('\\') {
+        Some((range, step)) => (range, step.trim().parse::<usize>().map_err(|_| format!("invalid step in '{}'", item))?),
+        None => (item, 1),
+    };
+    let (start, end) = range.split_once('-').unwrap_or((range, range));
+    let parse = |s: &str| s.trim().parse::<usize>().map_err(|_| format!("invalid range '{}'", item));
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start == 0 || end < start || step == 0 {
+        return Err(format!("invalid range '{}' (1-based, start <= end)", item));
+    }
+    Ok((start, end, step))
+}
+
+/// Parses `name = ranges`, ranges separated by spaces or commas.
+fn parse_column_set(definition: &str) -> Result<ColumnSet, String> {
+    let (name, ranges) = definition
+        .split_once('=')
+        .ok_or_else(|| format!("expected 'name = ranges' (got '{}')", definition))?;
+    let name = name.trim().trim_matches(|c| c == '\'' || c == '"').to_string();
+    if name.is_empty() {
+        return Err(format!("missing partition name in '{}'", definition));
+    }
+    let ranges = ranges
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(parse_range)
+        .collect::<Result<Vec<_>, _>>()?;
+    if ranges.is_empty() {
+        return Err(format!("partition '{}' has no range", name));
+    }
+    Ok(ColumnSet { name, ranges })
+}
+
+/// Sets read from NEXUS SETS blocks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NexusSets {
+    /// `charset` statements
+    pub charsets: Vec<ColumnSet>,
+    /// `charpartition` statements: name and subsets
+    pub charpartitions: Vec<(String, Vec<ColumnSet>)>,
+    /// `taxset` statements: name and taxon names
+    pub taxsets: Vec<(String, Vec<String>)>,
+}
+
+impl NexusSets {
+    /// Returns true if no set was read.
+    pub fn is_empty(&self) -> bool {
+        self.charsets.is_empty() && self.charpartitions.is_empty() && self.taxsets.is_empty()
+    }
+
+    /// Column sets to show as partitions: the charsets, or else the subsets of
+    /// the first charpartition.
+    pub fn partitions(&self) -> &[ColumnSet] {
+        match (&self.charsets[..], self.charpartitions.first()) {
+            ([], Some((_, subsets))) => subsets,
+            (charsets, _) => charsets,
+        }
+    }
+}
+
+/// Splits a set definition into its name and items (`name = items`), dropping
+/// the `*` (default) mark and a `(CHARACTERS = ...)` style qualifier.
+fn split_definition(definition: &str) -> Result<(String, String), String> {
+    let mut cleaned = definition.to_string();
+    while let (Some(open), Some(close)) = (cleaned.find('('), cleaned.find(')')) {
+        if close < open {
+            break;
+        }
+        cleaned.replace_range(open..=close, " ");
+    }
+    let (name, items) = cleaned
+        .split_once('=')
+        .ok_or_else(|| format!("expected 'name = items' (got '{}')", definition))?;
+    let name = name
+        .trim()
+        .trim_start_matches('*')
+        .trim()
+        .trim_matches(|c| c == '\'' || c == '"');
+    if name.is_empty() {
+        return Err(format!("missing name in '{}'", definition));
+    }
+    Ok((name.to_string(), items.to_string()))
+}
+
+/// Replaces a `.` range end (`7-.`, `3-.\3`, or `.` alone) by the last column.
+fn expand_last_column(item: &str, nchar: usize) -> String {
+    let (range, step) = item.split_at(item.find('\\').unwrap_or(item.len()));
+    match range.strip_suffix('.') {
+        Some(start) if start.is_empty() || start.ends_with('-') => format!("{}{}{}", start, nchar, step),
+        _ => item.to_string(),
+    }
+}
+
+/// Resolves column items: ranges (`.` is the last column) or charset names.
+fn column_items(
+    items: &str,
+    nchar: usize,
+    charsets: &[ColumnSet],
+) -> Result<Vec<(usize, usize, usize)>, String> {
+    let mut ranges = Vec::new();
+    for item in items.split_whitespace() {
+        let name = item.trim_matches(|c| c == '\'' || c == '"');
+        if let Some(set) = charsets
+            .iter()
+            .find(|set| set.name.eq_ignore_ascii_case(name))
+        {
+            ranges.extend(&set.ranges);
+        } else {
+            let range = parse_range(&expand_last_column(item, nchar))?;
+            if range.1 > nchar {
+                return Err(format!("range '{}' extends beyond NCHAR={}", item, nchar));
+            }
+            ranges.push(range);
+        }
+    }
+    Ok(ranges)
+}
+
+/// Parses the statements of NEXUS SETS blocks (other statements are ignored).
+/// `nchar` stands for `.` in ranges, and `taxa` resolves taxon numbers in taxsets.
+pub fn parse_sets_block(content: &str, nchar: usize, taxa: &[&str]) -> Result<NexusSets, String> {
+    let mut sets = NexusSets::default();
+    for statement in content.split(';').map(str::trim) {
+        let Some(keyword) = statement.split_whitespace().next() else {
+            continue;
+        };
+        let definition = statement[keyword.len()..].trim();
+        match keyword.to_ascii_lowercase().as_str() {
+            "charset" => {
+                let (name, items) = split_definition(definition)?;
+                let ranges = column_items(&items, nchar, &sets.charsets)?;
+                if ranges.is_empty() {
+                    return Err(format!("charset '{}' has no range", name));
+                }
+                sets.charsets.push(ColumnSet { name, ranges });
+            }
+            "charpartition" => {
+                let (name, items) = split_definition(definition)?;
+                let subsets = items
+                    .split(',')
+                    .map(|subset| {
+                        let (subset_name, items) = subset.split_once(':').ok_or_else(|| {
+                            format!("expected 'name: items' in charpartition '{}'", name)
+                        })?;
+                        let name = subset_name
+                            .trim()
+                            .trim_matches(|c| c == '\'' || c == '"')
+                            .to_string();
+                        Ok(ColumnSet {
+                            name,
+                            ranges: column_items(items, nchar, &sets.charsets)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                sets.charpartitions.push((name, subsets));
+            }
+            "taxset" => {
+                let (name, items) = split_definition(definition)?;
+                let mut names = Vec::new();
+                for item in taxon_items(&items) {
+                    match parse_range(&item) {
+                        Ok((start, end, step)) if end <= taxa.len() => {
+                            names.extend(
+                                (start..=end).step_by(step).map(|i| taxa[i - 1].to_string()),
+                            );
+                        }
+                        Ok(_) if !taxa.contains(&item.as_str()) => {
+                            return Err(format!("taxset '{}': no taxon {}", name, item));
+                        }
+                        _ => names.push(item),
+                    }
+                }
+                sets.taxsets.push((name, names));
+            }
+            _ => {}
+        }
+    }
+    Ok(sets)
+}
+
+/// Splits taxset items on whitespace, keeping quoted names together.
+fn taxon_items(items: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = items.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' {
+            chars.next();
+            let mut token = String::new();
+            while let Some(c) = chars.next() {
+                if c == '\'' {
+                    if chars.peek() == Some(&'\'') {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                token.push(c);
+            }
+            tokens.push(token);
+        } else {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek().filter(|c| !c.is_whitespace()) {
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+/// Parses a partition file: NEXUS `charset` statements (other statements such as
+/// IQ-TREE `charpartition` are ignored) or RAxML lines (`MODEL, name = ranges`).
+pub fn parse_partition_file(content: &str) -> Result<Vec<ColumnSet>, String> {
+    let is_nexus = content.lines().any(|l| l.trim().to_lowercase().starts_with("#nexus"))
+        || content.to_lowercase().contains("charset ");
+    let sets = if is_nexus {
+        content
+            .split(';')
+            .map(str::trim)
+            .filter_map(|statement| {
+                let keyword = statement.split_whitespace().next()?;
+                keyword.eq_ignore_ascii_case("charset").then(|| statement[keyword.len()..].trim())
+            })
+            .map(parse_column_set)
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|line| {
+                let (_, definition) = line
+                    .split_once(',')
+                    .filter(|(model, _)| !model.contains('='))
+                    .ok_or_else(|| format!("expected 'MODEL, name = ranges' (got '{}')", line))?;
+                parse_column_set(definition)
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    if sets.is_empty() {
+        return Err("no partition found (expected NEXUS charsets or RAxML lines)".to_string());
+    }
+    Ok(sets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(partitions: &[Partition], scheme: &PartitionScheme, positions: Option<&[u8]>) -> String {
+        let mut out = Vec::new();
+        write_partitions(&mut out, partitions, scheme, positions).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn loci() -> Vec<Partition> {
+        vec![Partition::new("LOC 1", 1, 9, true), Partition::new("LOC_2", 10, 15, false)]
+    }
+
+    #[test]
+    fn test_nexus_and_raxml() {
+        let nexus = write(&loci(), &PartitionScheme::default(), None);
+        assert_eq!(nexus, "#nexus\nbegin sets;\n    charset LOC_1 = 1-9;\n    charset LOC_2 = 10-15;\nend;\n");
+
+        let scheme = PartitionScheme { format: PartitionFormat::Raxml, ..Default::default() };
+        assert_eq!(write(&loci(), &scheme, None), "DNA, LOC_1 = 1-9\nLG+G, LOC_2 = 10-15\n");
+    }
+
+    #[test]
+    fn test_codon_positions() {
+        let partitions = loci();
+        let positions = codon_positions(&partitions, 0, 15);
+        assert_eq!(&positions[..9], &[0, 1, 2, 0, 1, 2, 0, 1, 2]);
+        // Frame 2: the first column is the third position of a previous codon
+        assert_eq!(&codon_positions(&partitions, 1, 15)[..4], &[2, 0, 1, 2]);
+
+        let scheme = PartitionScheme {
+            format: PartitionFormat::Iqtree,
+            models: "LOC_2=WAG,LOC_1_pos3=GTR+I+G".parse().unwrap(),
+            split_codons: true,
+        };
+        assert_eq!(
+            write(&partitions, &scheme, Some(&positions)),
+            "#nexus\nbegin sets;\n    charset LOC_1_pos1 = 1-7\\3;\n    charset LOC_1_pos2 = 2-8\\3;\n    \
+             charset LOC_1_pos3 = 3-9\\3;\n    charset LOC_2 = 10-15;\n    \
+             charpartition models = GTR+G:LOC_1_pos1, GTR+G:LOC_1_pos2, GTR+I+G:LOC_1_pos3, WAG:LOC_2;\nend;\n"
+        );
+    }
+
+    #[test]
+    fn test_codon_positions_after_trimming() {
+        // Columns 4-6 of a 9-column locus were removed, then column 9 (a third position)
+        let kept = [0, 1, 2, 6, 7];
+        let original = codon_positions(&[Partition::new("L", 1, 9, true)], 0, 9);
+        let positions: Vec<u8> = kept.iter().map(|&c| original[c]).collect();
+        let scheme = PartitionScheme {
+            format: PartitionFormat::Raxml,
+            split_codons: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            write(&[Partition::new("L", 1, 5, true)], &scheme, Some(&positions)),
+            "DNA, L_pos1 = 1-4\\3\nDNA, L_pos2 = 2-5\\3\nDNA, L_pos3 = 3\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_partition_files() {
+        let nexus = "#nexus\nbegin sets;\n    charset LOC_1_pos1 = 1-7\\3;\n    charset 'LOC 2' = 10-12 15;\n    \
+                     charpartition models = GTR+G:LOC_1_pos1, WAG:LOC_2;\nend;\n";
+        let sets = parse_partition_file(nexus).unwrap();
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets[0].columns(), vec![0, 3, 6]);
+        assert_eq!(sets[1].name, "LOC 2");
+        assert_eq!(sets[1].columns(), vec![9, 10, 11, 14]);
+
+        let raxml = "DNA, L_pos1 = 1-4\\3, 8\nLG+G, L2 = 10-12\n";
+        let sets = parse_partition_file(raxml).unwrap();
+        assert_eq!(sets[0].ranges, vec![(1, 4, 3), (8, 8, 1)]);
+        assert_eq!(sets[0].columns(), vec![0, 3, 7]);
+        assert_eq!(sets[1].name, "L2");
+
+        // Written files can be read back
+        let mut out = Vec::new();
+        let scheme = PartitionScheme { format: PartitionFormat::Iqtree, split_codons: true, ..Default::default() };
+        let positions = codon_positions(&loci(), 0, 15);
+        write_partitions(&mut out, &loci(), &scheme, Some(&positions)).unwrap();
+        let sets = parse_partition_file(&String::from_utf8(out).unwrap()).unwrap();
+        assert_eq!(sets.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), ["LOC_1_pos1", "LOC_1_pos2", "LOC_1_pos3", "LOC_2"]);
+
+        assert!(parse_partition_file("DNA, L = 5-2\n").is_err());
+        assert!(parse_partition_file("L = 1-5\n").is_err());
+        assert!(parse_partition_file("").is_err());
+    }
+
+    #[test]
+    fn test_parse_sets_block() {
+        let content = "charset gene1 = 1-6;\ncharset gene2 (CHARACTERS = dna) = 7-.;\n\
+                       charset pos3 = 3-.\\3;\ncharpartition * genes = first: gene1, second: 7-8 10;\n\
+                       taxset ingroup = 1-2 'taxon c';\noptions gapmode = missing;\n";
+        let sets = parse_sets_block(content, 10, &["a", "b", "taxon c"]).unwrap();
+        assert_eq!(sets.charsets.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), ["gene1", "gene2", "pos3"]);
+        assert_eq!(sets.charsets[1].columns(), vec![6, 7, 8, 9]);
+        assert_eq!(sets.charpartitions[0].0, "genes");
+        assert_eq!(sets.charpartitions[0].1[1].columns(), vec![6, 7, 9]);
+        assert_eq!(sets.taxsets, vec![("ingroup".to_string(), vec!["a".to_string(), "b".to_string(), "taxon c".to_string()])]);
+        assert_eq!(sets.partitions().len(), 3);
+
+        // Without charsets, the first charpartition gives the partitions
+        let sets = parse_sets_block("charpartition p = one: 1-5, two: 6-.;", 10, &[]).unwrap();
+        assert_eq!(sets.partitions()[1].columns(), vec![5, 6, 7, 8, 9]);
+
+        // Only a standalone `.` end stands for NCHAR, not dots in names or numbers
+        let sets = parse_sets_block("charset last = .;\ncharset pos3 = 3-.\\3;", 10, &[]).unwrap();
+        assert_eq!(sets.charsets[0].ranges, vec![(10, 10, 1)]);
+        assert_eq!(sets.charsets[1].ranges, vec![(3, 10, 3)]);
+        let sets = parse_sets_block("charset gene.1 = 1-4;\ncharset rest = gene.1 .;", 10, &[]).unwrap();
+        assert_eq!(sets.charsets[1].columns(), vec![0, 1, 2, 3, 9]);
+        let err = parse_sets_block("charset rest = gene.2 5-.;", 10, &[]).unwrap_err();
+        assert!(err.contains("'gene.2'"), "{}", err);
+        assert_eq!(expand_last_column("10.5", 12), "10.5");
+        let err = parse_sets_block("charset odd = 1-10.5;", 12, &[]).unwrap_err();
+        assert!(err.contains("invalid range '1-10.5'"), "{}", err);
+        assert!(parse_sets_block("charset broken 1-5;", 10, &[]).is_err());
+        assert!(parse_sets_block("charset long = 5-11;", 10, &[]).is_err());
+        assert!(parse_sets_block("taxset t = 4;", 10, &["a"]).is_err());
+        assert!(parse_sets_block("", 10, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_models_parsing() {
+        let models: PartitionModels = "LG+G, LOC_2=WAG".parse().unwrap();
+        assert_eq!(models.default.as_deref(), Some("LG+G"));
+        assert_eq!(models.unknown_names(&loci()), Vec::<&str>::new());
+        let models: PartitionModels = "LOC_9=WAG,LOC_1_pos2=HKY".parse().unwrap();
+        assert_eq!(models.unknown_names(&loci()), vec!["LOC_9"]);
+        assert!("LG,WAG".parse::<PartitionModels>().is_err());
+        assert!("=WAG".parse::<PartitionModels>().is_err());
+        assert!("DNA=GTR,DNA=HKY".parse::<PartitionModels>().is_err());
+    }
+
+    #[test]
+    fn test_models_by_data_type() {
+        let mixed = vec![Partition::new("nt", 1, 6, true), Partition::new("aa", 7, 10, false)];
+        let scheme = |models: &str| PartitionScheme {
+            format: PartitionFormat::Iqtree,
+            models: models.parse().unwrap(),
+            split_codons: false,
+        };
+        let write = |scheme: &PartitionScheme| {
+            let mut out = Vec::new();
+            write_partitions(&mut out, &mixed, scheme, None).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        // Per-type defaults, with the default for all partitions as fallback
+        let out = write(&scheme("DNA=HKY+G,AA=WAG"));
+        assert!(out.contains("charpartition models = HKY+G:nt, WAG:aa;"), "{}", out);
+        let out = write(&scheme("JTT,DNA=HKY"));
+        assert!(out.contains("charpartition models = HKY:nt, JTT:aa;"), "{}", out);
+        assert!(scheme("JTT,DNA=HKY").models.check_types(&mixed).is_ok());
+
+        // A bare default for mixed data is ambiguous, unless names cover one type
+        assert!(scheme("LG+G").models.check_types(&mixed).is_err());
+        assert!(scheme("LG+G,nt=GTR").models.check_types(&mixed).is_ok());
+        assert!(scheme("LG+G").models.check_types(&mixed[1..]).is_ok());
+    }
+}
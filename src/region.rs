@@ -0,0 +1,161 @@
+//! Column regions and sliding windows (`--region`, `--windows`).
+//!
+//! Coordinates are 1-based and inclusive. They are alignment columns by default,
+//! or ungapped positions of a reference sequence: a reference region then spans
+//! from the column of its first residue to the column of its last residue.
+
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::model::{is_gap, Sequence};
+
+/// A coordinate range, 1-based and inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    /// Parses `START-END`, e.g. `1001-2000`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid region '{}' (expected START-END, 1-based)", s);
+        let (start, end) = s.trim().split_once('-').ok_or_else(invalid)?;
+        let start: usize = start.trim().parse().map_err(|_| invalid())?;
+        let end: usize = end.trim().parse().map_err(|_| invalid())?;
+        if start == 0 || end < start {
+            return Err(invalid());
+        }
+        Ok(Region { start, end })
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// Sliding windows: `size` positions, starting every `step` positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Windows {
+    pub size: usize,
+    pub step: usize,
+}
+
+impl FromStr for Windows {
+    type Err = String;
+
+    /// Parses `SIZE:STEP`, or `SIZE` for adjacent windows.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid windows '{}' (expected SIZE:STEP, e.g. 500:100)", s);
+        let (size, step) = s.trim().split_once(':').unwrap_or((s.trim(), s.trim()));
+        let size: usize = size.trim().parse().map_err(|_| invalid())?;
+        let step: usize = step.trim().parse().map_err(|_| invalid())?;
+        if size == 0 || step == 0 {
+            return Err(invalid());
+        }
+        Ok(Windows { size, step })
+    }
+}
+
+impl Windows {
+    /// Full windows within `span` (windows running past its end are not emitted).
+    pub fn within(&self, span: Region) -> Vec<Region> {
+        (span.start..=span.end)
+            .step_by(self.step)
+            .map(|start| Region { start, end: start + self.size - 1 })
+            .take_while(|w| w.end <= span.end)
+            .collect()
+    }
+}
+
+/// Coordinate system of regions and windows.
+#[derive(Debug, Clone, Copy)]
+pub enum Coordinates<'a> {
+    /// Alignment columns, for an alignment of this length
+    Aligned(usize),
+    /// Ungapped positions of a reference sequence
+    Reference(&'a Sequence),
+}
+
+impl Coordinates<'_> {
+    /// Number of positions (alignment length, or ungapped reference length).
+    pub fn len(&self) -> usize {
+        match self {
+            Coordinates::Aligned(len) => *len,
+            Coordinates::Reference(seq) => seq.as_bytes().iter().filter(|&&b| !is_gap(b)).count(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The whole coordinate range.
+    pub fn span(&self) -> Option<Region> {
+        (!self.is_empty()).then(|| Region { start: 1, end: self.len() })
+    }
+
+    /// Alignment columns (0-based, half-open) covered by a region.
+    pub fn columns(&self, region: Region) -> Result<Range<usize>, String> {
+        let len = self.len();
+        if region.end > len {
+            let unit = match self {
+                Coordinates::Aligned(_) => "the alignment has",
+                Coordinates::Reference(_) => "the reference has",
+            };
+            return Err(format!("region {} is out of range ({} {} positions)", region, unit, len));
+        }
+        match self {
+            Coordinates::Aligned(_) => Ok(region.start - 1..region.end),
+            Coordinates::Reference(seq) => {
+                let residue_columns: Vec<usize> = seq
+                    .as_bytes()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &b)| !is_gap(b))
+                    .map(|(col, _)| col)
+                    .collect();
+                Ok(residue_columns[region.start - 1]..residue_columns[region.end - 1] + 1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsing() {
+        assert_eq!("1001-2000".parse::<Region>(), Ok(Region { start: 1001, end: 2000 }));
+        assert!("0-10".parse::<Region>().is_err());
+        assert!("10-5".parse::<Region>().is_err());
+        assert!("10".parse::<Region>().is_err());
+        assert_eq!("500:100".parse::<Windows>(), Ok(Windows { size: 500, step: 100 }));
+        assert_eq!("500".parse::<Windows>(), Ok(Windows { size: 500, step: 500 }));
+        assert!("500:0".parse::<Windows>().is_err());
+    }
+
+    #[test]
+    fn test_windows() {
+        let windows = Windows { size: 4, step: 3 }.within(Region { start: 1, end: 10 });
+        assert_eq!(windows, vec![Region { start: 1, end: 4 }, Region { start: 4, end: 7 }, Region { start: 7, end: 10 }]);
+        assert!(Windows { size: 20, step: 1 }.within(Region { start: 1, end: 10 }).is_empty());
+    }
+
+    #[test]
+    fn test_reference_coordinates() {
+        let reference = Sequence::new("ref", "--AC-GT-A");
+        let coords = Coordinates::Reference(&reference);
+        assert_eq!(coords.len(), 5);
+        // Residues 2-4 (C, G, T) span columns 4-7
+        assert_eq!(coords.columns(Region { start: 2, end: 4 }), Ok(3..7));
+        assert!(coords.columns(Region { start: 2, end: 6 }).is_err());
+        assert_eq!(Coordinates::Aligned(9).columns(Region { start: 2, end: 4 }), Ok(1..4));
+    }
+}
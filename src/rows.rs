@@ -0,0 +1,429 @@
+//! Display order of alignment rows.
+//!
+//! Rows are shown through a `RowView` that maps display rows (what the cursor
+//! and viewport index) to sequence indices in the `Alignment`. The view supports:
+//! - Sorting by name, ungapped length, gap content, identity to a reference row
+//!   or leaf order of a neighbour-joining tree (clades together)
+//! - Moving a single row up or down
+//! - Hiding rows
+//! - Pinning rows: pinned rows are listed first and stay on screen while scrolling
+//!
+//! The alignment itself is never reordered, so the translated alignment
+//! (same sequence indices) shares the same view.
+
+use crate::distance::{Deletion, DistanceCalculator, DistanceModel};
+use crate::model::{is_gap, Alignment, Sequence};
+use crate::tree::{self, Tree, TreeMethod};
+
+/// Criterion for `:sort`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Original file order
+    File,
+    /// Sequence name (A-Z, case-insensitive)
+    Name,
+    /// Ungapped length (longest first)
+    Length,
+    /// Proportion of gaps (fewest first)
+    Gaps,
+    /// Identity to a reference sequence (most similar first)
+    Identity,
+    /// Leaf order of a BioNJ tree of p-distances (clades together)
+    Tree,
+}
+
+impl SortKey {
+    /// All key names, as used by `:sort`.
+    pub const NAMES: &'static [&'static str] = &["file", "gaps", "identity", "length", "name", "tree"];
+
+    /// Returns the name of the key, as used by `:sort`.
+    pub fn name(self) -> &'static str {
+        match self {
+            SortKey::File => "file",
+            SortKey::Name => "name",
+            SortKey::Length => "length",
+            SortKey::Gaps => "gaps",
+            SortKey::Identity => "identity",
+            SortKey::Tree => "tree",
+        }
+    }
+
+    /// Parses a sort key name (unique prefixes allowed, e.g. `id`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        if name.is_empty() {
+            return None;
+        }
+        let mut found = Self::NAMES.iter().filter(|n| n.starts_with(&name));
+        let key = match (found.next(), found.next()) {
+            (Some(&key), None) => key,
+            _ => return None,
+        };
+        [SortKey::File, SortKey::Name, SortKey::Length, SortKey::Gaps, SortKey::Identity, SortKey::Tree]
+            .into_iter()
+            .find(|k| k.name() == key)
+    }
+}
+
+/// Number of non-gap characters in a sequence.
+pub fn ungapped_length(seq: &Sequence) -> usize {
+    seq.as_bytes().iter().filter(|&&b| !is_gap(b)).count()
+}
+
+/// Proportion of gap characters in a sequence (0.0 for an empty sequence).
+pub fn gap_fraction(seq: &Sequence) -> f64 {
+    if seq.is_empty() {
+        return 0.0;
+    }
+    (seq.len() - ungapped_length(seq)) as f64 / seq.len() as f64
+}
+
+/// Pairwise identity between two aligned sequences.
+///
+/// Identical sites over sites where neither sequence has a gap (case-insensitive).
+/// Returns 0.0 when no site can be compared.
+pub fn identity(a: &Sequence, b: &Sequence) -> f64 {
+    let (mut same, mut compared) = (0usize, 0usize);
+    for (&x, &y) in a.as_bytes().iter().zip(b.as_bytes()) {
+        if is_gap(x) || is_gap(y) {
+            continue;
+        }
+        compared += 1;
+        if x.eq_ignore_ascii_case(&y) {
+            same += 1;
+        }
+    }
+    if compared == 0 {
+        0.0
+    } else {
+        same as f64 / compared as f64
+    }
+}
+
+/// Maps display rows to sequence indices.
+#[derive(Debug, Clone, Default)]
+pub struct RowView {
+    /// All sequence indices in display order (hidden and pinned included)
+    order: Vec<usize>,
+    /// Hidden flag per sequence index
+    hidden: Vec<bool>,
+    /// Pinned sequence indices, in pin order
+    pinned: Vec<usize>,
+    /// Displayed sequence indices: pinned rows, then the rest of `order`
+    rows: Vec<usize>,
+}
+
+impl RowView {
+    /// Creates a view showing `count` sequences in file order.
+    pub fn new(count: usize) -> Self {
+        let mut view = Self {
+            order: (0..count).collect(),
+            hidden: vec![false; count],
+            pinned: Vec::new(),
+            rows: Vec::new(),
+        };
+        view.rebuild();
+        view
+    }
+
+    /// Recomputes the displayed rows.
+    fn rebuild(&mut self) {
+        self.rows.clear();
+        self.rows.extend(self.pinned.iter().copied().filter(|&i| !self.hidden[i]));
+        self.rows.extend(
+            self.order
+                .iter()
+                .copied()
+                .filter(|&i| !self.hidden[i] && !self.pinned.contains(&i)),
+        );
+    }
+
+    /// Displayed sequence indices, top to bottom.
+    pub fn rows(&self) -> &[usize] {
+        &self.rows
+    }
+
+    /// Number of displayed rows.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns true if no row is displayed.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Sequence index shown at a display row.
+    pub fn seq_index(&self, row: usize) -> Option<usize> {
+        self.rows.get(row).copied()
+    }
+
+    /// Display row of a sequence, or None if it is hidden.
+    pub fn display_row(&self, seq_index: usize) -> Option<usize> {
+        self.rows.iter().position(|&i| i == seq_index)
+    }
+
+    /// Number of displayed pinned rows (always the first display rows).
+    pub fn pinned_count(&self) -> usize {
+        self.pinned.iter().filter(|&&i| !self.hidden[i]).count()
+    }
+
+    /// Returns true if the sequence is pinned.
+    pub fn is_pinned(&self, seq_index: usize) -> bool {
+        self.pinned.contains(&seq_index)
+    }
+
+    /// Number of hidden sequences.
+    pub fn hidden_count(&self) -> usize {
+        self.hidden.iter().filter(|&&h| h).count()
+    }
+
+    /// Sorts all rows (pinned rows keep their pin order at the top).
+    ///
+    /// `reference` is the sequence index used by `SortKey::Identity`.
+    /// `SortKey::Tree` leaves the order unchanged if the sequences are not aligned.
+    /// The sort is stable, and `reverse` inverts the natural order of the key.
+    pub fn sort(&mut self, alignment: &Alignment, key: SortKey, reference: usize, reverse: bool) {
+        let seqs = &alignment.sequences;
+        match key {
+            SortKey::File => self.order.sort_unstable(),
+            SortKey::Name => self.order.sort_by_cached_key(|&i| seqs[i].id.to_lowercase()),
+            SortKey::Length => self.order.sort_by_cached_key(|&i| std::cmp::Reverse(ungapped_length(&seqs[i]))),
+            SortKey::Gaps => {
+                let values: Vec<f64> = seqs.iter().map(gap_fraction).collect();
+                self.order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+            }
+            SortKey::Identity => {
+                let values: Vec<f64> = seqs.iter().map(|s| identity(&seqs[reference], s)).collect();
+                // Reference first, then most similar
+                self.order.sort_by(|&a, &b| {
+                    (b == reference)
+                        .cmp(&(a == reference))
+                        .then(values[b].total_cmp(&values[a]))
+                });
+            }
+            SortKey::Tree => {
+                if let Ok(calculator) = DistanceCalculator::new(alignment, DistanceModel::P, Deletion::Pairwise) {
+                    let (distances, _) = tree::fill_undefined(&calculator.matrix().values);
+                    let mut position = vec![0; seqs.len()];
+                    for (k, i) in Tree::neighbor_joining(&distances, TreeMethod::BioNj).leaf_order().into_iter().enumerate() {
+                        position[i] = k;
+                    }
+                    self.order.sort_by_key(|&i| position[i]);
+                }
+            }
+        }
+        if reverse {
+            self.order.reverse();
+        }
+        self.rebuild();
+    }
+
+    /// Moves the row at `row` one position up (`up`) or down among its
+    /// neighbours of the same kind (pinned or not).
+    /// Returns the new display row, or None if it cannot move.
+    pub fn move_row(&mut self, row: usize, up: bool) -> Option<usize> {
+        let seq = self.seq_index(row)?;
+        let target_row = if up { row.checked_sub(1)? } else { row + 1 };
+        let other = self.seq_index(target_row)?;
+        let list = if self.is_pinned(seq) {
+            if !self.is_pinned(other) {
+                return None;
+            }
+            &mut self.pinned
+        } else {
+            if self.is_pinned(other) {
+                return None;
+            }
+            &mut self.order
+        };
+        // Swap with the displayed neighbour (skipping hidden rows in between)
+        let a = list.iter().position(|&i| i == seq)?;
+        let b = list.iter().position(|&i| i == other)?;
+        list.swap(a, b);
+        self.rebuild();
+        self.display_row(seq)
+    }
+
+    /// Hides sequences. Returns the number newly hidden.
+    pub fn hide(&mut self, seq_indices: &[usize]) -> usize {
+        let mut count = 0;
+        for &i in seq_indices {
+            if !self.hidden[i] {
+                self.hidden[i] = true;
+                count += 1;
+            }
+        }
+        self.rebuild();
+        count
+    }
+
+    /// Shows hidden sequences again. Returns the number shown.
+    pub fn unhide(&mut self, seq_indices: &[usize]) -> usize {
+        let mut count = 0;
+        for &i in seq_indices {
+            if self.hidden[i] {
+                self.hidden[i] = false;
+                count += 1;
+            }
+        }
+        self.rebuild();
+        count
+    }
+
+    /// Replaces the order of rows (a permutation of all sequence indices);
+    /// pinned rows stay at the top.
+    pub fn set_order(&mut self, order: Vec<usize>) {
+        debug_assert_eq!(order.len(), self.order.len());
+        self.order = order;
+        self.rebuild();
+    }
+
+    /// Shows all hidden sequences. Returns the number shown again.
+    pub fn unhide_all(&mut self) -> usize {
+        let count = self.hidden_count();
+        self.hidden.iter_mut().for_each(|h| *h = false);
+        self.rebuild();
+        count
+    }
+
+    /// Pins sequences (appended after those already pinned). Returns the number newly pinned.
+    pub fn pin(&mut self, seq_indices: &[usize]) -> usize {
+        let mut count = 0;
+        for &i in seq_indices {
+            if !self.pinned.contains(&i) {
+                self.pinned.push(i);
+                count += 1;
+            }
+        }
+        self.rebuild();
+        count
+    }
+
+    /// Unpins sequences. Returns the number unpinned.
+    pub fn unpin(&mut self, seq_indices: &[usize]) -> usize {
+        let before = self.pinned.len();
+        self.pinned.retain(|i| !seq_indices.contains(i));
+        self.rebuild();
+        before - self.pinned.len()
+    }
+
+    /// Unpins all sequences. Returns the number unpinned.
+    pub fn unpin_all(&mut self) -> usize {
+        let count = self.pinned.len();
+        self.pinned.clear();
+        self.rebuild();
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alignment() -> Alignment {
+        Alignment::new(vec![
+            Sequence::new("delta", "ACGT--"),
+            Sequence::new("Alpha", "ACGTAC"),
+            Sequence::new("charlie", "A-----"),
+            Sequence::new("bravo", "ACGAAC"),
+        ])
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let aln = alignment();
+        let mut view = RowView::new(4);
+
+        view.sort(&aln, SortKey::Length, 0, false);
+        assert_eq!(view.rows(), &[1, 3, 0, 2]);
+        view.sort(&aln, SortKey::Gaps, 0, false);
+        assert_eq!(view.rows(), &[1, 3, 0, 2]);
+
+        view.sort(&aln, SortKey::Name, 0, false);
+        assert_eq!(view.rows(), &[1, 3, 2, 0]);
+        view.sort(&aln, SortKey::Name, 0, true);
+        assert_eq!(view.rows(), &[0, 2, 3, 1]);
+
+        // Reference first; bravo differs from Alpha at one site
+        view.sort(&aln, SortKey::Identity, 1, false);
+        assert_eq!(view.rows()[0], 1);
+        assert_eq!(view.rows()[3], 3);
+
+        view.sort(&aln, SortKey::File, 0, false);
+        assert_eq!(view.rows(), &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_sort_tree_groups_clades() {
+        let aln = Alignment::new(vec![
+            Sequence::new("a1", "AAAAAAAAAA"),
+            Sequence::new("b1", "CCCCCCCCCC"),
+            Sequence::new("a2", "AAAAAAAAAT"),
+            Sequence::new("b2", "CCCCCCCCCT"),
+            Sequence::new("a3", "AAAAAAAATT"),
+        ]);
+        let mut view = RowView::new(5);
+        view.sort(&aln, SortKey::Tree, 0, false);
+        let position = |seq: usize| view.rows().iter().position(|&i| i == seq).unwrap();
+        assert_eq!(position(1).abs_diff(position(3)), 1);
+        assert_eq!(SortKey::from_name("t"), Some(SortKey::Tree));
+    }
+
+    #[test]
+    fn test_sort_key_names() {
+        assert_eq!(SortKey::from_name("id"), Some(SortKey::Identity));
+        assert_eq!(SortKey::from_name("Name"), Some(SortKey::Name));
+        assert_eq!(SortKey::from_name("x"), None);
+        assert_eq!(SortKey::from_name(""), None);
+    }
+
+    #[test]
+    fn test_identity() {
+        let a = Sequence::new("a", "ACGT-A");
+        let b = Sequence::new("b", "acGA-C");
+        // 5 comparable sites (gap column skipped), 3 identical
+        assert!((identity(&a, &b) - 0.6).abs() < 1e-9);
+        assert_eq!(identity(&a, &Sequence::new("c", "------")), 0.0);
+    }
+
+    #[test]
+    fn test_hide_and_pin() {
+        let mut view = RowView::new(4);
+        assert_eq!(view.hide(&[1]), 1);
+        assert_eq!(view.rows(), &[0, 2, 3]);
+        assert_eq!(view.display_row(1), None);
+
+        assert_eq!(view.pin(&[3]), 1);
+        assert_eq!(view.rows(), &[3, 0, 2]);
+        assert_eq!(view.pinned_count(), 1);
+
+        // A hidden pinned row is not displayed
+        view.hide(&[3]);
+        assert_eq!(view.pinned_count(), 0);
+        assert_eq!(view.unhide_all(), 2);
+        assert_eq!(view.rows(), &[3, 0, 1, 2]);
+
+        assert_eq!(view.unpin(&[3]), 1);
+        assert_eq!(view.rows(), &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_move_row() {
+        let mut view = RowView::new(4);
+        assert_eq!(view.move_row(0, true), None);
+        assert_eq!(view.move_row(0, false), Some(1));
+        assert_eq!(view.rows(), &[1, 0, 2, 3]);
+
+        // Moving skips hidden rows
+        view.hide(&[2]);
+        assert_eq!(view.move_row(1, false), Some(2));
+        assert_eq!(view.rows(), &[1, 3, 0]);
+
+        // Pinned and unpinned rows do not cross
+        view.pin(&[3]);
+        assert_eq!(view.rows(), &[3, 1, 0]);
+        assert_eq!(view.move_row(0, false), None);
+        assert_eq!(view.move_row(1, true), None);
+    }
+}
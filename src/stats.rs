@@ -0,0 +1,321 @@
+//! Alignment statistics (`--stats`) and site classification.
+//!
+//! Sites are classified from unambiguous states only: A, C, G, T for nucleotides
+//! and the 20 standard amino acids. Missing data (`N` or `X`, and `?`) is ignored;
+//! gaps and ambiguity codes are ignored too but make a site "unclean", which VCF
+//! mode uses to skip it.
+//!
+//! A site is variable with at least two states, and parsimony-informative with
+//! at least two states each present in at least two sequences.
+
+use std::io::Write;
+
+use crate::filter::ambiguity_fraction;
+use crate::model::{is_gap, Alignment};
+use crate::rows::{gap_fraction, ungapped_length};
+
+const AMINO_ACIDS: &[u8] = b"ACDEFGHIKLMNPQRSTVWY";
+
+/// States seen at one alignment column, as bit sets (bit 0 = `A`, bit 25 = `Z`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Site {
+    /// States present in at least one sequence
+    pub seen: u32,
+    /// States present in at least two sequences
+    pub repeated: u32,
+    /// Only unambiguous states and missing data (no gap, no ambiguity code)
+    pub clean: bool,
+}
+
+impl Site {
+    /// Number of distinct states.
+    pub fn states(&self) -> u32 {
+        self.seen.count_ones()
+    }
+
+    pub fn is_variable(&self) -> bool {
+        self.states() > 1
+    }
+
+    pub fn is_informative(&self) -> bool {
+        self.repeated.count_ones() > 1
+    }
+
+    /// The states seen, in alphabetical order.
+    pub fn residues(&self) -> Vec<u8> {
+        (0..26u8).filter(|&bit| self.seen & (1 << bit) != 0).map(|bit| b'A' + bit).collect()
+    }
+}
+
+/// Classifies each column of an alignment (sequences shorter than the
+/// alignment are treated as gapped at the end).
+pub fn classify_sites(alignment: &Alignment, nucleotide: bool) -> Vec<Site> {
+    let states: &[u8] = if nucleotide { b"ACGT" } else { AMINO_ACIDS };
+    let missing = if nucleotide { b'N' } else { b'X' };
+    let mut sites = vec![Site { clean: true, ..Site::default() }; alignment.alignment_length()];
+    for seq in &alignment.sequences {
+        let bytes = seq.as_bytes();
+        for (pos, site) in sites.iter_mut().enumerate() {
+            let b = bytes.get(pos).map_or(b'-', |b| b.to_ascii_uppercase());
+            if states.contains(&b) {
+                let bit = 1 << (b - b'A');
+                site.repeated |= site.seen & bit;
+                site.seen |= bit;
+            } else if b != missing && b != b'?' {
+                site.clean = false; // Gap or ambiguity code
+            }
+        }
+    }
+    sites
+}
+
+/// Per-sequence statistics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceStats {
+    pub id: String,
+    /// Non-gap residues
+    pub length: usize,
+    /// Proportion of gap characters (0-1)
+    pub gap_fraction: f64,
+    /// Proportion of `N` (or `X`) among non-gap residues (0-1)
+    pub ambiguity: f64,
+}
+
+/// Summary statistics of one alignment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlignmentStats {
+    pub sequences: usize,
+    /// Alignment length (longest sequence if not aligned)
+    pub length: usize,
+    /// All sequences have the same length
+    pub aligned: bool,
+    pub nucleotide: bool,
+    /// GC content among A, C, G, T (nucleotides only)
+    pub gc: Option<f64>,
+    /// GC content at third codon positions of the reading frame (nucleotides only)
+    pub gc3: Option<f64>,
+    /// Variable sites (aligned only)
+    pub variable: Option<usize>,
+    /// Parsimony-informative sites (aligned only)
+    pub informative: Option<usize>,
+    /// Proportion of gaps and missing data among all residues (0-1)
+    pub missing: f64,
+    pub per_sequence: Vec<SequenceStats>,
+}
+
+impl AlignmentStats {
+    /// Computes statistics, with codon positions counted from `frame` (0-2).
+    pub fn compute(alignment: &Alignment, frame: usize) -> Self {
+        let nucleotide = alignment.sequence_type.is_nucleotide();
+        let missing_char = if nucleotide { b'N' } else { b'X' };
+
+        let per_sequence = alignment
+            .sequences
+            .iter()
+            .map(|seq| SequenceStats {
+                id: seq.id.clone(),
+                length: ungapped_length(seq),
+                gap_fraction: gap_fraction(seq),
+                ambiguity: ambiguity_fraction(seq, nucleotide),
+            })
+            .collect();
+
+        let mut cells = 0usize;
+        let mut missing = 0usize;
+        // [A+T, G+C] overall and at third codon positions
+        let mut bases = [0usize; 2];
+        let mut bases3 = [0usize; 2];
+        for seq in &alignment.sequences {
+            for (pos, &b) in seq.as_bytes().iter().enumerate() {
+                cells += 1;
+                let b = b.to_ascii_uppercase();
+                if is_gap(b) || b == missing_char {
+                    missing += 1;
+                }
+                let gc = match b {
+                    b'A' | b'T' => 0,
+                    b'C' | b'G' => 1,
+                    _ => continue,
+                };
+                bases[gc] += 1;
+                if pos >= frame && (pos - frame) % 3 == 2 {
+                    bases3[gc] += 1;
+                }
+            }
+        }
+        let gc_content = |[at, gc]: [usize; 2]| (at + gc > 0).then(|| gc as f64 / (at + gc) as f64);
+
+        let sites = alignment.is_valid_alignment.then(|| classify_sites(alignment, nucleotide));
+        Self {
+            sequences: alignment.sequence_count(),
+            length: alignment.alignment_length(),
+            aligned: alignment.is_valid_alignment,
+            nucleotide,
+            gc: if nucleotide { gc_content(bases) } else { None },
+            gc3: if nucleotide { gc_content(bases3) } else { None },
+            variable: sites.as_ref().map(|s| s.iter().filter(|s| s.is_variable()).count()),
+            informative: sites.as_ref().map(|s| s.iter().filter(|s| s.is_informative()).count()),
+            missing: if cells > 0 { missing as f64 / cells as f64 } else { 0.0 },
+            per_sequence,
+        }
+    }
+
+    fn summary_fields(&self) -> [(&'static str, String); 9] {
+        let pct = |v: Option<f64>| v.map_or("NA".to_string(), |v| format!("{:.2}", v * 100.0));
+        let count = |v: Option<usize>| v.map_or("NA".to_string(), |v| v.to_string());
+        [
+            ("sequences", self.sequences.to_string()),
+            ("length", self.length.to_string()),
+            ("aligned", self.aligned.to_string()),
+            ("type", if self.nucleotide { "nucleotide" } else { "amino acid" }.to_string()),
+            ("gc_pct", pct(self.gc)),
+            ("gc3_pct", pct(self.gc3)),
+            ("variable_sites", count(self.variable)),
+            ("informative_sites", count(self.informative)),
+            ("missing_pct", pct(Some(self.missing))),
+        ]
+    }
+
+    /// Writes a single-file report as TSV: summary as `#` lines, then one row per sequence.
+    pub fn write_tsv(&self, out: &mut impl Write, file: &str) -> std::io::Result<()> {
+        writeln!(out, "# file: {}", file)?;
+        for (name, value) in self.summary_fields() {
+            writeln!(out, "# {}: {}", name, value)?;
+        }
+        writeln!(out, "id\tlength\tgap_pct\tambiguity_pct")?;
+        for s in &self.per_sequence {
+            writeln!(
+                out,
+                "{}\t{}\t{:.2}\t{:.2}",
+                s.id,
+                s.length,
+                s.gap_fraction * 100.0,
+                s.ambiguity * 100.0
+            )?;
+        }
+        Ok(())
+    }
+
+    /// JSON object with the summary, and per-sequence statistics if `per_sequence`.
+    pub fn to_json(&self, file: &str, per_sequence: bool) -> String {
+        let mut fields = vec![format!("\"file\": {}", json_string(file))];
+        for (name, value) in self.summary_fields() {
+            let value = match name {
+                "aligned" => value,
+                "type" => json_string(&value),
+                _ if value == "NA" => "null".to_string(),
+                _ => value,
+            };
+            fields.push(format!("\"{}\": {}", name, value));
+        }
+        if per_sequence {
+            let rows: Vec<String> = self
+                .per_sequence
+                .iter()
+                .map(|s| {
+                    format!(
+                        "{{\"id\": {}, \"length\": {}, \"gap_pct\": {:.2}, \"ambiguity_pct\": {:.2}}}",
+                        json_string(&s.id),
+                        s.length,
+                        s.gap_fraction * 100.0,
+                        s.ambiguity * 100.0
+                    )
+                })
+                .collect();
+            fields.push(format!("\"per_sequence\": [\n    {}\n  ]", rows.join(",\n    ")));
+        }
+        format!("{{\n  {}\n}}", fields.join(",\n  "))
+    }
+}
+
+/// Writes a multi-file report as TSV, one row per file.
+pub fn write_summary_tsv(out: &mut impl Write, rows: &[(String, AlignmentStats)]) -> std::io::Result<()> {
+    let Some((_, first)) = rows.first() else {
+        return Ok(());
+    };
+    let names: Vec<&str> = first.summary_fields().iter().map(|(name, _)| *name).collect();
+    writeln!(out, "file\t{}", names.join("\t"))?;
+    for (file, stats) in rows {
+        let values: Vec<String> = stats.summary_fields().into_iter().map(|(_, v)| v).collect();
+        writeln!(out, "{}\t{}", file, values.join("\t"))?;
+    }
+    Ok(())
+}
+
+/// Quotes and escapes a JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Sequence;
+
+    fn alignment(seqs: &[&str]) -> Alignment {
+        Alignment::new(
+            seqs.iter()
+                .enumerate()
+                .map(|(i, s)| Sequence::new(format!("s{}", i + 1), s))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_classify_sites() {
+        // Columns: constant, singletons, informative, with gap, with N, ambiguity code
+        let aln = alignment(&["AAAAAA", "ACCANR", "AAC-CA", "AGAACA"]);
+        let sites = classify_sites(&aln, true);
+        let variable: Vec<bool> = sites.iter().map(|s| s.is_variable()).collect();
+        let informative: Vec<bool> = sites.iter().map(|s| s.is_informative()).collect();
+        let clean: Vec<bool> = sites.iter().map(|s| s.clean).collect();
+        assert_eq!(variable, vec![false, true, true, false, true, false]);
+        assert_eq!(informative, vec![false, false, true, false, false, false]);
+        assert_eq!(clean, vec![true, true, true, false, true, false]);
+        assert_eq!(sites[2].residues(), b"AC".to_vec());
+    }
+
+    #[test]
+    fn test_alignment_stats() {
+        // Frame 0: third positions are columns 3 and 6
+        let aln = alignment(&["ATGGCC", "ATGGCA", "ATN-CA"]);
+        let stats = AlignmentStats::compute(&aln, 0);
+        assert_eq!(stats.sequences, 3);
+        assert_eq!(stats.length, 6);
+        assert_eq!(stats.variable, Some(1));
+        assert_eq!(stats.informative, Some(0));
+        // 8 G/C among 16 A/C/G/T; third positions: G, C, G, A, A (N ignored)
+        assert_eq!(stats.gc, Some(0.5));
+        assert_eq!(stats.gc3, Some(0.6));
+        assert_eq!(stats.missing, 2.0 / 18.0);
+        assert_eq!(stats.per_sequence[2].length, 5);
+
+        let mut out = Vec::new();
+        stats.write_tsv(&mut out, "gene.fasta").unwrap();
+        let tsv = String::from_utf8(out).unwrap();
+        assert!(tsv.contains("# variable_sites: 1\n"));
+        assert!(tsv.contains("s3\t5\t16.67\t20.00\n"));
+
+        let json = stats.to_json("a\"b.fasta", false);
+        assert!(json.contains("\"file\": \"a\\\"b.fasta\""));
+        assert!(json.contains("\"aligned\": true"));
+        assert!(!json.contains("per_sequence"));
+
+        let protein = AlignmentStats::compute(&alignment(&["MKWLEQ", "MKWLE"]), 0);
+        assert_eq!((protein.gc, protein.variable), (None, None));
+        assert!(protein.to_json("p", true).contains("\"gc_pct\": null"));
+    }
+}
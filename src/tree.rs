@@ -0,0 +1,600 @@
+//! Phylogenetic trees: neighbour joining (`--nj`, `:sort tree`) and Newick
+//! trees shown next to the alignment (`--tree`, `:tree`).
+//!
+//! Trees are built from a distance matrix with NJ (Saitou & Nei 1987) or BioNJ
+//! (Gascuel 1997), which weights each join by the variance of the distances.
+//! The result is unrooted: the last three nodes are joined at a trifurcation.
+//! Negative branch lengths are set to zero when written.
+//!
+//! Leaves are numbered from 0 and named by the caller (sequence names for NJ,
+//! labels read from the file for Newick). A `cladogram` lays the tree out on a
+//! grid with one leaf per row, ignoring branch lengths.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Tree building algorithm.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TreeMethod {
+    /// Neighbour joining
+    Nj,
+    /// BioNJ (variance-weighted neighbour joining)
+    #[default]
+    BioNj,
+}
+
+impl FromStr for TreeMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "nj" => Ok(TreeMethod::Nj),
+            "bionj" => Ok(TreeMethod::BioNj),
+            _ => Err(format!("invalid tree method '{}' (use nj or bionj)", s)),
+        }
+    }
+}
+
+impl fmt::Display for TreeMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TreeMethod::Nj => "NJ",
+            TreeMethod::BioNj => "BioNJ",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    /// Leaf index (`None` for internal nodes)
+    leaf: Option<usize>,
+    /// Children and their branch lengths
+    children: Vec<(usize, f64)>,
+}
+
+/// An unrooted tree, stored from an arbitrary internal root.
+#[derive(Debug, Clone)]
+pub struct Tree {
+    nodes: Vec<Node>,
+    root: usize,
+}
+
+impl Tree {
+    /// Builds a tree from a symmetric distance matrix (one row per leaf).
+    pub fn neighbor_joining(distances: &[Vec<f64>], method: TreeMethod) -> Self {
+        let n = distances.len();
+        let mut nodes: Vec<Node> = (0..n).map(|i| Node { leaf: Some(i), children: Vec::new() }).collect();
+        if n <= 1 {
+            return Tree { nodes, root: 0 };
+        }
+
+        // Working matrices over node indices; `active` lists the nodes left to join
+        let mut d: Vec<Vec<f64>> = distances.to_vec();
+        let mut v: Vec<Vec<f64>> = distances.to_vec();
+        let mut active: Vec<usize> = (0..n).collect();
+        let join = |nodes: &mut Vec<Node>, children: Vec<(usize, f64)>| {
+            nodes.push(Node { leaf: None, children });
+            nodes.len() - 1
+        };
+
+        while active.len() > 3 {
+            let r = active.len();
+            let sums: Vec<f64> = active.iter().map(|&a| active.iter().map(|&b| d[a][b]).sum()).collect();
+
+            // Pair minimizing Q(i,j) = (r-2) d(i,j) - S(i) - S(j)
+            let (mut best, mut best_q) = ((0, 1), f64::INFINITY);
+            for x in 0..r {
+                for y in x + 1..r {
+                    let q = (r - 2) as f64 * d[active[x]][active[y]] - sums[x] - sums[y];
+                    if q < best_q {
+                        best_q = q;
+                        best = (x, y);
+                    }
+                }
+            }
+            let (x, y) = best;
+            let (i, j) = (active[x], active[y]);
+            let dij = d[i][j];
+            let li = 0.5 * dij + (sums[x] - sums[y]) / (2.0 * (r - 2) as f64);
+            let lj = dij - li;
+
+            let lambda = match method {
+                TreeMethod::Nj => 0.5,
+                TreeMethod::BioNj if v[i][j] > 0.0 => {
+                    let diff: f64 = active.iter().filter(|&&k| k != i && k != j).map(|&k| v[j][k] - v[i][k]).sum();
+                    (0.5 + diff / (2.0 * (r - 2) as f64 * v[i][j])).clamp(0.0, 1.0)
+                }
+                TreeMethod::BioNj => 0.5,
+            };
+
+            let u = join(&mut nodes, vec![(i, li), (j, lj)]);
+            let du: Vec<f64> = (0..u)
+                .map(|k| lambda * (d[i][k] - li) + (1.0 - lambda) * (d[j][k] - lj))
+                .collect();
+            let vu: Vec<f64> = (0..u)
+                .map(|k| lambda * v[i][k] + (1.0 - lambda) * v[j][k] - lambda * (1.0 - lambda) * v[i][j])
+                .collect();
+            for (k, row) in d.iter_mut().enumerate() {
+                row.push(du[k]);
+            }
+            for (k, row) in v.iter_mut().enumerate() {
+                row.push(vu[k]);
+            }
+            d.push(du.into_iter().chain([0.0]).collect());
+            v.push(vu.into_iter().chain([0.0]).collect());
+
+            active.retain(|&k| k != i && k != j);
+            active.push(u);
+        }
+
+        let root = match active[..] {
+            [a, b] => join(&mut nodes, vec![(a, d[a][b] / 2.0), (b, d[a][b] / 2.0)]),
+            [a, b, c] => {
+                let la = (d[a][b] + d[a][c] - d[b][c]) / 2.0;
+                let lb = d[a][b] - la;
+                let lc = d[a][c] - la;
+                join(&mut nodes, vec![(a, la), (b, lb), (c, lc)])
+            }
+            _ => unreachable!("neighbour joining stops at two or three nodes"),
+        };
+        Tree { nodes, root }
+    }
+
+    /// Reads a Newick tree. Returns the tree and the leaf labels (by leaf index).
+    ///
+    /// Quoted labels, comments (`[...]`) and branch lengths are supported;
+    /// internal node labels (e.g. support values) are ignored.
+    pub fn parse_newick(text: &str) -> Result<(Self, Vec<String>), String> {
+        let mut parser = NewickParser { bytes: text.as_bytes(), pos: 0 };
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut names: Vec<String> = Vec::new();
+        // Children of the nodes whose ')' has not been read yet
+        let mut open: Vec<Vec<(usize, f64)>> = Vec::new();
+
+        let root = 'subtree: loop {
+            parser.skip_blank()?;
+            if parser.peek() == Some(b'(') {
+                parser.pos += 1;
+                open.push(Vec::new());
+                continue;
+            }
+            let name = parser.label()?;
+            if name.is_empty() {
+                return Err(parser.error("expected a leaf name"));
+            }
+            nodes.push(Node { leaf: Some(names.len()), children: Vec::new() });
+            names.push(name);
+            let mut node = nodes.len() - 1;
+
+            loop {
+                parser.skip_blank()?;
+                let length = if parser.peek() == Some(b':') {
+                    parser.pos += 1;
+                    parser.length()?
+                } else {
+                    0.0
+                };
+                let Some(children) = open.last_mut() else {
+                    break 'subtree node;
+                };
+                children.push((node, length));
+                parser.skip_blank()?;
+                match parser.peek() {
+                    Some(b',') => {
+                        parser.pos += 1;
+                        continue 'subtree;
+                    }
+                    Some(b')') => {
+                        parser.pos += 1;
+                        let children = open.pop().unwrap_or_default();
+                        nodes.push(Node { leaf: None, children });
+                        node = nodes.len() - 1;
+                        parser.label()?;
+                    }
+                    _ => return Err(parser.error("expected ',' or ')'")),
+                }
+            }
+        };
+
+        parser.skip_blank()?;
+        if parser.peek() == Some(b';') {
+            parser.pos += 1;
+            parser.skip_blank()?;
+        }
+        if parser.peek().is_some() {
+            return Err(parser.error("unexpected text after the tree"));
+        }
+        Ok((Tree { nodes, root }, names))
+    }
+
+    /// Number of leaves.
+    pub fn leaf_count(&self) -> usize {
+        self.nodes.iter().filter(|n| n.leaf.is_some()).count()
+    }
+
+    /// Number of nodes (leaves included).
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Root node.
+    pub fn root(&self) -> usize {
+        self.root
+    }
+
+    /// Leaf index of a node (None for internal nodes).
+    pub fn leaf(&self, node: usize) -> Option<usize> {
+        self.nodes[node].leaf
+    }
+
+    /// Node of each leaf, by leaf index.
+    pub fn leaf_nodes(&self) -> Vec<usize> {
+        let mut leaf_nodes = vec![0; self.leaf_count()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            if let Some(leaf) = node.leaf {
+                leaf_nodes[leaf] = i;
+            }
+        }
+        leaf_nodes
+    }
+
+    /// Parent of each node (None for the root).
+    pub fn parents(&self) -> Vec<Option<usize>> {
+        let mut parents = vec![None; self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            for &(child, _) in &node.children {
+                parents[child] = Some(i);
+            }
+        }
+        parents
+    }
+
+    /// Nodes of the subtree below `node` (itself included), depth-first.
+    pub fn subtree(&self, node: usize) -> Vec<usize> {
+        let mut order = Vec::new();
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            order.push(node);
+            stack.extend(self.nodes[node].children.iter().rev().map(|&(child, _)| child));
+        }
+        order
+    }
+
+    /// Leaf indices below `node`, in tree order.
+    pub fn leaves_under(&self, node: usize) -> Vec<usize> {
+        self.subtree(node).into_iter().filter_map(|n| self.nodes[n].leaf).collect()
+    }
+
+    /// Leaf indices in tree order (depth-first), so that clades are contiguous.
+    pub fn leaf_order(&self) -> Vec<usize> {
+        self.leaves_under(self.root)
+    }
+
+    /// Lays out the tree as a cladogram of `width` columns, one row per entry of
+    /// `rows` (the leaf shown on that row, if any).
+    ///
+    /// Each cell holds the `LINE_*` segments drawn through it and the node they
+    /// belong to (a vertical bar belongs to its node, a horizontal one to the
+    /// child it leads to). Clades with a single displayed leaf are drawn as a
+    /// plain branch, so hidden rows simply disappear from the drawing.
+    pub fn cladogram(&self, rows: &[Option<usize>], width: usize) -> Vec<Vec<(u8, usize)>> {
+        let mut grid = vec![vec![(0u8, 0usize); width]; rows.len()];
+        let mut leaf_rows = vec![None; self.leaf_count()];
+        for (row, leaf) in rows.iter().enumerate() {
+            if let Some(&leaf) = leaf.as_ref() {
+                leaf_rows[leaf].get_or_insert(row);
+            }
+        }
+
+        // Drawn node standing for each subtree, and its row (bottom-up)
+        let order = self.subtree(self.root);
+        let mut drawn: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut y: Vec<usize> = vec![0; self.nodes.len()];
+        let mut drawn_children: Vec<Vec<usize>> = vec![Vec::new(); self.nodes.len()];
+        for &node in order.iter().rev() {
+            if let Some(leaf) = self.nodes[node].leaf {
+                if let Some(row) = leaf_rows[leaf] {
+                    drawn[node] = Some(node);
+                    y[node] = row;
+                }
+                continue;
+            }
+            let mut children: Vec<usize> = self.nodes[node].children.iter().filter_map(|&(c, _)| drawn[c]).collect();
+            children.sort_by_key(|&c| y[c]);
+            match children[..] {
+                [] => {}
+                [only] => drawn[node] = Some(only),
+                [first, .., last] => {
+                    drawn[node] = Some(node);
+                    y[node] = (y[first] + y[last]) / 2;
+                    drawn_children[node] = children;
+                }
+            }
+        }
+        let Some(root) = drawn[self.root] else {
+            return grid;
+        };
+
+        // Columns: internal nodes by depth, leaves one past the right edge
+        let mut depth = vec![0usize; self.nodes.len()];
+        let mut internal = Vec::new();
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            if !drawn_children[node].is_empty() {
+                internal.push(node);
+            }
+            for &child in &drawn_children[node] {
+                depth[child] = depth[node] + 1;
+                stack.push(child);
+            }
+        }
+        let levels = internal.iter().map(|&n| depth[n]).max().map_or(1, |d| d + 1);
+        let column = |node: usize| {
+            if drawn_children[node].is_empty() {
+                width
+            } else {
+                depth[node] * width.saturating_sub(1) / levels
+            }
+        };
+
+        let set = |grid: &mut Vec<Vec<(u8, usize)>>, row: usize, col: usize, lines: u8, node: usize| {
+            if let Some(cell) = grid[row].get_mut(col) {
+                if cell.0 == 0 || lines & (LINE_UP | LINE_DOWN) != 0 {
+                    cell.1 = node;
+                }
+                cell.0 |= lines;
+            }
+        };
+        // Horizontal branch from the column after `start` to the node
+        let branch = |grid: &mut Vec<Vec<(u8, usize)>>, row: usize, start: usize, node: usize| {
+            let end = column(node);
+            for col in start..end {
+                set(grid, row, col, LINE_LEFT | LINE_RIGHT, node);
+            }
+            if start > 0 && start <= end {
+                set(grid, row, end, LINE_LEFT, node);
+            }
+        };
+
+        branch(&mut grid, y[root], 0, root);
+        for &node in &internal {
+            let col = column(node);
+            let children = &drawn_children[node];
+            let (top, bottom) = (y[children[0]], y[children[children.len() - 1]]);
+            for row in top..=bottom {
+                let mut lines = 0;
+                if row > top {
+                    lines |= LINE_UP;
+                }
+                if row < bottom {
+                    lines |= LINE_DOWN;
+                }
+                set(&mut grid, row, col, lines, node);
+            }
+            for &child in children {
+                set(&mut grid, y[child], col, LINE_RIGHT, node);
+                branch(&mut grid, y[child], col + 1, child);
+            }
+        }
+        grid
+    }
+
+    /// Writes the tree in Newick format, naming leaves from `names`.
+    pub fn to_newick(&self, names: &[String]) -> String {
+        let mut out = String::new();
+        self.write_node(self.root, names, &mut out);
+        out.push(';');
+        out
+    }
+
+    fn write_node(&self, node: usize, names: &[String], out: &mut String) {
+        let node = &self.nodes[node];
+        if let Some(leaf) = node.leaf {
+            out.push_str(&newick_name(&names[leaf]));
+            return;
+        }
+        out.push('(');
+        for (k, &(child, length)) in node.children.iter().enumerate() {
+            if k > 0 {
+                out.push(',');
+            }
+            self.write_node(child, names, out);
+            out.push_str(&format!(":{:.6}", length.max(0.0)));
+        }
+        out.push(')');
+    }
+}
+
+/// Segments of a cladogram cell (see `Tree::cladogram`).
+pub const LINE_UP: u8 = 1;
+pub const LINE_DOWN: u8 = 2;
+pub const LINE_LEFT: u8 = 4;
+pub const LINE_RIGHT: u8 = 8;
+
+/// Cursor over Newick text.
+struct NewickParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl NewickParser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn error(&self, message: &str) -> String {
+        match self.peek() {
+            Some(_) => format!("invalid Newick at character {}: {}", self.pos + 1, message),
+            None => format!("invalid Newick: unexpected end of tree ({})", message),
+        }
+    }
+
+    /// Skips whitespace and `[...]` comments.
+    fn skip_blank(&mut self) -> Result<(), String> {
+        loop {
+            match self.peek() {
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(b'[') => match self.bytes[self.pos..].iter().position(|&b| b == b']') {
+                    Some(end) => self.pos += end + 1,
+                    None => return Err("invalid Newick: unterminated comment".to_string()),
+                },
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Reads a quoted or unquoted label (empty if there is none).
+    fn label(&mut self) -> Result<String, String> {
+        self.skip_blank()?;
+        if self.peek() == Some(b'\'') {
+            let mut label = Vec::new();
+            self.pos += 1;
+            loop {
+                match self.peek() {
+                    Some(b'\'') if self.bytes.get(self.pos + 1) == Some(&b'\'') => {
+                        label.push(b'\'');
+                        self.pos += 2;
+                    }
+                    Some(b'\'') => {
+                        self.pos += 1;
+                        return Ok(String::from_utf8_lossy(&label).into_owned());
+                    }
+                    Some(b) => {
+                        label.push(b);
+                        self.pos += 1;
+                    }
+                    None => return Err("invalid Newick: unterminated quoted label".to_string()),
+                }
+            }
+        }
+        let start = self.pos;
+        while let Some(b) = self.peek() {
+            if b.is_ascii_whitespace() || b"(),:;[".contains(&b) {
+                break;
+            }
+            self.pos += 1;
+        }
+        Ok(String::from_utf8_lossy(&self.bytes[start..self.pos]).into_owned())
+    }
+
+    /// Reads a branch length.
+    fn length(&mut self) -> Result<f64, String> {
+        self.skip_blank()?;
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_alphanumeric() || b"+-.".contains(&b)) {
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or_default();
+        text.parse().map_err(|_| {
+            self.pos = start;
+            self.error("invalid branch length")
+        })
+    }
+}
+
+/// Quotes a Newick label if it contains spaces or punctuation.
+fn newick_name(name: &str) -> String {
+    if name.chars().any(|c| c.is_whitespace() || "()[]':;,".contains(c)) {
+        format!("'{}'", name.replace('\'', "''"))
+    } else {
+        name.to_string()
+    }
+}
+
+/// Replaces undefined distances by the largest defined one (1.0 if none).
+/// Returns the matrix and the number of replaced pairs.
+pub fn fill_undefined(values: &[Vec<Option<f64>>]) -> (Vec<Vec<f64>>, usize) {
+    let max = values.iter().flatten().flatten().copied().fold(None, |m: Option<f64>, v| Some(m.map_or(v, |m| m.max(v))));
+    let fill = max.unwrap_or(1.0);
+    let missing = values.iter().flatten().filter(|v| v.is_none()).count() / 2;
+    let filled = values.iter().map(|row| row.iter().map(|v| v.unwrap_or(fill)).collect()).collect();
+    (filled, missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Additive distances of the unrooted tree ((A:1,B:2):2,C:1,D:3).
+    fn additive() -> Vec<Vec<f64>> {
+        vec![
+            vec![0.0, 3.0, 4.0, 6.0],
+            vec![3.0, 0.0, 5.0, 7.0],
+            vec![4.0, 5.0, 0.0, 4.0],
+            vec![6.0, 7.0, 4.0, 0.0],
+        ]
+    }
+
+    fn names(n: &[&str]) -> Vec<String> {
+        n.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_recovers_additive_tree() {
+        for method in [TreeMethod::Nj, TreeMethod::BioNj] {
+            let tree = Tree::neighbor_joining(&additive(), method);
+            let newick = tree.to_newick(&names(&["A", "B", "C", "D"]));
+            // A and B are joined first; the remaining three nodes form the root
+            assert_eq!(newick, "(C:1.000000,D:3.000000,(A:1.000000,B:2.000000):2.000000);", "{}", method);
+            assert_eq!(tree.leaf_order(), vec![2, 3, 0, 1]);
+        }
+    }
+
+    #[test]
+    fn test_small_trees_and_names() {
+        let two = Tree::neighbor_joining(&[vec![0.0, 0.2], vec![0.2, 0.0]], TreeMethod::BioNj);
+        assert_eq!(two.to_newick(&names(&["a b", "c'd"])), "('a b':0.100000,'c''d':0.100000);");
+        let one = Tree::neighbor_joining(&[vec![0.0]], TreeMethod::BioNj);
+        assert_eq!(one.to_newick(&names(&["a"])), "a;");
+
+        let (filled, missing) = fill_undefined(&[vec![Some(0.0), None], vec![None, Some(0.0)]]);
+        assert_eq!((filled[0][1], missing), (0.0, 1));
+        let (filled, _) = fill_undefined(&[
+            vec![Some(0.0), Some(0.3), None],
+            vec![Some(0.3), Some(0.0), Some(0.1)],
+            vec![None, Some(0.1), Some(0.0)],
+        ]);
+        assert_eq!(filled[0][2], 0.3);
+    }
+
+    #[test]
+    fn test_parse_newick() {
+        let (tree, labels) = Tree::parse_newick("((A:0.1,'B c':0.2)90:0.05,[note] C:0.3);\n").unwrap();
+        assert_eq!(labels, names(&["A", "B c", "C"]));
+        assert_eq!(tree.leaf_order(), vec![0, 1, 2]);
+        assert_eq!(tree.to_newick(&labels), "((A:0.100000,'B c':0.200000):0.050000,C:0.300000);");
+        let parents = tree.parents();
+        assert_eq!(parents[tree.leaf_nodes()[0]], parents[tree.leaf_nodes()[1]]);
+
+        let (single, labels) = Tree::parse_newick("A;").unwrap();
+        assert_eq!((single.leaf_count(), labels), (1, names(&["A"])));
+        for bad in ["((A,B);", "(A,B));", "(A,,B);", "(A:x,B);", "(A,B); C"] {
+            assert!(Tree::parse_newick(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_cladogram() {
+        let draw = |grid: Vec<Vec<(u8, usize)>>| -> Vec<String> {
+            grid.iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|&(lines, _)| match lines {
+                            0 => ' ',
+                            l if l & (LINE_UP | LINE_DOWN) == 0 => '-',
+                            l if l & (LINE_LEFT | LINE_RIGHT) == 0 => '|',
+                            _ => '+',
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+        let (tree, _) = Tree::parse_newick("((A,B),C);").unwrap();
+        assert_eq!(draw(tree.cladogram(&[Some(0), Some(1), Some(2)], 4)), vec!["++--", "|+--", "+---"]);
+
+        // Hidden B: the (A,B) clade becomes a plain branch to A; unknown rows stay blank
+        let grid = tree.cladogram(&[Some(0), None, Some(2)], 4);
+        assert_eq!(draw(grid.clone()), vec!["+---", "|   ", "+---"]);
+        assert_eq!(grid[0][2].1, tree.leaf_nodes()[0]);
+    }
+}
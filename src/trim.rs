@@ -0,0 +1,347 @@
+//! Column trimming (trimAl-like).
+//!
+//! Trimming is done in two steps:
+//! 1. `keep_mask` decides, per column, whether it is kept according to a `TrimMethod`
+//! 2. `enforce_codons` (nucleotides only) keeps or removes whole codons of a reading
+//!    frame within a segment: a codon is kept only if its three columns are kept.
+//!
+//! The kept columns (0-based, in order) are the column map: they give the original
+//! position of each column of the trimmed alignment, and are used to remap
+//! partition coordinates (`remap_range`).
+//!
+//! Automated methods follow the ideas of trimAl (Capella-Gutiérrez et al. 2009):
+//! - `gappyout`: the gap cut-off is the "knee" of the sorted gap distribution,
+//!   where the curve of gap fraction against cumulative column count turns steepest
+//! - `strict`: the gappyout cut-off, plus a conservation cut-off found the same way
+//!   on the residue variability of each column, then kept blocks shorter than
+//!   `MIN_BLOCK` columns are removed
+
+use std::fmt;
+use std::io::Write;
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::model::{is_gap, Alignment, Sequence};
+
+/// Shortest block of kept columns left by `TrimMethod::Strict`.
+const MIN_BLOCK: usize = 3;
+
+/// Criterion for removing columns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrimMethod {
+    /// Remove columns with a gap fraction above the threshold (0.0-1.0)
+    GapThreshold(f64),
+    /// Remove columns made only of gaps
+    AllGaps,
+    /// Automated gap cut-off (trimAl -gappyout)
+    Gappyout,
+    /// Automated gap and conservation cut-offs (trimAl -strict)
+    Strict,
+}
+
+impl FromStr for TrimMethod {
+    type Err = String;
+
+    /// Parses `allgaps`, `gappyout`, `strict`, or a gap threshold such as `0.5`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "allgaps" | "nogaps" => Ok(TrimMethod::AllGaps),
+            "gappyout" => Ok(TrimMethod::Gappyout),
+            "strict" => Ok(TrimMethod::Strict),
+            other => match other.parse::<f64>() {
+                Ok(t) if (0.0..=1.0).contains(&t) => Ok(TrimMethod::GapThreshold(t)),
+                _ => Err(format!(
+                    "invalid trim method '{}' (expected allgaps, gappyout, strict, or a gap threshold 0-1)",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for TrimMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrimMethod::GapThreshold(t) => write!(f, "gap threshold {}", t),
+            TrimMethod::AllGaps => write!(f, "allgaps"),
+            TrimMethod::Gappyout => write!(f, "gappyout"),
+            TrimMethod::Strict => write!(f, "strict"),
+        }
+    }
+}
+
+/// Number of gaps in each column.
+fn gap_counts(alignment: &Alignment) -> Vec<usize> {
+    let mut counts = vec![0; alignment.alignment_length()];
+    for seq in &alignment.sequences {
+        for (col, count) in counts.iter_mut().enumerate() {
+            // Missing trailing positions (unaligned input) count as gaps
+            if seq.byte_at(col).is_none_or(is_gap) {
+                *count += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// Variability of each column: 1 - (count of the most frequent residue / sequences).
+/// Gaps never count as the most frequent residue.
+fn variability(alignment: &Alignment) -> Vec<f64> {
+    let n = alignment.sequence_count();
+    (0..alignment.alignment_length())
+        .map(|col| {
+            let mut counts = [0usize; 256];
+            for seq in &alignment.sequences {
+                if let Some(b) = seq.byte_at(col).filter(|&b| !is_gap(b)) {
+                    counts[b.to_ascii_uppercase() as usize] += 1;
+                }
+            }
+            let max = counts.iter().copied().max().unwrap_or(0);
+            1.0 - max as f64 / n.max(1) as f64
+        })
+        .collect()
+}
+
+/// Finds the knee of a distribution: the largest value `v` such that values
+/// `<= v` are kept, chosen where the sorted curve turns steepest.
+///
+/// Returns None if there are fewer than three distinct values.
+fn knee_cutoff(values: &[f64]) -> Option<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let total = sorted.len() as f64;
+
+    // Distinct values with the fraction of columns at or below them
+    let mut points: Vec<(f64, f64)> = Vec::new();
+    for (i, &v) in sorted.iter().enumerate() {
+        let x = (i + 1) as f64 / total;
+        match points.last_mut() {
+            Some(last) if last.1 == v => last.0 = x,
+            _ => points.push((x, v)),
+        }
+    }
+    if points.len() < 3 {
+        return None;
+    }
+
+    let slopes: Vec<f64> = points
+        .windows(2)
+        .map(|w| (w[1].1 - w[0].1) / (w[1].0 - w[0].0))
+        .collect();
+    // Point i sits between slopes[i-1] and slopes[i]
+    let mut best = (f64::MIN, points[0].1);
+    for i in 1..slopes.len() {
+        let ratio = slopes[i] / slopes[i - 1];
+        if ratio > best.0 {
+            best = (ratio, points[i].1);
+        }
+    }
+    Some(best.1)
+}
+
+/// Decides which columns are kept (true) by the method.
+pub fn keep_mask(alignment: &Alignment, method: TrimMethod) -> Vec<bool> {
+    let n = alignment.sequence_count();
+    let gaps = gap_counts(alignment);
+    let gap_fraction: Vec<f64> = gaps.iter().map(|&g| g as f64 / n.max(1) as f64).collect();
+    let not_all_gaps = |col: usize| gaps[col] < n;
+
+    match method {
+        TrimMethod::AllGaps => (0..gaps.len()).map(not_all_gaps).collect(),
+        TrimMethod::GapThreshold(t) => (0..gaps.len())
+            .map(|col| not_all_gaps(col) && gap_fraction[col] <= t)
+            .collect(),
+        TrimMethod::Gappyout | TrimMethod::Strict => {
+            let gap_cut = knee_cutoff(&gap_fraction);
+            let mut mask: Vec<bool> = (0..gaps.len())
+                .map(|col| not_all_gaps(col) && gap_cut.is_none_or(|cut| gap_fraction[col] <= cut))
+                .collect();
+            if method == TrimMethod::Strict {
+                let var = variability(alignment);
+                if let Some(var_cut) = knee_cutoff(&var) {
+                    for (keep, v) in mask.iter_mut().zip(&var) {
+                        *keep &= *v <= var_cut;
+                    }
+                }
+                remove_short_blocks(&mut mask, MIN_BLOCK);
+            }
+            mask
+        }
+    }
+}
+
+/// Removes runs of kept columns shorter than `min_len`.
+fn remove_short_blocks(mask: &mut [bool], min_len: usize) {
+    let mut start = 0;
+    while start < mask.len() {
+        if !mask[start] {
+            start += 1;
+            continue;
+        }
+        let end = (start..mask.len()).find(|&i| !mask[i]).unwrap_or(mask.len());
+        if end - start < min_len {
+            mask[start..end].iter_mut().for_each(|k| *k = false);
+        }
+        start = end;
+    }
+}
+
+/// Keeps whole codons within `segment`: codons start at `segment.start + frame`,
+/// and a codon is kept only if its three columns are kept.
+/// Columns outside complete codons (frame offset, trailing partial codon) are left as is.
+pub fn enforce_codons(mask: &mut [bool], segment: Range<usize>, frame: usize) {
+    let mut start = segment.start + frame;
+    while start + 3 <= segment.end.min(mask.len()) {
+        let keep = mask[start..start + 3].iter().all(|&k| k);
+        mask[start..start + 3].iter_mut().for_each(|k| *k = keep);
+        start += 3;
+    }
+}
+
+/// Returns the kept column indices (0-based) of a mask: the column map.
+pub fn kept_columns(mask: &[bool]) -> Vec<usize> {
+    mask.iter().enumerate().filter(|(_, &k)| k).map(|(i, _)| i).collect()
+}
+
+/// Builds the trimmed alignment from the kept columns.
+pub fn apply(alignment: &Alignment, kept: &[usize]) -> Alignment {
+    let sequences = alignment
+        .sequences
+        .iter()
+        .map(|seq| {
+            let data = kept.iter().filter_map(|&col| seq.byte_at(col)).collect();
+            Sequence::from_bytes(seq.id.clone(), data)
+        })
+        .collect();
+    let mut trimmed = Alignment::new(sequences);
+    trimmed.sequence_type = alignment.sequence_type;
+    trimmed.annotations = Box::new(alignment.annotations.select_columns(kept));
+    trimmed
+}
+
+/// Trims an alignment and returns it with its column map.
+///
+/// With `codon_frame` (0-2), columns are removed by whole codons of that frame
+/// within each segment (e.g. one segment per gene of a supermatrix; the whole
+/// alignment if `segments` is empty).
+pub fn trim_alignment(
+    alignment: &Alignment,
+    method: TrimMethod,
+    segments: &[Range<usize>],
+    codon_frame: Option<usize>,
+) -> (Alignment, Vec<usize>) {
+    let mut mask = keep_mask(alignment, method);
+    if let Some(frame) = codon_frame {
+        if segments.is_empty() {
+            enforce_codons(&mut mask, 0..alignment.alignment_length(), frame);
+        }
+        for segment in segments {
+            enforce_codons(&mut mask, segment.clone(), frame);
+        }
+    }
+    let kept = kept_columns(&mask);
+    (apply(alignment, &kept), kept)
+}
+
+/// Maps a 1-based inclusive range of original columns to the trimmed alignment.
+/// Returns None if no column of the range is kept.
+pub fn remap_range(kept: &[usize], start: usize, end: usize) -> Option<(usize, usize)> {
+    let first = kept.partition_point(|&c| c + 1 < start);
+    let last = kept.partition_point(|&c| c < end);
+    (first < last).then_some((first + 1, last))
+}
+
+/// Writes the column map: one line per kept column, `new<TAB>original` (1-based).
+pub fn write_column_map(path: &Path, kept: &[usize]) -> std::io::Result<()> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    writeln!(file, "# trimmed\toriginal")?;
+    for (new, &orig) in kept.iter().enumerate() {
+        writeln!(file, "{}\t{}", new + 1, orig + 1)?;
+    }
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alignment(rows: &[&str]) -> Alignment {
+        Alignment::new(
+            rows.iter()
+                .enumerate()
+                .map(|(i, r)| Sequence::new(format!("s{}", i), r))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_parse_method() {
+        assert_eq!("gappyout".parse::<TrimMethod>(), Ok(TrimMethod::Gappyout));
+        assert_eq!("0.5".parse::<TrimMethod>(), Ok(TrimMethod::GapThreshold(0.5)));
+        assert!("1.5".parse::<TrimMethod>().is_err());
+        assert!("fast".parse::<TrimMethod>().is_err());
+    }
+
+    #[test]
+    fn test_gap_threshold_and_all_gaps() {
+        let aln = alignment(&["A-C-", "A-CT", "AGC-", "A-C-"]);
+        assert_eq!(keep_mask(&aln, TrimMethod::AllGaps), vec![true, true, true, true]);
+        // Column 1 and 3 are 75% gaps
+        assert_eq!(keep_mask(&aln, TrimMethod::GapThreshold(0.5)), vec![true, false, true, false]);
+
+        let aln = alignment(&["A-C", "A-C"]);
+        assert_eq!(keep_mask(&aln, TrimMethod::AllGaps), vec![true, false, true]);
+        // Threshold 1.0 still removes all-gap columns
+        assert_eq!(keep_mask(&aln, TrimMethod::GapThreshold(1.0)), vec![true, false, true]);
+    }
+
+    #[test]
+    fn test_gappyout_removes_gappy_tail() {
+        // 10 sequences: many complete columns, a few slightly gappy, a few very gappy
+        let mut rows = vec![String::new(); 10];
+        let profile = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 8, 9];
+        for &gaps in &profile {
+            for (i, row) in rows.iter_mut().enumerate() {
+                row.push(if i < gaps { '-' } else { 'A' });
+            }
+        }
+        let refs: Vec<&str> = rows.iter().map(|r| r.as_str()).collect();
+        let mask = keep_mask(&alignment(&refs), TrimMethod::Gappyout);
+        assert!(mask[..11].iter().all(|&k| k));
+        assert!(!mask[11] && !mask[12]);
+    }
+
+    #[test]
+    fn test_short_blocks_removed() {
+        let mut mask = vec![true, true, false, true, true, true, false, true];
+        remove_short_blocks(&mut mask, 3);
+        assert_eq!(mask, vec![false, false, false, true, true, true, false, false]);
+    }
+
+    #[test]
+    fn test_enforce_codons() {
+        let mut mask = vec![true, true, true, true, false, true, true, true];
+        enforce_codons(&mut mask, 0..8, 0);
+        assert_eq!(mask, vec![true, true, true, false, false, false, true, true]);
+
+        // Frame +2: codons start at column 1; column 0 is left as is
+        let mut mask = vec![false, true, true, false, true, true, true];
+        enforce_codons(&mut mask, 0..7, 1);
+        assert_eq!(mask, vec![false, false, false, false, true, true, true]);
+    }
+
+    #[test]
+    fn test_apply_and_remap() {
+        let aln = alignment(&["ACGT", "TGCA"]);
+        let kept = vec![0, 2, 3];
+        let trimmed = apply(&aln, &kept);
+        assert_eq!(trimmed.sequences[0].as_str(), "AGT");
+        assert_eq!(trimmed.alignment_length(), 3);
+
+        // Original partitions 1-2 and 3-4 (1-based)
+        assert_eq!(remap_range(&kept, 1, 2), Some((1, 1)));
+        assert_eq!(remap_range(&kept, 3, 4), Some((2, 3)));
+        assert_eq!(remap_range(&kept, 2, 2), None);
+    }
+}
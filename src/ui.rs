@@ -1,7 +1,7 @@
 //! TUI rendering module.
 //!
 //! This module handles all visual rendering using ratatui:
-//! - Layout with sticky sequence names on the left
+//! - Layout with sticky sequence names on the left (and an optional tree panel)
 //! - Colored nucleotide/amino acid display
 //! - Status bar with position and mode info
 //! - Hint bar with basic commands
@@ -22,11 +22,10 @@ use ratatui::{
     Frame,
 };
 
-use crate::model::{AppMode, AppState, ViewMode};
+use crate::model::{AppMode, AppState, Palette, TreePanelState, ViewMode, TREE_PANEL_WIDTH};
+use crate::tree::{LINE_DOWN, LINE_LEFT, LINE_RIGHT, LINE_UP};
 use glyphs::Glyphs;
 
-/// Width reserved for sequence names (including border and padding).
-const NAME_PANEL_WIDTH: u16 = 20;
 /// Minimum width for the sequence panel.
 const MIN_SEQ_PANEL_WIDTH: u16 = 10;
 /// Height of the status bar.
@@ -126,20 +125,24 @@ pub fn render(frame: &mut Frame, state: &AppState) {
     let status_area = main_layout[1];
     let hint_area = main_layout[2];
 
-    // Split content area: names panel (left) + sequence panel (right)
+    // Split content area: tree panel (if any) + names panel (left) + sequence panel (right)
+    let tree_width = if state.tree_panel.is_some() { TREE_PANEL_WIDTH } else { 0 };
     let content_layout = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Length(NAME_PANEL_WIDTH),
+            Constraint::Length(tree_width),
+            Constraint::Length(state.name_width),
             Constraint::Min(MIN_SEQ_PANEL_WIDTH),
         ])
         .split(content_area);
 
-    let names_area = content_layout[0];
-    let sequences_area = content_layout[1];
+    let tree_area = content_layout[0];
+    let names_area = content_layout[1];
+    let sequences_area = content_layout[2];
 
-    // Calculate visible dimensions (accounting for borders)
-    let visible_rows = (sequences_area.height.saturating_sub(2)) as usize; // -2 for borders
+    // Calculate visible dimensions (accounting for borders and annotation tracks)
+    let tracks = state.annotation_tracks().len();
+    let visible_rows = (sequences_area.height.saturating_sub(2) as usize).saturating_sub(tracks); // -2 for borders
     let visible_cols = (sequences_area.width.saturating_sub(2)) as usize; // -2 for borders
 
     // Update state viewport if dimensions changed
@@ -147,10 +150,13 @@ pub fn render(frame: &mut Frame, state: &AppState) {
     // We'll handle this properly in the main loop
 
     // Render each panel
+    if let Some(panel) = &state.tree_panel {
+        render_tree_panel(frame, state, panel, &glyphs, tree_area, visible_rows);
+    }
     render_names_panel(frame, state, names_area, visible_rows);
     render_sequences_panel(frame, state, sequences_area, visible_rows, visible_cols);
     render_status_bar(frame, state, &glyphs, status_area);
-    render_hint_bar(frame, &glyphs, hint_area);
+    render_hint_bar(frame, state, &glyphs, hint_area);
 
     // Render help overlay if active
     if state.show_help {
@@ -175,38 +181,73 @@ pub fn render(frame: &mut Frame, state: &AppState) {
     // Render file browser if active
     if let Some(browser) = &state.file_browser {
         render_file_browser(frame, browser, &glyphs, area);
-}
+    }
+
+    // Render sequence finder if active
+    if let Some(finder) = &state.sequence_finder {
+        render_sequence_finder(frame, state, finder, &glyphs, area);
+    }
+
+    // Render closest relatives if active
+    if let Some(panel) = &state.distance_panel {
+        render_distance_panel(frame, state, panel, &glyphs, area);
+    }
+
+    // Render character block picker if active
+    if let Some(picker) = &state.block_picker {
+        render_block_picker(frame, state, picker, &glyphs, area);
+    }
 }
 
 /// Renders the sequence names panel (sticky, always visible).
 fn render_names_panel(frame: &mut Frame, state: &AppState, area: Rect, visible_rows: usize) {
     let mut lines: Vec<Line> = Vec::new();
-    let alignment = state.active_alignment();
-
-    let start_row = state.viewport.first_row;
-    let end_row = (start_row + visible_rows).min(alignment.sequence_count());
+    let last_pinned = state.frozen_rows().checked_sub(1);
+    let tree = state.tree_panel.as_ref();
+    let clade = tree.and_then(|t| Some(t.clade_sequences(t.selected?))).unwrap_or_default();
+
+    // Labels of the annotation tracks shown above the sequences
+    for (tag, _) in state.annotation_tracks() {
+        let label = format!("#=GC {}", tag);
+        lines.push(Line::from(Span::styled(label, Style::default().fg(Color::Yellow))));
+    }
 
-    for row_idx in start_row..end_row {
-        if let Some(seq) = alignment.get(row_idx) {
+    for row_idx in state.screen_rows().into_iter().take(visible_rows) {
+        if let Some(seq) = state.row_sequence(row_idx) {
             let is_current = row_idx == state.cursor.row;
+            let seq_index = state.rows.seq_index(row_idx);
+
+            // Collapsed clades show the number of hidden sequences
+            let suffix = tree
+                .zip(seq_index)
+                .and_then(|(t, i)| t.collapsed_at(i))
+                .map(|c| format!(" +{}", c.hidden.len()))
+                .unwrap_or_default();
 
             // Truncate name if too long
-            let max_name_len = (NAME_PANEL_WIDTH.saturating_sub(3)) as usize;
+            let max_name_len = (state.name_width.saturating_sub(3)) as usize;
+            let max_name_len = max_name_len.saturating_sub(suffix.len());
             let name = if seq.id.len() > max_name_len {
                 let truncate_len = max_name_len.saturating_sub(3);
-                format!("{}...", &seq.id[..truncate_len])
+                format!("{}...{}", &seq.id[..truncate_len], suffix)
             } else {
-                seq.id.clone()
+                format!("{}{}", seq.id, suffix)
             };
 
-            let style = if is_current {
+            let mut style = if is_current {
                 Style::default()
                     .fg(Color::Black)
                     .bg(Color::White)
                     .add_modifier(Modifier::BOLD)
+            } else if seq_index.is_some_and(|i| clade.contains(&i)) {
+                Style::default().fg(Color::Cyan)
             } else {
                 Style::default().fg(Color::White)
             };
+            // Underline the last pinned row to separate it from scrolling rows
+            if Some(row_idx) == last_pinned {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
 
             lines.push(Line::from(Span::styled(name, style)));
         }
@@ -220,6 +261,78 @@ fn render_names_panel(frame: &mut Frame, state: &AppState, area: Rect, visible_r
     frame.render_widget(paragraph, area);
 }
 
+/// Renders the tree panel: a cladogram with one leaf per displayed row.
+fn render_tree_panel(
+    frame: &mut Frame,
+    state: &AppState,
+    panel: &TreePanelState,
+    glyphs: &Glyphs,
+    area: Rect,
+    visible_rows: usize,
+) {
+    let width = area.width.saturating_sub(2) as usize;
+    let leaves: Vec<Option<usize>> = state.rows.rows().iter().map(|&i| panel.seq_leaves[i]).collect();
+    let grid = panel.tree.cladogram(&leaves, width);
+
+    let mut in_clade = vec![false; panel.tree.node_count()];
+    if let Some(selected) = panel.selected {
+        for node in panel.tree.subtree(selected) {
+            in_clade[node] = true;
+        }
+    }
+
+    // Blank lines next to the annotation tracks
+    let mut lines: Vec<Line> = vec![Line::default(); state.annotation_tracks().len()];
+    for row_idx in state.screen_rows().into_iter().take(visible_rows) {
+        let Some(cells) = grid.get(row_idx) else {
+            continue;
+        };
+        let mut spans: Vec<Span> = cells
+            .iter()
+            .map(|&(lines, node)| {
+                let color = if lines != 0 && in_clade[node] { Color::Cyan } else { Color::Gray };
+                Span::styled(tree_char(lines, state.fancy_ui).to_string(), Style::default().fg(color))
+            })
+            .collect();
+        let collapsed = state.rows.seq_index(row_idx).and_then(|i| panel.collapsed_at(i));
+        if let (Some(_), Some(last)) = (collapsed, spans.last_mut()) {
+            *last = Span::styled(glyphs.arrow_right, Style::default().fg(Color::Yellow));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    let block = Block::default().borders(Borders::ALL).title("Tree");
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Character drawing the segments of a cladogram cell.
+fn tree_char(lines: u8, fancy: bool) -> char {
+    let horizontal = lines & (LINE_LEFT | LINE_RIGHT) != 0;
+    let vertical = lines & (LINE_UP | LINE_DOWN) != 0;
+    if !fancy {
+        return match (horizontal, vertical) {
+            (false, false) => ' ',
+            (true, false) => '-',
+            (false, true) => '|',
+            (true, true) => '+',
+        };
+    }
+    match (lines & LINE_UP != 0, lines & LINE_DOWN != 0, lines & LINE_LEFT != 0, lines & LINE_RIGHT != 0) {
+        (false, false, false, false) => ' ',
+        (false, false, _, _) => '─',
+        (_, _, false, false) => '│',
+        (false, true, false, true) => '┌',
+        (true, false, false, true) => '└',
+        (false, true, true, false) => '┐',
+        (true, false, true, false) => '┘',
+        (true, true, false, true) => '├',
+        (true, true, true, false) => '┤',
+        (false, true, true, true) => '┬',
+        (true, false, true, true) => '┴',
+        (true, true, true, true) => '┼',
+    }
+}
+
 /// Renders the sequences panel with colored nucleotides/amino acids.
 fn render_sequences_panel(
     frame: &mut Frame,
@@ -232,14 +345,33 @@ fn render_sequences_panel(
     let seq_type = alignment.sequence_type;
     let mut lines: Vec<Line> = Vec::new();
 
-    let start_row = state.viewport.first_row;
-    let end_row = (start_row + visible_rows).min(alignment.sequence_count());
     let start_col = state.viewport.first_col;
     let end_col = (start_col + visible_cols).min(alignment.alignment_length());
+    let last_pinned = state.frozen_rows().checked_sub(1);
+
+    // Annotation tracks (Stockholm #=GC SS_cons, RF), the cursor column highlighted
+    for (_, data) in state.annotation_tracks() {
+        let spans: Vec<Span> = (start_col..end_col)
+            .map(|col_idx| {
+                let c = data.get(col_idx).map_or(' ', |&b| b as char);
+                let style = if col_idx == state.cursor.col {
+                    Style::default().fg(Color::Black).bg(Color::Yellow)
+                } else {
+                    Style::default().fg(Color::Yellow)
+                };
+                Span::styled(c.to_string(), style)
+            })
+            .collect();
+        lines.push(Line::from(spans));
+    }
+
+    // Insert columns (A2M/A3M, RF gaps) are drawn without background, match states filled
+    let inserts: Vec<bool> = (start_col..end_col).map(|col_idx| state.is_insert_column(col_idx)).collect();
 
-    for row_idx in start_row..end_row {
-        if let Some(seq) = alignment.get(row_idx) {
+    for row_idx in state.screen_rows().into_iter().take(visible_rows) {
+        if let Some(seq) = state.row_sequence(row_idx) {
             let is_current_row = row_idx == state.cursor.row;
+            let separator = if Some(row_idx) == last_pinned { Modifier::UNDERLINED } else { Modifier::empty() };
             let mut spans: Vec<Span> = Vec::new();
 
             for col_idx in start_col..end_col {
@@ -249,17 +381,29 @@ fn render_sequences_panel(
                 let bg_color = get_color_for_sequence_type(c, seq_type);
                 let fg_color = Color::Black;
 
-                let style = if is_cursor {
+                let is_insert = inserts[col_idx - start_col];
+
+                let style = if state.palette == Palette::Mono {
+                    if is_cursor {
+                        Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD)
+                    } else if is_insert {
+                        Style::default().add_modifier(Modifier::DIM)
+                    } else {
+                        Style::default()
+                    }
+                } else if is_cursor {
                     // Invert colors for cursor position
                     Style::default()
                         .fg(bg_color)
                         .bg(Color::White)
                         .add_modifier(Modifier::BOLD)
+                } else if is_insert {
+                    Style::default().fg(bg_color)
                 } else {
                     Style::default().fg(fg_color).bg(bg_color)
                 };
 
-                spans.push(Span::styled(c.to_string(), style));
+                spans.push(Span::styled(c.to_string(), style.add_modifier(separator)));
             }
 
             lines.push(Line::from(spans));
@@ -267,7 +411,7 @@ fn render_sequences_panel(
     }
 
     // Show file name, view mode info, and visible range in title
-    let view_info = match state.view_mode {
+    let mut view_info = match state.view_mode {
         ViewMode::Nucleotide => "NT".to_string(),
         ViewMode::AminoAcid => format!(
             "AA, code {}, frame +{}",
@@ -275,11 +419,29 @@ fn render_sequences_panel(
             state.translation_settings.frame + 1
         ),
     };
+    if state.gapped.is_some() {
+        view_info.push_str(", degapped");
+    }
+    if let Some(title) = state.blocks.get(state.block) {
+        view_info.push_str(&format!(", matrix {}", title));
+    }
+    // After :trim, also show the position in the loaded file (NT columns only)
+    let site = match (&state.column_map, state.view_mode) {
+        (Some(map), ViewMode::Nucleotide) => match map.get(state.cursor.col) {
+            Some(orig) => format!("{} (orig. {})", state.cursor.col + 1, orig + 1),
+            None => (state.cursor.col + 1).to_string(),
+        },
+        _ => (state.cursor.col + 1).to_string(),
+    };
+    let site = match state.partition_at(state.cursor.col) {
+        Some(name) => format!("{} | Part: {}", site, name),
+        None => site,
+    };
     let title = format!(
         "{} ({}) [Site: {} | View: {}-{}/{}]",
         state.file_name,
         view_info,
-        state.cursor.col + 1,
+        site,
         start_col + 1,
         end_col,
         alignment.alignment_length()
@@ -295,10 +457,13 @@ fn render_sequences_panel(
 fn render_status_bar(frame: &mut Frame, state: &AppState, glyphs: &Glyphs, area: Rect) {
     let alignment = state.active_alignment();
     
+    let hidden = state.rows.hidden_count();
+    let hidden_info = if hidden > 0 { format!(" (+{} hidden)", hidden) } else { String::new() };
     let position_info = format!(
-        "Seq {}/{} | Col {}/{} ",
+        "Seq {}/{}{} | Col {}/{} ",
         state.cursor.row + 1,
-        alignment.sequence_count(),
+        state.rows.len(),
+        hidden_info,
         state.cursor.col + 1,
         alignment.alignment_length()
     );
@@ -328,81 +493,15 @@ fn render_status_bar(frame: &mut Frame, state: &AppState, glyphs: &Glyphs, area:
         }
         AppMode::Command(cmd) => {
             // Mode label with cyan background, input area with white background + cursor
-            let mode_label = " COMMAND ";
-            let input_with_cursor = format!(":{}{}", cmd, glyphs.cursor);
-            let input_len = input_with_cursor.len() + 1; // +1 for space after
-            let mode_len = mode_label.len();
-            
-            Line::from(vec![
-                Span::styled(
-                    mode_label,
-                    Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(
-                    format!(" {} ", input_with_cursor),
-                    Style::default().fg(Color::Black).bg(Color::White),
-                ),
-                Span::styled(
-                    " ".repeat((area.width as usize).saturating_sub(mode_len + input_len + 1 + position_info.len())),
-                    Style::default().bg(Color::Cyan),
-                ),
-                Span::styled(
-                    position_info.clone(),
-                    Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
-                ),
-            ])
+            render_input_line(" COMMAND ", ':', cmd, state.input_cursor, glyphs, area, &position_info)
         }
         AppMode::Search(pattern) => {
             // Search forward mode
-            let mode_label = " SEARCH ";
-            let input_with_cursor = format!("/{}{}", pattern, glyphs.cursor);
-            let input_len = input_with_cursor.len() + 1;
-            let mode_len = mode_label.len();
-            
-            Line::from(vec![
-                Span::styled(
-                    mode_label,
-                    Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(
-                    format!(" {} ", input_with_cursor),
-                    Style::default().fg(Color::Black).bg(Color::White),
-                ),
-                Span::styled(
-                    " ".repeat((area.width as usize).saturating_sub(mode_len + input_len + 1 + position_info.len())),
-                    Style::default().bg(Color::Cyan),
-                ),
-                Span::styled(
-                    position_info.clone(),
-                    Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
-                ),
-            ])
+            render_input_line(" SEARCH ", '/', pattern, state.input_cursor, glyphs, area, &position_info)
         }
         AppMode::SearchBackward(pattern) => {
             // Search backward mode
-            let mode_label = " SEARCH ";
-            let input_with_cursor = format!("?{}{}", pattern, glyphs.cursor);
-            let input_len = input_with_cursor.len() + 1;
-            let mode_len = mode_label.len();
-            
-            Line::from(vec![
-                Span::styled(
-                    mode_label,
-                    Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(
-                    format!(" {} ", input_with_cursor),
-                    Style::default().fg(Color::Black).bg(Color::White),
-                ),
-                Span::styled(
-                    " ".repeat((area.width as usize).saturating_sub(mode_len + input_len + 1 + position_info.len())),
-                    Style::default().bg(Color::Cyan),
-                ),
-                Span::styled(
-                    position_info.clone(),
-                    Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
-                ),
-            ])
+            render_input_line(" SEARCH ", '?', pattern, state.input_cursor, glyphs, area, &position_info)
         }
         AppMode::TranslationSettings => {
             let left_content = " TRANSLATE | Use j/k for code, h/l for frame ";
@@ -429,8 +528,66 @@ fn render_status_bar(frame: &mut Frame, state: &AppState, glyphs: &Glyphs, area:
     frame.render_widget(paragraph, area);
 }
 
+/// Builds the status line for command/search input, with the cursor at `cursor`
+/// (shown as a block at the end of the line, or as a reversed character inside it).
+fn render_input_line<'a>(
+    mode_label: &'a str,
+    prefix: char,
+    text: &'a str,
+    cursor: usize,
+    glyphs: &Glyphs,
+    area: Rect,
+    position_info: &str,
+) -> Line<'a> {
+    let input_style = Style::default().fg(Color::Black).bg(Color::White);
+    let cursor = cursor.min(text.len());
+    let (before, after) = text.split_at(cursor);
+    let mut after_chars = after.chars();
+    let (cursor_text, cursor_style, rest) = match after_chars.next() {
+        Some(c) => (c.to_string(), input_style.add_modifier(Modifier::REVERSED), after_chars.as_str()),
+        None => (glyphs.cursor.to_string(), input_style, ""),
+    };
+
+    // " :" + text + cursor (+1 at end of line) + " "
+    let input_len = 2 + text.chars().count() + usize::from(after.is_empty()) + 1;
+    let mode_len = mode_label.len();
+
+    Line::from(vec![
+        Span::styled(
+            mode_label,
+            Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(format!(" {}{}", prefix, before), input_style),
+        Span::styled(cursor_text, cursor_style),
+        Span::styled(format!("{} ", rest), input_style),
+        Span::styled(
+            " ".repeat((area.width as usize).saturating_sub(mode_len + input_len + position_info.len())),
+            Style::default().bg(Color::Cyan),
+        ),
+        Span::styled(
+            position_info.to_string(),
+            Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ),
+    ])
+}
+
 /// Renders the hint bar at the very bottom with basic commands.
-fn render_hint_bar(frame: &mut Frame, glyphs: &Glyphs, area: Rect) {
+fn render_hint_bar(frame: &mut Frame, state: &AppState, glyphs: &Glyphs, area: Rect) {
+    // While cycling through completions, list the candidates instead of the hints
+    if let Some(completion) = &state.completion {
+        let mut spans = Vec::new();
+        for (i, candidate) in completion.candidates.iter().enumerate() {
+            let style = if completion.selected == Some(i) {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            spans.push(Span::styled(format!(" {} ", candidate.label), style));
+        }
+        frame.render_widget(Paragraph::new(Line::from(spans)), area);
+        return;
+    }
+
     let arrows = format!(
         " {}{}{}{} ",
         glyphs.arrow_left, glyphs.arrow_up, glyphs.arrow_down, glyphs.arrow_right
@@ -506,6 +663,7 @@ fn render_help_overlay(frame: &mut Frame, state: &AppState, glyphs: &Glyphs, are
                 Line::from("  :e             Open file browser"),
                 Line::from("  :<number>      Jump to sequence/row number"),
                 Line::from("  :w file.fa     Save to FASTA (single-line seqs)"),
+                Line::from("  :w file.sto    Save to Stockholm (with annotations)"),
                 Line::from(""),
                 Line::from(Span::styled("CLI MODE", Style::default().add_modifier(Modifier::BOLD))),
                 Line::from(""),
@@ -558,6 +716,20 @@ fn render_help_overlay(frame: &mut Frame, state: &AppState, glyphs: &Glyphs, are
                 Line::from("  g0 / gm / g$   First/middle/last visible column"),
                 Line::from("  <num>|         Go to column (e.g., 50|)"),
                 Line::from(""),
+                Line::from("  Alt+k / Alt+j  Move row up/down"),
+                Line::from("  gp             Pin/unpin row (stays on top)"),
+                Line::from("  :sort[!] name|length|gaps|identity|tree|file"),
+                Line::from("  :hide [pat] / :unhide, :pin [pat] / :unpin [pat]"),
+                Line::from("  :degap         Toggle raw (ungapped) sequences"),
+                Line::from("  zi / :inserts  Hide/show A2M/A3M insert columns"),
+                Line::from("  :dist [p|identity|jc|k2p] [complete]  Closest rows"),
+                Line::from("  :tree [file]   Show a Newick tree (no file: close)"),
+                Line::from("  gc / zc / zo / zR  Select clade / collapse / expand / all"),
+                Line::from("  :sets [file]   Show sets / read partitions; ]p / [p  Next/prev"),
+                Line::from("  :block [name]  Choose a NEXUS character matrix"),
+                Line::from(""),
+                Line::from("  :map {keys} {action} / :unmap {keys}  Remap keys"),
+                Line::from(""),
                 Line::from(Span::styled(tab_hint, Style::default().fg(Color::DarkGray))),
             ]);
         }
@@ -573,6 +745,7 @@ fn render_help_overlay(frame: &mut Frame, state: &AppState, glyphs: &Glyphs, are
                 Line::from("  ?pattern       Search backward"),
                 Line::from("  n              Find next match"),
                 Line::from("  N              Find previous match"),
+                Line::from("  Ctrl+P / :seq  Fuzzy-find a sequence by name"),
                 Line::from(""),
                 Line::from("  Searches both sequence names and sequences."),
                 Line::from("  Search is case-insensitive."),
@@ -650,7 +823,7 @@ fn render_translation_settings_overlay(
     let frame_spans: Vec<Span> = (0..3)
         .map(|f| {
             let label = format!(" +{} ", f + 1);
-            if f == state.translation_settings.selected_frame as usize {
+            if f == state.translation_settings.selected_frame {
                 Span::styled(
                     label,
                     Style::default()
@@ -940,12 +1113,227 @@ fn render_file_browser(
     frame.render_widget(paragraph, popup_area);
 }
 
+/// Renders the fuzzy sequence finder popup.
+fn render_sequence_finder(
+    frame: &mut Frame,
+    state: &AppState,
+    finder: &crate::model::SequenceFinderState,
+    glyphs: &Glyphs,
+    area: Rect,
+) {
+    // Calculate centered popup dimensions
+    let popup_width = 70.min(area.width.saturating_sub(4));
+    let popup_height = 20.min(area.height.saturating_sub(4));
+
+    let popup_x = (area.width.saturating_sub(popup_width)) / 2;
+    let popup_y = (area.height.saturating_sub(popup_height)) / 2;
+
+    let popup_area = Rect::new(popup_x, popup_y, popup_width, popup_height);
+
+    // Clear the area behind the popup
+    frame.render_widget(Clear, popup_area);
+
+    let visible_height = (popup_height.saturating_sub(2)) as usize;
+    let mut lines: Vec<Line> = Vec::new();
+
+    // Query line
+    lines.push(Line::from(vec![
+        Span::styled("> ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+        Span::styled(finder.query.clone(), Style::default().fg(Color::White)),
+        Span::styled(glyphs.cursor, Style::default().fg(Color::White)),
+    ]));
+    lines.push(Line::from(glyphs.h_separator.repeat((popup_width.saturating_sub(2)) as usize)));
+
+    // Determine scroll window based on selection (query, separator, blank and hint lines excluded)
+    let scroll_height = visible_height.saturating_sub(4).max(1);
+    let mut start_idx = finder.scroll_offset;
+    if finder.selected < start_idx {
+        start_idx = finder.selected;
+    } else if finder.selected >= start_idx + scroll_height {
+        start_idx = finder.selected.saturating_sub(scroll_height.saturating_sub(1));
+    }
+    let end_idx = (start_idx + scroll_height).min(finder.matches.len());
+
+    let num_width = state.rows.len().to_string().len();
+    let max_name_len = (popup_width as usize).saturating_sub(num_width + 5);
+
+    for idx in start_idx..end_idx {
+        let entry = &finder.matches[idx];
+        let is_selected = idx == finder.selected;
+        let base = if is_selected {
+            Style::default().fg(Color::White).bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let highlight = base.fg(Color::Yellow).add_modifier(Modifier::BOLD);
+
+        let mut spans = vec![Span::styled(
+            format!("{:>width$} ", entry.row + 1, width = num_width),
+            base.fg(Color::DarkGray),
+        )];
+        let name = state.row_sequence(entry.row).map(|s| s.id.as_str()).unwrap_or("");
+        let mut shown = 0;
+        for (i, c) in name.chars().take(max_name_len).enumerate() {
+            let style = if entry.positions.contains(&i) { highlight } else { base };
+            spans.push(Span::styled(c.to_string(), style));
+            shown += 1;
+        }
+        if is_selected {
+            // Pad to full width for the highlight
+            let used = num_width + 1 + shown;
+            spans.push(Span::styled(
+                " ".repeat((popup_width as usize).saturating_sub(2 + used)),
+                base,
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    // Show count and hint at bottom
+    while lines.len() < visible_height.saturating_sub(1) {
+        lines.push(Line::from(""));
+    }
+    let nav_hint = format!(
+        " {}/{}  {}/{}:Navigate  Enter:Jump  Esc:Cancel",
+        finder.matches.len(),
+        state.rows.len(),
+        glyphs.arrow_up,
+        glyphs.arrow_down,
+    );
+    lines.push(Line::from(Span::styled(
+        nav_hint,
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Find Sequence ")
+        .title_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(Color::Black));
+
+    let paragraph = Paragraph::new(lines).block(block);
+    frame.render_widget(paragraph, popup_area);
+}
+
+/// Renders the closest relatives of a sequence (`:dist`).
+fn render_distance_panel(
+    frame: &mut Frame,
+    state: &AppState,
+    panel: &crate::model::DistancePanelState,
+    glyphs: &Glyphs,
+    area: Rect,
+) {
+    let popup_width = 70.min(area.width.saturating_sub(4));
+    let popup_height = 20.min(area.height.saturating_sub(4));
+    let popup_x = (area.width.saturating_sub(popup_width)) / 2;
+    let popup_y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(popup_x, popup_y, popup_width, popup_height);
+    frame.render_widget(Clear, popup_area);
+
+    let alignment = state.active_alignment();
+    let reference = alignment.get(panel.reference).map(|s| s.id.as_str()).unwrap_or("");
+    let visible_height = (popup_height.saturating_sub(2)) as usize;
+    let mut lines: Vec<Line> = vec![
+        Line::from(Span::styled(
+            format!("{} ({})", panel.model, panel.deletion),
+            Style::default().fg(Color::Cyan),
+        )),
+        Line::from(glyphs.h_separator.repeat((popup_width.saturating_sub(2)) as usize)),
+    ];
+
+    // Keep the selection in view (header, separator and hint lines excluded)
+    let list_height = visible_height.saturating_sub(3).max(1);
+    let start_idx = panel.selected.saturating_sub(list_height - 1);
+    let end_idx = (start_idx + list_height).min(panel.entries.len());
+    let values: Vec<String> = panel.entries.iter().map(|&(_, v)| panel.model.format(v)).collect();
+    let value_width = values.iter().map(|v| v.len()).max().unwrap_or(0);
+    let max_name_len = (popup_width as usize).saturating_sub(value_width + 5);
+
+    for (idx, &(seq_index, _)) in panel.entries.iter().enumerate().take(end_idx).skip(start_idx) {
+        let style = if idx == panel.selected {
+            Style::default().fg(Color::White).bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let name: String = alignment.get(seq_index).map(|s| s.id.as_str()).unwrap_or("").chars().take(max_name_len).collect();
+        lines.push(Line::from(Span::styled(
+            format!("{:>vw$}  {:<nw$}", values[idx], name, vw = value_width, nw = max_name_len),
+            style,
+        )));
+    }
+
+    while lines.len() < visible_height.saturating_sub(1) {
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(Span::styled(
+        format!(" {}/{}:Navigate  Enter:Jump  Esc:Close", glyphs.arrow_up, glyphs.arrow_down),
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" Closest to {} ", reference))
+        .title_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(Color::Black));
+
+    let paragraph = Paragraph::new(lines).block(block);
+    frame.render_widget(paragraph, popup_area);
+}
+
+/// Renders the NEXUS character block picker (`:block`).
+fn render_block_picker(
+    frame: &mut Frame,
+    state: &AppState,
+    picker: &crate::model::BlockPickerState,
+    glyphs: &Glyphs,
+    area: Rect,
+) {
+    let title_width = state.blocks.iter().map(|t| t.chars().count()).max().unwrap_or(0) as u16;
+    let popup_width = (title_width + 14).max(40).min(area.width.saturating_sub(4));
+    let popup_height = (state.blocks.len() as u16 + 3).min(area.height.saturating_sub(4));
+    let popup_x = (area.width.saturating_sub(popup_width)) / 2;
+    let popup_y = (area.height.saturating_sub(popup_height)) / 2;
+    let popup_area = Rect::new(popup_x, popup_y, popup_width, popup_height);
+    frame.render_widget(Clear, popup_area);
+
+    // Keep the selection in view (hint line excluded)
+    let list_height = (popup_height.saturating_sub(3) as usize).max(1);
+    let start_idx = picker.selected.saturating_sub(list_height - 1);
+    let mut lines: Vec<Line> = Vec::new();
+    for (idx, title) in state.blocks.iter().enumerate().skip(start_idx).take(list_height) {
+        let style = if idx == picker.selected {
+            Style::default().fg(Color::White).bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let shown = if idx == state.block { "  (shown)" } else { "" };
+        lines.push(Line::from(Span::styled(format!(" {:>2}. {}{}", idx + 1, title, shown), style)));
+    }
+    lines.push(Line::from(Span::styled(
+        format!(" {}/{}:Navigate  Enter:Show  Esc:Close", glyphs.arrow_up, glyphs.arrow_down),
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Character matrices ")
+        .title_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(Color::Black));
+
+    let paragraph = Paragraph::new(lines).block(block);
+    frame.render_widget(paragraph, popup_area);
+}
+
 /// Calculates the visible dimensions for the sequence panel.
-pub fn calculate_visible_dimensions(terminal_width: u16, terminal_height: u16) -> (usize, usize) {
-    // Account for name panel, sequence panel borders, status bar, and hint bar
-    // Sequence panel width = terminal_width - NAME_PANEL_WIDTH
+pub fn calculate_visible_dimensions(
+    terminal_width: u16,
+    terminal_height: u16,
+    left_width: u16,
+) -> (usize, usize) {
+    // Account for name (and tree) panels, sequence panel borders, status bar, and hint bar
+    // Sequence panel width = terminal_width - left_width
     // Visible cols = sequence panel width - 2 (for left/right borders)
-    let visible_cols = (terminal_width.saturating_sub(NAME_PANEL_WIDTH + 2)) as usize;
+    let visible_cols = (terminal_width.saturating_sub(left_width + 2)) as usize;
     let visible_rows = (terminal_height.saturating_sub(STATUS_BAR_HEIGHT + HINT_BAR_HEIGHT + 2)) as usize;
     (visible_rows, visible_cols)
 }
@@ -981,10 +1369,14 @@ mod tests {
 
     #[test]
     fn test_visible_dimensions() {
-        let (rows, cols) = calculate_visible_dimensions(100, 50);
+        let (rows, cols) = calculate_visible_dimensions(100, 50, 20);
         // 100 - 20 (name panel) - 2 (sequence panel borders) = 78 cols
         // 50 - 1 (status) - 1 (hint) - 2 (borders) = 46 rows
         assert_eq!(cols, 78);
         assert_eq!(rows, 46);
+
+        // Wider name panel leaves fewer sequence columns
+        let (_, cols) = calculate_visible_dimensions(100, 50, 30);
+        assert_eq!(cols, 68);
     }
 }